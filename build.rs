@@ -50,25 +50,70 @@ fn tags() -> Vec<Tag> {
         // 120 is 20% more likely, and 80 is 20% less likely.
         P("Hearing", "i32"),
 
+        // How long a Character keeps searching for an enemy it has lost sight of. Characters
+        // without this use time::SEARCH.
+        P("Persistence", "Time"),
+
         S("CanOpenDoor"),
 
+        // Characters without this are Medium.
+        P("Size", "BodySize"),
+
+        // Characters that charge their target when it's a few cells away in a straight line.
+        S("Charges"),
+
         // The object is something that can be picked up and placed into a
         // Character's inventory.
         S("Portable"),
 
         P("Weapon", "Weapon"),
+
+        // Weapons (or Characters who fight with one) that can also attack characters two
+        // cells away in a straight line as long as the cell between them is passable.
+        S("Reach"),
         P("Armor", "Slot"),
 
         // Percentage of damage reduction, normally used with Armor.
         P("Mitigation", "i32"), // TODO: add a type? eg physical, fire, etc
 
+        // Percent chance to block attacks. Used with Characters (e.g. guards carry shields)
+        // and with items wielded in the off hand.
+        P("Block", "i32"),
+
         // Can be used to dig through wood or stone structures (i.e. doors and
         // walls). Ineffective against metal.
         S("PickAxe"),
 
+        // Makes a noise when it lands after being thrown. NPCs that hear the noise will
+        // go investigate.
+        P("Noise", "Sound"),
+
+        // Items with a key can be used to unlock objects with the same lock, e.g.
+        // Key("iron") unlocks a door with Lock("iron").
+        P("Key", "&'static str"),
+        P("Lock", "&'static str"),
+
+        // Player can pull this to activate a circuit, see circuits.rs.
+        S("Lever"),
+
+        // Activates a circuit when a character steps on it.
+        S("PressurePlate"),
+
+        // Name of the circuit a lever or pressure plate is part of.
+        P("Circuit", "String"),
+
         // Description will have the sign's message.
         S("Sign"),
 
+        // Radius of the light given off by an item while it's wielded, see lighting.rs.
+        P("Light", "i32"),
+
+        // Turns of fuel left in a Light. Lights without this never burn out.
+        P("Fuel", "Durability"),
+
+        // Items like oil flasks that add this many turns of fuel to a wielded Light.
+        P("Refuel", "i32"),
+
         S("EmpSword"),// TODO: do we want UniqueNPC and UniqueItem?
 
         // Used for objects that are the lowest layer in a Cell, e.g. grassy ground.
@@ -83,6 +128,9 @@ fn tags() -> Vec<Tag> {
 
         P("Behavior", "Behavior"),
 
+        // Special attacks used by NPCs, see abilities.rs.
+        P("Abilities", "Vec<Ability>"),
+
         // Typically at zero durability an object will change somehow, e.g. a
         // door will become open or a character will die.
         P("Durability", "Durability"),
@@ -93,6 +141,9 @@ fn tags() -> Vec<Tag> {
         // Characters and portable objects all have names.
         P("Name", "&'static str"),
 
+        // Name the player has given to an item. This is used in place of Name.
+        P("Called", "String"),
+
         // ---- Stats --------------------------------------------------------------------
         // These don't confer any extra abilities (that's skills). Stats merely allow you
         // to do more of what you can already do.
@@ -106,6 +157,9 @@ fn tags() -> Vec<Tag> {
         // weapons and armor beause heavy weapons have a very small crit chance and heavy
         // armor significantly reduces dodge.
         P("Dexterity", "i32"),
+
+        // How far the character can see (characters without this use pov::RADIUS).
+        P("Vision", "i32"),
     ]
 }
 
@@ -127,7 +181,7 @@ fn generate_tag_file(out_dir: &str) -> Result<(), std::io::Error> {
     let mut f = File::create(&dest_path)?;
 
     // Write out the Tag enum.
-    writeln!(f, "#[derive(Clone, Debug, Eq, PartialEq)]")?;
+    writeln!(f, "#[derive(Clone, Debug, Eq, PartialEq, Serialize)]")?;
     writeln!(f, "pub enum Tag {{")?;
     let tags = tags();
     for tag in &tags {
@@ -196,7 +250,7 @@ fn generate_obj_file(out_dir: &str) -> Result<(), std::io::Error> {
                 Tag::S(_) => (),
                 Tag::P(name, arg) => {
                     let lname = name.to_lowercase();
-                    if arg.contains('<') {
+                    if arg.contains('<') || *arg == "String" {
                         // If the argument is a collection then we want to return a reference
                         // to the value (and a mutable version).
                         writeln!(f, "    pub fn {lname}_value(&self) -> Option<&{arg}> {{")?;
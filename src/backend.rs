@@ -1,51 +1,127 @@
 //! Contains the game logic, i.e. everything but rendering, user input, and program initialization.
+mod abilities;
 mod actions;
 mod ai;
+mod ambience;
 mod arena;
+mod catalog;
+mod charge;
+mod checksum;
+mod circuits;
+mod combat_events;
+mod conducts;
+mod crowd;
+mod danger;
+mod data_dir;
+mod decorations;
+mod discoveries;
+mod free_actions;
+mod fumbles;
+mod hints;
+mod intents;
 mod interactions;
+mod journal;
+mod knockback;
 mod level;
+mod lighting;
+mod loadouts;
+mod lore;
 mod make;
 mod melee;
 mod message;
+mod network;
 mod object;
+mod observation;
 mod old_pov;
+mod opportunity;
 mod persistence;
+mod planning;
+mod polymorph;
 mod pov;
 mod primitives;
+mod regeneration;
+mod routes;
+mod running;
 mod scheduler;
 mod sound;
+mod statuses;
 mod tag;
+mod targeting;
+mod teleport;
 mod time;
 
+pub use ai::AiPlan;
 pub use arena::*;
+pub use catalog::{load_catalog, set_pseudo_locale, text as catalog_text};
+pub use charge::CHARGE_DISTANCE;
+pub use checksum::Divergence;
+pub use combat_events::CombatEvent;
+pub use conducts::Conduct;
+pub use danger::Threat;
+pub use data_dir::{data_dir, data_path, migrate_legacy_files, set_data_dir};
+pub use discoveries::{Discovery, MonsterNotes};
+pub use persistence::{export_json, import_json, PlayTime, SaveInfo};
+pub use polymorph::POLYMORPH_FORMS;
+pub use pov::{set_fov_algorithm, FovAlgorithm};
 // use chrono::format::Item;
+pub use hints::Hint;
+pub use intents::{Intent, Plan};
+pub use interactions::Interactable;
+pub use journal::{Quest, QuestState};
+pub use level::Extent;
+pub use lore::load_lore;
 pub use message::{Message, Topic};
+pub use network::{serve, Client, Metrics, Observer, Policy, Query, Request, Response, SaveLock, Snapshot};
 pub use object::{ObjectName, Symbol};
+pub use observation::{observe_session, parse_action, NpcObservation, Observation, PlayerObservation};
 pub use primitives::Color;
 pub use primitives::Point;
 pub use primitives::Size;
+pub use running::Run;
+pub use scheduler::{Outcome, ScheduledObj};
+pub use statuses::Status;
 pub use tag::{Disposition, Slot};
-
+pub use targeting::{Projection, THROW_RANGE};
+pub use time::Time;
+
+use ambience::Ambience;
+use checksum::Checkpoint;
+use chrono::TimeZone;
+use circuits::Circuits;
+use combat_events::CombatEvents;
+use conducts::Conducts;
+use crowd::Crowd;
+use decorations::{Decoration, Decorations};
 use derive_more::Display;
+use discoveries::Discoveries;
+use fumbles::Fumbles;
 use interactions::{Interactions, PreHandler, PreResult};
+use journal::{Journal, QuestId};
 use level::Level;
+use lighting::Lighting;
+use loadouts::Loadouts;
 use make::new_obj;
 use object::Object;
 use old_pov::OldPoV;
+use persistence::{SaveFile, Saver};
+use planning::Plans;
+use polymorph::Polymorphs;
 use pov::PoV;
 use rand::prelude::*;
 use rand::rngs::SmallRng;
 use rand::RngCore;
 use rand_distr::StandardNormal;
+use routes::Routes;
 use scheduler::Scheduler;
 use sound::Sound;
+use statuses::Statuses;
 use std::cell::{RefCell, RefMut};
 use std::cmp::{max, min};
-use std::fs::File;
+use std::fs;
 use std::io::{Error, Write};
+use std::time::Instant;
 use tag::*;
 use tag::{Durability, Material, Tag};
-use time::Time;
 
 #[cfg(debug_assertions)]
 use fnv::FnvHashSet;
@@ -54,21 +130,26 @@ const MAX_MESSAGES: usize = 1000;
 const MAX_QUEUED_EVENTS: usize = 1_000; // TODO: make this even larger?
 const MAX_INVENTORY: usize = 25; // TODO: review this later
 
+/// Examining a cell with more objects than this gives a summary instead of a line per
+/// object (Game::examine_stack can be used to list them).
+pub const MAX_EXAMINE_LINES: usize = 5;
+
 // TODO: These numbers are not very intelligible. If that becomes an issue we could use
 // a newtype string (e.g. "wall 97") or a simple struct with a static string ref and a
 // counter.
-#[derive(Clone, Copy, Debug, Display, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Display, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Oid(u64);
 
 /// Represents what the player wants to do next. Most of these will use up the player's
-/// remaining time units, but some like (Examine) don't take any time.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+/// remaining time units, but some like (Examine) don't take any time. These are what is
+/// saved so commands that take multiple turns should be an Intent instead.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Action {
     Drop(Oid),
 
     /// Print descriptions for objects at the cell. Note that any cell can be examined but
     /// cells that are not in the player's PoV will have either an unhelpful description or
-    /// a stale description.
+    /// a stale description. This is a free action, see Game::free_action.
     Examine {
         loc: Point,
         wizard: bool,
@@ -93,6 +174,77 @@ pub enum Action {
     // Be sure to add new actions to the end (or saved games will break).
     WieldMainHand(Oid),
     WieldOffHand(Oid),
+
+    /// Player has committed to keeping a conduct (this is done at the start of a game).
+    Challenge(Conduct),
+
+    /// Drop a group of items as a single action.
+    DropMany(Vec<Oid>),
+
+    /// Remember the currently equipped items as an equipment set (index is 0, 1, or 2).
+    SaveLoadout(u8),
+
+    /// Equip the items in a saved equipment set. This is only used by old saved games,
+    /// the frontend now uses Intent::EquipSet which expands into Remove, Wear, etc.
+    SwapLoadout(u8),
+
+    /// Throw an inventory item towards a cell.
+    Throw {
+        oid: Oid,
+        target: Point,
+    },
+
+    /// Give an inventory item a custom name (an empty name restores the normal name).
+    Call {
+        oid: Oid,
+        name: String,
+    },
+
+    /// Temporarily change the character at loc into another kind of character (this is
+    /// currently only available in wizard mode).
+    Polymorph {
+        loc: Point,
+        into: ObjectName,
+    },
+
+    /// Move the player directly to loc (this is currently only available in wizard mode).
+    Teleport {
+        loc: Point,
+    },
+
+    /// Push the character in the adjacent cell one cell further away.
+    Shove {
+        dx: i32,
+        dy: i32,
+    },
+
+    /// Wizard mode was used with this game (so it shouldn't count towards the player's
+    /// profile).
+    WizardMode,
+
+    /// Pick up a group of items from the player's cell as a single action (items that
+    /// don't fit into the inventory are left behind).
+    PickUpMany(Vec<Oid>),
+
+    /// Move up to CHARGE_DISTANCE cells in a straight line and attack the enemy at the end
+    /// for extra damage.
+    Charge {
+        dx: i32,
+        dy: i32,
+    },
+
+    /// Carefully move to an adjacent cell without giving enemies a free attack (this takes
+    /// longer than a normal move).
+    Withdraw {
+        dx: i32,
+        dy: i32,
+    },
+
+    /// Use an inventory item on an adjacent cell, e.g. a key on a locked door.
+    UseOn {
+        oid: Oid,
+        target: Point,
+    },
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -103,9 +255,9 @@ pub enum ItemKind {
     Other,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct InvItem {
-    pub name: &'static str,
+    pub name: String,
     pub kind: ItemKind,
     pub equipped: Option<Slot>,
     pub oid: Oid, // used with commands like Action::Wield
@@ -117,6 +269,8 @@ pub enum Tile {
     Visible { bg: Color, fg: Color, symbol: Symbol },
     /// player can't see this but has in the past, note that this may not reflect the current state
     Stale(Symbol),
+    /// like Stale but the player hasn't seen this for a long time (only with the fog of memory challenge)
+    Faded(Symbol),
     /// player has never seen this location (and it may not exist)
     NotVisible,
 }
@@ -129,12 +283,27 @@ pub enum State {
     LostGame,
 }
 
+/// How hurt an NPC looks.
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+pub enum Wounds {
+    #[display(fmt = "unharmed")]
+    Unharmed,
+
+    #[display(fmt = "bloodied")]
+    Bloodied,
+
+    #[display(fmt = "near death")]
+    NearDeath,
+}
+
 /// Used for NPCs visible to the player.
 pub struct Npc {
+    pub loc: Point,
     pub letter: char,
     pub color: Color,
     pub observed_hps: (i32, i32), // current and max where max is in [1, 10] (based on perception)
     pub actual_hps: Option<(i32, i32)>, // set if wizard mode
+    pub wounds: Option<Wounds>,   // set if wizard mode or the player has seen this kind of NPC fight
     pub name: &'static str,
     pub disposition: Disposition,
     pub is_sleeping: bool,
@@ -142,74 +311,91 @@ pub struct Npc {
 
 /// Top-level backend object encapsulating the game state.
 pub struct Game {
-    stream: Vec<Action>, // used to reconstruct games
-    file: Option<File>,  // actions are perodically saved here
-    state: State,        // game milestones, eg won game
+    stream: Vec<Action>,          // used to reconstruct games
+    checkpoints: Vec<Checkpoint>, // saved along with stream
+    saver: Option<Saver>,         // actions are perodically saved here (via a worker thread)
+    path: String,                 // where the game is saved
+    seed: u64,
+    num_actions: u64,                    // includes actions that have already been saved
+    next_checkpoint: u64,                // action index at which the next checkpoint is due
+    replay_checkpoints: Vec<Checkpoint>, // checkpoints from the saved game that haven't been verified yet
+    divergence: Option<Divergence>,      // set if replay didn't match the saved game
+    play_time: PlayTime,                 // totals from previous sessions
+    session_start: Instant,
+    state: State,         // game milestones, eg won game
+    wizard: bool,         // true if wizard mode has been used with this game
+    combat_details: bool, // wizard mode breakdowns of attacks (not saved)
     rng: RefCell<SmallRng>,
     scheduler: Scheduler,
 
     level: Level,
     players_move: bool,
 
-    messages: Vec<Message>,     // messages shown to the player
-    interactions: Interactions, // double dispatch action tables, e.g. player vs door
-    pov: PoV,                   // locations that the player can currently see
-    old_pov: OldPoV,            // locations that the user has seen in the past (this will often be stale data)
+    messages: Vec<Message>,      // messages shown to the player
+    messages_removed: usize,     // number of messages discarded from the front of messages
+    hints: Vec<Hint>,            // tutorial hints that the UI hasn't yet processed
+    journal: Journal,            // quests the player is working on
+    conducts: Conducts,          // voluntary restrictions the player has kept (or broken)
+    discoveries: Discoveries,    // kinds of objects the player has seen
+    loadouts: Loadouts,          // equipment sets
+    polymorphs: Polymorphs,      // characters that are temporarily in another form
+    routes: Routes,              // cached paths for NPCs far from the player
+    plans: Plans,                // paths found in parallel for NPCs in the current round
+    statuses: Statuses,          // temporary effects on characters
+    circuits: Circuits,          // levers and pressure plates linked to doors
+    ambience: Ambience,          // flavor messages that are occasionally shown
+    crowd: Crowd,                // spectators, their regions, and their bets
+    lighting: Lighting,          // dark regions of the level
+    fumbles: Fumbles,            // counts of the player's fumbled attacks
+    noises: Vec<(Point, Sound)>, // noises made since the player's last turn (for wizard mode)
+    decorations: Decorations,    // cosmetic marks on cells, e.g. blood
+    combat_events: CombatEvents, // damage and statuses for UI effects
+    interactions: Interactions,  // double dispatch action tables, e.g. player vs door
+    pov: PoV,                    // locations that the player can currently see
+    old_pov: OldPoV,             // locations that the user has seen in the past (this will often be stale data)
 }
 
 // Public API.
 impl Game {
     /// Start a brand new game and save it to path.
     pub fn new_game(path: &str, seed: u64) -> Game {
-        let mut messages = Vec::new();
-
-        info!("new {path}");
-        let file = match persistence::new_game(path, seed) {
-            Ok(se) => Some(se),
-            Err(err) => {
-                messages.push(Message::new(
-                    Topic::Error,
-                    &format!("Couldn't open {path} for writing: {err}"),
-                ));
-                None
-            }
-        };
-
-        messages.push(Message {
-            topic: Topic::Important,
-            text: String::from("Welcome to 1k-deaths!"),
-        });
-        messages.push(Message {
-            topic: Topic::Important,
-            text: String::from("Are you the hero who will destroy the Crippled God's sword?"),
-        });
-        messages.push(Message {
-            topic: Topic::Important,
-            text: String::from("Press the '?' key for help."),
-        });
+        Game::start_game(path, seed, pov::new_game_fov_algorithm())
+    }
 
-        Game::new(messages, seed, file)
+    /// A game that is never saved, e.g. the backdrop for the title screen.
+    pub fn new_unsaved(seed: u64) -> Game {
+        Game::new(Vec::new(), "", seed, pov::new_game_fov_algorithm(), None)
     }
 
     /// Load a saved game and return the actions so that they can be replayed.
     pub fn old_game(path: &str, warnings: Vec<String>) -> (Game, Vec<Action>) {
         let mut seed = 1;
+        let mut fov = FovAlgorithm::default();
         let mut actions = Vec::new();
+        let mut checkpoints = Vec::new();
+        let mut play_time = PlayTime::default();
         let mut messages = Vec::new();
 
         let mut file = None;
         info!("loading {path}");
         match persistence::load_game(path) {
-            Ok((s, a)) => {
-                seed = s;
-                actions = a;
+            Ok(saved) => {
+                seed = saved.seed;
+                fov = saved.fov;
+                actions = saved.actions;
+                checkpoints = saved.checkpoints;
+                play_time = saved.play_time;
+                messages.extend(saved.warnings.iter().map(|w| Message::new(Topic::Warning, w)));
+                if let Some(&last) = saved.timestamps.last() {
+                    if let chrono::LocalResult::Single(date) = chrono::Local.timestamp_millis_opt(last) {
+                        info!("{path} was last saved on {}", date.to_rfc2822());
+                    }
+                }
             }
             Err(err) => {
                 info!("loading file had err: {err}");
-                messages.push(Message::new(
-                    Topic::Error,
-                    &format!("Couldn't open {path} for reading: {err}"),
-                ));
+                let text = catalog::text("save.cant_read", &[("path", &path), ("err", &err)]);
+                messages.push(Message::new(Topic::Error, &text));
             }
         };
 
@@ -218,10 +404,8 @@ impl Game {
             file = match persistence::open_game(path) {
                 Ok(se) => Some(se),
                 Err(err) => {
-                    messages.push(Message::new(
-                        Topic::Error,
-                        &format!("Couldn't open {path} for appending: {err}"),
-                    ));
+                    let text = catalog::text("save.cant_append", &[("path", &path), ("err", &err)]);
+                    messages.push(Message::new(Topic::Error, &text));
                     None
                 }
             };
@@ -230,7 +414,13 @@ impl Game {
         messages.extend(warnings.iter().map(|w| Message::new(Topic::Warning, w)));
 
         if file.is_some() {
-            (Game::new(messages, seed, file), actions)
+            let mut game = Game::new(messages, path, seed, fov, file);
+            game.replay_checkpoints = checkpoints;
+            game.play_time.played = play_time.played;
+            game.play_time.sessions += play_time.sessions;
+            let text = catalog::text("game.welcome_back", &[("played", &game.play_time)]);
+            game.messages.push(Message::new(Topic::Normal, &text));
+            (game, actions)
         } else {
             let mut game = Game::new_game(path, seed);
             game.messages.extend(messages);
@@ -238,12 +428,33 @@ impl Game {
         }
     }
 
+    /// Returns summaries for the saved games in dir (sorted so that the most recently
+    /// modified games are first).
+    pub fn saved_games(dir: &str) -> Vec<SaveInfo> {
+        let mut games = Vec::new();
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "game") {
+                    let modified = entry.metadata().and_then(|m| m.modified()).ok();
+                    let path = path.to_string_lossy().to_string();
+                    match persistence::read_info(&path) {
+                        Ok(info) => games.push((modified, info)),
+                        Err(err) => info!("skipping {path}: {err}"),
+                    }
+                }
+            }
+        }
+        games.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        games.into_iter().map(|(_, info)| info).collect()
+    }
+
     pub fn dump_state<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         self.dump_pov(writer)?;
         self.scheduler.dump(writer, self)
     }
 
-    pub fn recent_messages(&self, limit: usize) -> impl Iterator<Item = &Message> {
+    pub fn recent_messages(&self, limit: usize) -> impl DoubleEndedIterator<Item = &Message> {
         let iter = self.messages.iter();
         if limit < self.messages.len() {
             iter.skip(self.messages.len() - limit)
@@ -252,6 +463,12 @@ impl Game {
         }
     }
 
+    /// Returns the number of messages that have been added, including those that have been
+    /// discarded. UIs can use this to figure out which messages are new.
+    pub fn num_messages(&self) -> usize {
+        self.messages_removed + self.messages.len()
+    }
+
     pub fn add_mesg(&mut self, mesg: Message) {
         self.messages.push(mesg);
     }
@@ -275,35 +492,85 @@ impl Game {
     // Either we need to allow the player to move or we need to re-render because an
     // obhect did something.
     pub fn advance_time(&mut self, replay: bool) {
-        if Scheduler::player_is_ready(self) {
+        let was_over = self.game_over();
+        if Scheduler::player_is_ready(self, replay) {
+            if !self.players_move {
+                PoV::refresh(self);
+                Discoveries::update(self);
+            }
             self.players_move = true;
         } else {
             if !replay {
-                self.stream.push(Action::Object);
+                self.push_action(Action::Object);
             }
             OldPoV::update(self);
             PoV::refresh(self);
         }
+        self.expire_polymorphs();
+        self.expire_statuses();
+        self.check_game_over(was_over);
     }
 
     pub fn player_acted(&mut self, action: Action) {
         self.do_player_acted(action, false);
     }
 
+    /// Note that Action::Object entries only record that objects did something: the
+    /// objects are actually given a chance to act when the player's turn is reached.
     pub fn replay_action(&mut self, action: Action) {
         if let Action::Object = action {
-            self.advance_time(true);
+            self.num_actions += 1;
         } else {
+            while !self.players_turn() {
+                self.advance_time(true);
+            }
             self.do_player_acted(action, true);
         }
     }
 
+    /// Returns the player actions that haven't yet been written to the saved game (oldest
+    /// first). This is intended for tests.
+    pub fn unsaved_actions(&self) -> Vec<&Action> {
+        self.stream.iter().filter(|a| !matches!(a, Action::Object)).collect()
+    }
+
+    /// Returns the total time spent playing this game (including earlier sessions).
+    pub fn play_time(&self) -> PlayTime {
+        let elapsed = self.session_start.elapsed().as_millis() as u64;
+        PlayTime {
+            played: self.play_time.played + elapsed,
+            sessions: self.play_time.sessions,
+        }
+    }
+
+    /// Returns whether the player is still adventuring or how the game ended.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn used_wizard_mode(&self) -> bool {
+        self.wizard
+    }
+
+    pub fn quests(&self) -> &[Quest] {
+        self.journal.quests()
+    }
+
+    /// Returns information about where the replay went wrong if replaying a saved game
+    /// didn't produce the same results as the original game.
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
     /// If loc is valid and within the player's Field if View (FoV) then return the terrain.
     /// Otherwise return None.
     pub fn tile(&self, loc: &Point) -> Tile {
         let tile = if self.pov.visible(self, loc) {
             let (_, obj) = self.level.get_bottom(loc);
-            let bg = obj.to_bg_color();
+            let bg = match self.decorations.get(loc) {
+                Some(decoration) if obj.to_bg_color() == Color::Black => decoration.bg_color(),
+                _ => obj.to_bg_color(),
+            };
 
             let (_, obj) = self.level.get_top(loc);
             let (fg, symbol) = obj.to_fg_symbol();
@@ -311,6 +578,16 @@ impl Game {
             Tile::Visible { bg, fg, symbol }
         } else {
             match self.old_pov.get(loc) {
+                Some(symbol) if self.challenges().contains(&Conduct::FogOfMemory) => {
+                    let age = self.old_pov.age(loc, self.now()).unwrap();
+                    if age >= time::MEMORY_DECAY {
+                        Tile::NotVisible // forgotten (but not yet pruned)
+                    } else if age >= time::MEMORY_DECAY / 2 {
+                        Tile::Faded(*symbol)
+                    } else {
+                        Tile::Stale(*symbol)
+                    }
+                }
                 Some(symbol) => Tile::Stale(*symbol),
                 None => Tile::NotVisible, // not visible and never seen
             }
@@ -319,6 +596,36 @@ impl Game {
         tile
     }
 
+    /// Returns the top-left and bottom-right locations the player has seen (or None if
+    /// the player hasn't seen anything yet).
+    pub fn explored_extents(&self) -> Option<(Point, Point)> {
+        let (top_left, bottom_right) = self.old_pov.extents()?;
+        let (min, max) = self.level.bounds();
+        Some((
+            Point::new(top_left.x.max(min.x), top_left.y.max(min.y)),
+            Point::new(bottom_right.x.min(max.x), bottom_right.y.min(max.y)),
+        ))
+    }
+
+    /// Returns the top-left and bottom-right locations of the level. Note that for
+    /// unbounded levels this will grow as the level is changed.
+    pub fn level_bounds(&self) -> (Point, Point) {
+        self.level.bounds()
+    }
+
+    pub fn level_size(&self) -> Size {
+        Size::new(self.level.width(), self.level.height())
+    }
+
+    pub fn level_extent(&self) -> Extent {
+        self.level.extent()
+    }
+
+    /// Levels are bounded by default.
+    pub fn set_level_extent(&mut self, extent: Extent) {
+        self.level.set_extent(extent);
+    }
+
     pub fn target_next(&self, old_loc: &Point, delta: i32) -> Option<Point> {
         // Find the NPCs near the player that are actually visible to the player.
         let chars: Vec<Point> = self
@@ -335,6 +642,9 @@ impl Game {
             })
             .filter(|loc| self.pov.visible(self, &loc))
             .collect();
+        if chars.is_empty() {
+            return None;
+        }
 
         // Find the Character closest to old_loc.
         let mut index = 0;
@@ -412,61 +722,212 @@ impl Game {
         }
 
         for (oid, obj) in self.player_inv_iter() {
-            let kind = if obj.has(WEAPON_ID) {
-                match obj.weapon_value().unwrap() {
-                    Weapon::OneHand => ItemKind::OneHandWeapon,
-                    Weapon::TwoHander => ItemKind::TwoHandWeapon,
-                }
-            } else if obj.has(ARMOR_ID) {
-                ItemKind::Armor
-            } else {
-                ItemKind::Other
-            };
-            self.push_inv_item(&mut items, kind, None, oid);
+            self.push_inv_item(&mut items, item_kind(obj), None, oid);
         }
 
         items
     }
 
+    /// Returns the items in the player's cell that can be picked up.
+    pub fn items_here(&self) -> Vec<InvItem> {
+        let mut items = Vec::new();
+        for (oid, obj) in self.level.cell_iter(&self.player_loc()) {
+            if obj.has(PORTABLE_ID) {
+                self.push_inv_item(&mut items, item_kind(obj), None, oid);
+            }
+        }
+        items
+    }
+
+    /// Returns a description of the player's cell: the terrain followed by any items.
+    pub fn describe_floor(&self) -> Vec<String> {
+        let loc = self.player_loc();
+        let terrain = self.level.get_bottom(&loc).1;
+        let mut lines = vec![catalog::text(
+            "floor.standing_on",
+            &[("terrain", &terrain.description())],
+        )];
+        let items = self.items_here();
+        if items.is_empty() {
+            lines.push(catalog::text("floor.no_items", &[]));
+        } else {
+            lines.push(catalog::text("floor.items", &[]));
+            lines.extend(items.iter().map(|item| format!("   {}", item.name)));
+        }
+        lines
+    }
+
+    /// Returns the number of inventory slots used by the player and the number available.
+    pub fn inventory_space(&self) -> (usize, usize) {
+        let player = self.level.get(&self.player_loc(), CHARACTER_ID).unwrap().1;
+        (player.inventory_value().unwrap().len(), MAX_INVENTORY)
+    }
+
     // TODO:
     // should we check the Strength and Dexterity tags?
     // should we check the Durability tag?
     pub fn describe_item(&self, oid: Oid) -> Vec<String> {
-        let mut desc = Vec::new();
         let obj = self.level.obj(oid).0;
-        desc.push(obj.description().to_string());
-        if let Some(weapon) = obj.weapon_value() {
-            let suffix = match weapon {
-                Weapon::OneHand => {
-                    desc.push("It is a one handed weapon.".to_string());
-                    " in main hand"
-                }
-                Weapon::TwoHander => {
-                    desc.push("It is a two handed weapon.".to_string());
-                    ""
-                }
-            };
+        let mut desc = vec![obj.description()];
+        desc.extend(item_stats(obj));
+        desc
+    }
 
-            if let Some(damage) = obj.damage_value() {
-                if let Some(delay) = obj.delay_value() {
-                    // TODO: need to account for stats (and penalties)
-                    let dps = (damage as f64) / ((delay.as_ms() as f64) / 1000.0);
-                    desc.push(format!(
-                        "Base damage is {damage} with delay {delay} ({dps:.1} dps{suffix})."
-                    ));
-                }
+    /// Rebuilds the game without the last turns player actions. Note that this rewrites
+    /// the saved game. This is intended for wizard mode.
+    pub fn undo(&mut self, turns: usize) {
+        // Ensure that everything has been written out to the saved game.
+        self.save_actions();
+        self.saver = None;
+
+        let loaded = persistence::load_game(&self.path);
+        self.saver = persistence::open_game(&self.path).ok().map(Saver::new);
+        let actions = match loaded {
+            Ok(saved) => saved.actions,
+            Err(err) => {
+                let mesg = catalog::text("undo.cant_load", &[("path", &self.path), ("err", &err)]);
+                self.messages.push(Message::new(Topic::Error, &mesg));
+                return;
             }
-            if let Some(percent) = obj.crit_value() {
-                // TODO: does crit chance work differently for off hand?
-                // TODO: need to account for stats (and penalties)
-                // TODO: this should probably be factored into dps
-                desc.push(format!("It has a {percent}% to critically hit."));
+        };
+        let mut actions: Vec<Action> = actions.into_iter().filter(|a| !matches!(a, Action::Object)).collect();
+
+        // Undo itself is a wizard command so we always want to keep the WizardMode marker.
+        actions.retain(|a| !matches!(a, Action::WizardMode));
+        let turns = min(turns, actions.len());
+        actions.truncate(actions.len() - turns);
+        if self.wizard {
+            actions.insert(0, Action::WizardMode);
+        }
+        info!("undoing {turns} turns ({} turns left)", actions.len());
+
+        // We don't have snapshots so we need to replay everything (but the replay is not
+        // rendered so this should be fairly quick). We're replaying as if the player was
+        // doing the actions so that the saved game is rewritten along with its checkpoints.
+        // That's done with a temporary file so that the saved game is intact if something
+        // goes wrong while replaying.
+        let temp_path = format!("{}.undo", self.path);
+        let mut game = Game::start_game(&temp_path, self.seed, self.pov.algorithm());
+        for action in actions {
+            while !game.players_turn() {
+                game.advance_time(false);
             }
+            game.player_acted(action);
         }
-        if let Some(percent) = obj.mitigation_value() {
-            desc.push(format!("It will mitigate damage by {percent}%."));
+        game.save_actions();
+        game.saver = None;
+        if let Err(err) = fs::rename(&temp_path, &self.path) {
+            let mesg = catalog::text("undo.cant_replace", &[("path", &self.path), ("err", &err)]);
+            self.messages.push(Message::new(Topic::Error, &mesg));
+            let _ = fs::remove_file(&temp_path);
+            return;
         }
-        desc
+        game.path = self.path.clone();
+        game.saver = persistence::open_game(&game.path).ok().map(Saver::new);
+
+        game.play_time = self.play_time();
+        game.session_start = Instant::now();
+        game.messages.clear();
+        let text = catalog::text("undo.done", &[("turns", &turns)]);
+        game.messages.push(Message::new(Topic::Important, &text));
+        *self = game;
+    }
+
+    /// Returns the game as it was the last turns player actions ago along with the actions
+    /// that remain so that the player can replay what led to their death. The returned
+    /// game is never saved.
+    pub fn review(&mut self, turns: usize) -> Option<(Game, Vec<Action>)> {
+        // Ensure that everything has been written out to the saved game.
+        self.save_actions();
+        self.saver = None;
+
+        let loaded = persistence::load_game(&self.path);
+        self.saver = persistence::open_game(&self.path).ok().map(Saver::new);
+        let actions = match loaded {
+            Ok(saved) => saved.actions,
+            Err(err) => {
+                let mesg = catalog::text("review.cant_load", &[("path", &self.path), ("err", &err)]);
+                self.messages.push(Message::new(Topic::Error, &mesg));
+                return None;
+            }
+        };
+
+        // Object actions are replayed along with the player actions so that the review
+        // matches what actually happened.
+        let mut index = actions.len();
+        let mut count = 0;
+        while index > 0 && count < turns {
+            index -= 1;
+            if !matches!(actions[index], Action::Object) {
+                count += 1;
+            }
+        }
+        info!("reviewing {count} turns ({} actions)", actions.len() - index);
+
+        let mut game = Game::new(Vec::new(), "", self.seed, self.pov.algorithm(), None);
+        let mut actions = actions;
+        let tail = actions.split_off(index);
+        for action in actions {
+            game.replay_action(action);
+        }
+        game.messages.clear();
+        Some((game, tail))
+    }
+
+    /// Writes a copy of the saved game to path. Note that the game continues to be saved
+    /// to its original path.
+    pub fn save_copy(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // Dropping the saver ensures that everything has been written out.
+        self.save_actions();
+        self.saver = None;
+
+        let result = fs::copy(&self.path, path);
+        self.saver = persistence::open_game(&self.path).ok().map(Saver::new);
+        result?;
+        info!("copied {} to {path}", self.path);
+        Ok(())
+    }
+
+    /// Returns a report on memory usage. This is intended for wizard mode.
+    pub fn audit(&self) -> Vec<String> {
+        let mut report = Vec::new();
+        self.level.audit(&mut report);
+        self.old_pov.audit(&mut report);
+        report.push(format!(
+            "messages: {} (capacity {}, {} bytes)",
+            self.messages.len(),
+            self.messages.capacity(),
+            persistence::encoded_size(&self.messages)
+        ));
+        report.push(format!(
+            "unsaved actions: {} (capacity {}, {} bytes), checkpoints: {} ({} bytes)",
+            self.stream.len(),
+            self.stream.capacity(),
+            persistence::encoded_size(&self.stream),
+            self.checkpoints.len(),
+            persistence::encoded_size(&self.checkpoints)
+        ));
+        report.push(format!("scheduled objects: {}", self.scheduler.num_entries()));
+        report
+    }
+
+    /// Releases excess memory and returns a report on memory usage. This is intended for
+    /// wizard mode.
+    pub fn compact(&mut self) -> Vec<String> {
+        self.level.compact();
+        self.old_pov.compact();
+        self.messages.shrink_to_fit();
+        self.stream.shrink_to_fit();
+        self.audit()
+    }
+
+    /// Wizard mode option to add a message breaking down the math behind each attack.
+    pub fn set_combat_details(&mut self, enable: bool) {
+        self.combat_details = enable;
+    }
+
+    pub fn combat_details(&self) -> bool {
+        self.combat_details
     }
 
     #[cfg(debug_assertions)]
@@ -478,12 +939,42 @@ impl Game {
 
 // Backend methods.
 impl Game {
-    fn new(messages: Vec<Message>, seed: u64, file: Option<File>) -> Game {
+    fn start_game(path: &str, seed: u64, fov: FovAlgorithm) -> Game {
+        let mut messages = Vec::new();
+
+        info!("new {path}");
+        let file = match persistence::new_game(path, seed, fov) {
+            Ok(se) => Some(se),
+            Err(err) => {
+                let text = catalog::text("save.cant_write", &[("path", &path), ("err", &err)]);
+                messages.push(Message::new(Topic::Error, &text));
+                None
+            }
+        };
+
+        messages.push(Message::new(Topic::Important, &catalog::text("game.welcome", &[])));
+        messages.push(Message::new(Topic::Important, &catalog::text("game.help", &[])));
+
+        Game::new(messages, path, seed, fov, file)
+    }
+
+    fn new(messages: Vec<Message>, path: &str, seed: u64, fov: FovAlgorithm, file: Option<SaveFile>) -> Game {
         info!("using seed {seed}");
         let mut game = Game {
             stream: Vec::new(),
-            file,
+            checkpoints: Vec::new(),
+            saver: file.map(Saver::new),
+            path: path.to_string(),
+            seed,
+            num_actions: 0,
+            next_checkpoint: 0,
+            replay_checkpoints: Vec::new(),
+            divergence: None,
+            play_time: PlayTime { played: 0, sessions: 1 },
+            session_start: Instant::now(),
             state: State::Adventuring,
+            wizard: false,
+            combat_details: false,
             scheduler: Scheduler::new(),
 
             // TODO: SmallRng is not guaranteed to be portable so results may
@@ -494,11 +985,31 @@ impl Game {
             players_move: false,
 
             messages,
+            messages_removed: 0,
+            hints: Vec::new(),
+            journal: Journal::new(),
+            conducts: Conducts::new(),
+            discoveries: Discoveries::new(),
+            loadouts: Loadouts::new(),
+            polymorphs: Polymorphs::new(),
+            routes: Routes::new(),
+            plans: Plans::new(),
+            statuses: Statuses::new(),
+            circuits: Circuits::new(),
+            ambience: Ambience::new(seed),
+            crowd: Crowd::new(),
+            lighting: Lighting::new(),
+            fumbles: Fumbles::default(),
+            noises: Vec::new(),
+            decorations: Decorations::new(),
+            combat_events: CombatEvents::new(),
             interactions: Interactions::new(),
-            pov: PoV::new(),
+            pov: PoV::new(fov),
             old_pov: OldPoV::new(),
         };
         game.init_game(include_str!("backend/maps/start.txt"));
+        game.journal.start(QuestId::DestroySword);
+        game.journal.start(QuestId::KillRhulad);
         game
     }
 
@@ -508,36 +1019,154 @@ impl Game {
 
         OldPoV::update(self);
         PoV::refresh(self);
+        Discoveries::update(self);
     }
 
     fn game_over(&self) -> bool {
         matches!(self.state, State::LostGame | State::WonGame)
     }
 
+    fn check_game_over(&mut self, was_over: bool) {
+        if !was_over && self.game_over() {
+            if self.state == State::WonGame {
+                let text = catalog::text("quest.destroy_sword.done", &[]);
+                self.journal.complete(QuestId::DestroySword, &text);
+            } else {
+                self.journal.fail_active();
+            }
+            let text = catalog::text("game.played", &[("played", &self.play_time())]);
+            let mesg = Message::new(Topic::Important, &text);
+            self.messages.push(mesg);
+            let mesg = Message::new(Topic::Important, &self.conducts_summary());
+            self.messages.push(mesg);
+            if let Some(text) = self.fumbles_summary() {
+                self.messages.push(Message::new(Topic::Important, &text));
+            }
+        }
+    }
+
     fn do_player_acted(&mut self, action: Action, replay: bool) {
         // TODO: probably want to return something to indicate whether a UI refresh is neccesary
         // TODO: maybe something fine grained, like only need to update messages
         trace!("player is doing {action:?}");
+        self.noises.clear();
+        let was_over = self.game_over();
         let duration = match action {
             Action::Drop(oid) => {
                 // TODO: dropping heavy stuff should cause noise?
                 if !self.game_over() {
-                    let player = self.level.get_mut(&self.player_loc(), CHARACTER_ID).unwrap().1;
-                    let equipped = player.equipped_value_mut().unwrap();
-                    if let Some(slot) = equipped
-                        .iter()
-                        .find_map(|(s, &o)| if o == Some(oid) { Some(s) } else { None })
-                    {
-                        self.drop_equipped(oid, slot);
+                    if self.drop_item(oid) {
                         time::DIAGNOL_MOVE
                     } else {
-                        self.drop_unequipped(oid);
                         time::DIAGNOL_MOVE / 2
                     }
                 } else {
                     Time::zero()
                 }
             }
+            Action::DropMany(ref oids) => {
+                // Equipped items have to be removed one by one but unequipped items can
+                // all be dropped at once.
+                if !self.game_over() && !oids.is_empty() {
+                    let mut delay = time::DIAGNOL_MOVE / 2;
+                    for &oid in oids.iter() {
+                        if self.drop_item(oid) {
+                            delay += time::DIAGNOL_MOVE / 2;
+                        }
+                    }
+                    delay
+                } else {
+                    Time::zero()
+                }
+            }
+            Action::Challenge(conduct) => {
+                self.do_challenge(conduct);
+                Time::zero()
+            }
+            Action::WizardMode => {
+                self.wizard = true;
+                Time::zero()
+            }
+            Action::Charge { dx, dy } => {
+                assert!((-1..=1).contains(&dx));
+                assert!((-1..=1).contains(&dy));
+                assert!(dx != 0 || dy != 0);
+                if !self.game_over() {
+                    let player_loc = self.player_loc();
+                    self.do_charge(&player_loc, dx, dy)
+                } else {
+                    Time::zero()
+                }
+            }
+            Action::Withdraw { dx, dy } => {
+                assert!((-1..=1).contains(&dx));
+                assert!((-1..=1).contains(&dy));
+                assert!(dx != 0 || dy != 0);
+                if !self.game_over() {
+                    self.do_withdraw(dx, dy)
+                } else {
+                    Time::zero()
+                }
+            }
+            Action::PickUpMany(ref oids) => {
+                if !self.game_over() {
+                    self.pick_up_many(oids)
+                } else {
+                    Time::zero()
+                }
+            }
+            Action::SaveLoadout(index) => {
+                self.save_loadout(index);
+                Time::zero()
+            }
+            Action::SwapLoadout(index) => {
+                if !self.game_over() {
+                    self.swap_loadout(index)
+                } else {
+                    Time::zero()
+                }
+            }
+            Action::UseOn { oid, target } => {
+                if !self.game_over() {
+                    self.use_on(oid, &target)
+                } else {
+                    Time::zero()
+                }
+            }
+            Action::Throw { oid, target } => {
+                if !self.game_over() {
+                    self.do_throw(oid, &target)
+                } else {
+                    Time::zero()
+                }
+            }
+            Action::Call { oid, ref name } => {
+                self.do_call(oid, name);
+                Time::zero()
+            }
+            Action::Polymorph { loc, into } => {
+                if !self.game_over() {
+                    self.do_polymorph(&loc, into);
+                }
+                Time::zero()
+            }
+            Action::Teleport { loc } => {
+                if !self.game_over() && self.do_teleport(Oid(0), &loc) {
+                    time::CARDINAL_MOVE
+                } else {
+                    Time::zero()
+                }
+            }
+            Action::Shove { dx, dy } => {
+                assert!((-1..=1).contains(&dx));
+                assert!((-1..=1).contains(&dy));
+                assert!(dx != 0 || dy != 0);
+                if !self.game_over() {
+                    self.do_shove(dx, dy)
+                } else {
+                    Time::zero()
+                }
+            }
             Action::Examine { loc, wizard } => {
                 self.examine(&loc, wizard);
                 Time::zero()
@@ -548,23 +1177,33 @@ impl Game {
                 assert!(dx != 0 || dy != 0);
                 if !self.game_over() {
                     let player = self.player_loc();
-                    let new_loc = Point::new(player.x + dx, player.y + dy);
-                    match self.try_interact(&player, &new_loc) {
-                        PreResult::Acted(taken, sound) => {
-                            assert!(taken > Time::zero());
-                            self.handle_noise(&self.player_loc(), sound);
-                            taken
-                        }
-                        PreResult::ZeroAction => Time::zero(),
-                        PreResult::DidntAct => {
-                            let old_loc = self.player_loc();
-                            self.do_move(Oid(0), &old_loc, &new_loc);
-                            let (duration, volume) = self.interact_post_move(&new_loc);
-                            self.handle_noise(&new_loc, sound::QUIET + volume);
-                            if old_loc.diagnol(&new_loc) {
-                                time::DIAGNOL_MOVE + duration
-                            } else {
-                                time::CARDINAL_MOVE + duration
+                    let new_loc = self.stagger(Oid(0), &player, Point::new(player.x + dx, player.y + dy));
+                    let (dx, dy) = (new_loc.x - player.x, new_loc.y - player.y);
+                    if let Some(taken) = self.player_reach_attack(dx, dy) {
+                        self.handle_noise(&player, sound::QUIET);
+                        taken
+                    } else {
+                        match self.try_interact(&player, &new_loc) {
+                            PreResult::Acted(taken, sound) => {
+                                assert!(taken > Time::zero());
+                                self.handle_noise(&self.player_loc(), sound);
+                                taken
+                            }
+                            PreResult::ZeroAction => Time::zero(),
+                            PreResult::DidntAct => {
+                                let old_loc = self.player_loc();
+                                if !self.opportunity_attacks(&old_loc, &new_loc) {
+                                    time::CARDINAL_MOVE // killed or knocked back while moving away
+                                } else {
+                                    self.do_move(Oid(0), &old_loc, &new_loc);
+                                    let (duration, volume) = self.interact_post_move(&new_loc);
+                                    self.handle_noise(&new_loc, sound::QUIET + volume);
+                                    if old_loc.diagnol(&new_loc) {
+                                        time::DIAGNOL_MOVE + duration
+                                    } else {
+                                        time::CARDINAL_MOVE + duration
+                                    }
+                                }
                             }
                         }
                     }
@@ -596,6 +1235,7 @@ impl Game {
                         delay += time::DIAGNOL_MOVE;
                     }
                     self.wear(oid);
+                    self.break_conduct(Conduct::NoArmor);
                     delay
                 } else {
                     Time::zero()
@@ -631,22 +1271,44 @@ impl Game {
         if duration > Time::zero() {
             self.scheduler.player_acted(duration, &self.rng);
             self.players_move = false;
+            self.burn_fuel();
 
             OldPoV::update(self);
             PoV::refresh(self);
         }
 
-        if !replay {
-            self.stream.push(action);
+        let index = self.num_actions;
+        if replay {
+            self.num_actions += 1;
+            if let Some(checkpoint) = self.checkpoint_due(index) {
+                self.verify_checkpoint(checkpoint);
+            }
+        } else {
+            self.push_action(action);
+            if let Some(checkpoint) = self.checkpoint_due(index) {
+                self.checkpoints.push(checkpoint);
+            }
             if self.stream.len() >= MAX_QUEUED_EVENTS {
                 self.save_actions();
             }
+            self.report_save_errors();
         }
+        self.check_game_over(was_over);
+        self.trim_messages();
+    }
+
+    fn trim_messages(&mut self) {
         while self.messages.len() > MAX_MESSAGES {
             self.messages.remove(0); // TODO: this is an O(N) operation for Vec, may want to switch to circular_queue
+            self.messages_removed += 1;
         }
     }
 
+    fn push_action(&mut self, action: Action) {
+        self.stream.push(action);
+        self.num_actions += 1;
+    }
+
     // TODO: Not sure we'll need this in the future.
     fn loc(&self, oid: Oid) -> Option<Point> {
         self.level.try_loc(oid)
@@ -713,14 +1375,20 @@ impl Game {
         }
     }
 
-    fn manage_item_mesg(&mut self, oid: Oid, action: &str) {
+    /// Returns the name the player has given the item or its normal name.
+    pub fn item_name(&self, oid: Oid) -> String {
         let obj = self.level.obj(oid).0;
-        let name: &'static str = obj.name_value().unwrap();
-        let mesg = Message {
-            topic: Topic::Normal,
-            text: format!("You {action} the {name}."),
-        };
-        self.messages.push(mesg);
+        match obj.called_value() {
+            Some(name) => name.clone(),
+            None => obj.name_value().unwrap().to_string(),
+        }
+    }
+
+    // key is the catalog key for the message, e.g. "item.drop".
+    fn manage_item_mesg(&mut self, oid: Oid, key: &str) {
+        let name = self.item_name(oid);
+        let text = catalog::text(key, &[("name", &name)]);
+        self.messages.push(Message::new(Topic::Normal, &text));
     }
 
     fn wield(&mut self, oid: Oid, slot: Slot) {
@@ -754,7 +1422,7 @@ impl Game {
             if cfg!(debug_assertions) {
                 player.invariant();
             }
-            self.manage_item_mesg(oid, "wield"); // at the very end to satisfy the borrow checker
+            self.manage_item_mesg(oid, "item.wield"); // at the very end to satisfy the borrow checker
         }
 
         assert!(self.level.obj(oid).1.is_none()); // oid must exist and not have a loc
@@ -779,6 +1447,22 @@ impl Game {
         blocks
     }
 
+    // Returns true if the item was equipped.
+    fn drop_item(&mut self, oid: Oid) -> bool {
+        let player = self.level.get_mut(&self.player_loc(), CHARACTER_ID).unwrap().1;
+        let equipped = player.equipped_value_mut().unwrap();
+        if let Some(slot) = equipped
+            .iter()
+            .find_map(|(s, &o)| if o == Some(oid) { Some(s) } else { None })
+        {
+            self.drop_equipped(oid, slot);
+            true
+        } else {
+            self.drop_unequipped(oid);
+            false
+        }
+    }
+
     fn wear(&mut self, oid: Oid) {
         {
             let obj = self.level.obj(oid).0;
@@ -796,7 +1480,7 @@ impl Game {
             if cfg!(debug_assertions) {
                 player.invariant();
             }
-            self.manage_item_mesg(oid, "worn"); // at the very end to satisfy the borrow checker
+            self.manage_item_mesg(oid, "item.wear"); // at the very end to satisfy the borrow checker
         }
 
         assert!(self.level.obj(oid).1.is_none()); // oid must exist and not have a loc
@@ -809,8 +1493,7 @@ impl Game {
         assert!(equipped[slot] == Some(oid));
         equipped[slot] = None;
 
-        self.level.add_oid(oid, loc);
-        self.manage_item_mesg(oid, "drop");
+        self.place_dropped(oid, loc);
     }
 
     fn drop_unequipped(&mut self, oid: Oid) {
@@ -820,8 +1503,17 @@ impl Game {
         let i = inv.iter().position(|&o| o == oid).unwrap();
         inv.remove(i);
 
+        self.place_dropped(oid, loc);
+    }
+
+    // Items dropped into shallow water will sink if the water deepens.
+    fn place_dropped(&mut self, oid: Oid, loc: Point) {
+        self.manage_item_mesg(oid, "item.drop");
+        if self.level.get_bottom(&loc).1.terrain_value().unwrap() == Terrain::ShallowWater {
+            let text = catalog::text("item.lands_in_water", &[("name", &self.item_name(oid))]);
+            self.messages.push(Message::new(Topic::Warning, &text));
+        }
         self.level.add_oid(oid, loc);
-        self.manage_item_mesg(oid, "drop");
     }
 
     fn remove(&mut self, oid: Oid) {
@@ -844,7 +1536,7 @@ impl Game {
         }
 
         assert!(self.level.obj(oid).1.is_none()); // oid must exist and not have a loc
-        self.manage_item_mesg(oid, "remove");
+        self.manage_item_mesg(oid, "item.remove");
     }
 
     fn player_inv_iter(&self) -> impl Iterator<Item = (Oid, &Object)> {
@@ -852,31 +1544,14 @@ impl Game {
     }
 
     fn push_inv_item(&self, items: &mut Vec<InvItem>, kind: ItemKind, equipped: Option<Slot>, oid: Oid) {
-        let obj = self.level.obj(oid).0;
         items.push(InvItem {
-            name: obj.name_value().unwrap(),
+            name: self.item_name(oid),
             kind,
             equipped,
             oid,
         });
     }
 
-    pub fn inv_item(&self, ch: &Object, tid: Tid) -> Option<&Object> {
-        if let Some(oids) = ch.inventory_value() {
-            for oid in oids {
-                let obj = self.level.obj(*oid).0;
-                if obj.has(tid) {
-                    return Some(obj);
-                }
-            }
-        }
-        None
-    }
-
-    pub fn in_inv(&self, ch: &Object, tid: Tid) -> bool {
-        self.inv_item(ch, tid).is_some()
-    }
-
     fn examine(&mut self, loc: &Point, wizard: bool) {
         let suffix = if wizard { format!(" {}", loc) } else { "".to_string() };
         if self.pov.visible(self, &loc) {
@@ -887,38 +1562,36 @@ impl Game {
                     if wizard {
                         format!("{} {obj:?}", obj.description())
                     } else {
-                        obj.description().to_string()
+                        obj.description()
                     }
                 })
                 .collect();
             if descs.len() == 1 {
-                self.messages.push(Message {
-                    topic: Topic::Normal,
-                    text: format!("You see {}{suffix}.", descs[0]),
-                });
+                let text = catalog::text("examine.one", &[("desc", &descs[0]), ("loc", &suffix)]);
+                self.messages.push(Message::new(Topic::Normal, &text));
+            } else if descs.len() > MAX_EXAMINE_LINES {
+                let text = catalog::text("examine.pile", &[("count", &descs.len()), ("loc", &suffix)]);
+                self.messages.push(Message::new(Topic::Normal, &text));
             } else {
+                let text = catalog::text("examine.many", &[("loc", &suffix)]);
+                self.messages.push(Message::new(Topic::Normal, &text));
+                for desc in descs {
+                    let text = catalog::text("examine.many.item", &[("desc", &desc)]);
+                    self.messages.push(Message::new(Topic::Normal, &text));
+                }
+            }
+            if let Some(decoration) = self.decorations.get(loc) {
                 self.messages.push(Message {
                     topic: Topic::Normal,
-                    text: format!("You see{suffix}"),
+                    text: decoration.description(),
                 });
-                for desc in descs {
-                    // TODO: at some point we'll want to cap the number of lines
-                    self.messages.push(Message {
-                        topic: Topic::Normal,
-                        text: format!("   {desc}."),
-                    });
-                }
             }
         } else if self.old_pov.get(&loc).is_some() {
-            self.messages.push(Message {
-                topic: Topic::Normal,
-                text: format!("You can no longer see there{suffix}."),
-            });
+            let text = catalog::text("examine.stale", &[("loc", &suffix)]);
+            self.messages.push(Message::new(Topic::Normal, &text));
         } else {
-            self.messages.push(Message {
-                topic: Topic::Normal,
-                text: format!("You've never seen there{suffix}."),
-            });
+            let text = catalog::text("examine.unseen", &[("loc", &suffix)]);
+            self.messages.push(Message::new(Topic::Normal, &text));
         };
     }
 
@@ -946,6 +1619,7 @@ impl Game {
         };
 
         Npc {
+            loc: *loc,
             letter,
             color,
             observed_hps,
@@ -954,6 +1628,11 @@ impl Game {
             } else {
                 None
             },
+            wounds: if wizard || self.discoveries.knows_wounds(obj.object_name()) {
+                Some(wounds(&durability))
+            } else {
+                None
+            },
             name: obj.name_value().unwrap(),
             disposition: obj.disposition_value().unwrap(),
             is_sleeping,
@@ -992,11 +1671,15 @@ impl Game {
         let mut handlers = Vec::new();
         {
             let oids = self.level.cell(new_loc);
+            let mut seen = Vec::new(); // handlers deal with every object with their tag
             for oid in oids.iter().rev() {
                 let obj = self.level.obj(*oid).0;
                 for tag in obj.iter() {
                     if let Some(handler) = self.interactions.find_post_handler(&Tag::Player, tag) {
-                        handlers.push(*handler);
+                        if !seen.contains(&tag.to_id()) {
+                            seen.push(tag.to_id());
+                            handlers.push(*handler);
+                        }
                     }
                 }
             }
@@ -1133,16 +1816,33 @@ impl Game {
     }
 
     fn save_actions(&mut self) {
-        if let Some(se) = &mut self.file {
-            if let Err(err) = persistence::append_game(se, &self.stream) {
-                self.messages
-                    .push(Message::new(Topic::Error, &format!("Couldn't save game: {err}")));
-            }
+        // Empty chunks would only record play time and that's saved with the next chunk
+        // (or lost if the player quits without acting which doesn't matter much).
+        if self.stream.is_empty() && self.checkpoints.is_empty() {
+            return;
+        }
+
+        let play_time = self.play_time();
+        if let Some(saver) = &self.saver {
+            let actions = std::mem::take(&mut self.stream);
+            let checkpoints = std::mem::take(&mut self.checkpoints);
+            saver.append(actions, checkpoints, play_time);
         }
         // If we can't save there's not much we can do other than clear. (Still worthwhile
         // appending onto the stream because we may want a wizard command to show the last
         // few events).
         self.stream.clear();
+        self.checkpoints.clear();
+    }
+
+    // Saving happens on a worker thread so errors are reported after the fact.
+    fn report_save_errors(&mut self) {
+        if let Some(saver) = &self.saver {
+            for err in saver.errors() {
+                let text = catalog::text("save.failed", &[("err", &err)]);
+                self.messages.push(Message::new(Topic::Error, &text));
+            }
+        }
     }
 
     fn dump_cell<W: Write>(&self, writer: &mut W, loc: &Point) -> Result<(), Error> {
@@ -1216,6 +1916,67 @@ impl Game {
     }
 }
 
+/// Returns the combat related stats for a weapon or armor item.
+fn item_stats(obj: &Object) -> Vec<String> {
+    let mut desc = Vec::new();
+    if let Some(weapon) = obj.weapon_value() {
+        let key = match weapon {
+            Weapon::OneHand => {
+                desc.push(catalog::text("stats.one_hand", &[]));
+                "stats.damage.main_hand"
+            }
+            Weapon::TwoHander => {
+                desc.push(catalog::text("stats.two_hand", &[]));
+                "stats.damage"
+            }
+        };
+
+        if let Some(damage) = obj.damage_value() {
+            if let Some(delay) = obj.delay_value() {
+                // TODO: need to account for stats (and penalties)
+                let dps = format!("{:.1}", (damage as f64) / ((delay.as_ms() as f64) / 1000.0));
+                desc.push(catalog::text(
+                    key,
+                    &[("damage", &damage), ("delay", &delay), ("dps", &dps)],
+                ));
+            }
+        }
+        if let Some(percent) = obj.crit_value() {
+            // TODO: does crit chance work differently for off hand?
+            // TODO: need to account for stats (and penalties)
+            // TODO: this should probably be factored into dps
+            desc.push(catalog::text("stats.crit", &[("percent", &percent)]));
+        }
+    }
+    if let Some(percent) = obj.mitigation_value() {
+        desc.push(catalog::text("stats.mitigation", &[("percent", &percent)]));
+    }
+    desc
+}
+
+fn wounds(durability: &Durability) -> Wounds {
+    if durability.current >= durability.max {
+        Wounds::Unharmed
+    } else if 4 * durability.current > durability.max {
+        Wounds::Bloodied
+    } else {
+        Wounds::NearDeath
+    }
+}
+
+fn item_kind(obj: &Object) -> ItemKind {
+    if obj.has(WEAPON_ID) {
+        match obj.weapon_value().unwrap() {
+            Weapon::OneHand => ItemKind::OneHandWeapon,
+            Weapon::TwoHander => ItemKind::TwoHandWeapon,
+        }
+    } else if obj.has(ARMOR_ID) {
+        ItemKind::Armor
+    } else {
+        ItemKind::Other
+    }
+}
+
 /// Returns a number with the standard normal distribution centered on x where the
 /// values are all within +/- the given percentage.
 fn rand_normal64(x: i64, percent: i32, rng: &RefCell<SmallRng>) -> i64 {
@@ -1277,3 +2038,32 @@ impl Drop for Game {
         self.save_actions();
     }
 }
+
+/// Starts a new game using a save file unique to the calling test (tests in different
+/// files are run concurrently) and advances time until it's the player's turn.
+#[cfg(test)]
+#[track_caller]
+fn test_game() -> Game {
+    let caller = std::panic::Location::caller();
+    let name = caller.file().replace(['/', '\\', '.'], "-");
+    let path = format!("/tmp/saved-{name}-{}.game", caller.line());
+    let _ = fs::remove_file(&path);
+
+    let mut game = Game::new_game(&path, 1);
+    while !game.players_turn() {
+        game.advance_time(false);
+    }
+    game
+}
+
+/// Returns the first cell orthogonally adjacent to the player that the player could move
+/// into.
+#[cfg(test)]
+fn open_neighbor(game: &Game) -> Point {
+    let loc = game.player_loc();
+    [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .into_iter()
+        .map(|(dx, dy)| Point::new(loc.x + dx, loc.y + dy))
+        .find(|neighbor| game.can_teleport_to(Oid(0), neighbor))
+        .unwrap()
+}
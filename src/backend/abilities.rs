@@ -0,0 +1,172 @@
+//! Special attacks used by NPCs. Abilities are listed with the NPC (see make.rs), have
+//! cooldowns, and are picked by the AI when their conditions are met. Big attacks are
+//! telegraphed the turn before they happen so that the player has a chance to react.
+use super::ai::Acted;
+use super::catalog;
+use super::statuses::Status;
+use super::tag::*;
+use super::time::{self, Time};
+use super::{Game, Message, Oid, Point, Topic};
+
+/// Percent of normal damage done by a raging attack.
+const RAGE_DAMAGE: i32 = 300;
+
+/// Percent of normal damage done by a shield bash.
+const BASH_DAMAGE: i32 = 50;
+
+/// Characters within this many cells are affected by a fear aura.
+const FEAR_RADIUS: i32 = 3;
+
+impl Ability {
+    pub fn new(kind: AbilityKind, cooldown: Time) -> Ability {
+        Ability {
+            kind,
+            cooldown,
+            ready: Time::zero(),
+            primed: false,
+        }
+    }
+}
+
+impl Game {
+    /// Called by the AI when an NPC is attacking. Returns None if the NPC didn't use an
+    /// ability (in which case it'll do its usual thing).
+    pub(super) fn try_ability(&mut self, oid: Oid, loc: &Point, defender_loc: &Point, units: Time) -> Option<Acted> {
+        let abilities = self.level.obj(oid).0.abilities_value()?.clone();
+        let now = self.now();
+        for (index, ability) in abilities.iter().enumerate() {
+            if ability.ready > now || !self.ability_applies(ability.kind, oid, loc, defender_loc) {
+                continue;
+            }
+            let delay = self.ability_delay(ability.kind, loc);
+            if delay > units {
+                continue;
+            }
+
+            let name = self.level.obj(oid).0.name_value().unwrap();
+            let mut ability = *ability;
+            if telegraphed(ability.kind) && !ability.primed {
+                debug!("{oid} is telegraphing {}", ability.kind);
+                let key = format!("ability.{}.telegraph", key(ability.kind));
+                let mesg = Message::new(Topic::Important, &catalog::text(&key, &[("name", &name)]));
+                self.messages.push(mesg);
+                ability.primed = true;
+                self.update_ability(oid, index, ability);
+                return Some(Acted::Acted(time::DIAGNOL_MOVE));
+            }
+
+            debug!("{oid} is using {}", ability.kind);
+            ability.primed = false;
+            ability.ready = now + ability.cooldown;
+            self.update_ability(oid, index, ability);
+
+            let key = format!("ability.{}", key(ability.kind));
+            let mesg = Message::new(Topic::Important, &catalog::text(&key, &[("name", &name)]));
+            self.messages.push(mesg);
+            self.use_ability(ability.kind, loc, defender_loc);
+            return Some(Acted::Acted(delay));
+        }
+        None
+    }
+
+    fn ability_applies(&self, kind: AbilityKind, oid: Oid, loc: &Point, defender_loc: &Point) -> bool {
+        match kind {
+            AbilityKind::FearAura => {
+                let defender = self.level.get(defender_loc, CHARACTER_ID).unwrap().0;
+                loc.distance2(defender_loc) <= FEAR_RADIUS * FEAR_RADIUS
+                    && !self.has_status(defender, Status::Frightened)
+            }
+            AbilityKind::Rage => {
+                let durability = self.level.obj(oid).0.durability_value().unwrap();
+                loc.adjacent(defender_loc) && 2 * durability.current <= durability.max
+            }
+            AbilityKind::ShieldBash => loc.adjacent(defender_loc),
+        }
+    }
+
+    fn ability_delay(&self, kind: AbilityKind, loc: &Point) -> Time {
+        match kind {
+            AbilityKind::FearAura => time::secs(2),
+            AbilityKind::Rage | AbilityKind::ShieldBash => self.melee_delay(loc),
+        }
+    }
+
+    fn use_ability(&mut self, kind: AbilityKind, loc: &Point, defender_loc: &Point) {
+        match kind {
+            AbilityKind::FearAura => {
+                let defender = self.level.get(defender_loc, CHARACTER_ID).unwrap().0;
+                self.add_status(defender, Status::Frightened, time::FRIGHTENED);
+            }
+            AbilityKind::Rage => {
+                let attacker = self.level.get(loc, CHARACTER_ID).unwrap().0;
+                self.add_status(attacker, Status::Hasted, time::HASTED);
+                self.do_scaled_melee_attack(loc, defender_loc, RAGE_DAMAGE);
+            }
+            AbilityKind::ShieldBash => {
+                self.do_scaled_melee_attack(loc, defender_loc, BASH_DAMAGE);
+                if let Some((defender, obj)) = self.level.get(defender_loc, CHARACTER_ID) {
+                    if !self.game_over() {
+                        let displaceable = obj.can_be_displaced();
+                        self.add_status(defender, Status::Confused, time::CONFUSED);
+                        if displaceable {
+                            let _ = self.do_knockback(loc, defender_loc, 1);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_ability(&mut self, oid: Oid, index: usize, ability: Ability) {
+        let obj = self.level.obj_mut(oid);
+        obj.abilities_value_mut().unwrap()[index] = ability;
+    }
+}
+
+fn telegraphed(kind: AbilityKind) -> bool {
+    matches!(kind, AbilityKind::Rage)
+}
+
+fn key(kind: AbilityKind) -> &'static str {
+    match kind {
+        AbilityKind::FearAura => "fear_aura",
+        AbilityKind::Rage => "rage",
+        AbilityKind::ShieldBash => "shield_bash",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::ObjectName;
+    use crate::backend::{open_neighbor, test_game};
+
+    #[test]
+    fn test_abilities() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        let loc = open_neighbor(&game);
+
+        // Big attacks are telegraphed before they're used.
+        let oid = game.add_object(&loc, new_obj(ObjectName::Icarium));
+        let obj = game.level.obj_mut(oid);
+        let max = obj.durability_value().unwrap().max;
+        obj.replace(Tag::Durability(Durability { current: max / 4, max }));
+
+        let units = time::secs(100);
+        assert!(game.try_ability(oid, &loc, &player_loc, units).is_some());
+        assert!(game.recent_messages(1).any(|m| m.text.contains("eyes go cold")));
+        assert!(game.try_ability(oid, &loc, &player_loc, units).is_some());
+        assert!(game.recent_messages(5).any(|m| m.text.contains("flies into a rage")));
+
+        // Abilities can't be used again until their cooldown expires.
+        assert!(game.try_ability(oid, &loc, &player_loc, units).is_none());
+
+        // Fear auras frighten the player.
+        game.level.remove(oid);
+        let oid = game.add_object(&loc, new_obj(ObjectName::ImrothTheCruel));
+        assert!(game.try_ability(oid, &loc, &player_loc, units).is_some());
+        assert!(game.has_status(Oid(0), Status::Frightened));
+    }
+}
@@ -17,10 +17,8 @@ impl Game {
         debug!("digging at {obj_loc} for {damage} damage");
 
         if damage < durability.current {
-            let mesg = Message::new(
-                Topic::Normal,
-                "You chip away at the wall with your pick-axe.", // TODO: probably should have slightly differet text for wooden walls (if we ever add them)
-            );
+            // TODO: probably should have slightly differet text for wooden walls (if we ever add them)
+            let mesg = Message::new(Topic::Normal, &catalog::text("dig.chip", &[]));
             self.messages.push(mesg);
 
             let obj = self.level.get(&obj_loc, TERRAIN_ID).unwrap().1;
@@ -31,7 +29,7 @@ impl Game {
             }));
             self.replace_object(obj_loc, obj_oid, obj);
         } else {
-            let mesg = Message::new(Topic::Important, "You destroy the wall!");
+            let mesg = Message::new(Topic::Important, &catalog::text("dig.destroyed", &[]));
             self.messages.push(mesg);
             self.destroy_object(obj_loc, obj_oid);
             self.pov.dirty();
@@ -47,6 +45,8 @@ impl Game {
             debug!("flood deep from {loc} to {new_loc}");
             let bad_oid = self.level.get(&new_loc, TERRAIN_ID).unwrap().0;
             self.replace_object(&new_loc, bad_oid, new_obj(ObjectName::DeepWater));
+            self.player_hears(&new_loc, sound::QUIET, "sound.rushing_water");
+            self.sink_items(&new_loc);
 
             if new_loc == self.player_loc() {
                 if let Some(newer_loc) = self.find_neighbor(&self.player_loc(), |candidate| {
@@ -57,10 +57,7 @@ impl Game {
                         || terrain == Terrain::Ground
                         || terrain == Terrain::Rubble
                 }) {
-                    let mesg = Message {
-                        topic: Topic::Normal,
-                        text: "You step away from the rising water.".to_string(),
-                    };
+                    let mesg = Message::new(Topic::Normal, &catalog::text("flood.step_away", &[]));
                     self.messages.push(mesg);
 
                     trace!("flood is moving player from {} to {}", self.player_loc(), newer_loc);
@@ -74,10 +71,7 @@ impl Game {
                     };
                     self.scheduler.force_acted(Oid(0), units, &self.rng);
                 } else {
-                    let mesg = Message {
-                        topic: Topic::Important,
-                        text: "You drown!".to_string(),
-                    };
+                    let mesg = Message::new(Topic::Fatal, &catalog::text("flood.drown", &[]));
                     self.messages.push(mesg);
 
                     self.state = State::LostGame;
@@ -91,6 +85,24 @@ impl Game {
         }
     }
 
+    // Items are lost when deep water covers them (except for the Emperor's sword which
+    // the player needs to win).
+    fn sink_items(&mut self, loc: &Point) {
+        let oids: Vec<Oid> = self
+            .level
+            .cell_iter(loc)
+            .filter(|(_, obj)| obj.has(PORTABLE_ID) && !obj.has(EMP_SWORD_ID))
+            .map(|(oid, _)| oid)
+            .collect();
+        for oid in oids {
+            if self.pov.visible(self, loc) {
+                let text = catalog::text("flood.sinks", &[("name", &self.item_name(oid))]);
+                self.messages.push(Message::new(Topic::Warning, &text));
+            }
+            self.level.remove(oid);
+        }
+    }
+
     pub fn do_flood_shallow(&mut self, oid: Oid, loc: Point) -> Scheduled {
         if let Some(new_loc) = self.find_neighbor(&loc, |candidate| {
             let obj = self.level.get(&candidate, TERRAIN_ID).unwrap().1;
@@ -126,7 +138,9 @@ impl Game {
         self.level.moved(oid, old_loc, new_loc);
         if oid.0 == 0 {
             self.pov.dirty();
+            self.check_terrain_hints(new_loc);
         }
+        self.check_pressure_plate(oid, new_loc);
     }
 
     pub fn do_open_door(&mut self, oid: Oid, ch_loc: &Point, obj_loc: &Point, obj_oid: Oid) {
@@ -136,34 +150,175 @@ impl Game {
         self.pov.dirty();
     }
 
-    pub fn do_ignore(&mut self, oid: Oid, obj_loc: &Point, obj_oid: Oid, why: &str) {
+    /// NPCs open doors from the cell next to them (they'll move into the door on their
+    /// next turn).
+    pub fn do_npc_open_door(&mut self, oid: Oid, door_loc: &Point) {
+        debug!("{oid} is opening the door at {door_loc}");
+        self.npc_door_changed(oid, door_loc, ObjectName::OpenDoor, "door.npc_opens");
+    }
+
+    pub fn do_npc_close_door(&mut self, oid: Oid, door_loc: &Point) {
+        debug!("{oid} is closing the door at {door_loc}");
+        self.npc_door_changed(oid, door_loc, ObjectName::ClosedDoor, "door.npc_closes");
+    }
+
+    // If the player can't see the door then they'll continue to see the old door state
+    // (via OldPoV) until they see it again though they may hear it.
+    fn npc_door_changed(&mut self, oid: Oid, door_loc: &Point, new_name: ObjectName, key: &str) {
+        PoV::refresh(self);
+        if self.pov.visible(self, door_loc) {
+            let name = self.level.obj(oid).0.name_value().unwrap();
+            let text = catalog::text(key, &[("name", &name)]);
+            self.messages.push(Message::new(Topic::Normal, &text));
+        } else {
+            self.player_hears(door_loc, sound::QUIET, "sound.door");
+        }
+        let door = self.level.get_bottom(door_loc).0;
+        self.replace_object(door_loc, door, new_obj(new_name));
+        self.pov.dirty();
+    }
+
+    pub fn do_call(&mut self, oid: Oid, name: &str) {
+        let obj = self.level.obj_mut(oid);
+        let old_name = obj.name_value().unwrap();
+        let name = name.trim();
+        let text = if name.is_empty() {
+            obj.remove(CALLED_ID);
+            catalog::text("call.cleared", &[("old_name", &old_name)])
+        } else {
+            obj.set(Tag::Called(name.to_string()));
+            catalog::text("call.named", &[("old_name", &old_name), ("name", &name)])
+        };
+        self.messages.push(Message::new(Topic::Normal, &text));
+    }
+
+    /// Used when the player doesn't have enough inventory space to pick up an item.
+    pub fn do_ignore(&mut self, oid: Oid, obj_loc: &Point, obj_oid: Oid) {
         let obj = self.level.obj(obj_oid).0;
         debug!("{oid} is ignoring {obj_oid}/{obj} at {obj_loc}");
-        let name: &'static str = obj.name_value().unwrap();
-        let mesg = Message {
-            topic: Topic::Normal,
-            text: format!("{why} pick up the {name}."),
-        };
-        self.messages.push(mesg);
+        let name = self.item_name(obj_oid);
+        let text = catalog::text("pick_up.no_room", &[("name", &name)]);
+        self.messages.push(Message::new(Topic::Normal, &text));
     }
 
     pub fn do_pick_up(&mut self, oid: Oid, obj_loc: &Point, obj_oid: Oid) {
         let obj = self.level.obj(obj_oid).0;
         debug!("{oid} is picking up {obj_oid}/{obj} at {obj_loc}");
-        let name: &'static str = obj.name_value().unwrap();
-        let mesg = Message {
-            topic: Topic::Normal,
-            text: format!("You pick up the {name}."),
-        };
-        self.messages.push(mesg);
+        let name = self.item_name(obj_oid);
+        let is_emp_sword = obj.has(EMP_SWORD_ID);
+        let kind = obj.object_name();
+        let text = catalog::text("pick_up.done", &[("name", &name)]);
+        self.messages.push(Message::new(Topic::Normal, &text));
+        if oid.0 == 0 {
+            self.add_hint(Hint::PickedUpItem);
+            self.discoveries.identify(kind);
+        }
+        if is_emp_sword {
+            let text = catalog::text("quest.destroy_sword.vitr", &[]);
+            self.journal.set_hint(QuestId::DestroySword, &text);
+        }
 
         self.level.pickup(obj_loc, obj_oid);
     }
 
+    // Returns the time taken which is zero if nothing was picked up.
+    pub(super) fn pick_up_many(&mut self, oids: &[Oid]) -> Time {
+        let loc = self.player_loc();
+        let mut taken = Time::zero();
+        for &oid in oids.iter() {
+            if !self.level.cell(&loc).contains(&oid) {
+                continue;
+            }
+            let (used, max) = self.inventory_space();
+            if used < max {
+                self.do_pick_up(Oid(0), &loc, oid);
+                taken += time::PICK_UP;
+            } else {
+                self.do_ignore(Oid(0), &loc, oid);
+            }
+        }
+        taken
+    }
+
     pub fn do_shove_doorman(&mut self, oid: Oid, old_loc: &Point, ch: Oid, new_loc: &Point) {
         debug!("shoving doorman from {old_loc} to {new_loc}");
+        if !self.level.obj(ch).0.has(SCHEDULED_ID) {
+            // Now that someone worthy has come along he starts tending his door.
+            self.level.obj_mut(ch).set(Tag::Scheduled);
+            self.schedule_new_obj(ch);
+        }
         self.do_force_move(ch, old_loc, new_loc);
         let player_loc = self.player_loc();
         self.do_move(oid, &player_loc, old_loc);
+        let _ = self.interact_post_move(old_loc); // the shove time already covers this
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{open_neighbor, test_game};
+
+    #[test]
+    fn test_call() {
+        let mut game = test_game();
+        let oid = game.level.add(new_obj(ObjectName::PickAxe), None);
+        let name = game.item_name(oid);
+
+        game.player_acted(Action::Call {
+            oid,
+            name: " Trusty ".to_string(),
+        });
+        assert_eq!(game.item_name(oid), "Trusty");
+        assert!(game.recent_messages(1).any(|m| m.text.contains("\"Trusty\"")));
+
+        // Empty names restore the original name.
+        game.player_acted(Action::Call {
+            oid,
+            name: String::new(),
+        });
+        assert_eq!(game.item_name(oid), name);
+    }
+
+    #[test]
+    fn test_pick_up_many() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        let loc = open_neighbor(&game);
+        let (dx, dy) = (loc.x - player_loc.x, loc.y - player_loc.y);
+        let axe = game.add_object(&loc, new_obj(ObjectName::PickAxe));
+        let sandals = game.add_object(&loc, new_obj(ObjectName::LeatherSandals));
+
+        // Stepping onto several items doesn't pick them up.
+        game.player_acted(Action::Move { dx, dy });
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+        assert_eq!(game.items_here().len(), 2);
+
+        let (used, _) = game.inventory_space();
+        game.player_acted(Action::PickUpMany(vec![sandals, axe]));
+        assert!(game.items_here().is_empty());
+        assert_eq!(game.inventory_space().0, used + 2);
+    }
+
+    #[test]
+    fn test_drop_into_water() {
+        let mut game = test_game();
+        let loc = game.player_loc();
+        let terrain = game.level.get_bottom(&loc).0;
+        game.level.replace(&loc, terrain, new_obj(ObjectName::ShallowWater));
+
+        let axe = game.level.add(new_obj(ObjectName::PickAxe), None);
+        let player = game.level.get_mut(&loc, CHARACTER_ID).unwrap().1;
+        player.inventory_value_mut().unwrap().push(axe);
+        game.player_acted(Action::Drop(axe));
+        assert!(game.recent_messages(1).any(|m| m.text.contains("lands in the water")));
+        assert_eq!(game.items_here().len(), 1);
+
+        // Items sink when the water gets deeper.
+        game.sink_items(&loc);
+        assert!(game.level.try_obj(axe).is_none());
+        assert!(game.items_here().is_empty());
     }
 }
@@ -16,6 +16,48 @@ pub enum Acted {
     Removed,
 }
 
+/// What an NPC is currently trying to do. This is used by wizard mode to debug the AI.
+pub struct AiPlan {
+    pub behavior: String,
+    pub target: Option<Point>,
+
+    /// The route the NPC will follow to target (excluding the NPC's own cell).
+    pub path: Vec<Point>,
+}
+
+impl Game {
+    pub fn ai_plan(&self, loc: &Point) -> Option<AiPlan> {
+        let (oid, ch) = self.level.get(loc, CHARACTER_ID)?;
+        if oid.0 == 0 {
+            return None;
+        }
+        let (behavior, target) = match ch.behavior_value()? {
+            Behavior::Attacking(defender, defender_loc) => (format!("attacking {defender}"), Some(defender_loc)),
+            Behavior::MovingTo(target) => ("moving".to_string(), Some(target)),
+            Behavior::Searching(defender, origin, seen) => {
+                let elapsed = self.now() - seen;
+                (
+                    format!("searching for {defender} (lost {elapsed} ago)"),
+                    Some(search_loc(&origin, elapsed)),
+                )
+            }
+            Behavior::Sleeping => ("sleeping".to_string(), None),
+            Behavior::Wandering(end) => (format!("wandering until {end}"), None),
+        };
+        let path = match target {
+            Some(target) => {
+                let callback = |new_loc: Point, neighbors: &mut Vec<(Point, Time)>| {
+                    successors(&self.level, ch, new_loc, &target, neighbors)
+                };
+                let find = PathFind::new(*loc, target, callback);
+                find.path().iter().skip(1).copied().collect()
+            }
+            None => Vec::new(),
+        };
+        Some(AiPlan { behavior, target, path })
+    }
+}
+
 /// Returns Some((duration, extra)) if the object decided to perform an action. extra is
 /// additional time subtracted from the object's time units but not added to other objects
 /// time units (it's used with objects that want to schedule future actions further into
@@ -30,18 +72,27 @@ pub fn acted(game: &mut Game, oid: Oid, units: Time) -> Acted {
             } else {
                 unreachable!("{oid} is a scheduled terrain but not shallow or deep water!");
             }
+        } else if obj.has(DOORMAN_ID) {
+            tend_door(game, oid, units)
         } else {
             // TODO: will have to special case alternate goals, eg
             // whether to go grab a good item that is in los
             // whether to move closer to group/pack leader
             //
             // Currently NPCs don't make noise which is probably OK.
-            match obj.behavior_value() {
+            let behavior = obj.behavior_value();
+            let spectator = obj.has(SPECTATOR_ID);
+            if let Some(acted) = recover_weapon(game, oid, units) {
+                return acted;
+            }
+            match behavior {
                 Some(Behavior::Attacking(defender, defender_loc)) => attack(game, oid, defender, defender_loc, units),
                 Some(Behavior::MovingTo(loc)) => move_towards(game, oid, &loc, units),
+                Some(Behavior::Searching(defender, origin, seen)) => search(game, oid, defender, origin, seen, units),
                 Some(Behavior::Sleeping) => Acted::DidntAct, // NPCs transition out of this via handle_noise
+                Some(Behavior::Wandering(_)) if spectator => spectate(game, oid, units),
                 Some(Behavior::Wandering(end)) => wander(game, oid, end, units),
-                None => unreachable!("{obj} is scheduled but has no ai handler"),
+                None => unreachable!("{oid} is scheduled but has no ai handler"),
             }
         }
     } else {
@@ -59,16 +110,20 @@ fn attack(game: &mut Game, attacker: Oid, defender: Oid, old_defender_loc: Point
         }
     }
 
-    // TODO: this assumes that the defender is the player (or the pair are in the player's pov)
-    if game.pov.visible(game, &defender_loc) {
+    if can_see(game, &attacker_loc, defender) {
         // If the defender can be seen then update where the attacker thinks he is,
         if defender_loc != old_defender_loc {
             let behavior = Behavior::Attacking(defender, defender_loc);
             game.replace_behavior(&attacker_loc, behavior);
         }
 
-        // and either attack him or move towards his actual location.
-        if attacker_loc.adjacent(&defender_loc) {
+        // then either use a special ability,
+        if let Some(acted) = game.try_ability(attacker, &attacker_loc, &defender_loc, units) {
+            return acted;
+        }
+
+        // or attack him or move towards his actual location.
+        if attacker_loc.adjacent(&defender_loc) || can_reach(game, &attacker_loc, &defender_loc) {
             let delay = game.melee_delay(&attacker_loc);
             if delay <= units {
                 game.do_melee_attack(&attacker_loc, &defender_loc);
@@ -76,6 +131,12 @@ fn attack(game: &mut Game, attacker: Oid, defender: Oid, old_defender_loc: Point
             } else {
                 Acted::DidntAct
             }
+        } else if let Some((dx, dy, delay)) = game.charge_plan(&attacker_loc, &defender_loc) {
+            if delay <= units {
+                Acted::Acted(game.do_charge(&attacker_loc, dx, dy))
+            } else {
+                Acted::DidntAct
+            }
         } else {
             if units >= time::DIAGNOL_MOVE {
                 if let Some(acted) = try_move_towards(game, attacker, &defender_loc) {
@@ -91,14 +152,35 @@ fn attack(game: &mut Game, attacker: Oid, defender: Oid, old_defender_loc: Point
             }
         }
     } else {
-        // If the defender cannot be seen then move towards his last known location.
-        debug!("{attacker} can no longer see {defender} and has started moving towards his last known location");
-        let behavior = Behavior::MovingTo(old_defender_loc);
+        // If the defender cannot be seen then search around his last known location.
+        debug!("{attacker} can no longer see {defender} and has started searching around {old_defender_loc}");
+        let behavior = Behavior::Searching(defender, old_defender_loc, game.now());
         game.replace_behavior(&attacker_loc, behavior);
         Acted::DidntAct
     }
 }
 
+// Only the player's PoV is tracked so visibility is treated as symmetric. TODO: NPCs
+// fighting other NPCs always know where their target is.
+fn can_see(game: &Game, watcher_loc: &Point, target: Oid) -> bool {
+    if target.0 == 0 {
+        game.pov.in_los(game, watcher_loc)
+    } else {
+        true
+    }
+}
+
+// Reach weapons can hit characters two cells away in a straight line.
+fn can_reach(game: &Game, attacker_loc: &Point, defender_loc: &Point) -> bool {
+    let dx = defender_loc.x - attacker_loc.x;
+    let dy = defender_loc.y - attacker_loc.y;
+    if (dx == 0 || dx.abs() == 2) && (dy == 0 || dy.abs() == 2) && (dx != 0 || dy != 0) {
+        game.reach_target(attacker_loc, dx / 2, dy / 2) == Some(*defender_loc)
+    } else {
+        false
+    }
+}
+
 pub fn extra_flood_delay(game: &Game) -> Time {
     let rng = &mut *game.rng();
     let t: i64 = 60 + rng.gen_range(0..(400 * 6));
@@ -128,35 +210,24 @@ fn deep_flood(game: &mut Game, oid: Oid, units: Time) -> Acted {
     }
 }
 
-/// Returns the next location from start to target using the lowest Time path.
-fn find_next_loc_to(game: &Game, ch: &Object, start: &Point, target: &Point) -> Option<Point> {
-    let callback = |loc: Point, neighbors: &mut Vec<(Point, Time)>| successors(game, ch, loc, target, neighbors);
-    let find = PathFind::new(*start, *target, callback);
-    if let Some(loc) = find.next() {
-        let character = &game.level.get(&loc, CHARACTER_ID);
-        if character.is_none() {
-            Some(loc)
-        } else {
-            // We want to allow moving towards a character, but not into it.
-            None
-        }
-    } else {
-        None
-    }
-}
-
-fn successors(game: &Game, ch: &Object, loc: Point, target: &Point, neighbors: &mut Vec<(Point, Time)>) {
+/// Note that this only reads the level (and only the cells adjacent to loc) so that it can
+/// be used to find paths in parallel, see planning.rs.
+pub fn successors(level: &Level, ch: &Object, loc: Point, target: &Point, neighbors: &mut Vec<(Point, Time)>) {
     let deltas = vec![(-1, -1), (-1, 1), (-1, 0), (1, -1), (1, 1), (1, 0), (0, -1), (0, 1)];
     for delta in deltas {
         let new_loc = Point::new(loc.x + delta.0, loc.y + delta.1);
-        let character = &game.level.get(&new_loc, CHARACTER_ID);
+        if !level.in_bounds(&new_loc) {
+            continue;
+        }
+        let character = &level.get(&new_loc, CHARACTER_ID);
         if character.is_none() || new_loc == *target {
-            let (_, terrain) = game.level.get_bottom(&new_loc);
+            let (_, terrain) = level.get_bottom(&new_loc);
             if ch.impassible_terrain(terrain).is_none() {
+                let delay = ch.terrain_delay(terrain.terrain_value().unwrap());
                 if loc.diagnol(&new_loc) {
-                    neighbors.push((new_loc, time::DIAGNOL_MOVE)); // TODO: should also factor in a post-move handler
+                    neighbors.push((new_loc, time::DIAGNOL_MOVE + delay));
                 } else {
-                    neighbors.push((new_loc, time::CARDINAL_MOVE));
+                    neighbors.push((new_loc, time::CARDINAL_MOVE + delay));
                 }
             }
         }
@@ -184,6 +255,81 @@ fn move_towards(game: &mut Game, oid: Oid, target_loc: &Point, units: Time) -> A
     }
 }
 
+// NPCs that fumbled their weapon will pick it back up before doing anything else.
+fn recover_weapon(game: &mut Game, oid: Oid, units: Time) -> Option<Acted> {
+    let loc = game.loc(oid)?;
+    let obj = game.level.obj(oid).0;
+    if !obj.has(INVENTORY_ID) || obj.equipped_value()?[Slot::MainHand].is_some() {
+        return None;
+    }
+    let weapon = game.level.cell_iter(&loc).find(|(_, o)| o.has(WEAPON_ID))?.0;
+    if units >= time::PICK_UP {
+        game.do_npc_recover_weapon(oid, &loc, weapon);
+        Some(Acted::Acted(time::PICK_UP))
+    } else {
+        Some(Acted::DidntAct)
+    }
+}
+
+// NPCs that lose track of their target first go to where they last saw it and then spiral
+// outwards until they either spot it again or their Persistence runs out.
+fn search(game: &mut Game, oid: Oid, defender: Oid, origin: Point, seen: Time, units: Time) -> Acted {
+    let loc = game.loc(oid).unwrap();
+    if let Some(defender_loc) = game.loc(defender) {
+        if can_see(game, &loc, defender) {
+            debug!("{oid} spotted {defender} again");
+            game.replace_behavior(&loc, Behavior::Attacking(defender, defender_loc));
+            return attack(game, oid, defender, defender_loc, units);
+        }
+    }
+
+    let now = game.now();
+    let obj = game.level.obj(oid).0;
+    let persistence = obj.persistence_value().unwrap_or(time::SEARCH);
+    if game.loc(defender).is_none() || now - seen > persistence {
+        debug!("{oid} gave up searching for {defender}");
+        game.replace_behavior(&loc, Behavior::Wandering(now + DIAGNOL_MOVE * 8));
+        return Acted::DidntAct;
+    }
+
+    if units >= DIAGNOL_MOVE {
+        let target = search_loc(&origin, now - seen);
+        if let Some(acted) = try_move_towards(game, oid, &target) {
+            return acted;
+        }
+
+        // The search point is unreachable (or we're already there) so poke around a bit.
+        let obj = game.level.obj(oid).0;
+        if let Some(new_loc) = game.find_empty_cell(obj, &loc) {
+            game.do_move(oid, &loc, &new_loc);
+            if loc.diagnol(&new_loc) {
+                return Acted::Acted(DIAGNOL_MOVE);
+            } else {
+                return Acted::Acted(CARDINAL_MOVE);
+            }
+        }
+    }
+    Acted::DidntAct
+}
+
+/// Returns where an NPC that has been searching for elapsed time should be heading. This
+/// starts at origin and then visits the corners of larger and larger squares around it.
+pub fn search_loc(origin: &Point, elapsed: Time) -> Point {
+    let leg = (elapsed.as_ms() / (CARDINAL_MOVE * 3).as_ms()) as i32;
+    if leg == 0 {
+        *origin
+    } else {
+        let radius = 1 + (leg - 1) / 4;
+        let (dx, dy) = match (leg - 1) % 4 {
+            0 => (radius, -radius),
+            1 => (radius, radius),
+            2 => (-radius, radius),
+            _ => (-radius, -radius),
+        };
+        Point::new(origin.x + dx, origin.y + dy)
+    }
+}
+
 fn shallow_flood(game: &mut Game, oid: Oid, units: Time) -> Acted {
     if units >= time::FLOOD {
         let flood = {
@@ -221,7 +367,7 @@ fn find_flee_loc(game: &Game, attacker_loc: &Point, defender_loc: &Point) -> Opt
         // don't bother fleeing to a really close point
         if d2 > 4 * 4 {
             let callback = |new_loc: Point, neighbors: &mut Vec<(Point, Time)>| {
-                successors(game, attacker, new_loc, &candidate, neighbors)
+                successors(&game.level, attacker, new_loc, &candidate, neighbors)
             };
             let find = PathFind::new(*attacker_loc, candidate, callback);
             if let Some(next_loc) = find.next() {
@@ -245,19 +391,19 @@ fn find_flee_loc(game: &Game, attacker_loc: &Point, defender_loc: &Point) -> Opt
 
 fn start_fleeing(game: &mut Game, attacker: Oid, attacker_loc: &Point, defender: Oid, defender_loc: &Point) -> bool {
     if let Some(flee_loc) = find_flee_loc(game, attacker_loc, defender_loc) {
-        debug!("{attacker} is hurt and has started fleeing from {defender}");
+        debug!("{attacker} has started fleeing from {defender}");
         let behavior = Behavior::MovingTo(flee_loc);
         game.replace_behavior(&attacker_loc, behavior);
         true
     } else {
-        debug!("{attacker} is hurt and wanted to flee but was unable to");
+        debug!("{attacker} wanted to flee but was unable to");
         false
     }
 }
 
 fn switched_to_attacking(game: &mut Game, oid: Oid, units: Time) -> Option<Acted> {
     let loc = game.loc(oid)?;
-    if game.pov.visible(game, &loc) && !wants_to_flee(game, &loc) {
+    if game.pov.in_los(game, &loc) && !wants_to_flee(game, &loc) {
         let obj = game.level.get_mut(&loc, BEHAVIOR_ID).unwrap().1;
         if let Some(Disposition::Aggressive) = obj.disposition_value() {
             // we're treating visibility as a symmetric operation, TODO: which is probably not quite right
@@ -269,19 +415,41 @@ fn switched_to_attacking(game: &mut Game, oid: Oid, units: Time) -> Option<Acted
 }
 
 fn try_move_towards(game: &mut Game, oid: Oid, target_loc: &Point) -> Option<Acted> {
-    let ch = &game.level.obj(oid).0;
+    let ch = game.level.obj(oid).0.clone();
     let old_loc = game.loc(oid).unwrap();
     if old_loc == *target_loc {
         debug!("didn't move because already at {target_loc}");
         return None; // we're at the target so we're no longer moving towards it
     }
 
-    if let Some(new_loc) = find_next_loc_to(game, ch, &old_loc, target_loc) {
+    if let Some(new_loc) = game.next_route_loc(oid, &ch, &old_loc, target_loc) {
+        let new_loc = game.stagger(oid, &old_loc, new_loc);
+        let door = game.level.get_bottom(&new_loc).1;
+        if door.terrain_value() == Some(Terrain::ClosedDoor) {
+            if door.has(LOCK_ID) {
+                debug!("didn't move because the door at {new_loc} is locked");
+                return None;
+            }
+            game.do_npc_open_door(oid, &new_loc);
+            return Some(Acted::Acted(OPEN_DOOR));
+        }
+        if !game.opportunity_attacks(&old_loc, &new_loc) {
+            if game.level.try_obj(oid).is_none() {
+                return Some(Acted::Removed); // killed while moving away from the player
+            }
+            return Some(Acted::Acted(CARDINAL_MOVE)); // knocked back
+        }
         game.do_move(oid, &old_loc, &new_loc);
+        let terrain = game.level.get_bottom(&new_loc).1.terrain_value().unwrap();
+        let mut delay = game.level.obj(oid).0.terrain_delay(terrain);
+        if closes_door_behind(game, &ch, &old_loc) {
+            game.do_npc_close_door(oid, &old_loc);
+            delay += OPEN_DOOR;
+        }
         if old_loc.diagnol(&new_loc) {
-            Some(Acted::Acted(DIAGNOL_MOVE)) // TODO: probably should do post move interactions
+            Some(Acted::Acted(DIAGNOL_MOVE + delay)) // TODO: probably should do post move interactions
         } else {
-            Some(Acted::Acted(CARDINAL_MOVE))
+            Some(Acted::Acted(CARDINAL_MOVE + delay))
         }
     } else {
         debug!("didn't move because can't find a path from {old_loc} to {target_loc}");
@@ -289,6 +457,73 @@ fn try_move_towards(game: &mut Game, oid: Oid, target_loc: &Point) -> Option<Act
     }
 }
 
+// Guards close doors behind them unless they're busy chasing someone.
+fn closes_door_behind(game: &Game, ch: &Object, old_loc: &Point) -> bool {
+    if !ch.has(GUARD_ID) || matches!(ch.behavior_value(), Some(Behavior::Attacking(_, _))) {
+        return false;
+    }
+    let door = game.level.get_bottom(old_loc).1;
+    door.terrain_value() == Some(Terrain::OpenDoor) && game.level.cell_iter(old_loc).count() == 1
+}
+
+// The Doorman only starts acting once the player has shoved past him. After that he keeps
+// his door open (guards tend to close it behind them).
+fn tend_door(game: &mut Game, oid: Oid, units: Time) -> Acted {
+    let loc = game.loc(oid).unwrap();
+    let door_loc = (-2..=2)
+        .flat_map(|dy| (-2..=2).map(move |dx| Point::new(loc.x + dx, loc.y + dy)))
+        .find(|candidate| game.level.get_bottom(candidate).1.terrain_value() == Some(Terrain::ClosedDoor));
+    match door_loc {
+        Some(door_loc) if loc.adjacent(&door_loc) => {
+            if units >= OPEN_DOOR {
+                game.do_npc_open_door(oid, &door_loc);
+                Acted::Acted(OPEN_DOOR)
+            } else {
+                Acted::DidntAct
+            }
+        }
+        Some(door_loc) if units >= DIAGNOL_MOVE => {
+            // Step next to the door so that it can be opened next turn.
+            let ch = game.level.obj(oid).0;
+            let new_loc = game.find_neighbor(&loc, |candidate| {
+                candidate.adjacent(&door_loc)
+                    && game.level.get(candidate, CHARACTER_ID).is_none()
+                    && ch.impassible_terrain(game.level.get_bottom(candidate).1).is_none()
+            });
+            if let Some(new_loc) = new_loc {
+                game.do_move(oid, &loc, &new_loc);
+                if loc.diagnol(&new_loc) {
+                    Acted::Acted(DIAGNOL_MOVE)
+                } else {
+                    Acted::Acted(CARDINAL_MOVE)
+                }
+            } else {
+                Acted::DidntAct
+            }
+        }
+        _ => Acted::DidntAct,
+    }
+}
+
+// Spectators mill about within the crowd regions, see crowd.rs.
+fn spectate(game: &mut Game, oid: Oid, units: Time) -> Acted {
+    if units < DIAGNOL_MOVE {
+        return Acted::DidntAct;
+    }
+    let loc = game.loc(oid).unwrap();
+    match game.crowd_step(&loc) {
+        Some(new_loc) => {
+            game.do_move(oid, &loc, &new_loc);
+            if loc.diagnol(&new_loc) {
+                Acted::Acted(DIAGNOL_MOVE)
+            } else {
+                Acted::Acted(CARDINAL_MOVE)
+            }
+        }
+        None => Acted::DidntAct,
+    }
+}
+
 fn wander(game: &mut Game, oid: Oid, end: Time, units: Time) -> Acted {
     if let Some(acted) = switched_to_attacking(game, oid, units) {
         info!("{oid} was wandering but switched to attacking");
@@ -314,8 +549,10 @@ fn wander(game: &mut Game, oid: Oid, end: Time, units: Time) -> Acted {
 }
 
 fn wants_to_flee(game: &Game, attacker_loc: &Point) -> bool {
-    let attacker = game.level.get(attacker_loc, CHARACTER_ID).unwrap().1;
-    if let Some(percent) = attacker.flees_value() {
+    let (oid, attacker) = game.level.get(attacker_loc, CHARACTER_ID).unwrap();
+    if game.has_status(oid, Status::Frightened) {
+        true
+    } else if let Some(percent) = attacker.flees_value() {
         let durability = attacker.durability_value().unwrap();
         let x = (durability.current as f64) / (durability.max as f64);
         x <= (percent as f64) / 100.0
@@ -323,3 +560,48 @@ fn wants_to_flee(game: &Game, attacker_loc: &Point) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_game;
+
+    #[test]
+    fn test_search() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        let guard_loc = Point::new(player_loc.x + 100, player_loc.y);
+        let guard = game.add_object(&guard_loc, new_obj(ObjectName::Guard));
+        game.replace_behavior(&guard_loc, Behavior::Attacking(Oid(0), player_loc));
+
+        // The guard can't see the player so it starts searching where it last saw him,
+        assert!(matches!(acted(&mut game, guard, DIAGNOL_MOVE), Acted::DidntAct));
+        let seen = game.now();
+        let behavior = game.level.obj(guard).0.behavior_value();
+        assert_eq!(behavior, Some(Behavior::Searching(Oid(0), player_loc, seen)));
+
+        // spiraling outwards from there,
+        let leg = CARDINAL_MOVE * 3;
+        assert_eq!(search_loc(&player_loc, Time::zero()), player_loc);
+        assert_eq!(
+            search_loc(&player_loc, leg),
+            Point::new(player_loc.x + 1, player_loc.y - 1)
+        );
+        assert_eq!(
+            search_loc(&player_loc, leg * 3),
+            Point::new(player_loc.x - 1, player_loc.y + 1)
+        );
+        assert_eq!(
+            search_loc(&player_loc, leg * 5),
+            Point::new(player_loc.x + 2, player_loc.y - 2)
+        );
+
+        // until it gives up.
+        let seen = game.now() - time::SEARCH - time::secs(1);
+        let behavior = Behavior::Searching(Oid(0), player_loc, seen);
+        game.level.obj_mut(guard).replace(Tag::Behavior(behavior));
+        assert!(matches!(acted(&mut game, guard, DIAGNOL_MOVE), Acted::DidntAct));
+        let behavior = game.level.obj(guard).0.behavior_value();
+        assert!(matches!(behavior, Some(Behavior::Wandering(_))));
+    }
+}
@@ -0,0 +1,87 @@
+//! Ambient events are low frequency flavor messages, e.g. water dripping somewhere or the
+//! murmur of a crowd. Each level's map file lists its events and, as the scheduler advances
+//! time, one is occasionally picked and shown to the player. They don't affect game play:
+//! they have their own random number generator (seeded from the game's seed) so that they
+//! don't perturb the rolls used for everything else.
+use super::time::{self, Time};
+use super::{Game, Message, Topic};
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+
+/// Range, in seconds, between ambient events (~40 to ~110 turns).
+const MIN_DELAY: i64 = 300;
+const MAX_DELAY: i64 = 900;
+
+/// Mixed into the game's seed so that ambience rolls differ from the main rolls.
+const SEED_SALT: u64 = 0xA3B1_E5C7;
+
+pub struct Ambience {
+    events: Vec<String>,
+    next: Time, // when the next event happens
+    rng: SmallRng,
+}
+
+impl Ambience {
+    pub fn new(seed: u64) -> Ambience {
+        Ambience {
+            events: Vec::new(),
+            next: Time::max(),
+            rng: SmallRng::seed_from_u64(seed ^ SEED_SALT),
+        }
+    }
+
+    fn delay(&mut self) -> Time {
+        time::secs(self.rng.gen_range(MIN_DELAY..=MAX_DELAY))
+    }
+}
+
+impl Game {
+    /// Used by make::level with the lines from the map's ambience section.
+    pub(super) fn set_ambience(&mut self, events: Vec<String>) {
+        self.ambience.events = events;
+        self.ambience.next = self.scheduler.now() + self.ambience.delay();
+    }
+
+    /// Called by the scheduler each time it advances time.
+    pub(super) fn ambient_events(&mut self) {
+        if self.scheduler.now() >= self.ambience.next && !self.game_over() {
+            if let Some(text) = self.ambience.events.choose(&mut self.ambience.rng) {
+                self.messages.push(Message::new(Topic::Environmental, text));
+            }
+            self.ambience.next = self.scheduler.now() + self.ambience.delay();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Action;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_ambience() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let ambient = |game: &Game| -> Vec<String> {
+            game.messages
+                .iter()
+                .filter(|m| m.topic == Topic::Environmental)
+                .map(|m| m.text.clone())
+                .collect()
+        };
+
+        // Resting for a while should produce at least one event from the map.
+        let mut game = Game::new_game(&path, 1);
+        for _ in 0..200 {
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.player_acted(Action::Rest);
+        }
+        let events = ambient(&game);
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|text| game.ambience.events.contains(text)));
+    }
+}
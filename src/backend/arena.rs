@@ -149,8 +149,19 @@ impl Game {
     fn new_arena(seed: u64) -> Game {
         let mut game = Game {
             stream: Vec::new(),
-            file: None,
+            checkpoints: Vec::new(),
+            saver: None,
+            path: String::new(),
+            seed,
+            num_actions: 0,
+            next_checkpoint: 0,
+            replay_checkpoints: Vec::new(),
+            divergence: None,
+            play_time: PlayTime::default(),
+            session_start: Instant::now(),
             state: State::Adventuring,
+            wizard: false,
+            combat_details: false,
             scheduler: Scheduler::new(),
 
             rng: RefCell::new(SmallRng::seed_from_u64(seed)),
@@ -159,8 +170,26 @@ impl Game {
             players_move: false,
 
             messages: Vec::new(),
+            messages_removed: 0,
+            hints: Vec::new(),
+            journal: Journal::new(),
+            conducts: Conducts::new(),
+            discoveries: Discoveries::new(),
+            loadouts: Loadouts::new(),
+            polymorphs: Polymorphs::new(),
+            routes: Routes::new(),
+            plans: Plans::new(),
+            statuses: Statuses::new(),
+            circuits: Circuits::new(),
+            ambience: Ambience::new(seed),
+            crowd: Crowd::new(),
+            lighting: Lighting::new(),
+            fumbles: Fumbles::default(),
+            noises: Vec::new(),
+            decorations: Decorations::new(),
+            combat_events: CombatEvents::new(),
             interactions: Interactions::new(),
-            pov: PoV::new(),
+            pov: PoV::new(FovAlgorithm::default()),
             old_pov: OldPoV::new(),
         };
         game.init_game(include_str!("maps/arena.txt"));
@@ -223,13 +252,14 @@ impl Game {
 
         if let Some(obj) = self.level.try_obj(oid) {
             match obj.behavior_value() {
-                Some(Behavior::Attacking(_, _)) => return true, // both still in combat
-                Some(Behavior::Sleeping) => return true,        // opponent hasn't been hit yet
-                Some(Behavior::Wandering(_)) => return true,    // opponent hasn't been hit yet
-                _ => false,                                     // typically opponent started fleeing
+                Some(Behavior::Attacking(_, _)) => true, // both still in combat
+                Some(Behavior::Searching(..)) => true,   // lost sight of the player
+                Some(Behavior::Sleeping) => true,        // opponent hasn't been hit yet
+                Some(Behavior::Wandering(_)) => true,    // opponent hasn't been hit yet
+                _ => false,                              // typically opponent started fleeing
             }
         } else {
-            return false; // opponent was killed
+            false // opponent was killed
         }
     }
 
@@ -239,7 +269,7 @@ impl Game {
 
         let loc = self.loc(attacker_id).unwrap();
         let delay = self.melee_delay(&loc);
-        let hits = self.hit_prob(attacker_id, defender_id);
+        let hits = self.defense_probs(attacker_id, defender_id).land();
 
         let weapon = {
             let attacker = self.level.obj(attacker_id).0;
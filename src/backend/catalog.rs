@@ -0,0 +1,688 @@
+//! Player-facing text is looked up by key so that it can be translated without editing
+//! game logic. The English text is built in and a language file can replace any of it.
+//! Language files have lines like `combat.missed = {attacker} missed {defender}.` where
+//! the braced names are parameters filled in by the game. Multi-line text (e.g. help) uses
+//! \n for line breaks. Blank lines and lines starting with # are ignored.
+//!
+//! There is also a pseudo-locale which accents and brackets catalog text so that any
+//! text that hasn't been moved into the catalog stands out. Help key labels like [[q]]
+//! are left alone so that the help can still be validated.
+use fnv::FnvHashMap;
+use std::fmt::Display;
+use std::fs;
+use std::sync::{OnceLock, RwLock};
+
+// Sorted by key so that lookups can use a binary search.
+const ENGLISH: &[(&str, &str)] = &[
+    ("ability.fear_aura", "{name} radiates an aura of dread."),
+    ("ability.rage", "{name} flies into a rage!"),
+    ("ability.rage.telegraph", "{name}'s eyes go cold."),
+    ("ability.shield_bash", "{name} bashes you with a shield."),
+    ("attack.friend", "Why would you attack a friend?"),
+    ("call.cleared", "You no longer call the {old_name} anything."),
+    ("call.named", "You call the {old_name} \"{name}\"."),
+    ("charge.no_room", "There's no room to charge."),
+    ("charge.pull_up", "You pull up short of {name}."),
+    ("charge.stopped", "Your charge is stopped by {what}."),
+    ("charge.too_close", "You need room to charge."),
+    ("circuit.door_blocked", "Something is blocking a door from closing."),
+    ("circuit.door_closes", "A door slams shut."),
+    ("circuit.door_opens", "A door swings open."),
+    ("circuit.lever", "You pull the lever."),
+    ("circuit.plate.npc", "Something clicks under {name}."),
+    ("circuit.plate.player", "Something clicks under your feet."),
+    ("combat.blocked", "{defender} blocked an attack from {attacker}."),
+    ("combat.crit", "{attacker} critically hit {defender} for {damage} damage."),
+    ("combat.crit_no_damage", "{attacker} critically hit {defender} for no damage."),
+    ("combat.crit_over_kill", "{attacker} critically hit {defender} for {damage} damage ({over} over kill)."),
+    ("combat.hit", "{attacker} hit {defender} for {damage} damage."),
+    ("combat.hit_no_damage", "{attacker} hit {defender} for no damage."),
+    ("combat.hit_over_kill", "{attacker} hit {defender} for {damage} damage ({over} over kill)."),
+    ("combat.killed_by", "You were killed by {attacker}."),
+    ("combat.missed", "{attacker} missed {defender}."),
+    ("combat.parried", "{defender} parried an attack from {attacker}."),
+    ("combat.you", "You"),
+    ("combat.you_object", "you"),
+    ("conduct.challenge", "You've taken the {conduct} challenge."),
+    ("conduct.failed", "You've failed the {conduct} challenge!"),
+    ("conduct.kept", "Conducts kept: {conducts}."),
+    ("conduct.none_kept", "You didn't keep any conducts."),
+    ("crowd.bet", "The spectator bets {gold} gold on you surviving."),
+    ("crowd.boos", "The crowd boos!"),
+    ("crowd.cheers", "The crowd cheers!"),
+    ("crowd.flees", "The crowd flees in terror!"),
+    ("crowd.gasps", "The crowd gasps."),
+    ("crowd.payout", "The spectators who bet on you pay you {gold} gold."),
+    ("decoration.blood", "There is blood here."),
+    ("decoration.gouges", "Something has gouged this."),
+    ("decoration.graffiti", "Someone has scratched \"{text}\" here."),
+    ("dig.bounces", "Your pick-axe bounces off the metal wall doing no damage."),
+    ("dig.chip", "You chip away at the wall with your pick-axe."),
+    ("dig.destroyed", "You destroy the wall!"),
+    ("direction.east", "east"),
+    ("direction.here", "here"),
+    ("direction.north", "north"),
+    ("direction.northeast", "northeast"),
+    ("direction.northwest", "northwest"),
+    ("direction.south", "south"),
+    ("direction.southeast", "southeast"),
+    ("direction.southwest", "southwest"),
+    ("direction.west", "west"),
+    ("door.npc_closes", "{name} closes a door."),
+    ("door.npc_opens", "{name} opens a door."),
+    ("doorman.not_worthy", "You are not worthy."),
+    ("examine.many", "You see{loc}"),
+    ("examine.many.item", "   {desc}."),
+    ("examine.one", "You see {desc}{loc}."),
+    ("examine.pile", "You see a pile of {count} objects{loc}."),
+    ("examine.press_l", "Press l to read more."),
+    ("examine.press_p", "Press p to list them."),
+    ("examine.stale", "You can no longer see there{loc}."),
+    ("examine.unseen", "You've never seen there{loc}."),
+    ("flood.drown", "You drown!"),
+    ("flood.sinks", "The {name} sinks out of sight."),
+    ("flood.step_away", "You step away from the rising water."),
+    ("floor.items", "Items here:"),
+    ("floor.no_items", "There are no items here."),
+    ("floor.standing_on", "You are standing on {terrain}."),
+    ("fumble.drop.npc", "{name} fumbles and drops its {item}."),
+    ("fumble.drop.player", "You fumble and drop your {item}!"),
+    ("fumble.recover.npc", "{name} picks up its {item}."),
+    ("fumble.scratch.npc", "{name} fumbles and cuts itself with its {item}."),
+    ("fumble.scratch.player", "You fumble and cut yourself with your {item}!"),
+    ("fumble.summary", "You fumbled {total} times (dropped your weapon {dropped} times and cut yourself {scratched} times)."),
+    ("game.help", "Press the '?' key for help."),
+    ("game.lost", "You've lost the game!"),
+    ("game.played", "You {played}."),
+    ("game.welcome", "Welcome to 1k-deaths!"),
+    ("game.welcome_back", "Welcome back! You've {played}."),
+    ("game.won", "You have won the game!!"),
+    ("help.auto_play", r#"Auto-play lets NPCs act while the player follows a simple policy.
+
+[[space]] pauses and resumes auto-play.
+[[p]] switches between resting, wandering, and hunting the closest enemy.
+[[+]] speed up.
+[[-]] slow down.
+[[?]] show this help.
+[[escape]] and [[q]] exit auto-play."#),
+    ("help.cutscene", r#"Cutscenes are shown at the start and end of the game.
+
+[[space]] or [[return]] or [[right-arrow]] show the next page.
+[[b]] or [[left-arrow]] show the previous page.
+[[?]] show this help.
+[[escape]] or [[q]] skip the rest of the cutscene."#),
+    ("help.discoveries", r#"Lists the kinds of objects you have seen this game. Stats are shown
+for items you have picked up and characters you have fought. What you
+noticed while fighting is also shown and is remembered in later games
+(unless --no-monster-memory is used).
+
+[[space]] scroll down by one full screen.
+[[b]] scroll up by one full screen.
+[[down-arrow]] scroll down by one line.
+[[up-arrow]] scroll up by one line.
+[[/]] type text to show only matching entries (return to finish).
+[[?]] show this help.
+[[escape]] and [[q]] exit this mode."#),
+    ("help.examine", r#"Move the focus to examine the contents of a cell.
+The focus is drawn with reversed colors. In wizard mode the route the
+examined NPC is following is also drawn.
+
+The focus can be moved with the usual keys:
+[[7]] [[8]] [[9]]                  [[up-arrow]]
+[[4]]   [[6]]           [[left-arrow]]   [[right-arrow]]
+[[1]] [[2]] [[3]]                 [[down-arrow]]
+
+[[tab]] can be used to select the next character.
+[[shift-tab]] can be used to select the previous character.
+[[l]] show lore for the examined cell (if it has any).
+[[p]] list the objects in the examined cell.
+[[t]] travel to the examined cell (using cells you've seen).
+[[?]] show this help.
+[[escape]] and [[q]] exit examine mode."#),
+    ("help.interact", r#"Select what to interact with. Candidates are drawn with a dark background
+and the selected candidate is drawn with reversed colors.
+
+[[tab]] or [[down-arrow]] selects the next candidate.
+[[shift-tab]] or [[up-arrow]] selects the previous candidate.
+[[return]] interacts with the selected candidate.
+[[?]] show this help.
+[[escape]] and [[q]] cancel."#),
+    ("help.inventory", r#"Used to manage the items you've picked up.
+
+Selection can be moved using the numeric keypad or arrow keys:
+[[7]] [[8]] [[9]]                  [[up-arrow]]
+[[4]]   [[6]]           [[left-arrow]]   [[right-arrow]]
+[[1]] [[2]] [[3]]                 [[down-arrow]]
+
+[[return]] operates on the selection.
+[[space]] marks or unmarks the selection.
+[[d]] drops the marked items (or the selection if nothing is marked).
+[[e]] saves the equipped items as an equipment set.
+[[s]] changes the sort order.
+[[/]] filters items by name, type return to finish or escape to clear the filter.
+[[?]] shows this help.
+[[escape]] and [[q]] exit the inventory screen."#),
+    ("help.main", r#"Help for the main game. Note that help is context sensitive,
+e.g. examine mode has its own set of commands and its own help screen.
+
+Movement is done using the numeric keypad or arrow keys:
+[[7]] [[8]] [[9]]                  [[up-arrow]]
+[[4]]   [[6]]           [[left-arrow]]   [[right-arrow]]
+[[1]] [[2]] [[3]]                 [[down-arrow]]
+
+Running (moving until something interesting happens) uses shifted vi keys:
+[[Y]] [[K]] [[U]]
+[[H]]   [[L]]
+[[B]] [[J]] [[N]]
+
+[[5]] or [[s]] rest for one turn.
+[[S]] rest until healed (or something interesting happens).
+[[a]] interact with something next to you (e.g. a door or a character).
+[[c]] charge in a direction and attack the first enemy you reach for extra damage.
+[[d]] toggle the danger overlay (cells visible enemies can see or attack next turn).
+[[e]] swap to the next equipment set (sets are saved in the inventory screen).
+[[g]] pick up items from the ground.
+[[i]] manage inventory items.
+[[:]] list the items on the ground without picking them up.
+[[j]] show the quest journal.
+[[r]] after dying replay the last turns to see what happened.
+[[v]] shove an adjacent character.
+[[w]] withdraw: move slowly away from enemies without giving them a free attack.
+[[x]] examine visible cells.
+[[\]] list the kinds of objects you've seen.
+[[{]] and [[}]] shrink or grow the messages pane.
+[[|]] move the details pane to the left, hide it, or move it back to the right.
+[[control-p]] show recent messages.
+[[?]] show this help.
+[[q]] save and quit"#),
+    ("help.pick_up", r#"Pick up items from the ground.
+
+[[up-arrow]] and [[down-arrow]] select an item.
+[[space]] marks or unmarks the selected item.
+[[a]] marks all the items.
+[[return]] picks up the marked items (or the selected item if none are marked).
+[[?]] show this help.
+[[escape]] and [[q]] exit this screen."#),
+    ("help.replay", r#"Replaying a saved game.
+
+[[space]] toggles replay on and off.
+[[s]] single step replay.
+[[+]] speed up replay.
+[[-]] slow down replay.
+[[?]] show this help.
+[[q]] save and quit.
+[[escape]] exits replay mode."#),
+    ("help.scheduler", r#"Lists the objects that are scheduled to act along with the
+results of their last few chances to act.
+
+[[space]] scroll down by one full screen.
+[[b]] scroll up by one full screen.
+[[down-arrow]] scroll down by one line.
+[[up-arrow]] scroll up by one line.
+[[s]] change the sort order.
+[[?]] show this help.
+[[escape]] and [[q]] exit this mode."#),
+    ("help.stack", r#"Lists the objects in the examined cell.
+
+[[down-arrow]] select the next object.
+[[up-arrow]] select the previous object.
+[[space]] select an object one screen down.
+[[b]] select an object one screen up.
+[[return]] show the selected object in discoveries.
+[[?]] show this help.
+[[escape]] and [[q]] exit this mode."#),
+    ("help.target", r#"Select where to {verb}. The path is drawn with a dark background and anything
+that blocks the path is drawn in red. The target is drawn with reversed colors.
+
+The target can be moved with the usual keys:
+[[7]] [[8]] [[9]]                  [[up-arrow]]
+[[4]]   [[6]]           [[left-arrow]]   [[right-arrow]]
+[[1]] [[2]] [[3]]                 [[down-arrow]]
+
+[[tab]] can be used to select the next enemy.
+[[shift-tab]] can be used to select the previous enemy.
+[[return]] {verb} at the target.
+[[?]] show this help.
+[[escape]] and [[q]] cancel."#),
+    ("help.text", r#"There are a number of keys that allow the screen to be scrolled.
+
+Scroll down by one full screen:
+[[space]] or [[f]] or [[control-f]] or [[control-v]]
+
+[[b]] or [[control-b]] scroll up by one full screen.
+
+Scroll down by one line:
+[[down-arrow]] or [[return]] or [[d]] or [[e]] or [[j]]
+[[control-d]] or [[control-e]] or [[control-j]] or [[control-n]]
+
+Scroll up by one line:
+[[up-arrow]] or [[u]] or [[k]] or [[p]] or [[y]]
+[[control-u]] or [[control-k]] or [[control-p]] or [[control-y]]
+
+[[?]] show this help.
+[[escape]] and [[q]] exit this mode."#),
+    ("help.wizard", "Wizard mode commands:"),
+    ("help.wizard.examine.control_g", "[[control-g]] teleport the player to the examined cell."),
+    ("help.wizard.examine.control_y", "[[control-y]] polymorph the examined character into a random NPC for a while."),
+    ("help.wizard.main.control_a", "[[control-a]] compact memory and show memory usage."),
+    ("help.wizard.main.control_b", "[[control-b]] auto-play so that NPCs can be watched over many turns."),
+    ("help.wizard.main.control_d", "[[control-d]] dump game state to state-xxx.txt."),
+    ("help.wizard.main.control_e", "[[control-e]] export the explored map to map-xxx.html."),
+    ("help.wizard.main.control_k", "[[control-k]] toggle a breakdown of the damage math for each attack."),
+    ("help.wizard.main.control_n", "[[control-n]] toggle the noise overlay (chance NPCs heard noises made this turn)."),
+    ("help.wizard.main.control_o", "[[control-o]] show the objects that are scheduled to act."),
+    ("help.wizard.main.control_t", "[[control-t]] toggle the danger overlay using all enemies and cells."),
+    ("help.wizard.main.control_u", "[[control-u]] undo the last few turns (rewrites the saved game)."),
+    ("help.wizard.main.control_y", "[[control-y]] polymorph the player into a random NPC for a while."),
+    ("hint", "Hint: {text}"),
+    ("hint.attacked", "You're under attack! Move into an enemy to attack it. Resting with 5 or s lets you recover hit points (when it's safe)."),
+    ("hint.near_vitr", "Vitr is raw chaos: touching it is usually fatal but it can also destroy things."),
+    ("hint.near_water", "Shallow water can be waded through but deep water is impassible (and may spread)."),
+    ("hint.picked_up_item", "Press i to manage your inventory, e.g. to wield weapons or wear armor."),
+    ("impassible.deep_water", "The water is too deep."),
+    ("impassible.door", "You fail to open the door."),
+    ("impassible.leave_water", "You can't leave the water."),
+    ("impassible.too_big", "You're too big to fit through the door."),
+    ("impassible.tree", "The tree's are too thick to travel through."),
+    ("impassible.vitr", "Do you have a death wish?"),
+    ("impassible.wall", "You bump into the wall."),
+    ("interact.candidate", "Interact with {name}?"),
+    ("interact.nothing", "There's nothing nearby to interact with."),
+    ("item.drop", "You drop the {name}."),
+    ("item.lands_in_water", "The {name} lands in the water."),
+    ("item.remove", "You remove the {name}."),
+    ("item.throw", "You throw the {name}."),
+    ("item.wear", "You wear the {name}."),
+    ("item.wield", "You wield the {name}."),
+    ("knockback.npc", "{name} is knocked back."),
+    ("knockback.player", "You are knocked back!"),
+    ("light.flickers", "Your {item} flickers."),
+    ("light.out", "Your {item} goes out."),
+    ("light.refueled", "You refill your {item}."),
+    ("light.sputters", "Your {item} sputters, it's nearly out of fuel."),
+    ("loadout.already_using", "You're already using that equipment set."),
+    ("loadout.none", "There are no equipment sets (use e in the inventory screen to save one)."),
+    ("loadout.saved", "Saved equipment set {index} ({name})."),
+    ("lock.locked", "The door is locked."),
+    ("lock.unlocked", "You unlock the door with the {item}."),
+    ("noise.landed", "The {name} clatters noisily."),
+    ("object.bell", "a small brass bell that rings loudly when it's thrown"),
+    ("object.broadsword", "a dull broadsword"),
+    ("object.broken_god", "One of seven broken Logros T'lan Imass worshipped as gods by the Teblor."),
+    ("object.closed_door", "a closed door"),
+    ("object.dagger", "a pointy dagger"),
+    ("object.deep_water", "deep water"),
+    ("object.dirt", "a patch of dirt"),
+    ("object.doorman", "a royal guard"),
+    ("object.emperor_sword", "the Sword of the Crippled God"),
+    ("object.greater_armory_sign", "a sign that says 'the Greater Armory'"),
+    ("object.guard", "a low level guard armed with a spear"),
+    ("object.icarium", "Icarium Lifestealer, a mixed blood Jahgut. He looks extremely dangerous"),
+    ("object.iron_key", "a heavy iron key"),
+    ("object.lantern", "a hooded oil lantern"),
+    ("object.leather_chest", "a leather chest"),
+    ("object.leather_gloves", "a leather gloves"),
+    ("object.leather_hat", "a leather hat"),
+    ("object.leather_legs", "leather shin guard"),
+    ("object.leather_sandals", "a leather sandals"),
+    ("object.lesser_armory_sign", "a sign that says 'the Lesser Armory'"),
+    ("object.lever", "a lever set into the floor"),
+    ("object.locked_door", "a closed door with an iron lock"),
+    ("object.long_knife", "a shiny long knife"),
+    ("object.long_sword", "a nicked long sword"),
+    ("object.metal_wall", "a metal wall"),
+    ("object.mighty_sword", "the Sword of Impending Doom"),
+    ("object.oil_flask", "a stoppered flask of lamp oil"),
+    ("object.open_door", "an open door"),
+    ("object.pick_axe", "a pick-axe"),
+    ("object.player", "yourself"),
+    ("object.pressure_plate", "a stone plate that shifts slightly when stepped on"),
+    ("object.rhulad", "the Emperor of a Thousand Deaths"),
+    ("object.rock", "a fist sized rock, good for throwing"),
+    ("object.rubble", "a destroyed wall"),
+    ("object.shallow_water", "shallow water"),
+    ("object.spear", "a long ash spear"),
+    ("object.spectator", "a spectator"),
+    ("object.stone_wall", "a stone wall"),
+    ("object.torch", "a pitch soaked torch"),
+    ("object.tree", "a tree"),
+    ("object.vitr", "a pool of chaotic acid"),
+    ("opportunity.npc", "You strike at {name} as it backs away."),
+    ("opportunity.player", "{name} strikes at you as you disengage."),
+    ("pick_up.done", "You pick up the {name}."),
+    ("pick_up.many", "There are {count} items here."),
+    ("pick_up.no_room", "You don't have enough inventory space to pick up the {name}."),
+    ("pick_up.nothing", "There's nothing here to pick up."),
+    ("polymorph.end.npc", "{name} returns to its normal form."),
+    ("polymorph.end.player", "You return to your normal form."),
+    ("polymorph.nobody", "There's nothing there to polymorph."),
+    ("polymorph.npc", "{old_name} turns into {new_name}!"),
+    ("polymorph.player", "You turn into {name}!"),
+    ("profile.title", "You've earned the title \"{title}\"."),
+    ("quest.destroy_sword", "Destroy the Crippled God's sword."),
+    ("quest.destroy_sword.dissolved", "You carefully place the Emperor's sword into the vitr and watch it dissolve."),
+    ("quest.destroy_sword.done", "The sword has been dissolved in vitr."),
+    ("quest.destroy_sword.fallen", "The sword lies where Rhulad fell."),
+    ("quest.destroy_sword.hint", "Emperor Rhulad wields the sword somewhere within the palace."),
+    ("quest.destroy_sword.vitr", "Vitr is said to dissolve almost anything, even the work of gods."),
+    ("quest.kill_rhulad", "Kill Emperor Rhulad."),
+    ("quest.kill_rhulad.done", "Rhulad is dead, for now."),
+    ("quest.kill_rhulad.hint", "Rhulad has died many times before. Perhaps this time it will stick."),
+    ("rest.not_needed", "You don't need to rest."),
+    ("review.cant_load", "Couldn't load {path} for review: {err}"),
+    ("review.not_dead", "You can only review after dying."),
+    ("review.press_r", "Press r to review your last {turns} turns."),
+    ("rhulad.killed", "The Crippled God whispers, 'You shall pay for this mortal'."),
+    ("save.cant_append", "Couldn't open {path} for appending: {err}"),
+    ("save.cant_read", "Couldn't open {path} for reading: {err}"),
+    ("save.cant_write", "Couldn't open {path} for writing: {err}"),
+    ("save.failed", "Couldn't save game: {err}"),
+    ("save.torn", "The end of {path} was damaged ({reason}) so the last few turns were lost."),
+    ("shove.doesnt_budge", "{name} doesn't budge."),
+    ("shove.nobody", "There's nobody there to shove."),
+    ("shove.too_big", "{name} is too big to shove."),
+    ("sign.read", "You see a sign {desc}."),
+    ("slam.npc", "{name} slams into {what} for {damage} damage."),
+    ("slam.player", "You slam into {what} for {damage} damage."),
+    ("sound.door", "a door"),
+    ("sound.fighting", "fighting"),
+    ("sound.heard", "You hear {what} to the {dir}."),
+    ("sound.rushing_water", "rushing water"),
+    ("spectator.praise1", "I can't believe that the Emperor is dead."),
+    ("spectator.praise2", "You're my hero!"),
+    ("spectator.praise3", "You've done the impossible!"),
+    ("spectator.taunt1", "I hope you're prepared to die!"),
+    ("spectator.taunt2", "The last champion only lasted thirty seconds."),
+    ("spectator.taunt3", "How can you defeat a man who will not stay dead?"),
+    ("spectator.taunt4", "I have 10 gold on you lasting over two minutes!"),
+    ("spectator.taunt5", "You're just another dead man walking."),
+    ("stats.attributes", "It has {strength} strength and {dexterity} dexterity."),
+    ("stats.crit", "It has a {percent}% to critically hit."),
+    ("stats.damage", "Base damage is {damage} with delay {delay} ({dps} dps)."),
+    ("stats.damage.main_hand", "Base damage is {damage} with delay {delay} ({dps} dps in main hand)."),
+    ("stats.hit_points", "It has {hps} hit points."),
+    ("stats.mitigation", "It will mitigate damage by {percent}%."),
+    ("stats.one_hand", "It is a one handed weapon."),
+    ("stats.two_hand", "It is a two handed weapon."),
+    ("stats.unarmed", "Unarmed it does {damage} base damage with delay {delay}."),
+    ("status.blind.end.npc", "{name} can see again."),
+    ("status.blind.end.player", "You can see again."),
+    ("status.blind.start.npc", "{name} is blinded."),
+    ("status.blind.start.player", "You are blind!"),
+    ("status.confused.end.npc", "{name} looks less confused."),
+    ("status.confused.end.player", "Your head clears."),
+    ("status.confused.stagger", "You stagger."),
+    ("status.confused.start.npc", "{name} looks confused."),
+    ("status.confused.start.player", "You are dazed!"),
+    ("status.dazzled.end.npc", "{name} is no longer dazzled."),
+    ("status.dazzled.end.player", "Your vision clears."),
+    ("status.dazzled.start.npc", "{name} is dazzled."),
+    ("status.dazzled.start.player", "You are dazzled!"),
+    ("status.frightened.end.npc", "{name} regains its courage."),
+    ("status.frightened.end.player", "You feel brave again."),
+    ("status.frightened.start.npc", "{name} looks frightened."),
+    ("status.frightened.start.player", "You are frightened!"),
+    ("status.hasted.end.npc", "{name} slows down."),
+    ("status.hasted.end.player", "You feel yourself slow down."),
+    ("status.hasted.start.npc", "{name} speeds up."),
+    ("status.hasted.start.player", "You feel yourself speed up."),
+    ("status.healing.end.npc", "{name} stops healing."),
+    ("status.healing.end.player", "You stop healing."),
+    ("status.healing.start.npc", "{name}'s wounds begin to close."),
+    ("status.healing.start.player", "Your wounds begin to close."),
+    ("status.slowed.end.npc", "{name} speeds up."),
+    ("status.slowed.end.player", "You feel yourself speed up."),
+    ("status.slowed.start.npc", "{name} slows down."),
+    ("status.slowed.start.player", "You feel sluggish."),
+    ("teleport.cant", "You can't teleport there."),
+    ("terrain.rubble", "You pick your way through the rubble."),
+    ("terrain.shallow_water", "You splash through the water."),
+    ("undo.cant_load", "Couldn't load {path} for undo: {err}"),
+    ("undo.cant_replace", "Couldn't replace {path} for undo: {err}"),
+    ("undo.done", "Undid {turns} turns."),
+    ("use.cant", "You can't use the {item} there."),
+    ("use.too_far", "You need to be next to what you're using that on."),
+    ("verb.charge", "charge"),
+    ("verb.shove", "shove"),
+    ("verb.throw", "throw"),
+    ("verb.withdraw", "withdraw"),
+    ("withdraw.no_room", "There's no room to withdraw there."),
+];
+
+/// Text loaded from a language file along with the pseudo-locale setting.
+#[derive(Default)]
+struct Catalog {
+    texts: FnvHashMap<String, String>,
+    pseudo: bool,
+}
+
+impl Catalog {
+    fn load(&mut self, path: &str) -> Result<Vec<String>, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("Couldn't read {path}: {err}"))?;
+
+        let mut warnings = Vec::new();
+        let mut texts = FnvHashMap::default();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_line(line) {
+                Ok((key, text)) => {
+                    texts.insert(key.to_string(), text);
+                }
+                Err(err) => warnings.push(format!("{path} line {}: {err}", i + 1)),
+            }
+        }
+        self.texts = texts;
+        Ok(warnings)
+    }
+
+    // Keys that aren't in the catalog fall back to the key itself so that a typo doesn't
+    // take down the game.
+    fn text(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let template = match self.texts.get(key).map(|s| s.as_str()).or_else(|| english(key)) {
+            Some(template) => template,
+            None => {
+                warn!("'{key}' isn't in the message catalog");
+                key
+            }
+        };
+        if self.pseudo {
+            substitute(&pseudo_localize(template), args)
+        } else {
+            substitute(template, args)
+        }
+    }
+}
+
+// The catalog is shared by every thread, e.g. the server's client threads.
+fn catalog() -> &'static RwLock<Catalog> {
+    static CATALOG: OnceLock<RwLock<Catalog>> = OnceLock::new();
+    CATALOG.get_or_init(|| RwLock::new(Catalog::default()))
+}
+
+/// Replaces the built in text with text from a language file. Returns warnings for
+/// problems that didn't prevent the file from being used (e.g. unknown keys).
+pub fn load_catalog(path: &str) -> Result<Vec<String>, String> {
+    catalog().write().unwrap().load(path)
+}
+
+/// When enabled catalog text is accented and wrapped in brackets.
+pub fn set_pseudo_locale(enabled: bool) {
+    catalog().write().unwrap().pseudo = enabled;
+}
+
+/// Returns the text for key with the braced parameters replaced by args.
+pub fn text(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    catalog().read().unwrap().text(key, args)
+}
+
+// Parameters are replaced in a single pass so that braces within argument values (e.g.
+// names the player gave to items) are copied as is.
+fn substitute(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let arg = rest.find('}').and_then(|end| {
+            args.iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|arg| (end, arg.1))
+        });
+        match arg {
+            Some((end, value)) => {
+                result.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn english(key: &str) -> Option<&'static str> {
+    ENGLISH
+        .binary_search_by_key(&key, |(k, _)| k)
+        .ok()
+        .map(|i| ENGLISH[i].1)
+}
+
+fn parse_line(line: &str) -> Result<(&str, String), String> {
+    let (key, text) = line.split_once('=').ok_or("expected 'key = text'")?;
+    let (key, text) = (key.trim(), text.trim().replace("\\n", "\n"));
+    let default = english(key).ok_or(format!("unknown key '{key}'"))?;
+    for param in params(&text) {
+        if !params(default).contains(&param) {
+            return Err(format!("'{key}' doesn't have a '{param}' parameter"));
+        }
+    }
+    Ok((key, text))
+}
+
+fn params(text: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let text = strip_labels(text);
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find('{') {
+        match rest[start..].find('}') {
+            Some(end) => {
+                result.push(rest[start + 1..start + end].to_string());
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    result
+}
+
+// Help uses labels like [[{]] which aren't parameters.
+fn strip_labels(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        result.push_str(&rest[..start]);
+        match rest[start..].find("]]") {
+            Some(end) => rest = &rest[start + end + 2..],
+            None => return result,
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// Parameters and help labels are left alone so that they can still be used.
+fn pseudo_localize(text: &str) -> String {
+    let mut result = String::from("\u{27E6}");
+    let mut in_param = false;
+    let mut in_label = false;
+    let mut prev = ' ';
+    for ch in text.chars() {
+        match ch {
+            '[' if prev == '[' => in_label = true,
+            ']' if prev == ']' => in_label = false,
+            '{' if !in_label => in_param = true,
+            '}' if !in_label => in_param = false,
+            _ => (),
+        }
+        prev = ch;
+        let ch = if in_param || in_label {
+            ch
+        } else {
+            match ch {
+                'a' => 'á',
+                'e' => 'é',
+                'i' => 'í',
+                'o' => 'ö',
+                'u' => 'ü',
+                'A' => 'Å',
+                'E' => 'É',
+                'I' => 'Î',
+                'O' => 'Ø',
+                'U' => 'Ü',
+                _ => ch,
+            }
+        };
+        result.push(ch);
+    }
+    result.push('\u{27E7}');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The catalog is shared by all threads so tests use their own instance (other tests
+    // check message text).
+    #[test]
+    fn test_catalog() {
+        let mut catalog = Catalog::default();
+        let text = catalog.text("combat.missed", &[("attacker", &"You"), ("defender", &"a guard")]);
+        assert_eq!(text, "You missed a guard.");
+
+        let path = format!("/tmp/catalog-{}.txt", line!());
+        fs::write(
+            &path,
+            "# comment\ncombat.missed = {defender} dodged {attacker}.\nbogus = text\nslam.npc = {bogus}\nhelp.wizard = Wizard\\ncommands:\n",
+        )
+        .unwrap();
+        let warnings = catalog.load(&path).unwrap();
+        assert_eq!(warnings.len(), 2);
+        let text = catalog.text("combat.missed", &[("attacker", &"You"), ("defender", &"a guard")]);
+        assert_eq!(text, "a guard dodged You.");
+        assert_eq!(catalog.text("help.wizard", &[]), "Wizard\ncommands:");
+
+        catalog.pseudo = true;
+        assert_eq!(catalog.text("combat.you", &[]), "\u{27E6}Yöü\u{27E7}");
+        let text = catalog.text("knockback.npc", &[("name", &"Rhulad")]);
+        assert_eq!(text, "\u{27E6}Rhulad ís knöckéd báck.\u{27E7}");
+        let text = catalog.text("help.main", &[]);
+        assert!(text.contains("[[{]] ánd [[}]]"), "{text}");
+    }
+
+    #[test]
+    fn test_substitute() {
+        let catalog = Catalog::default();
+
+        // Argument values aren't substituted again.
+        let text = catalog.text("call.named", &[("old_name", &"{name}"), ("name", &"{old_name}")]);
+        assert_eq!(text, "You call the {name} \"{old_name}\".");
+
+        // Unknown keys and parameters are left alone.
+        assert_eq!(catalog.text("no.such.key", &[("name", &"x")]), "no.such.key");
+        assert_eq!(substitute("{a} {b} {", &[("a", &1)]), "1 {b} {");
+    }
+
+    #[test]
+    fn test_sorted() {
+        for pair in ENGLISH.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{} should come before {}", pair[1].0, pair[0].0);
+        }
+    }
+}
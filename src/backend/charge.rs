@@ -0,0 +1,164 @@
+//! Characters can charge up to CHARGE_DISTANCE cells in a straight line and attack the
+//! character they run into for extra damage. Heavy NPCs (like the Broken) use this to
+//! close in on the player.
+use super::catalog;
+use super::sound;
+use super::tag::*;
+use super::time::{self, Time};
+use super::{Game, Message, Oid, Point, Topic};
+
+/// Maximum number of cells a charge can move.
+pub const CHARGE_DISTANCE: i32 = 4;
+
+/// Percent of normal damage done by the attack at the end of a charge.
+const CHARGE_DAMAGE: i32 = 150;
+
+impl Game {
+    /// Moves the character at loc in the (dx, dy) direction until it runs into another
+    /// character (which it then attacks), is blocked, or has moved CHARGE_DISTANCE cells.
+    /// Returns the time taken which is zero if the character couldn't move at all.
+    pub(super) fn do_charge(&mut self, loc: &Point, dx: i32, dy: i32) -> Time {
+        let oid = self.level.get(loc, CHARACTER_ID).unwrap().0;
+        let step = if dx != 0 && dy != 0 {
+            time::DIAGNOL_MOVE
+        } else {
+            time::CARDINAL_MOVE
+        };
+        debug!("{oid} is charging from {loc} towards {dx}, {dy}");
+
+        let mut taken = Time::zero();
+        let mut loc = *loc;
+        for moved in 0..=CHARGE_DISTANCE {
+            let next = Point::new(loc.x + dx, loc.y + dy);
+            if let Some((defender, _)) = self.level.get(&next, CHARACTER_ID) {
+                if moved > 0 && self.can_charge(oid, defender) {
+                    taken += self.melee_delay(&loc);
+                    self.do_scaled_melee_attack(&loc, &next, CHARGE_DAMAGE);
+                } else if oid.0 == 0 {
+                    let name = self.level.obj(defender).0.name_value().unwrap();
+                    let text = if moved > 0 {
+                        catalog::text("charge.pull_up", &[("name", &name)])
+                    } else {
+                        catalog::text("charge.too_close", &[])
+                    };
+                    let topic = if moved > 0 { Topic::Normal } else { Topic::Failed };
+                    self.messages.push(Message::new(topic, &text));
+                }
+                break;
+            }
+            if moved == CHARGE_DISTANCE || self.game_over() {
+                break;
+            }
+            if !self.can_teleport_to(oid, &next) {
+                if oid.0 == 0 {
+                    let what = self.level.get_bottom(&next).1.description();
+                    let text = if moved > 0 {
+                        catalog::text("charge.stopped", &[("what", &what)])
+                    } else {
+                        catalog::text("charge.no_room", &[])
+                    };
+                    let topic = if moved > 0 { Topic::Normal } else { Topic::Failed };
+                    self.messages.push(Message::new(topic, &text));
+                }
+                break;
+            }
+
+            self.do_move(oid, &loc, &next);
+            taken += step;
+            if oid.0 == 0 {
+                taken += self.interact_post_move(&next).0;
+            }
+            loc = next;
+        }
+
+        if oid.0 == 0 && taken > Time::zero() {
+            self.handle_noise(&loc, sound::QUIET);
+        }
+        taken
+    }
+
+    /// If the attacker at attacker_loc can charge the defender returns the direction of
+    /// the charge and how long it will take.
+    pub(super) fn charge_plan(&self, attacker_loc: &Point, defender_loc: &Point) -> Option<(i32, i32, Time)> {
+        let (attacker_id, attacker) = self.level.get(attacker_loc, CHARACTER_ID).unwrap();
+        if !attacker.has(CHARGES_ID) {
+            return None;
+        }
+
+        let (dx, dy) = (defender_loc.x - attacker_loc.x, defender_loc.y - attacker_loc.y);
+        if dx != 0 && dy != 0 && dx.abs() != dy.abs() {
+            return None;
+        }
+        let distance = i32::max(dx.abs(), dy.abs());
+        if !(2..=CHARGE_DISTANCE + 1).contains(&distance) {
+            return None;
+        }
+
+        let (dx, dy) = (dx.signum(), dy.signum());
+        for i in 1..distance {
+            let loc = Point::new(attacker_loc.x + i * dx, attacker_loc.y + i * dy);
+            if !self.can_teleport_to(attacker_id, &loc) {
+                return None;
+            }
+        }
+
+        let step = if dx != 0 && dy != 0 {
+            time::DIAGNOL_MOVE
+        } else {
+            time::CARDINAL_MOVE
+        };
+        let delay = step * (distance - 1) as i64 + self.melee_delay(attacker_loc);
+        Some((dx, dy, delay))
+    }
+
+    // The player charges enemies and NPCs charge the player.
+    fn can_charge(&self, attacker: Oid, defender: Oid) -> bool {
+        if attacker.0 == 0 {
+            let defender = self.level.obj(defender).0;
+            matches!(defender.disposition_value(), Some(Disposition::Aggressive))
+        } else {
+            defender.0 == 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::test_game;
+    use crate::backend::{Action, ObjectName};
+
+    #[test]
+    fn test_charge() {
+        let mut game = test_game();
+
+        // Find a direction with room to charge an enemy.
+        let player_loc = game.player_loc();
+        let deltas = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+        let (dx, dy) = *deltas
+            .iter()
+            .find(|(dx, dy)| {
+                (1..=3).all(|i| game.can_teleport_to(Oid(0), &Point::new(player_loc.x + i * dx, player_loc.y + i * dy)))
+            })
+            .unwrap();
+        let far = Point::new(player_loc.x + 3 * dx, player_loc.y + 3 * dy);
+        let enemy = game.add_object(&far, new_obj(ObjectName::ImrothTheCruel));
+        assert!(game.charge_plan(&far, &player_loc).is_some());
+
+        // The player stops next to the enemy and attacks it.
+        game.player_acted(Action::Charge { dx, dy });
+        assert_eq!(
+            game.player_loc(),
+            Point::new(player_loc.x + 2 * dx, player_loc.y + 2 * dy)
+        );
+        let behavior = game.level.obj(enemy).0.behavior_value();
+        assert!(matches!(behavior, Some(Behavior::Attacking(Oid(0), _))));
+
+        // There has to be room to charge.
+        let loc = game.player_loc();
+        game.player_acted(Action::Charge { dx, dy });
+        assert_eq!(game.player_loc(), loc);
+        assert!(game.recent_messages(1).any(|m| m.text.contains("room to charge")));
+    }
+}
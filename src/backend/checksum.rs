@@ -0,0 +1,390 @@
+//! Checksums used to detect when replaying a saved game diverges from the original game.
+//! This will typically happen when game logic changes in a way that isn't compatible with
+//! older saved games. Every so often a Checkpoint is recorded in the saved game and, when
+//! replaying, the checkpoint is recomputed and compared against the saved version.
+use super::object::Symbol;
+use super::tag::CHARACTER_ID;
+use super::{Game, Point};
+use fnv::FnvHasher;
+use rand::RngCore;
+use std::hash::Hasher;
+
+/// Number of actions between checkpoints. Smaller values make it easier to find the
+/// action that caused the divergence but bloat saved games.
+pub const CHECKPOINT_INTERVAL: u64 = 100;
+
+/// Radius of the area around the player that is saved with each checkpoint.
+const SURROUNDINGS_RADIUS: i32 = 5;
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub index: u64,                // index of the action within the saved game
+    pub turn: i64,                 // scheduler time (in ms)
+    pub checksum: u64,             // covers the level, the scheduler, and the RNG
+    pub surroundings: Vec<String>, // ASCII rendering of the cells around the player
+}
+
+/// Recorded when a replayed game's checkpoint doesn't match the saved checkpoint.
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    pub index: u64,
+    pub turn: i64,
+    pub report: Vec<String>, // human readable description including a diff of the player's surroundings
+}
+
+impl Game {
+    pub(super) fn checkpoint(&self, index: u64) -> Checkpoint {
+        Checkpoint {
+            index,
+            turn: self.scheduler.now().as_ms(),
+            checksum: self.checksum(),
+            surroundings: self.surroundings(),
+        }
+    }
+
+    /// Called after an action is replayed (or done for the first time). If a checkpoint
+    /// is due then this returns it.
+    pub(super) fn checkpoint_due(&mut self, index: u64) -> Option<Checkpoint> {
+        if index >= self.next_checkpoint {
+            self.next_checkpoint = index + CHECKPOINT_INTERVAL;
+            Some(self.checkpoint(index))
+        } else {
+            None
+        }
+    }
+
+    /// Compares a checkpoint computed while replaying against the one in the saved game.
+    pub(super) fn verify_checkpoint(&mut self, actual: Checkpoint) {
+        while !self.replay_checkpoints.is_empty() && self.replay_checkpoints[0].index < actual.index {
+            // This can happen if CHECKPOINT_INTERVAL changes.
+            self.replay_checkpoints.remove(0);
+        }
+        if !self.replay_checkpoints.is_empty() && self.replay_checkpoints[0].index == actual.index {
+            let expected = self.replay_checkpoints.remove(0);
+            if expected.checksum != actual.checksum || expected.turn != actual.turn {
+                let divergence = Divergence {
+                    index: actual.index,
+                    turn: actual.turn,
+                    report: divergence_report(&expected, &actual),
+                };
+                for line in divergence.report.iter() {
+                    error!("{line}");
+                }
+                self.divergence = Some(divergence);
+            }
+        }
+    }
+
+    fn checksum(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        hasher.write_u8(self.state as u8);
+        self.level.checksum(&mut hasher);
+        self.scheduler.checksum(&mut hasher);
+
+        // We don't want to disturb the RNG so we use a copy to verify that it's in sync.
+        let mut rng = self.rng.borrow().clone();
+        hasher.write_u64(rng.next_u64());
+        hasher.finish()
+    }
+
+    fn surroundings(&self) -> Vec<String> {
+        let center = self.player_loc();
+        let mut lines = Vec::new();
+        for dy in -SURROUNDINGS_RADIUS..=SURROUNDINGS_RADIUS {
+            let mut line = String::new();
+            for dx in -SURROUNDINGS_RADIUS..=SURROUNDINGS_RADIUS {
+                let loc = Point::new(center.x + dx, center.y + dy);
+                line.push(self.cell_char(&loc));
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn cell_char(&self, loc: &Point) -> char {
+        if let Some((_, ch)) = self.level.get(loc, CHARACTER_ID) {
+            if let Some(durability) = ch.durability_value() {
+                // Include a hint about HPs so that combat differences show up.
+                if durability.current < durability.max {
+                    return match ch.to_fg_symbol().1 {
+                        Symbol::Player => '&',
+                        Symbol::Npc(c) => c.to_ascii_uppercase(),
+                        _ => '?',
+                    };
+                }
+            }
+        }
+        self.level.get_top(loc).1.to_fg_symbol().1.to_ascii()
+    }
+}
+
+fn divergence_report(expected: &Checkpoint, actual: &Checkpoint) -> Vec<String> {
+    let mut report = vec![
+        format!("Replay diverged at action {} (turn {} ms).", actual.index, actual.turn),
+        format!(
+            "Expected turn {} ms and checksum {:X}.",
+            expected.turn, expected.checksum
+        ),
+        format!("Found turn {} ms and checksum {:X}.", actual.turn, actual.checksum),
+        "".to_string(),
+        "Player surroundings (expected, actual, diff):".to_string(),
+    ];
+    let blank = String::new();
+    let count = std::cmp::max(expected.surroundings.len(), actual.surroundings.len());
+    for i in 0..count {
+        let e = expected.surroundings.get(i).unwrap_or(&blank);
+        let a = actual.surroundings.get(i).unwrap_or(&blank);
+        let diff: String = e
+            .chars()
+            .zip(a.chars())
+            .map(|(x, y)| if x == y { ' ' } else { '^' })
+            .collect();
+        report.push(format!("{e}   {a}   {diff}"));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::tag::{Durability, Tag};
+    use crate::backend::{Action, ObjectName, Oid};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::fs;
+    use std::thread;
+
+    /// Checksum for test_determinism's game.
+    const DETERMINISM_CHECKSUM: u64 = 9565082089211036809;
+
+    // Plays a game the same way that the terminal UI does.
+    fn play(path: &str, seed: u64, count: usize) -> (u64, Vec<Action>) {
+        let mut game = Game::new_game(path, seed);
+        play_game(&mut game, seed, count)
+    }
+
+    fn play_game(game: &mut Game, seed: u64, count: usize) -> (u64, Vec<Action>) {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut actions = Vec::new();
+        while actions.len() < count {
+            if game.players_turn() {
+                let action = match rng.gen_range(0..5) {
+                    0 => Action::Rest,
+                    _ => Action::Move {
+                        dx: rng.gen_range(-1..=1),
+                        dy: if rng.gen_bool(0.5) { -1 } else { 1 },
+                    },
+                };
+                game.player_acted(action.clone());
+                actions.push(action);
+            } else {
+                game.advance_time(false);
+            }
+        }
+        (game.checksum(), actions)
+    }
+
+    // Replays a game the same way that the terminal UI does.
+    fn replay(game: &mut Game, mut actions: Vec<Action>) {
+        while !actions.is_empty() && game.divergence().is_none() {
+            if game.players_turn() {
+                let action = actions.remove(0);
+                game.replay_action(action);
+            } else {
+                game.advance_time(true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        // Does replaying a game produce the same state as the original game?
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let (checksum, _) = play(&path, 1, 3 * CHECKPOINT_INTERVAL as usize);
+
+        let (mut game, actions) = Game::old_game(&path, Vec::new());
+        assert!(!game.replay_checkpoints.is_empty());
+        replay(&mut game, actions);
+        assert!(game.divergence().is_none());
+        assert_eq!(game.checksum(), checksum);
+    }
+
+    #[test]
+    fn test_skip_replay() {
+        // Replaying without the UI driving time should also work.
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let (checksum, _) = play(&path, 2, 2 * CHECKPOINT_INTERVAL as usize);
+
+        let (mut game, actions) = Game::old_game(&path, Vec::new());
+        for action in actions {
+            game.replay_action(action);
+        }
+        assert!(game.divergence().is_none());
+        assert_eq!(game.checksum(), checksum);
+    }
+
+    #[test]
+    fn test_replay_killed_npcs() {
+        // NPCs killed before their turn in the round are removed by the scheduler which
+        // shouldn't throw off the action indexes used by checkpoints.
+        fn add_guards(game: &mut Game) -> Vec<(i32, i32)> {
+            let player_loc = game.player_loc();
+            let mut deltas = Vec::new();
+            for (dx, dy) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)] {
+                let loc = Point::new(player_loc.x + dx, player_loc.y + dy);
+                if game.can_teleport_to(Oid(0), &loc) {
+                    let oid = game.add_object(&loc, new_obj(ObjectName::Guard));
+                    let durability = Durability { current: 1, max: 100 };
+                    game.level.obj_mut(oid).replace(Tag::Durability(durability));
+                    deltas.push((dx, dy));
+                }
+            }
+            deltas
+        }
+
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        let deltas = add_guards(&mut game);
+        for &(dx, dy) in deltas.iter().cycle().take(3 * deltas.len()) {
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.player_acted(Action::Move { dx, dy });
+        }
+        let (checksum, _) = play_game(&mut game, 1, 2 * CHECKPOINT_INTERVAL as usize);
+        let num_actions = game.num_actions;
+        drop(game);
+
+        let (mut game, actions) = Game::old_game(&path, Vec::new());
+        add_guards(&mut game);
+        replay(&mut game, actions);
+        assert!(game.divergence().is_none());
+        assert_eq!(game.num_actions, num_actions);
+        assert!(game.stream.is_empty());
+        assert_eq!(game.checksum(), checksum);
+    }
+
+    #[test]
+    fn test_divergence() {
+        // Do we detect when a replay doesn't match the saved game?
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        play(&path, 3, 3 * CHECKPOINT_INTERVAL as usize);
+
+        let (mut game, mut actions) = Game::old_game(&path, Vec::new());
+        let index = actions.iter().position(|a| matches!(a, Action::Move { .. })).unwrap();
+        actions.insert(index, Action::Rest);
+        replay(&mut game, actions);
+
+        let divergence = game.divergence().expect("replay should have diverged");
+        assert!(divergence.index >= index as u64);
+        assert!(divergence.report[0].contains("Replay diverged"));
+    }
+
+    #[test]
+    fn test_determinism() {
+        // Playing the same seed with the same actions should always produce the same game.
+        let path1 = format!("/tmp/saved-{}.game", line!());
+        let path2 = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path1);
+        let _ = fs::remove_file(&path2);
+
+        // The second game is played on another thread so that collections using a
+        // randomly seeded hasher iterate in a different order.
+        let (checksum1, actions1) = play(&path1, 7, 2 * CHECKPOINT_INTERVAL as usize);
+        let (checksum2, actions2) = thread::spawn(move || play(&path2, 7, 2 * CHECKPOINT_INTERVAL as usize))
+            .join()
+            .unwrap();
+        assert_eq!(actions1, actions2);
+        assert_eq!(checksum1, checksum2);
+
+        // Catches differences that are the same within a run, e.g. code that depends on
+        // how a hashed collection happens to be laid out. This has to be updated whenever
+        // game logic intentionally changes.
+        assert_eq!(checksum1, DETERMINISM_CHECKSUM);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_invariants_are_read_only() {
+        // Enabling --invariants shouldn't change how the game plays out.
+        let path1 = format!("/tmp/saved-{}.game", line!());
+        let path2 = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path1);
+        let _ = fs::remove_file(&path2);
+
+        let (expected, actions1) = play(&path1, 9, CHECKPOINT_INTERVAL as usize);
+
+        let mut game = Game::new_game(&path2, 9);
+        game.set_invariants(true);
+        let (checksum, actions2) = play_game(&mut game, 9, CHECKPOINT_INTERVAL as usize);
+        assert_eq!(actions1, actions2);
+        assert_eq!(checksum, expected);
+    }
+
+    #[test]
+    fn test_undo() {
+        // Undoing turns should be the same as never having done them.
+        let path1 = format!("/tmp/saved-{}.game", line!());
+        let path2 = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path1);
+        let _ = fs::remove_file(&path2);
+
+        let (expected, _) = play(&path2, 4, 140);
+
+        let mut game = Game::new_game(&path1, 4);
+        let mut rng = SmallRng::seed_from_u64(4);
+        let mut count = 0;
+        while count < 150 {
+            if game.players_turn() {
+                let action = match rng.gen_range(0..5) {
+                    0 => Action::Rest,
+                    _ => Action::Move {
+                        dx: rng.gen_range(-1..=1),
+                        dy: if rng.gen_bool(0.5) { -1 } else { 1 },
+                    },
+                };
+                game.player_acted(action);
+                count += 1;
+            } else {
+                game.advance_time(false);
+            }
+        }
+        game.undo(10);
+        assert_eq!(game.checksum(), expected);
+        assert!(fs::metadata(format!("{path1}.undo")).is_err());
+
+        // The rewritten saved game should also replay correctly.
+        drop(game);
+        let (mut game, actions) = Game::old_game(&path1, Vec::new());
+        replay(&mut game, actions);
+        assert!(game.divergence().is_none());
+        assert_eq!(game.checksum(), expected);
+    }
+
+    #[test]
+    fn test_undo_keeps_wizard_mode() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 4);
+        for action in [Action::Rest, Action::Rest, Action::WizardMode] {
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.player_acted(action);
+        }
+        assert!(game.used_wizard_mode());
+
+        game.undo(1);
+        assert!(game.used_wizard_mode());
+    }
+}
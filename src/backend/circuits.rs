@@ -0,0 +1,149 @@
+//! Levers and pressure plates that open and close doors elsewhere on the level. Circuits
+//! are declared at the end of a level file, e.g. `gate = 10,5 12,5 14,7`. Levers and
+//! pressure plates at the listed points activate the circuit and everything else at the
+//! listed points is toggled when it's activated.
+use super::catalog;
+use super::make::new_obj;
+use super::pov::PoV;
+use super::sound;
+use super::tag::*;
+use super::{Game, Message, ObjectName, Oid, Point, Topic};
+use fnv::FnvHashMap;
+
+pub struct Circuits {
+    targets: FnvHashMap<String, Vec<Point>>,
+}
+
+impl Circuits {
+    pub fn new() -> Circuits {
+        Circuits {
+            targets: FnvHashMap::default(),
+        }
+    }
+}
+
+impl Game {
+    /// Used when loading a level to add the lever, pressure plate, or terrain at loc to
+    /// the named circuit.
+    pub(super) fn add_to_circuit(&mut self, name: &str, loc: &Point) {
+        if let Some(oid) = self.trigger(loc) {
+            self.level.obj_mut(oid).set(Tag::Circuit(name.to_string()));
+        } else {
+            self.circuits.targets.entry(name.to_string()).or_default().push(*loc);
+        }
+    }
+
+    pub(super) fn pull_lever(&mut self, loc: &Point) {
+        let text = catalog::text("circuit.lever", &[]);
+        self.messages.push(Message::new(Topic::Normal, &text));
+
+        let obj = self.level.get(loc, LEVER_ID).unwrap().1;
+        if let Some(name) = obj.circuit_value().cloned() {
+            self.activate_circuit(&name);
+        }
+    }
+
+    /// Called when a character moves into a new cell.
+    pub(super) fn check_pressure_plate(&mut self, oid: Oid, loc: &Point) {
+        if let Some((_, obj)) = self.level.get(loc, PRESSURE_PLATE_ID) {
+            let name = obj.circuit_value().cloned();
+            PoV::refresh(self); // the player may have just moved
+            if oid.0 == 0 {
+                let text = catalog::text("circuit.plate.player", &[]);
+                self.messages.push(Message::new(Topic::Normal, &text));
+            } else if self.pov.visible(self, loc) {
+                let who = self.level.obj(oid).0.name_value().unwrap();
+                let text = catalog::text("circuit.plate.npc", &[("name", &who)]);
+                self.messages.push(Message::new(Topic::Normal, &text));
+            }
+            if let Some(name) = name {
+                debug!("{oid} stepped on a pressure plate at {loc}");
+                self.activate_circuit(&name);
+            }
+        }
+    }
+
+    fn activate_circuit(&mut self, name: &str) {
+        debug!("activating circuit {name}");
+        let locs = self.circuits.targets.get(name).cloned().unwrap_or_default();
+        PoV::refresh(self);
+        for loc in locs {
+            let (oid, obj) = self.level.get_bottom(&loc);
+            let (new_name, key) = match obj.terrain_value() {
+                Some(Terrain::ClosedDoor) => (ObjectName::OpenDoor, "circuit.door_opens"),
+                Some(Terrain::OpenDoor) => (ObjectName::ClosedDoor, "circuit.door_closes"),
+                _ => continue,
+            };
+
+            let visible = self.pov.visible(self, &loc);
+            if new_name == ObjectName::ClosedDoor && self.level.get(&loc, CHARACTER_ID).is_some() {
+                if visible {
+                    let text = catalog::text("circuit.door_blocked", &[]);
+                    self.messages.push(Message::new(Topic::Normal, &text));
+                }
+                continue;
+            }
+            if visible {
+                let text = catalog::text(key, &[]);
+                self.messages.push(Message::new(Topic::Normal, &text));
+            } else {
+                self.player_hears(&loc, sound::QUIET, "sound.door");
+            }
+            self.replace_object(&loc, oid, new_obj(new_name));
+        }
+        self.pov.dirty();
+    }
+
+    fn trigger(&self, loc: &Point) -> Option<Oid> {
+        self.level
+            .cell_iter(loc)
+            .find(|(_, obj)| obj.has(LEVER_ID) || obj.has(PRESSURE_PLATE_ID))
+            .map(|(oid, _)| oid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_game;
+    use crate::backend::Action;
+
+    #[test]
+    fn test_circuits() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        let at = |n: i32, (dx, dy): (i32, i32)| Point::new(player_loc.x + n * dx, player_loc.y + n * dy);
+        let deltas: Vec<(i32, i32)> = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .filter(|&delta| (1..=2).all(|n| game.can_teleport_to(Oid(0), &at(n, delta))))
+            .collect();
+        let (d, e) = (deltas[0], deltas[1]);
+
+        game.add_object(&at(1, e), new_obj(ObjectName::Lever));
+        game.add_object(&at(1, d), new_obj(ObjectName::PressurePlate));
+        let door_loc = at(2, d);
+        let oid = game.level.get_bottom(&door_loc).0;
+        game.replace_object(&door_loc, oid, new_obj(ObjectName::ClosedDoor));
+        for loc in [at(1, e), at(1, d), door_loc] {
+            game.add_to_circuit("gate", &loc);
+        }
+        let terrain = |game: &Game| game.level.get_bottom(&door_loc).1.terrain_value().unwrap();
+
+        // Pulling the lever opens the door.
+        game.player_acted(Action::Move { dx: e.0, dy: e.1 });
+        assert!(game.recent_messages(3).any(|m| m.text.contains("pull the lever")));
+        assert_eq!(game.player_loc(), player_loc);
+        assert_eq!(terrain(&game), Terrain::OpenDoor);
+
+        // Stepping on the pressure plate closes it again.
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+        game.player_acted(Action::Move { dx: d.0, dy: d.1 });
+        assert_eq!(game.player_loc(), at(1, d));
+        assert!(game
+            .recent_messages(3)
+            .any(|m| m.text.contains("clicks under your feet")));
+        assert_eq!(terrain(&game), Terrain::ClosedDoor);
+    }
+}
@@ -0,0 +1,98 @@
+//! Combat events record what happened to characters in a structured form so that UIs can
+//! show visual effects (e.g. floating damage numbers) without parsing messages. Like
+//! decorations they are purely cosmetic so they aren't saved or checkpointed.
+use super::statuses::Status;
+use super::{Game, Point};
+use std::collections::VecDeque;
+
+/// Oldest events are discarded once there are more than this.
+const MAX_EVENTS: usize = 64;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CombatEvent {
+    /// The character at loc lost hit points.
+    Damaged { loc: Point, amount: i32 },
+
+    /// The character at loc started a new status.
+    StatusAdded { loc: Point, status: Status },
+}
+
+pub struct CombatEvents {
+    events: VecDeque<CombatEvent>,
+    removed: usize, // number of events discarded from the front of events
+}
+
+impl CombatEvents {
+    pub fn new() -> CombatEvents {
+        CombatEvents {
+            events: VecDeque::new(),
+            removed: 0,
+        }
+    }
+
+    fn push(&mut self, event: CombatEvent) {
+        self.events.push_back(event);
+        while self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+            self.removed += 1;
+        }
+    }
+}
+
+impl Game {
+    /// Total number of combat events, including ones that have been discarded. Like
+    /// num_messages this can be used to find new events.
+    pub fn num_combat_events(&self) -> usize {
+        self.combat_events.removed + self.combat_events.events.len()
+    }
+
+    /// Returns up to limit of the most recent combat events, oldest first.
+    pub fn recent_combat_events(&self, limit: usize) -> impl Iterator<Item = &CombatEvent> {
+        let events = &self.combat_events.events;
+        events.iter().skip(events.len().saturating_sub(limit))
+    }
+
+    pub(super) fn push_combat_event(&mut self, event: CombatEvent) {
+        self.combat_events.push(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::time;
+    use crate::backend::Oid;
+
+    #[test]
+    fn test_events() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = std::fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        let count = game.num_combat_events();
+        game.add_status(Oid(0), Status::Hasted, time::HASTED);
+        game.add_status(Oid(0), Status::Hasted, time::HASTED); // extending a status isn't a new event
+        assert_eq!(game.num_combat_events(), count + 1);
+        let loc = game.player_loc();
+        assert_eq!(
+            game.recent_combat_events(1).next(),
+            Some(&CombatEvent::StatusAdded {
+                loc,
+                status: Status::Hasted
+            })
+        );
+
+        for amount in 0..(MAX_EVENTS as i32 + 10) {
+            game.push_combat_event(CombatEvent::Damaged { loc, amount });
+        }
+        assert_eq!(game.num_combat_events(), count + 1 + MAX_EVENTS + 10);
+        assert_eq!(game.recent_combat_events(usize::MAX).count(), MAX_EVENTS);
+        assert_eq!(
+            game.recent_combat_events(1).next(),
+            Some(&CombatEvent::Damaged {
+                loc,
+                amount: MAX_EVENTS as i32 + 9
+            })
+        );
+    }
+}
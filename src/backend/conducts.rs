@@ -0,0 +1,144 @@
+//! Conducts are voluntary restrictions, e.g. never wearing armor. Whether they were kept is
+//! computed as the game runs so, like everything else, they are rebuilt when a saved game
+//! is replayed. Challenges are conducts that the player has committed to at the start of
+//! the game: they are recorded in the action stream and the player is warned if one is
+//! broken.
+use super::{catalog, Game, Message, Oid, Topic};
+use derive_more::Display;
+
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Conduct {
+    #[display(fmt = "never wore armor")]
+    NoArmor,
+
+    #[display(fmt = "never killed a neutral")]
+    NoNeutralKills,
+
+    #[display(fmt = "pacifist")]
+    Pacifist,
+
+    /// Not really a conduct: remembered locations are forgotten if they aren't seen for a
+    /// long time. It's listed here so that it can be chosen at the start of a game and
+    /// recorded in the action stream.
+    #[display(fmt = "fog of memory")]
+    FogOfMemory,
+}
+
+pub const ALL_CONDUCTS: [Conduct; 3] = [Conduct::NoArmor, Conduct::NoNeutralKills, Conduct::Pacifist];
+
+pub struct Conducts {
+    broken: Vec<Conduct>,
+    challenges: Vec<Conduct>,
+    provoked: Vec<Oid>, // neutral NPCs that the player has attacked
+}
+
+impl Conducts {
+    pub fn new() -> Conducts {
+        Conducts {
+            broken: Vec::new(),
+            challenges: Vec::new(),
+            provoked: Vec::new(),
+        }
+    }
+}
+
+impl Game {
+    /// Returns each conduct along with whether it has been kept so far.
+    pub fn conducts(&self) -> Vec<(Conduct, bool)> {
+        ALL_CONDUCTS
+            .iter()
+            .map(|c| (*c, !self.conducts.broken.contains(c)))
+            .collect()
+    }
+
+    pub fn challenges(&self) -> &[Conduct] {
+        &self.conducts.challenges
+    }
+
+    /// Should be called before the player does anything else.
+    pub fn add_challenge(&mut self, conduct: Conduct) {
+        while !self.players_turn() {
+            self.advance_time(false);
+        }
+        self.player_acted(super::Action::Challenge(conduct));
+    }
+
+    pub(super) fn do_challenge(&mut self, conduct: Conduct) {
+        if !self.conducts.challenges.contains(&conduct) {
+            self.conducts.challenges.push(conduct);
+            let text = catalog::text("conduct.challenge", &[("conduct", &conduct)]);
+            let mesg = Message::new(Topic::Important, &text);
+            self.messages.push(mesg);
+        }
+    }
+
+    pub(super) fn player_attacked_neutral(&mut self, oid: Oid) {
+        if !self.conducts.provoked.contains(&oid) {
+            self.conducts.provoked.push(oid);
+        }
+    }
+
+    pub(super) fn player_killed(&mut self, oid: Oid) {
+        self.break_conduct(Conduct::Pacifist);
+        if self.conducts.provoked.contains(&oid) {
+            self.break_conduct(Conduct::NoNeutralKills);
+        }
+    }
+
+    pub(super) fn break_conduct(&mut self, conduct: Conduct) {
+        if !self.conducts.broken.contains(&conduct) {
+            self.conducts.broken.push(conduct);
+            if self.conducts.challenges.contains(&conduct) {
+                let text = catalog::text("conduct.failed", &[("conduct", &conduct)]);
+                let mesg = Message::new(Topic::Warning, &text);
+                self.messages.push(mesg);
+            }
+        }
+    }
+
+    /// Used when the game ends.
+    pub(super) fn conducts_summary(&self) -> String {
+        let kept: Vec<String> = self
+            .conducts()
+            .iter()
+            .filter_map(|(c, kept)| if *kept { Some(c.to_string()) } else { None })
+            .collect();
+        if kept.is_empty() {
+            catalog::text("conduct.none_kept", &[])
+        } else {
+            catalog::text("conduct.kept", &[("conducts", &kept.join(", "))])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Action;
+    use std::fs;
+
+    #[test]
+    fn test_replay_challenges() {
+        // Challenges are part of the action stream so they should survive a reload.
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        game.add_challenge(Conduct::Pacifist);
+        for _ in 0..10 {
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.player_acted(Action::Rest);
+        }
+        drop(game);
+
+        let (mut game, actions) = Game::old_game(&path, Vec::new());
+        for action in actions {
+            game.replay_action(action);
+        }
+        assert!(game.divergence().is_none());
+        assert_eq!(game.challenges(), &[Conduct::Pacifist]);
+        assert!(game.conducts().iter().all(|(_, kept)| *kept));
+    }
+}
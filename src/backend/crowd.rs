@@ -0,0 +1,248 @@
+//! Spectators are a crowd of simple AIs: they mill about within roped off regions listed
+//! in the map, cheer (or boo) fights that the player is in, take bets on the player, and
+//! flee once the Broken appear. Crowd rolls use the game's random number generator so that
+//! they're covered by checkpoints and replay the same way.
+use super::catalog;
+use super::pov::PoV;
+use super::tag::*;
+use super::{Game, Message, Oid, Point, Topic};
+use rand::prelude::*;
+
+/// Chance that an idle spectator shuffles to a new spot.
+const MOVE_PROB: f64 = 0.25;
+
+/// Chance that watching spectators react to a hit.
+const CHEER_PROB: f64 = 0.3;
+
+/// Range of gold that spectators will bet on the player.
+const MIN_BET: i32 = 5;
+const MAX_BET: i32 = 50;
+
+/// Reputation lost when the player attacks a spectator.
+const OUTRAGE: i32 = 10;
+
+/// Reputation gained when the player wins the crowd's bets.
+const CHAMPION: i32 = 25;
+
+/// How the crowd regards the player.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CrowdStats {
+    pub reputation: i32,
+    pub gold: i32, // winnings from the crowd's bets
+    pub bets: i32, // gold that spectators currently have riding on the player
+}
+
+pub struct Crowd {
+    regions: Vec<(Point, Point)>, // inclusive top left and bottom right corners
+    exit: Option<Point>,          // where spectators flee to
+    fleeing: bool,
+    bets: Vec<(Oid, i32)>,
+    stats: CrowdStats,
+}
+
+impl Crowd {
+    pub fn new() -> Crowd {
+        Crowd {
+            regions: Vec::new(),
+            exit: None,
+            fleeing: false,
+            bets: Vec::new(),
+            stats: CrowdStats::default(),
+        }
+    }
+
+    fn in_region(&self, loc: &Point) -> bool {
+        self.regions
+            .iter()
+            .any(|(tl, br)| tl.x <= loc.x && loc.x <= br.x && tl.y <= loc.y && loc.y <= br.y)
+    }
+}
+
+impl Game {
+    pub fn crowd_stats(&self) -> CrowdStats {
+        let mut stats = self.crowd.stats;
+        stats.bets = self.crowd.bets.iter().map(|(_, gold)| gold).sum();
+        stats
+    }
+
+    /// Used by make::level with the crowd section of the map.
+    pub(super) fn add_crowd_region(&mut self, top_left: Point, bottom_right: Point) {
+        self.crowd.regions.push((top_left, bottom_right));
+    }
+
+    pub(super) fn set_crowd_exit(&mut self, loc: Point) {
+        self.crowd.exit = Some(loc);
+    }
+
+    /// Used by the AI for spectators that aren't doing anything else. Spectators outside
+    /// the crowd regions (or that have fled) stay put.
+    pub(super) fn crowd_step(&mut self, loc: &Point) -> Option<Point> {
+        if self.crowd.fleeing || !self.crowd.in_region(loc) || !self.rng().gen_bool(MOVE_PROB) {
+            return None;
+        }
+
+        let ch = self.level.get(loc, CHARACTER_ID).unwrap().1;
+        let candidates: Vec<Point> = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| Point::new(loc.x + dx, loc.y + dy)))
+            .filter(|candidate| {
+                self.crowd.in_region(candidate)
+                    && self.level.get(candidate, CHARACTER_ID).is_none()
+                    && ch.impassible_terrain(self.level.get_bottom(candidate).1).is_none()
+            })
+            .collect();
+        candidates.choose(&mut *self.rng()).copied()
+    }
+
+    /// Called after each melee attack. Spectators that can see the fight (i.e. that the
+    /// player can see) will occasionally react to it.
+    pub(super) fn crowd_reacts(&mut self, attacker_id: Oid, defender_id: Oid, spectator: bool, damage: i32) {
+        if attacker_id.0 != 0 && defender_id.0 != 0 {
+            return;
+        }
+        PoV::refresh(self); // the player may have moved, e.g. by charging
+        let watching = self.level.npcs().any(|oid| {
+            let (obj, loc) = self.level.obj(oid);
+            obj.has(SPECTATOR_ID) && loc.is_some_and(|loc| self.pov.visible(self, &loc))
+        });
+        if !watching {
+            return;
+        }
+
+        let key = if spectator && attacker_id.0 == 0 {
+            self.crowd.stats.reputation -= OUTRAGE;
+            "crowd.boos"
+        } else if damage == 0 || !self.rng().gen_bool(CHEER_PROB) {
+            return;
+        } else if attacker_id.0 == 0 {
+            self.crowd.stats.reputation += 1;
+            "crowd.cheers"
+        } else {
+            "crowd.gasps"
+        };
+        let text = catalog::text(key, &[]);
+        self.messages.push(Message::new(Topic::NPCSpeaks, &text));
+    }
+
+    /// Spectators that the player talks to may put some gold on the player surviving.
+    pub(super) fn place_bet(&mut self, spectator: Oid) {
+        if self.crowd.fleeing || self.crowd.bets.iter().any(|(oid, _)| *oid == spectator) {
+            return;
+        }
+        let gold = self.rng().gen_range(MIN_BET..=MAX_BET);
+        debug!("{spectator} bet {gold} gold on the player");
+        self.crowd.bets.push((spectator, gold));
+
+        let text = catalog::text("crowd.bet", &[("gold", &gold)]);
+        self.messages.push(Message::new(Topic::NPCSpeaks, &text));
+    }
+
+    /// Called when Rhulad dies: the player collects the bets of spectators that are still
+    /// alive.
+    pub(super) fn pay_bets(&mut self) {
+        let bets = std::mem::take(&mut self.crowd.bets);
+        let gold: i32 = bets
+            .iter()
+            .filter(|(oid, _)| self.level.try_obj(*oid).is_some())
+            .map(|(_, gold)| gold)
+            .sum();
+        if gold > 0 {
+            self.crowd.stats.gold += gold;
+            self.crowd.stats.reputation += CHAMPION;
+            let text = catalog::text("crowd.payout", &[("gold", &gold)]);
+            self.messages.push(Message::new(Topic::Important, &text));
+        }
+    }
+
+    pub(super) fn crowd_flees(&mut self) {
+        if self.crowd.fleeing {
+            return;
+        }
+        self.crowd.fleeing = true;
+
+        let spectators: Vec<Point> = self
+            .level
+            .npcs()
+            .filter_map(|oid| {
+                let (obj, loc) = self.level.obj(oid);
+                if obj.has(SPECTATOR_ID) {
+                    loc
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if let Some(exit) = self.crowd.exit {
+            for loc in spectators.iter() {
+                self.replace_behavior(loc, Behavior::MovingTo(exit));
+            }
+        }
+        if spectators.iter().any(|loc| self.pov.visible(self, loc)) {
+            let text = catalog::text("crowd.flees", &[]);
+            self.messages.push(Message::new(Topic::Important, &text));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{new_obj, Action, ObjectName};
+    use super::*;
+    use crate::backend::test_game;
+
+    #[test]
+    fn test_crowd() {
+        let mut game = test_game();
+
+        // Pack the roped off regions to stress the AI.
+        let regions = game.crowd.regions.clone();
+        assert!(!regions.is_empty());
+        for (tl, br) in regions.iter() {
+            for y in tl.y..=br.y {
+                for x in tl.x..=br.x {
+                    let loc = Point::new(x, y);
+                    let open = game.level.get_bottom(&loc).1.terrain_value() == Some(Terrain::Ground);
+                    if open && game.level.get(&loc, CHARACTER_ID).is_none() && (x + y) % 2 == 0 {
+                        game.add_object(&loc, new_obj(ObjectName::Spectator));
+                    }
+                }
+            }
+        }
+        let spectators: Vec<Oid> = game
+            .level
+            .npcs()
+            .filter(|oid| game.level.obj(*oid).0.has(SPECTATOR_ID))
+            .collect();
+        assert!(spectators.len() > 20);
+        let start: Vec<Option<Point>> = spectators.iter().map(|oid| game.level.try_loc(*oid)).collect();
+
+        // Spectators mill about but stay within the ropes.
+        for _ in 0..50 {
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.player_acted(Action::Rest);
+        }
+        let end: Vec<Option<Point>> = spectators.iter().map(|oid| game.level.try_loc(*oid)).collect();
+        assert!(start != end);
+        assert!(end.iter().all(|loc| game.crowd.in_region(&loc.unwrap())));
+
+        // Bets are only placed once per spectator and are paid out when Rhulad dies.
+        game.place_bet(spectators[0]);
+        game.place_bet(spectators[0]);
+        game.place_bet(spectators[1]);
+        let stats = game.crowd_stats();
+        assert!(stats.bets >= 2 * MIN_BET && stats.bets <= 2 * MAX_BET);
+        game.pay_bets();
+        let stats = game.crowd_stats();
+        assert_eq!(stats.bets, 0);
+        assert!(stats.gold >= 2 * MIN_BET);
+        assert_eq!(stats.reputation, CHAMPION);
+
+        // Once the Broken appear the crowd heads for the exit.
+        game.crowd_flees();
+        let behavior = game.level.obj(spectators[0]).0.behavior_value();
+        assert_eq!(behavior, Some(Behavior::MovingTo(game.crowd.exit.unwrap())));
+        let loc = game.level.try_loc(spectators[1]).unwrap();
+        assert_eq!(game.crowd_step(&loc), None);
+    }
+}
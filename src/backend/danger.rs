@@ -0,0 +1,94 @@
+//! Estimates how dangerous cells near the player are. This uses the same movement costs
+//! and line of sight rules as the AI so that it reflects what NPCs will actually do.
+use super::primitives::FoV;
+use super::tag::*;
+use super::time::{self, Time};
+use super::{ai, pov, Game, Point};
+use fnv::FnvHashMap;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Threat {
+    /// Number of hostile characters that can see the cell.
+    pub seen_by: u8,
+
+    /// Number of hostile characters that can attack the cell next turn.
+    pub reachable_by: u8,
+}
+
+impl Game {
+    /// Returns threats for cells near the player. Normally only characters and cells the
+    /// player can see are considered but the wizard version uses everything within range.
+    pub fn threats(&self, wizard: bool) -> FnvHashMap<Point, Threat> {
+        let player_loc = self.player_loc();
+        let in_range = |loc: &Point| loc.distance2(&player_loc) <= pov::RADIUS * pov::RADIUS;
+        let known = |loc: &Point| {
+            if wizard {
+                in_range(loc)
+            } else {
+                self.pov.visible(self, loc)
+            }
+        };
+
+        let tracked = |loc: &Point| {
+            if wizard {
+                loc.distance2(&player_loc) <= 4 * pov::RADIUS * pov::RADIUS
+            } else {
+                self.pov.visible(self, loc)
+            }
+        };
+
+        let mut threats: FnvHashMap<Point, Threat> = FnvHashMap::default();
+        for oid in self.level.npcs() {
+            let (ch, loc) = self.level.obj(oid);
+            let loc = loc.unwrap();
+            if ch.disposition_value() != Some(Disposition::Aggressive) {
+                continue;
+            }
+            if !tracked(&loc) {
+                continue;
+            }
+
+            let mut seen = Vec::new();
+            let mut view = FoV {
+                start: loc,
+                radius: pov::RADIUS,
+                visible_tile: |loc| seen.push(loc),
+                blocks_los: |loc| pov::blocks_los(self.level.cell_iter(&loc)),
+            };
+            view.visit();
+            for loc in seen.iter().filter(|loc| known(loc)) {
+                threats.entry(*loc).or_default().seen_by += 1;
+            }
+
+            // Anything adjacent to where the NPC can move to within a turn can be attacked.
+            let mut reachable = vec![loc];
+            let mut neighbors: Vec<(Point, Time)> = Vec::new();
+            ai::successors(&self.level, ch, loc, &loc, &mut neighbors);
+            for (new_loc, cost) in neighbors {
+                if cost <= time::DIAGNOL_MOVE {
+                    reachable.push(new_loc);
+                }
+            }
+            let mut attacked: Vec<Point> = Vec::new();
+            for from in reachable {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let target = Point::new(from.x + dx, from.y + dy);
+                        let terrain = self.level.get_bottom(&target).1;
+                        if target != loc
+                            && known(&target)
+                            && ch.impassible_terrain(terrain).is_none()
+                            && !attacked.contains(&target)
+                        {
+                            attacked.push(target);
+                        }
+                    }
+                }
+            }
+            for target in attacked {
+                threats.entry(target).or_default().reachable_by += 1;
+            }
+        }
+        threats
+    }
+}
@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "1k-deaths";
+
+// Files that older versions wrote into the working directory (saved games are handled
+// separately).
+const LEGACY_FILES: [&str; 6] = [
+    "layout.txt",
+    "message-options.txt",
+    "profile.txt",
+    "seen-hints.txt",
+    "sound-options.txt",
+    "symbol-options.txt",
+];
+
+thread_local!(static DATA_DIR: RefCell<PathBuf> = RefCell::new(PathBuf::from(".")));
+
+/// Sets the directory used for saved games, options, the profile, and the log. If dir
+/// is None then a platform appropriate location is used, e.g. ~/.local/share/1k-deaths
+/// on Linux, ~/Library/Application Support/1k-deaths on macOS, and %APPDATA%\1k-deaths
+/// on Windows. The directory is created if it doesn't already exist.
+pub fn set_data_dir(dir: Option<&str>) -> Result<PathBuf, String> {
+    let dir = match dir {
+        Some(dir) => PathBuf::from(dir),
+        None => platform_dir().unwrap_or_else(|| PathBuf::from(".")),
+    };
+    fs::create_dir_all(&dir).map_err(|err| format!("couldn't create {}: {err}", dir.display()))?;
+    DATA_DIR.with(|d| {
+        *d.borrow_mut() = dir.clone();
+    });
+    Ok(dir)
+}
+
+pub fn data_dir() -> PathBuf {
+    DATA_DIR.with(|d| d.borrow().clone())
+}
+
+/// Returns the path to name within the data directory.
+pub fn data_path(name: &str) -> String {
+    data_dir().join(name).to_string_lossy().to_string()
+}
+
+/// Moves files that older versions left in the working directory into the data directory
+/// (files that already exist in the data directory are left alone). Returns a description
+/// of each file that was moved.
+pub fn migrate_legacy_files() -> Vec<String> {
+    let dir = data_dir();
+    let legacy = Path::new(".");
+    if same_dir(legacy, &dir) {
+        return Vec::new();
+    }
+
+    let mut names: Vec<String> = LEGACY_FILES.iter().map(|name| name.to_string()).collect();
+    if let Ok(entries) = fs::read_dir(legacy) {
+        let mut games: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("saved") && name.ends_with(".game"))
+            .collect();
+        games.sort();
+        names.extend(games);
+    }
+
+    let mut moved = Vec::new();
+    for name in names {
+        let from = legacy.join(&name);
+        let to = dir.join(&name);
+        if !from.is_file() || to.exists() {
+            continue;
+        }
+        // Rename won't work across file systems so fall back to copying.
+        let result = fs::rename(&from, &to).or_else(|_| fs::copy(&from, &to).and_then(|_| fs::remove_file(&from)));
+        match result {
+            Ok(_) => moved.push(format!("moved {} to {}", from.display(), to.display())),
+            Err(err) => warn!("couldn't move {} to {}: {err}", from.display(), to.display()),
+        }
+    }
+    moved
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn platform_dir() -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join(APP_DIR))
+    } else if cfg!(target_os = "macos") {
+        home.map(|dir| dir.join("Library").join("Application Support").join(APP_DIR))
+    } else {
+        match env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join(APP_DIR)),
+            _ => home.map(|dir| dir.join(".local").join("share").join(APP_DIR)),
+        }
+    }
+}
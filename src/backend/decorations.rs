@@ -0,0 +1,116 @@
+//! Decorations are purely cosmetic marks left on cells by game events, e.g. blood after
+//! combat. They show up when examining cells and subtly change the cell's background
+//! color. They don't affect game play so they aren't objects and aren't checkpointed.
+use super::catalog;
+use super::primitives::Color;
+use super::{Game, Point};
+use fnv::FnvHashMap;
+use std::collections::VecDeque;
+
+/// Oldest decorations are discarded once a level has more than this.
+const MAX_DECORATIONS: usize = 256;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Decoration {
+    Blood,
+
+    /// Left by tools, e.g. a pick-axe on a wall.
+    Gouges,
+
+    /// Text scratched into the floor, these are placed by the level's map file.
+    Graffiti(String),
+}
+
+pub struct Decorations {
+    cells: FnvHashMap<Point, Decoration>,
+    order: VecDeque<Point>, // oldest first
+}
+
+impl Decorations {
+    pub fn new() -> Decorations {
+        Decorations {
+            cells: FnvHashMap::default(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Replaces any existing decoration at loc.
+    pub fn add(&mut self, loc: Point, decoration: Decoration) {
+        if self.cells.insert(loc, decoration).is_some() {
+            self.order.retain(|old| *old != loc);
+        }
+        self.order.push_back(loc);
+
+        while self.order.len() > MAX_DECORATIONS {
+            let old = self.order.pop_front().unwrap();
+            self.cells.remove(&old);
+        }
+    }
+
+    pub fn get(&self, loc: &Point) -> Option<&Decoration> {
+        self.cells.get(loc)
+    }
+}
+
+impl Decoration {
+    pub fn description(&self) -> String {
+        match self {
+            Decoration::Blood => catalog::text("decoration.blood", &[]),
+            Decoration::Gouges => catalog::text("decoration.gouges", &[]),
+            Decoration::Graffiti(text) => catalog::text("decoration.graffiti", &[("text", text)]),
+        }
+    }
+
+    /// Cells with decorations use this for their background (unless the terrain has a
+    /// non-black background).
+    pub fn bg_color(&self) -> Color {
+        match self {
+            Decoration::Blood => Color::Maroon,
+            Decoration::Gouges => Color::DimGray,
+            Decoration::Graffiti(_) => Color::DarkSlateGray,
+        }
+    }
+}
+
+impl Game {
+    pub(super) fn decorate(&mut self, loc: &Point, decoration: Decoration) {
+        self.decorations.add(*loc, decoration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cap() {
+        let mut decorations = Decorations::new();
+        for x in 0..(MAX_DECORATIONS as i32 + 10) {
+            decorations.add(Point::new(x, 0), Decoration::Blood);
+        }
+        assert_eq!(decorations.cells.len(), MAX_DECORATIONS);
+        assert!(decorations.get(&Point::new(0, 0)).is_none());
+        assert!(decorations.get(&Point::new(MAX_DECORATIONS as i32 + 9, 0)).is_some());
+
+        // Re-decorating a cell makes it the newest.
+        decorations.add(Point::new(10, 0), Decoration::Gouges);
+        for x in 1000..1250 {
+            decorations.add(Point::new(x, 0), Decoration::Blood);
+        }
+        assert_eq!(decorations.get(&Point::new(10, 0)), Some(&Decoration::Gouges));
+        assert_eq!(decorations.cells.len(), decorations.order.len());
+    }
+
+    #[test]
+    fn test_examine() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = std::fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        let loc = game.player_loc();
+        game.decorate(&loc, Decoration::Graffiti("Kilroy was here".to_string()));
+        game.free_action(super::super::Action::Examine { loc, wizard: false });
+        let last = game.recent_messages(1).next().unwrap();
+        assert!(last.text.contains("Kilroy was here"), "{}", last.text);
+    }
+}
@@ -0,0 +1,295 @@
+//! Tracks the kinds of objects that the player has seen this game so that players can
+//! learn about the bestiary and item pool. Stats are only included once the player has
+//! picked up the item or fought the character. Also tracks what the player has noticed
+//! while fighting characters (the terminal carries these notes over to later games).
+use super::make::new_obj;
+use super::object::Object;
+use super::tag::*;
+use super::{catalog, Color, Game, ObjectName, Oid, Point, Symbol};
+use fnv::FnvHashMap;
+use std::fmt::Display;
+
+/// Returned by Game::discoveries.
+pub struct Discovery {
+    pub name: String,
+    pub symbol: Symbol,
+    pub color: Color,
+    pub description: String,
+    pub stats: Vec<String>,          // empty if the object hasn't been identified
+    pub notes: Option<MonsterNotes>, // from this game and (if remembered) earlier games
+}
+
+/// What the player has noticed about a kind of character while fighting it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MonsterNotes {
+    /// Most damage it has done to the player in one hit.
+    pub max_damage: i32,
+
+    /// Largest percentage of the player's damage that its armor absorbed.
+    pub blocked: i32,
+}
+
+impl MonsterNotes {
+    /// Keeps the larger of each stat.
+    pub fn merge(&mut self, other: &MonsterNotes) {
+        self.max_damage = self.max_damage.max(other.max_damage);
+        self.blocked = self.blocked.max(other.blocked);
+    }
+}
+
+struct Seen {
+    order: usize,        // used to list discoveries in the order they were made
+    identified: bool,    // true if the player has learned its stats
+    seen_fighting: bool, // true if the player has watched it fight (or fought it)
+}
+
+pub struct Discoveries {
+    seen: FnvHashMap<ObjectName, Seen>,
+    notes: FnvHashMap<ObjectName, MonsterNotes>,
+    remembered: FnvHashMap<String, MonsterNotes>, // from earlier games, keyed by display name
+}
+
+impl Discoveries {
+    pub fn new() -> Discoveries {
+        Discoveries {
+            seen: FnvHashMap::default(),
+            notes: FnvHashMap::default(),
+            remembered: FnvHashMap::default(),
+        }
+    }
+
+    // This is called at the start of each of the player's turns (characters can move
+    // into view without the PoV changing). It can't be an ordinary method or we run into
+    // all sorts of borrowing grief.
+    pub fn update(game: &mut Game) {
+        let mut names: Vec<ObjectName> = game
+            .pov
+            .locations()
+            .flat_map(|loc| game.level.cell_iter(loc))
+            .filter(|(_, obj)| !obj.has(PLAYER_ID))
+            .map(|(_, obj)| obj.object_name())
+            .collect();
+        names.sort(); // visible locations are in hash order
+        names.dedup();
+        for name in names {
+            game.discoveries.add(name);
+        }
+    }
+
+    /// Called when the player learns the stats for an object, e.g. by picking it up.
+    pub fn identify(&mut self, name: ObjectName) {
+        self.add(name);
+        let seen = self.seen.get_mut(&name).unwrap();
+        seen.identified = true;
+        seen.seen_fighting = true;
+    }
+
+    /// Called when the player sees a character fight. This lets the player judge how
+    /// badly hurt characters of that kind are.
+    pub fn saw_fighting(&mut self, name: ObjectName) {
+        self.add(name);
+        self.seen.get_mut(&name).unwrap().seen_fighting = true;
+    }
+
+    pub fn knows_wounds(&self, name: ObjectName) -> bool {
+        self.seen.get(&name).is_some_and(|seen| seen.seen_fighting)
+    }
+
+    fn add(&mut self, name: ObjectName) {
+        let order = self.seen.len();
+        self.seen.entry(name).or_insert(Seen {
+            order,
+            identified: false,
+            seen_fighting: false,
+        });
+    }
+}
+
+impl Game {
+    /// Returns what the player noticed this game about the characters they fought, keyed
+    /// by the name used in discoveries.
+    pub fn monster_notes(&self) -> Vec<(String, MonsterNotes)> {
+        let mut notes: Vec<(String, MonsterNotes)> = self
+            .discoveries
+            .notes
+            .iter()
+            .map(|(name, notes)| (display_name(&new_obj(*name)), *notes))
+            .collect();
+        notes.sort_by(|a, b| a.0.cmp(&b.0));
+        notes
+    }
+
+    /// Notes from earlier games that are merged into the notes listed by discoveries.
+    pub fn remember_monsters(&mut self, notes: Vec<(String, MonsterNotes)>) {
+        self.discoveries.remembered = notes.into_iter().collect();
+    }
+
+    /// Called when an attack hits (damage is before armor and mitigated is after).
+    pub(super) fn note_strike(&mut self, attacker_id: Oid, defender_id: Oid, damage: i32, mitigated: i32) {
+        if defender_id.0 == 0 && attacker_id.0 != 0 {
+            let name = self.level.obj(attacker_id).0.object_name();
+            let notes = self.discoveries.notes.entry(name).or_default();
+            notes.max_damage = notes.max_damage.max(mitigated);
+        } else if attacker_id.0 == 0 && defender_id.0 != 0 && damage > 0 {
+            let name = self.level.obj(defender_id).0.object_name();
+            let notes = self.discoveries.notes.entry(name).or_default();
+            notes.blocked = notes.blocked.max(100 * (damage - mitigated) / damage);
+        }
+    }
+
+    /// Returns the kinds of objects the player has seen in the order they were seen.
+    pub fn discoveries(&self) -> Vec<Discovery> {
+        let mut names: Vec<(&ObjectName, &Seen)> = self.discoveries.seen.iter().collect();
+        names.sort_by_key(|(_, seen)| seen.order);
+        names
+            .iter()
+            .map(|(name, seen)| self.to_discovery(&new_obj(**name), seen.identified))
+            .collect()
+    }
+
+    /// Returns an entry for each object in a visible cell (empty if the player can't see
+    /// the cell). This is used to list piles of objects.
+    pub fn examine_stack(&self, loc: &Point) -> Vec<Discovery> {
+        if !self.pov.visible(self, loc) {
+            return Vec::new();
+        }
+        self.level
+            .cell_iter(loc)
+            .map(|(_, obj)| {
+                let identified = self
+                    .discoveries
+                    .seen
+                    .get(&obj.object_name())
+                    .is_some_and(|s| s.identified);
+                self.to_discovery(obj, identified)
+            })
+            .collect()
+    }
+
+    fn to_discovery(&self, obj: &Object, identified: bool) -> Discovery {
+        let (color, symbol) = obj.to_fg_symbol();
+        let notes = self.discoveries.notes.get(&obj.object_name());
+        let name = display_name(obj);
+        let notes = match (notes, self.discoveries.remembered.get(&name)) {
+            (Some(current), Some(old)) => {
+                let mut notes = *current;
+                notes.merge(old);
+                Some(notes)
+            }
+            (current, old) => current.or(old).copied(),
+        };
+        Discovery {
+            name,
+            symbol,
+            color,
+            description: obj.description(),
+            stats: if identified { stats(obj) } else { Vec::new() },
+            notes,
+        }
+    }
+}
+
+fn display_name(obj: &Object) -> String {
+    obj.name_value()
+        .map(|n| n.to_string())
+        .unwrap_or_else(|| obj.description())
+}
+
+fn stats(obj: &Object) -> Vec<String> {
+    let mut desc = Vec::new();
+    if obj.has(CHARACTER_ID) {
+        if let Some(durability) = obj.durability_value() {
+            desc.push(catalog::text("stats.hit_points", &[("hps", &durability.max)]));
+        }
+        if let (Some(damage), Some(delay)) = (obj.damage_value(), obj.delay_value()) {
+            let args: [(&str, &dyn Display); 2] = [("damage", &damage), ("delay", &delay)];
+            desc.push(catalog::text("stats.unarmed", &args));
+        }
+        if let (Some(strength), Some(dexterity)) = (obj.strength_value(), obj.dexterity_value()) {
+            let args: [(&str, &dyn Display); 2] = [("strength", &strength), ("dexterity", &dexterity)];
+            desc.push(catalog::text("stats.attributes", &args));
+        }
+    } else {
+        desc.extend(super::item_stats(obj));
+    }
+    desc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Action;
+    use crate::backend::{open_neighbor, test_game};
+    use std::fs;
+
+    #[test]
+    fn test_discoveries() {
+        let mut game = test_game();
+
+        // The player isn't listed but the terrain they are standing on is.
+        let discoveries = game.discoveries();
+        assert!(!discoveries.is_empty());
+        assert!(discoveries.iter().all(|d| d.symbol != Symbol::Player));
+        assert!(discoveries.iter().any(|d| d.description == "a patch of dirt"));
+
+        // Examining a cell lists everything in it (including the player).
+        let stack = game.examine_stack(&game.player_loc());
+        assert!(stack.len() >= 2);
+        assert!(stack.iter().any(|d| d.symbol == Symbol::Player));
+
+        // Characters are identified once the player fights them.
+        let player_loc = game.player_loc();
+        let loc = open_neighbor(&game);
+        game.add_object(&loc, new_obj(ObjectName::Spectator));
+        game.player_acted(Action::Rest);
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+        let spectator = |game: &Game| game.discoveries().into_iter().find(|d| d.name == "Spectator");
+        assert!(spectator(&game).unwrap().stats.is_empty());
+
+        if game.level.get(&loc, CHARACTER_ID).is_some() {
+            assert!(game.npcs(false).iter().all(|npc| npc.wounds.is_none()));
+            game.do_melee_attack(&player_loc, &loc);
+            assert!(!spectator(&game).unwrap().stats.is_empty());
+            assert!(game.discoveries.knows_wounds(ObjectName::Spectator));
+            let npcs = game.npcs(false);
+            assert!(npcs.iter().filter(|npc| npc.loc == loc).all(|npc| npc.wounds.is_some()));
+        }
+    }
+
+    #[test]
+    fn test_remembered_monsters() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        game.discoveries.add(ObjectName::Spectator);
+        let notes = |game: &Game| {
+            game.discoveries()
+                .into_iter()
+                .find(|d| d.name == "Spectator")
+                .and_then(|d| d.notes)
+        };
+        assert_eq!(notes(&game), None);
+
+        let old = MonsterNotes {
+            max_damage: 5,
+            blocked: 10,
+        };
+        game.remember_monsters(vec![("Spectator".to_string(), old)]);
+        assert_eq!(notes(&game), Some(old));
+
+        let current = MonsterNotes {
+            max_damage: 8,
+            blocked: 0,
+        };
+        game.discoveries.notes.insert(ObjectName::Spectator, current);
+        let expected = MonsterNotes {
+            max_damage: 8,
+            blocked: 10,
+        };
+        assert_eq!(notes(&game), Some(expected));
+        assert_eq!(game.monster_notes(), vec![("Spectator".to_string(), current)]);
+    }
+}
@@ -0,0 +1,98 @@
+//! Free actions are UI-only actions, like examining a cell, that take no game time. They
+//! don't give NPCs a chance to act, don't use random numbers, and aren't saved so adding
+//! or removing them doesn't affect replays.
+use super::{Action, Game};
+
+impl Action {
+    pub fn is_free(&self) -> bool {
+        matches!(self, Action::Examine { .. })
+    }
+}
+
+impl Game {
+    /// Note that this may be called even if it isn't the player's turn.
+    pub fn free_action(&mut self, action: Action) {
+        assert!(action.is_free(), "{action:?} isn't a free action");
+
+        #[cfg(debug_assertions)]
+        let (rng, now) = (self.rng.borrow().clone(), self.scheduler.now());
+
+        match action {
+            Action::Examine { loc, wizard } => self.examine(&loc, wizard),
+            _ => unreachable!(),
+        }
+        self.trim_messages();
+
+        #[cfg(debug_assertions)]
+        {
+            assert!(*self.rng.borrow() == rng, "{action:?} used the rng");
+            assert_eq!(self.scheduler.now(), now, "{action:?} took time");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Point;
+    use std::fs;
+
+    fn finish_turn(game: &mut Game) {
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+    }
+
+    fn play(path: &str, examine: bool) -> Game {
+        let _ = fs::remove_file(path);
+        let mut game = Game::new_game(path, 1);
+        let actions = [
+            Action::Rest,
+            Action::Move { dx: 1, dy: 0 },
+            Action::Move { dx: 0, dy: 1 },
+            Action::Rest,
+            Action::Move { dx: -1, dy: -1 },
+        ];
+        for action in actions {
+            finish_turn(&mut game);
+            if examine {
+                let loc = game.player_loc();
+                game.free_action(Action::Examine {
+                    loc: Point::new(loc.x + 1, loc.y),
+                    wizard: false,
+                });
+                game.free_action(Action::Examine { loc, wizard: true });
+            }
+            game.player_acted(action);
+        }
+        finish_turn(&mut game);
+        game
+    }
+
+    #[test]
+    fn test_free_actions() {
+        let path1 = format!("/tmp/saved-{}.game", line!());
+        let path2 = format!("/tmp/saved-{}.game", line!());
+        let game1 = play(&path1, false);
+        let game2 = play(&path2, true);
+
+        // Free actions don't change the game,
+        assert_eq!(game1.unsaved_actions(), game2.unsaved_actions());
+        assert_eq!(game1.player_loc(), game2.player_loc());
+        assert_eq!(game1.scheduler.now(), game2.scheduler.now());
+        assert!(*game1.rng.borrow() == *game2.rng.borrow());
+        assert!(game2.num_messages() > game1.num_messages());
+
+        // and replays aren't affected by them.
+        let loc = game1.player_loc();
+        drop(game1);
+        drop(game2);
+        let (mut game, actions) = Game::old_game(&path2, Vec::new());
+        assert!(!actions.iter().any(|a| a.is_free()));
+        for action in actions {
+            game.replay_action(action);
+        }
+        assert_eq!(game.player_loc(), loc);
+        assert!(game.divergence().is_none());
+    }
+}
@@ -0,0 +1,207 @@
+//! Attacks with a weapon occasionally go wrong: the attacker either drops the weapon into
+//! their cell or cuts themselves with it. There isn't a skill system yet so the chance of
+//! fumbling drops off as the attacker's dexterity exceeds what the weapon requires. NPCs
+//! that drop their weapon will pick it back up before doing anything else.
+use super::catalog;
+use super::melee::{linear_scale, MAX_STAT};
+use super::tag::*;
+use super::{Game, Message, Oid, Point, Topic};
+use rand::Rng;
+use std::fmt::Display;
+
+/// Range of fumble chances for characters that are barely able to use their weapon to
+/// those that are very dexterous.
+const MAX_FUMBLE: f64 = 0.03;
+const MIN_FUMBLE: f64 = 0.005;
+
+/// Percent of the weapon's damage done when the attacker cuts themselves.
+const SCRATCH_PERCENT: i32 = 25;
+
+/// Counts of the player's fumbles for the current game.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Fumbles {
+    pub dropped: u32,
+    pub scratched: u32,
+}
+
+impl Game {
+    pub fn fumbles(&self) -> Fumbles {
+        self.fumbles
+    }
+
+    /// Added to the messages shown when the game ends.
+    pub(super) fn fumbles_summary(&self) -> Option<String> {
+        let total = self.fumbles.dropped + self.fumbles.scratched;
+        if total > 0 {
+            let args: [(&str, &dyn Display); 3] = [
+                ("total", &total),
+                ("dropped", &self.fumbles.dropped),
+                ("scratched", &self.fumbles.scratched),
+            ];
+            Some(catalog::text("fumble.summary", &args))
+        } else {
+            None
+        }
+    }
+
+    /// Chance that an attack with weapon goes wrong (zero for unarmed attacks).
+    pub fn fumble_prob(&self, attacker_id: Oid, weapon: Option<Oid>) -> f64 {
+        let weapon = match weapon {
+            Some(weapon) => self.level.obj(weapon).0,
+            None => return 0.0,
+        };
+        let attacker = self.level.obj(attacker_id).0;
+        let dex = attacker.dexterity_value().unwrap() - weapon.dexterity_value().unwrap_or(0);
+        MAX_FUMBLE - linear_scale(dex, 0, MAX_STAT / 2, 0.0, MAX_FUMBLE - MIN_FUMBLE)
+    }
+
+    /// Called before a melee attack. Returns true if the attacker fumbled in which case
+    /// the attack doesn't happen.
+    pub(super) fn fumbled(&mut self, attacker_id: Oid, attacker_loc: &Point) -> bool {
+        let weapon = {
+            let attacker = self.level.obj(attacker_id).0;
+            attacker.equipped_value().and_then(|e| e[Slot::MainHand])
+        };
+        let p = self.fumble_prob(attacker_id, weapon);
+        if p == 0.0 || !self.rng().gen_bool(p) {
+            return false;
+        }
+
+        let weapon = weapon.unwrap();
+        let drop = self.rng().gen_bool(0.5);
+        if drop {
+            self.fumble_drop(attacker_id, attacker_loc, weapon);
+        } else {
+            self.fumble_scratch(attacker_id, attacker_loc, weapon);
+        }
+        true
+    }
+
+    /// Used by the AI to pick back up a weapon that was dropped.
+    pub(super) fn do_npc_recover_weapon(&mut self, oid: Oid, loc: &Point, weapon: Oid) {
+        debug!("{oid} is recovering {weapon}");
+        self.level.pickup(loc, weapon);
+        let obj = self.level.obj_mut(oid);
+        obj.inventory_value_mut().unwrap().retain(|&item| item != weapon);
+        obj.equipped_value_mut().unwrap()[Slot::MainHand] = Some(weapon);
+        self.fumble_mesg(oid, loc, weapon, "fumble.recover.npc", None);
+    }
+
+    fn fumble_drop(&mut self, attacker_id: Oid, attacker_loc: &Point, weapon: Oid) {
+        debug!("{attacker_id} fumbled and dropped {weapon}");
+        let attacker = self.level.obj_mut(attacker_id);
+        attacker.equipped_value_mut().unwrap()[Slot::MainHand] = None;
+        self.level.add_oid(weapon, *attacker_loc);
+
+        if attacker_id.0 == 0 {
+            self.fumbles.dropped += 1;
+        }
+        self.fumble_mesg(
+            attacker_id,
+            attacker_loc,
+            weapon,
+            "fumble.drop.npc",
+            Some("fumble.drop.player"),
+        );
+    }
+
+    // Scratches aren't fatal: they just add some texture to long fights.
+    fn fumble_scratch(&mut self, attacker_id: Oid, attacker_loc: &Point, weapon: Oid) {
+        let damage = self.level.obj(weapon).0.damage_value().unwrap_or(0) * SCRATCH_PERCENT / 100;
+        debug!("{attacker_id} fumbled and took {damage} damage from {weapon}");
+        let attacker = self.level.obj_mut(attacker_id);
+        if let Some(durability) = attacker.durability_value() {
+            attacker.replace(Tag::Durability(Durability {
+                current: (durability.current - damage.max(1)).max(1),
+                ..durability
+            }));
+        }
+
+        if attacker_id.0 == 0 {
+            self.fumbles.scratched += 1;
+        }
+        self.fumble_mesg(
+            attacker_id,
+            attacker_loc,
+            weapon,
+            "fumble.scratch.npc",
+            Some("fumble.scratch.player"),
+        );
+    }
+
+    fn fumble_mesg(&mut self, oid: Oid, loc: &Point, weapon: Oid, npc_key: &str, player_key: Option<&str>) {
+        let item = self.item_name(weapon);
+        let text = match player_key {
+            Some(key) if oid.0 == 0 => catalog::text(key, &[("item", &item)]),
+            _ if self.pov.visible(self, loc) => {
+                let name = self.level.obj(oid).0.name_value().unwrap();
+                catalog::text(npc_key, &[("name", &name), ("item", &item)])
+            }
+            _ => return,
+        };
+        self.messages.push(Message::new(Topic::Warning, &text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ai::{self, Acted};
+    use super::super::{new_obj, time, Action, ObjectName};
+    use super::*;
+    use crate::backend::test_game;
+
+    #[test]
+    fn test_fumbles() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        assert_eq!(game.fumble_prob(Oid(0), None), 0.0);
+
+        // Dexterity makes fumbles less likely.
+        let sword = game.level.add(new_obj(ObjectName::LongSword), None);
+        let player = game.level.get_mut(&player_loc, CHARACTER_ID).unwrap().1;
+        player.inventory_value_mut().unwrap().push(sword);
+        game.player_acted(Action::WieldMainHand(sword));
+        let clumsy = game.fumble_prob(Oid(0), Some(sword));
+        game.level.obj_mut(Oid(0)).replace(Tag::Dexterity(2 * MAX_STAT));
+        let nimble = game.fumble_prob(Oid(0), Some(sword));
+        assert!(clumsy <= MAX_FUMBLE && nimble >= MIN_FUMBLE && nimble < clumsy);
+
+        // Scratches hurt but aren't fatal.
+        let (hps, _) = game.player_hps();
+        game.fumble_scratch(Oid(0), &player_loc, sword);
+        assert!(game.player_hps().0 < hps);
+        game.level
+            .obj_mut(Oid(0))
+            .replace(Tag::Durability(Durability { current: 1, max: 100 }));
+        game.fumble_scratch(Oid(0), &player_loc, sword);
+        assert_eq!(game.player_hps().0, 1);
+
+        // Dropped weapons land in the attacker's cell.
+        game.fumble_drop(Oid(0), &player_loc, sword);
+        assert!(game.level.cell(&player_loc).contains(&sword));
+        let equipped = game.level.obj(Oid(0)).0.equipped_value().unwrap();
+        assert_eq!(equipped[Slot::MainHand], None);
+        assert_eq!(
+            game.fumbles(),
+            Fumbles {
+                dropped: 1,
+                scratched: 2
+            }
+        );
+        assert!(game.fumbles_summary().unwrap().contains("fumbled 3 times"));
+
+        // NPCs pick their weapon back up.
+        let loc = Point::new(player_loc.x + 1, player_loc.y);
+        let guard = game.add_object(&loc, new_obj(ObjectName::Guard));
+        let obj = game.level.obj_mut(guard);
+        obj.set(Tag::Inventory(Vec::new()));
+        obj.set(Tag::Equipped(Default::default()));
+        game.level.pickup(&player_loc, sword);
+        let player = game.level.obj_mut(Oid(0));
+        player.inventory_value_mut().unwrap().retain(|&item| item != sword);
+        game.level.add_oid(sword, loc);
+        assert!(matches!(ai::acted(&mut game, guard, time::secs(10)), Acted::Acted(_)));
+        let equipped = game.level.obj(guard).0.equipped_value().unwrap();
+        assert_eq!(equipped[Slot::MainHand], Some(sword));
+    }
+}
@@ -0,0 +1,54 @@
+//! Game logic records a Hint the first time the player runs into a mechanic. The UI decides
+//! whether to actually show the hint (e.g. based on which hints the player has already
+//! seen). Hints are not part of the game state so they don't affect replays.
+use super::tag::Terrain;
+use super::{catalog, Game, Point};
+use derive_more::Display;
+
+#[derive(Clone, Copy, Debug, Display, Eq, Hash, PartialEq)]
+pub enum Hint {
+    Attacked,
+    NearVitr,
+    NearWater,
+    PickedUpItem,
+}
+
+impl Hint {
+    pub fn text(self) -> String {
+        let key = match self {
+            Hint::Attacked => "hint.attacked",
+            Hint::NearVitr => "hint.near_vitr",
+            Hint::NearWater => "hint.near_water",
+            Hint::PickedUpItem => "hint.picked_up_item",
+        };
+        catalog::text(key, &[])
+    }
+}
+
+impl Game {
+    /// Returns hints that were triggered since the last call.
+    pub fn take_hints(&mut self) -> Vec<Hint> {
+        std::mem::take(&mut self.hints)
+    }
+
+    pub(super) fn add_hint(&mut self, hint: Hint) {
+        // The UI may not be interested in hints so don't allow these to pile up.
+        if !self.hints.contains(&hint) {
+            self.hints.push(hint);
+        }
+    }
+
+    /// Called after the player moves.
+    pub(super) fn check_terrain_hints(&mut self, loc: &Point) {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let neighbor = Point::new(loc.x + dx, loc.y + dy);
+                match self.level.get_bottom(&neighbor).1.terrain_value() {
+                    Some(Terrain::DeepWater | Terrain::ShallowWater) => self.add_hint(Hint::NearWater),
+                    Some(Terrain::Vitr) => self.add_hint(Hint::NearVitr),
+                    _ => (),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Intents are player commands that can take many turns, e.g. travel to a distant cell.
+//! They are expanded into primitive Actions one turn at a time (the UI calls intent_step
+//! once per turn and stops when it returns None) and only the primitive actions are saved.
+//! This keeps saved games stable as intents are added or their logic changes.
+use super::ai;
+use super::primitives::PathFind;
+use super::tag::CHARACTER_ID;
+use super::{Action, Game, Point, Time};
+
+/// Intents stop after this many steps in case something goes wrong.
+const MAX_STEPS: usize = 1000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Intent {
+    /// Walk to a cell using cells the player has seen.
+    TravelTo(Point),
+
+    /// Rest until the player is no longer regenerating hit points.
+    RestUntilHealed,
+
+    /// Swap to a saved equipment set (index is 0, 1, or 2).
+    EquipSet(u8),
+}
+
+/// State for an intent in progress, see Game::start_intent.
+pub struct Plan {
+    intent: Intent,
+    steps: usize,
+    seen_messages: usize, // from Game::num_messages
+}
+
+impl Game {
+    pub fn start_intent(&self, intent: Intent) -> Plan {
+        Plan {
+            intent,
+            steps: 0,
+            seen_messages: self.num_messages(),
+        }
+    }
+
+    /// Returns the next action for the intent or None if the intent has finished (or
+    /// was interrupted). Travel and rest stop when an enemy is visible or when a message
+    /// other than a normal message is added.
+    pub fn intent_step(&self, plan: &mut Plan) -> Option<Action> {
+        if plan.steps >= MAX_STEPS {
+            return None;
+        }
+        plan.steps += 1;
+
+        match plan.intent {
+            Intent::TravelTo(target) => {
+                if self.interrupted(&mut plan.seen_messages) {
+                    return None;
+                }
+                self.travel_step(&target)
+            }
+            Intent::RestUntilHealed => {
+                if self.interrupted(&mut plan.seen_messages) || !self.player_regenerating() {
+                    return None;
+                }
+                Some(Action::Rest)
+            }
+            Intent::EquipSet(index) => {
+                if self.game_over() {
+                    return None;
+                }
+                self.loadout_step(index)
+            }
+        }
+    }
+
+    // The path is recomputed each step because doors open, characters move, etc.
+    fn travel_step(&self, target: &Point) -> Option<Action> {
+        let start = self.player_loc();
+        if start == *target || self.old_pov.get(target).is_none() {
+            return None;
+        }
+
+        // Only use cells the player knows about.
+        let player = self.level.get(&start, CHARACTER_ID).unwrap().1;
+        let callback = |loc: Point, neighbors: &mut Vec<(Point, Time)>| {
+            ai::successors(&self.level, player, loc, target, neighbors);
+            neighbors.retain(|(loc, _)| self.old_pov.get(loc).is_some());
+        };
+        let find = PathFind::new(start, *target, callback);
+        let next = find.next()?; // None if there is no known path
+        Some(Action::Move {
+            dx: next.x - start.x,
+            dy: next.y - start.y,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::open_neighbor;
+    use crate::backend::tag::*;
+    use crate::backend::{ObjectName, Oid, OldPoV};
+    use std::fs;
+
+    fn finish_turn(game: &mut Game) {
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+    }
+
+    #[test]
+    fn test_travel() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        finish_turn(&mut game);
+        OldPoV::update(&mut game);
+
+        // Travel to a nearby cell that the player can see.
+        let start = game.player_loc();
+        let target = (-4..=4)
+            .flat_map(|dy| (-4..=4).map(move |dx| Point::new(start.x + dx, start.y + dy)))
+            .filter(|loc| game.can_teleport_to(Oid(0), loc) && game.old_pov.get(loc).is_some())
+            .max_by_key(|loc| start.distance2(loc))
+            .unwrap();
+        let mut plan = game.start_intent(Intent::TravelTo(target));
+        while let Some(action) = game.intent_step(&mut plan) {
+            assert!(matches!(action, Action::Move { .. }));
+            game.player_acted(action);
+            finish_turn(&mut game);
+        }
+        assert_eq!(game.player_loc(), target);
+
+        // Only primitive actions are saved.
+        drop(game);
+        let (game, actions) = Game::old_game(&path, Vec::new());
+        assert!(actions
+            .iter()
+            .all(|a| matches!(a, Action::Move { .. } | Action::Object)));
+        drop(game);
+
+        // Can't travel to cells that haven't been seen.
+        let game = Game::new_game(&path, 1);
+        let far = Point::new(start.x + 500, start.y);
+        let mut plan = game.start_intent(Intent::TravelTo(far));
+        assert!(game.intent_step(&mut plan).is_none());
+    }
+
+    #[test]
+    fn test_rest_until_healed() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        finish_turn(&mut game);
+
+        // Nothing to do when the player is healthy.
+        let mut plan = game.start_intent(Intent::RestUntilHealed);
+        assert!(game.intent_step(&mut plan).is_none());
+
+        let (_, max) = game.player_hps();
+        let player = game.level.get_mut(&game.player_loc(), CHARACTER_ID).unwrap().1;
+        let durability = player.durability_value().unwrap();
+        player.replace(Tag::Durability(Durability {
+            current: max / 2,
+            ..durability
+        }));
+
+        let mut plan = game.start_intent(Intent::RestUntilHealed);
+        while let Some(action) = game.intent_step(&mut plan) {
+            assert_eq!(action, Action::Rest);
+            game.player_acted(action);
+            finish_turn(&mut game);
+        }
+        assert_eq!(game.player_hps(), (max, max));
+
+        // Resting stops when an enemy shows up.
+        let player = game.level.get_mut(&game.player_loc(), CHARACTER_ID).unwrap().1;
+        player.replace(Tag::Durability(Durability {
+            current: max / 2,
+            ..durability
+        }));
+        let enemy_loc = open_neighbor(&game);
+        game.add_object(&enemy_loc, new_obj(ObjectName::ImrothTheCruel));
+        let mut plan = game.start_intent(Intent::RestUntilHealed);
+        assert!(game.intent_step(&mut plan).is_none());
+    }
+}
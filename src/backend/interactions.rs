@@ -1,6 +1,7 @@
 //! This is where the bulk of the logic exists to handle interactions between
 //! Characters and between items. It's structured as a lookup table of
 //! (tag1, tag2) => handler. For example (Player, Sign) => function_to_print_sign.
+use super::catalog;
 use super::sound::*;
 use super::tag::*;
 use super::*;
@@ -20,15 +21,26 @@ pub enum PreResult {
     DidntAct,
 }
 
+/// An adjacent cell that the player can interact with, see Game::interactables.
+pub struct Interactable {
+    pub loc: Point,
+    pub name: String, // e.g. "Rhulad" or "a closed door"
+}
+
 // ---- struct Interaction -------------------------------------------------
 pub type PreHandler = fn(&mut Game, &Point, &Point) -> PreResult;
 pub type PostHandler = fn(&mut Game, &Point) -> (Time, Sound);
 
+/// Used when the player uses an item (the Oid) on an adjacent cell, e.g. a key on a locked
+/// door. Returns None if the item can't be used on that cell.
+pub type UseHandler = fn(&mut Game, Oid, &Point) -> Option<(Time, Sound)>;
+
 // TODO:
 // do we need any other handlers? or maybe just comment missing ones?
 pub struct Interactions {
     pre_table: FnvHashMap<(Tid, Tid), PreHandler>,
     post_table: FnvHashMap<(Tid, Tid), PostHandler>,
+    use_table: FnvHashMap<(Tid, Tid), UseHandler>,
 }
 
 impl Interactions {
@@ -36,10 +48,12 @@ impl Interactions {
         let mut i = Interactions {
             pre_table: FnvHashMap::default(),
             post_table: FnvHashMap::default(),
+            use_table: FnvHashMap::default(),
         };
 
         i.pre_ins(PLAYER_ID, DOORMAN_ID, player_vs_doorman);
         i.pre_ins(PLAYER_ID, SPECTATOR_ID, player_vs_spectator);
+        i.pre_ins(PLAYER_ID, LEVER_ID, player_vs_lever);
         i.pre_ins(PLAYER_ID, CHARACTER_ID, player_vs_character);
         i.pre_ins(PLAYER_ID, TERRAIN_ID, player_vs_terrain_pre);
 
@@ -47,6 +61,11 @@ impl Interactions {
         i.post_ins(PLAYER_ID, SIGN_ID, player_vs_sign);
         i.post_ins(PLAYER_ID, TERRAIN_ID, player_vs_terrain_post);
 
+        i.use_ins(EMP_SWORD_ID, TERRAIN_ID, emp_sword_vs_terrain);
+        i.use_ins(KEY_ID, LOCK_ID, key_vs_lock);
+        i.use_ins(PICK_AXE_ID, TERRAIN_ID, pick_axe_vs_terrain);
+        i.use_ins(REFUEL_ID, PLAYER_ID, lighting::refuel_vs_player);
+
         i
     }
 
@@ -66,6 +85,14 @@ impl Interactions {
         self.post_table.get(&(tag0.to_id(), tag1.to_id()))
     }
 
+    /// Returns true if the player can interact with objects having tag (by moving into
+    /// them or onto them). Terrain doesn't count because the player can always move onto
+    /// (or bump into) terrain.
+    pub fn player_interacts_with(&self, tag: &Tag) -> bool {
+        let key = (PLAYER_ID, tag.to_id());
+        key.1 != TERRAIN_ID && (self.pre_table.contains_key(&key) || self.post_table.contains_key(&key))
+    }
+
     fn pre_ins(&mut self, id0: Tid, id1: Tid, handler: PreHandler) {
         self.pre_table.insert((id0, id1), handler);
     }
@@ -73,63 +100,112 @@ impl Interactions {
     fn post_ins(&mut self, id0: Tid, id1: Tid, handler: PostHandler) {
         self.post_table.insert((id0, id1), handler);
     }
+
+    fn use_ins(&mut self, id0: Tid, id1: Tid, handler: UseHandler) {
+        self.use_table.insert((id0, id1), handler);
+    }
+
+    /// Returns the handlers for using item on target (there may be more than one since
+    /// objects have multiple tags).
+    fn find_use_handlers(&self, item: &Object, target: &Object) -> Vec<UseHandler> {
+        let mut handlers = Vec::new();
+        for tag0 in item.iter() {
+            for tag1 in target.iter() {
+                if let Some(handler) = self.use_table.get(&(tag0.to_id(), tag1.to_id())) {
+                    handlers.push(*handler);
+                }
+            }
+        }
+        handlers
+    }
+}
+
+impl Game {
+    /// Returns the cells next to the player with something the player can interact with,
+    /// e.g. a character, a closed door, or a sign.
+    pub fn interactables(&self) -> Vec<Interactable> {
+        let player_loc = self.player_loc();
+        let mut candidates = Vec::new();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let loc = Point::new(player_loc.x + dx, player_loc.y + dy);
+                if loc == player_loc {
+                    continue;
+                }
+                let obj = self.level.cell_iter(&loc).map(|(_, obj)| obj).find(|obj| {
+                    obj.terrain_value() == Some(Terrain::ClosedDoor)
+                        || obj.iter().any(|tag| self.interactions.player_interacts_with(tag))
+                });
+                if let Some(obj) = obj {
+                    let name = obj
+                        .name_value()
+                        .map(|n| n.to_string())
+                        .unwrap_or_else(|| obj.description().to_string());
+                    candidates.push(Interactable { loc, name });
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Player is using an inventory item on an adjacent cell.
+    pub(super) fn use_on(&mut self, item: Oid, target: &Point) -> Time {
+        let player_loc = self.player_loc();
+        if !player_loc.adjacent(target) && player_loc != *target {
+            let text = catalog::text("use.too_far", &[]);
+            self.messages.push(Message::new(Topic::Failed, &text));
+            return Time::zero();
+        }
+        if let Some((taken, sound)) = self.use_item_on(item, target) {
+            self.handle_noise(target, sound);
+            taken
+        } else {
+            let text = catalog::text("use.cant", &[("item", &self.item_name(item))]);
+            self.messages.push(Message::new(Topic::Failed, &text));
+            Time::zero()
+        }
+    }
+
+    fn use_item_on(&mut self, item: Oid, loc: &Point) -> Option<(Time, Sound)> {
+        let handlers: Vec<UseHandler> = {
+            let item = self.level.obj(item).0;
+            self.level
+                .cell_iter(loc)
+                .flat_map(|(_, target)| self.interactions.find_use_handlers(item, target))
+                .collect()
+        };
+        handlers.into_iter().find_map(|handler| handler(self, item, loc))
+    }
+
+    // Bumping into something uses the first inventory item that works with it, e.g.
+    // bumping into a wall with a pick-axe digs into the wall.
+    fn auto_use(&mut self, loc: &Point) -> Option<(Time, Sound)> {
+        let items: Vec<Oid> = self.player_inv_iter().map(|(oid, _)| oid).collect();
+        items.into_iter().find_map(|item| self.use_item_on(item, loc))
+    }
 }
 
 // ---- Pre-move handlers ----------------------------------------------------------------
 fn player_vs_terrain_pre(game: &mut Game, player_loc: &Point, new_loc: &Point) -> PreResult {
-    let (oid, obj) = game.level.get_bottom(new_loc);
-    let player = game.level.get(player_loc, PLAYER_ID).unwrap().1;
+    if let Some((taken, sound)) = game.auto_use(new_loc) {
+        return PreResult::Acted(taken, sound);
+    }
 
     // A few terrain types are special cased.
+    let (oid, obj) = game.level.get_bottom(new_loc);
     let terrain = obj.terrain_value().unwrap();
-    match terrain {
-        Terrain::ClosedDoor => {
-            game.do_open_door(Oid(0), player_loc, new_loc, oid);
-            return PreResult::Acted(time::OPEN_DOOR, sound::VERY_QUIET);
-        }
-        Terrain::Vitr => {
-            if game.in_inv(player, EMP_SWORD_ID) {
-                let mesg = Message::new(
-                    Topic::Important,
-                    "You carefully place the Emperor's sword into the vitr and watch it dissolve.",
-                );
-                game.messages.push(mesg);
-
-                let mesg = Message::new(Topic::Important, "You have won the game!!");
-                game.messages.push(mesg);
-                game.state = State::WonGame;
-                return PreResult::Acted(time::DESTROY_EMP_SWORD, sound::QUIET);
-            }
-        }
-        Terrain::Wall => {
-            if game.in_inv(player, PICK_AXE_ID) {
-                let material = obj.material_value();
-                let delay = {
-                    let item = game.inv_item(player, PICK_AXE_ID).unwrap();
-                    item.delay_value().unwrap()
-                };
-                match material {
-                    Some(Material::Stone) => {
-                        let damage = 6;
-                        game.do_dig(Oid(0), new_loc, oid, damage);
-                        return PreResult::Acted(delay, sound::LOUD);
-                    }
-                    Some(Material::Metal) => {
-                        let mesg = Message::new(
-                            Topic::Normal,
-                            "Your pick-axe bounces off the metal wall doing no damage.",
-                        );
-                        game.messages.push(mesg);
-                        return PreResult::Acted(delay / 4, sound::QUIET);
-                    }
-                    None => unreachable!("Walls should always have a Material"),
-                }
-            }
+    if terrain == Terrain::ClosedDoor {
+        if obj.has(LOCK_ID) {
+            let mesg = Message::new(Topic::Normal, &catalog::text("lock.locked", &[]));
+            game.messages.push(mesg);
+            return PreResult::ZeroAction;
         }
-        _ => (),
+        game.do_open_door(Oid(0), player_loc, new_loc, oid);
+        return PreResult::Acted(time::OPEN_DOOR, sound::VERY_QUIET);
     }
 
     // But for most we just check to see if they are impassible or not.
+    let player = game.level.get(player_loc, PLAYER_ID).unwrap().1;
     if let Some(mesg) = player.impassible_terrain_type(terrain) {
         game.messages.push(mesg);
         PreResult::ZeroAction
@@ -149,7 +225,7 @@ fn player_vs_character(game: &mut Game, player_loc: &Point, new_loc: &Point) ->
             PreResult::Acted(delay, sound::QUIET)
         }
         Some(Disposition::Friendly) => {
-            let mesg = Message::new(Topic::Normal, "Why would you attack a friend?");
+            let mesg = Message::new(Topic::Normal, &catalog::text("attack.friend", &[]));
             game.messages.push(mesg);
             PreResult::ZeroAction
         }
@@ -168,7 +244,7 @@ fn player_vs_character(game: &mut Game, player_loc: &Point, new_loc: &Point) ->
 fn is_worthy(game: &Game) -> bool {
     let player = game.level.get(&game.player_loc(), PLAYER_ID).unwrap().1;
     if let Some(obj) = game.find_main_hand(player) {
-        return obj.description().contains("Doom");
+        return obj.object_name() == ObjectName::MightySword;
     }
     false
 }
@@ -176,44 +252,116 @@ fn is_worthy(game: &Game) -> bool {
 fn player_vs_doorman(game: &mut Game, _player_loc: &Point, doorman_loc: &Point) -> PreResult {
     if is_worthy(game) {
         let (oid, doorman) = game.level.get(doorman_loc, DOORMAN_ID).unwrap();
-        if let Some(to_loc) = game.find_empty_cell(doorman, doorman_loc) {
+        if !doorman.can_be_displaced() {
+            PreResult::ZeroAction
+        } else if let Some(to_loc) = game.find_empty_cell(doorman, doorman_loc) {
             game.do_shove_doorman(Oid(0), doorman_loc, oid, &to_loc);
             PreResult::Acted(time::SHOVE_DOORMAN, sound::QUIET)
         } else {
             PreResult::ZeroAction
         }
     } else {
-        let mesg = Message::new(Topic::NPCSpeaks, "You are not worthy.");
+        let mesg = Message::new(Topic::NPCSpeaks, &catalog::text("doorman.not_worthy", &[]));
         game.messages.push(mesg);
         PreResult::ZeroAction
     }
 }
 
-fn player_vs_spectator(game: &mut Game, _player_loc: &Point, _new_loc: &Point) -> PreResult {
-    let messages = if matches!(game.state, State::Adventuring) {
+fn player_vs_lever(game: &mut Game, _player_loc: &Point, lever_loc: &Point) -> PreResult {
+    game.pull_lever(lever_loc);
+    PreResult::Acted(time::PULL_LEVER, sound::QUIET)
+}
+
+fn player_vs_spectator(game: &mut Game, _player_loc: &Point, new_loc: &Point) -> PreResult {
+    let keys = if matches!(game.state, State::Adventuring) {
         vec![
-            "I hope you're prepared to die!",
-            "The last champion only lasted thirty seconds.",
-            "How can you defeat a man who will not stay dead?",
-            "I have 10 gold on you lasting over two minutes!",
-            "You're just another dead man walking.",
+            "spectator.taunt1",
+            "spectator.taunt2",
+            "spectator.taunt3",
+            "spectator.taunt4",
+            "spectator.taunt5",
         ]
     } else {
-        vec![
-            "I can't believe that the Emperor is dead.",
-            "You're my hero!",
-            "You've done the impossible!",
-        ]
+        vec!["spectator.praise1", "spectator.praise2", "spectator.praise3"]
     };
-    let text = messages.iter().choose(&mut *game.rng()).unwrap();
+    let key = keys.iter().choose(&mut *game.rng()).unwrap();
 
-    let mesg = Message::new(Topic::NPCSpeaks, text);
+    let mesg = Message::new(Topic::NPCSpeaks, &catalog::text(key, &[]));
     game.messages.push(mesg);
+    if matches!(game.state, State::Adventuring) {
+        let spectator = game.level.get(new_loc, CHARACTER_ID).unwrap().0;
+        game.place_bet(spectator);
+    }
     PreResult::Acted(time::SPEAK_TO_SPECTATOR, sound::QUIET)
 }
 
+// ---- Use handlers ---------------------------------------------------------------------
+fn emp_sword_vs_terrain(game: &mut Game, _item: Oid, loc: &Point) -> Option<(Time, Sound)> {
+    let obj = game.level.get_bottom(loc).1;
+    if obj.terrain_value() != Some(Terrain::Vitr) {
+        return None;
+    }
+
+    let mesg = Message::new(Topic::Important, &catalog::text("quest.destroy_sword.dissolved", &[]));
+    game.messages.push(mesg);
+
+    let mesg = Message::new(Topic::Important, &catalog::text("game.won", &[]));
+    game.messages.push(mesg);
+    game.state = State::WonGame;
+    Some((time::DESTROY_EMP_SWORD, sound::QUIET))
+}
+
+fn key_vs_lock(game: &mut Game, item: Oid, loc: &Point) -> Option<(Time, Sound)> {
+    let key = game.level.obj(item).0.key_value().unwrap();
+    let (oid, door) = game.level.get(loc, LOCK_ID).unwrap();
+    if door.lock_value() != Some(key) {
+        return None;
+    }
+
+    let text = catalog::text("lock.unlocked", &[("item", &game.item_name(item))]);
+    game.messages.push(Message::new(Topic::Normal, &text));
+    game.level.obj_mut(oid).remove(LOCK_ID);
+    Some((time::UNLOCK, sound::QUIET))
+}
+
+fn pick_axe_vs_terrain(game: &mut Game, item: Oid, loc: &Point) -> Option<(Time, Sound)> {
+    let (oid, obj) = game.level.get_bottom(loc);
+    if obj.terrain_value() != Some(Terrain::Wall) {
+        return None;
+    }
+
+    let delay = game.level.obj(item).0.delay_value().unwrap();
+    match obj.material_value() {
+        Some(Material::Stone) => {
+            let damage = 6;
+            game.do_dig(Oid(0), loc, oid, damage);
+            game.decorate(loc, Decoration::Gouges);
+            Some((delay, sound::LOUD))
+        }
+        Some(Material::Metal) => {
+            let mesg = Message::new(Topic::Normal, &catalog::text("dig.bounces", &[]));
+            game.messages.push(mesg);
+            game.decorate(loc, Decoration::Gouges);
+            Some((delay / 4, sound::QUIET))
+        }
+        None => unreachable!("Walls should always have a Material"),
+    }
+}
+
 // ---- Post-move handlers ---------------------------------------------------------------
+// If there are several items the player picks which to pick up with Action::PickUpMany.
 fn player_vs_portable(game: &mut Game, loc: &Point) -> (Time, Sound) {
+    let count = game
+        .level
+        .cell_iter(loc)
+        .filter(|(_, obj)| obj.has(PORTABLE_ID))
+        .count();
+    if count > 1 {
+        let mesg = Message::new(Topic::Normal, &catalog::text("pick_up.many", &[("count", &count)]));
+        game.messages.push(mesg);
+        return (Time::zero(), sound::NONE);
+    }
+
     let oid = game.level.get(loc, PORTABLE_ID).unwrap().0;
 
     let player = game.level.get_mut(loc, CHARACTER_ID).unwrap().1;
@@ -222,38 +370,103 @@ fn player_vs_portable(game: &mut Game, loc: &Point) -> (Time, Sound) {
         game.do_pick_up(Oid(0), loc, oid);
         (time::PICK_UP, sound::NONE)
     } else {
-        let why = "You don't have enough inventory space to ";
-        game.do_ignore(Oid(0), loc, oid, why);
+        game.do_ignore(Oid(0), loc, oid);
         (Time::zero(), sound::NONE)
     }
 }
 
 fn player_vs_sign(game: &mut Game, loc: &Point) -> (Time, Sound) {
     let (_, obj) = game.level.get(loc, SIGN_ID).unwrap();
-    let mesg = Message {
-        topic: Topic::Normal,
-        text: format!("You see a sign {}.", obj.description()),
-    };
+    let text = catalog::text("sign.read", &[("desc", &obj.description())]);
+    let mesg = Message::new(Topic::Normal, &text);
     game.messages.push(mesg);
     (Time::zero(), sound::NONE)
 }
 
 fn player_vs_terrain_post(game: &mut Game, loc: &Point) -> (Time, Sound) {
     let (_, obj) = game.level.get(loc, TERRAIN_ID).unwrap();
-    match obj.terrain_value().unwrap() {
+    let terrain = obj.terrain_value().unwrap();
+    let delay = game.level.get(loc, PLAYER_ID).unwrap().1.terrain_delay(terrain);
+    match terrain {
         Terrain::Rubble => {
-            let mesg = Message::new(Topic::Normal, "You pick your way through the rubble.");
+            let mesg = Message::new(Topic::Normal, &catalog::text("terrain.rubble", &[]));
             game.messages.push(mesg);
-            (time::MOVE_THRU_SHALLOW_WATER * 2, sound::QUIET)
+            (delay, sound::QUIET)
         }
         Terrain::ShallowWater => {
-            let mesg = Message::new(Topic::Normal, "You splash through the water.");
+            let mesg = Message::new(Topic::Normal, &catalog::text("terrain.shallow_water", &[]));
             game.messages.push(mesg);
-
-            // TODO: Some NPCs should not have a penalty (or maybe even be faster)
-            // TODO: May change for the player as well (especially if we have any small races)
-            (time::MOVE_THRU_SHALLOW_WATER, sound::QUIET) // just a little slower and a little louder
+            (delay, sound::QUIET) // a little louder (and usually a little slower)
         }
         _ => (Time::zero(), sound::NONE),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::{open_neighbor, test_game};
+    use std::fs;
+
+    #[test]
+    fn test_interactables() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        let names = |game: &Game| game.interactables().into_iter().map(|i| i.name).collect::<Vec<_>>();
+        assert!(!names(&game).iter().any(|n| n == "Spectator"));
+
+        let loc = open_neighbor(&game);
+        game.add_object(&loc, new_obj(ObjectName::Spectator));
+        let candidates = game.interactables();
+        let spectator = candidates.iter().find(|i| i.name == "Spectator").unwrap();
+        assert_eq!(spectator.loc, loc);
+    }
+
+    #[test]
+    fn test_use_on() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        let door_loc = open_neighbor(&game);
+        let (dx, dy) = (door_loc.x - player_loc.x, door_loc.y - player_loc.y);
+        let oid = game.level.get_bottom(&door_loc).0;
+        game.replace_object(&door_loc, oid, new_obj(ObjectName::LockedDoor));
+        let terrain = |game: &Game| game.level.get_bottom(&door_loc).1.terrain_value().unwrap();
+
+        // Locked doors can't be opened.
+        game.player_acted(Action::Move { dx, dy });
+        assert!(game.recent_messages(1).any(|m| m.text.contains("is locked")));
+        assert_eq!(terrain(&game), Terrain::ClosedDoor);
+
+        // Items have to be used on something they work with.
+        let axe = game.level.add(new_obj(ObjectName::PickAxe), None);
+        let key = game.level.add(new_obj(ObjectName::IronKey), None);
+        let player = game.level.get_mut(&player_loc, CHARACTER_ID).unwrap().1;
+        player.inventory_value_mut().unwrap().push(axe);
+        player.inventory_value_mut().unwrap().push(key);
+        game.player_acted(Action::UseOn {
+            oid: axe,
+            target: door_loc,
+        });
+        assert!(game
+            .recent_messages(1)
+            .any(|m| m.text.contains("can't use the pick-axe")));
+
+        // The right key unlocks the door,
+        game.player_acted(Action::UseOn {
+            oid: key,
+            target: door_loc,
+        });
+        assert!(game.recent_messages(1).any(|m| m.text.contains("unlock the door")));
+        assert!(!game.level.get_bottom(&door_loc).1.has(LOCK_ID));
+
+        // and then it can be opened.
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+        game.player_acted(Action::Move { dx, dy });
+        assert_eq!(terrain(&game), Terrain::OpenDoor);
+    }
+}
@@ -0,0 +1,104 @@
+//! Quests the player is working on. Quests are updated by interaction handlers and other
+//! game logic so, like the rest of the game state, they are rebuilt when a saved game is
+//! replayed.
+use super::catalog;
+use derive_more::Display;
+
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+pub enum QuestState {
+    Active,
+    Complete,
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QuestId {
+    DestroySword,
+    KillRhulad,
+}
+
+#[derive(Clone, Debug)]
+pub struct Quest {
+    pub objective: String,
+    pub state: QuestState,
+    pub hint: String, // progress hint, typically updated as the player makes progress
+    id: QuestId,
+}
+
+pub struct Journal {
+    quests: Vec<Quest>, // in the order they were started
+}
+
+impl Journal {
+    pub fn new() -> Journal {
+        Journal { quests: Vec::new() }
+    }
+
+    pub fn quests(&self) -> &[Quest] {
+        &self.quests
+    }
+
+    pub fn start(&mut self, id: QuestId) {
+        debug_assert!(self.find(id).is_none(), "{id:?} was already started");
+        let (objective, hint) = match id {
+            QuestId::DestroySword => ("quest.destroy_sword", "quest.destroy_sword.hint"),
+            QuestId::KillRhulad => ("quest.kill_rhulad", "quest.kill_rhulad.hint"),
+        };
+        self.quests.push(Quest {
+            objective: catalog::text(objective, &[]),
+            state: QuestState::Active,
+            hint: catalog::text(hint, &[]),
+            id,
+        });
+    }
+
+    pub fn set_hint(&mut self, id: QuestId, hint: &str) {
+        if let Some(quest) = self.find_mut(id) {
+            quest.hint = hint.to_string();
+        }
+    }
+
+    pub fn complete(&mut self, id: QuestId, hint: &str) {
+        if let Some(quest) = self.find_mut(id) {
+            quest.state = QuestState::Complete;
+            quest.hint = hint.to_string();
+        }
+    }
+
+    /// Used when the player dies.
+    pub fn fail_active(&mut self) {
+        for quest in self.quests.iter_mut() {
+            if quest.state == QuestState::Active {
+                quest.state = QuestState::Failed;
+            }
+        }
+    }
+
+    fn find(&self, id: QuestId) -> Option<&Quest> {
+        self.quests.iter().find(|q| q.id == id)
+    }
+
+    fn find_mut(&mut self, id: QuestId) -> Option<&mut Quest> {
+        self.quests.iter_mut().find(|q| q.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_states() {
+        let mut journal = Journal::new();
+        journal.start(QuestId::DestroySword);
+        journal.start(QuestId::KillRhulad);
+
+        journal.complete(QuestId::KillRhulad, "done");
+        journal.fail_active();
+
+        let quests = journal.quests();
+        assert_eq!(quests[0].state, QuestState::Failed);
+        assert_eq!(quests[1].state, QuestState::Complete);
+        assert_eq!(quests[1].hint, "done");
+    }
+}
@@ -0,0 +1,221 @@
+//! Heavy hits knock the defender back and the player can deliberately shove characters
+//! out of the way. Characters that are knocked into things like walls take damage.
+use super::catalog;
+use super::interactions::PreResult;
+use super::sound;
+use super::tag::*;
+use super::time::{self, Time};
+use super::{Game, Message, Oid, Point, State, Topic};
+
+/// Hits that do at least this percent of the defender's max HPs knock it back a cell.
+const HEAVY_HIT: i32 = 25;
+
+/// Damage taken when a character is knocked into something solid.
+const SLAM_DAMAGE: i32 = 5;
+
+impl Game {
+    /// Called after a melee attack to knock the defender back if the hit was heavy enough.
+    pub(super) fn heavy_hit_knockback(&mut self, attacker_loc: &Point, defender_loc: &Point, damage: i32) {
+        if let Some((_, defender)) = self.level.get(defender_loc, CHARACTER_ID) {
+            if let Some(durability) = defender.durability_value() {
+                if durability.current > 0 && 100 * damage >= HEAVY_HIT * durability.max && defender.can_be_displaced() {
+                    let _ = self.do_knockback(attacker_loc, defender_loc, 1);
+                }
+            }
+        }
+    }
+
+    /// Pushes the character at defender_loc up to cells away from attacker_loc. Returns
+    /// the character's new location (which will be defender_loc if it couldn't move or
+    /// None if it died).
+    pub(super) fn do_knockback(&mut self, attacker_loc: &Point, defender_loc: &Point, cells: i32) -> Option<Point> {
+        let attacker = self.level.get(attacker_loc, CHARACTER_ID).map(|(oid, _)| oid);
+        let defender = self.level.get(defender_loc, CHARACTER_ID).unwrap().0;
+        let dx = (defender_loc.x - attacker_loc.x).signum();
+        let dy = (defender_loc.y - attacker_loc.y).signum();
+
+        let mut loc = *defender_loc;
+        for _ in 0..cells {
+            let next = Point::new(loc.x + dx, loc.y + dy);
+            if self.can_teleport_to(defender, &next) {
+                if defender.0 == 0 {
+                    let _ = self.do_teleport(defender, &next);
+                } else {
+                    self.do_move(defender, &loc, &next);
+                }
+                loc = next;
+            } else {
+                let obstacle = self.level.get_bottom(&next).1;
+                if obstacle.blocks_los() {
+                    let what = obstacle.description();
+                    return self.slam(attacker, defender, &loc, &what);
+                }
+                break;
+            }
+        }
+
+        if loc != *defender_loc {
+            let text = if defender.0 == 0 {
+                catalog::text("knockback.player", &[])
+            } else {
+                let name = self.level.obj(defender).0.name_value().unwrap();
+                catalog::text("knockback.npc", &[("name", &name)])
+            };
+            self.messages.push(Message::new(Topic::Normal, &text));
+        }
+        Some(loc)
+    }
+
+    /// Player is trying to push the character at the adjacent cell one cell further away.
+    pub(super) fn do_shove(&mut self, dx: i32, dy: i32) -> Time {
+        let player_loc = self.player_loc();
+        let loc = Point::new(player_loc.x + dx, player_loc.y + dy);
+        let (oid, obj) = match self.level.get(&loc, CHARACTER_ID) {
+            Some(entry) => entry,
+            None => {
+                let mesg = Message::new(Topic::Failed, &catalog::text("shove.nobody", &[]));
+                self.messages.push(mesg);
+                return Time::zero();
+            }
+        };
+        let name = obj.name_value().unwrap();
+        let neutral = obj.disposition_value() == Some(Disposition::Neutral);
+
+        // Doormen have their own rules for who may pass.
+        if obj.has(DOORMAN_ID) {
+            return match self.try_interact(&player_loc, &loc) {
+                PreResult::Acted(taken, sound) => {
+                    self.handle_noise(&player_loc, sound);
+                    taken
+                }
+                _ => Time::zero(),
+            };
+        }
+
+        if !obj.can_be_displaced() {
+            let mesg = Message::new(Topic::Failed, &catalog::text("shove.too_big", &[("name", &name)]));
+            self.messages.push(mesg);
+            return Time::zero();
+        }
+
+        if neutral {
+            let obj = self.level.get_mut(&loc, CHARACTER_ID).unwrap().1;
+            obj.replace(Tag::Disposition(Disposition::Aggressive));
+            self.player_attacked_neutral(oid);
+        }
+        if self.level.obj(oid).0.has(BEHAVIOR_ID) {
+            self.react_to_attack(&player_loc, Oid(0), &loc);
+        }
+
+        if self.do_knockback(&player_loc, &loc, 1) == Some(loc) {
+            let mesg = Message::new(Topic::Normal, &catalog::text("shove.doesnt_budge", &[("name", &name)]));
+            self.messages.push(mesg);
+        }
+        self.handle_noise(&loc, sound::QUIET);
+        time::SHOVE
+    }
+
+    fn slam(&mut self, attacker: Option<Oid>, defender: Oid, loc: &Point, what: &str) -> Option<Point> {
+        let obj = self.level.obj_mut(defender);
+        let durability = obj.durability_value()?;
+        let current = durability.current - SLAM_DAMAGE;
+        obj.replace(Tag::Durability(Durability {
+            current,
+            max: durability.max,
+        }));
+
+        let name = obj.name_value().unwrap();
+        let (topic, text) = if defender.0 == 0 {
+            let topic = if current > 0 {
+                Topic::PlayerIsDamaged
+            } else {
+                Topic::Fatal
+            };
+            (
+                topic,
+                catalog::text("slam.player", &[("what", &what), ("damage", &SLAM_DAMAGE)]),
+            )
+        } else {
+            (
+                Topic::Normal,
+                catalog::text(
+                    "slam.npc",
+                    &[("name", &name), ("what", &what), ("damage", &SLAM_DAMAGE)],
+                ),
+            )
+        };
+        self.messages.push(Message::new(topic, &text));
+
+        if current > 0 {
+            Some(*loc)
+        } else if defender.0 == 0 {
+            self.messages
+                .push(Message::new(Topic::Important, &catalog::text("game.lost", &[])));
+            self.state = State::LostGame;
+            Some(*loc)
+        } else {
+            if attacker == Some(Oid(0)) {
+                self.player_killed(defender);
+            }
+            self.npc_died(loc, defender);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::{open_neighbor, test_game};
+    use crate::backend::{Action, ObjectName};
+
+    #[test]
+    fn test_shove() {
+        let mut game = test_game();
+
+        // Find a spot next to the player with room behind it for a spectator.
+        let player_loc = game.player_loc();
+        let deltas = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+        let (dx, dy) = *deltas
+            .iter()
+            .find(|(dx, dy)| {
+                let near = Point::new(player_loc.x + dx, player_loc.y + dy);
+                let far = Point::new(player_loc.x + 2 * dx, player_loc.y + 2 * dy);
+                game.can_teleport_to(Oid(0), &near) && game.can_teleport_to(Oid(0), &far)
+            })
+            .unwrap();
+        let near = Point::new(player_loc.x + dx, player_loc.y + dy);
+        let far = Point::new(player_loc.x + 2 * dx, player_loc.y + 2 * dy);
+        let oid = game.add_object(&near, new_obj(ObjectName::Spectator));
+
+        game.player_acted(Action::Shove { dx, dy });
+        assert_eq!(game.loc(oid), Some(far));
+        assert_eq!(game.player_loc(), player_loc);
+    }
+
+    #[test]
+    fn test_sizes() {
+        let mut game = test_game();
+
+        // Large characters can't be shoved.
+        let player_loc = game.player_loc();
+        let loc = open_neighbor(&game);
+        let (dx, dy) = (loc.x - player_loc.x, loc.y - player_loc.y);
+        let oid = game.add_object(&loc, new_obj(ObjectName::ImrothTheCruel));
+        game.player_acted(Action::Shove { dx, dy });
+        assert_eq!(game.loc(oid), Some(loc));
+        assert!(game.recent_messages(1).any(|m| m.text.contains("too big")));
+
+        // They also can't fit through doors.
+        let ch = game.level.obj(oid).0;
+        assert!(ch.impassible_terrain_type(Terrain::OpenDoor).is_some());
+
+        // Aquatic characters can swim but can't leave the water.
+        let mut fish = new_obj(ObjectName::Guard);
+        fish.set(Tag::Size(BodySize::Aquatic));
+        assert!(fish.impassible_terrain_type(Terrain::DeepWater).is_none());
+        assert!(fish.impassible_terrain_type(Terrain::Ground).is_some());
+        assert_eq!(fish.terrain_delay(Terrain::ShallowWater), Time::zero());
+    }
+}
@@ -1,28 +1,73 @@
 use crate::backend::tag::CHARACTER_ID;
 use rand::prelude::*;
 use rand::rngs::SmallRng;
-use std::cell::{Cell, RefCell};
+use std::cell::RefCell;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
 use super::*;
 use fnv::FnvHashMap;
 
+/// Unbounded levels add default cells this many cells on a side at a time.
+const CHUNK_SIZE: i32 = 16;
+
+/// What happens when a location outside of the level's map is changed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Extent {
+    /// The level is the size of its map: locations outside of it are always the default
+    /// terrain and can't be changed (and are never visible or used in paths).
+    Bounded,
+
+    /// The level grows as needed with default cells added a chunk at a time.
+    Unbounded,
+}
+
 struct Entry {
     obj: Object,
     loc: Option<Point>, // None for objects within Equipped or Inventory tags
 }
 
+/// Data derived from the level that is rebuilt as needed. Anything computed from these
+/// must depend only on the level itself (not on when, or how often, they were rebuilt) and
+/// read-only code, like the invariants, must not rebuild them. Otherwise enabling debug
+/// checks could change how the game plays out. These are locked so that the level can be
+/// shared with the threads that find paths for NPCs (see planning.rs).
+struct Caches {
+    npcs: Mutex<Vec<Oid>>,        // all NPCs sorted so that the first is closest to the player
+    sorted: AtomicBool,           // false if npcs needs to be re-sorted
+    locations: Mutex<Vec<Point>>, // locations on the level
+}
+
+/// Locations that were modified while recording was enabled.
+#[derive(Default)]
+struct Edits {
+    recording: bool,
+    locs: Vec<Point>,
+}
+
+impl Edits {
+    fn push(&mut self, loc: Point) {
+        if self.recording {
+            self.locs.push(loc);
+        }
+    }
+}
+
 pub struct Level {
     objects: FnvHashMap<Oid, Entry>,    // all existing objects are here
     cells: FnvHashMap<Point, Vec<Oid>>, // objects within each cell on the map
-    npcs: RefCell<Vec<Oid>>,            // all NPCs sorted so that the first is closest to the player
-    sorted: Cell<bool>,                 // false if npcs needs to be re-sorted
-    locations: RefCell<Vec<Point>>,     // locations on the level
+    caches: Caches,                     // lazily rebuilt data derived from the above
     next_id: u64,                       // 0 is the player, 1 is the default object
     player_loc: Point,
     default: Object,
     default_oids: Vec<Oid>,
-    constructing: bool, // level is in the process of being constructed
-    changed: Point,     // the loc that was last modified, used for cheap invariants
+    extent: Extent,
+    top_left: Point,     // bounding box of the cells, inclusive
+    bottom_right: Point, // note that not all of these cells exist
+    constructing: bool,  // level is in the process of being constructed
+    changed: Point,      // the loc that was last modified, used for cheap invariants
+    edits: Edits,        // locs modified since paths were planned, see planning.rs
     #[cfg(debug_assertions)]
     invariants: bool, // if true then expensive checks are enabled
 }
@@ -32,14 +77,20 @@ impl Level {
         Level {
             objects: FnvHashMap::default(),
             cells: FnvHashMap::default(),
-            npcs: RefCell::new(Vec::new()),
-            locations: RefCell::new(Vec::new()),
-            sorted: Cell::new(true),
+            caches: Caches {
+                npcs: Mutex::new(Vec::new()),
+                sorted: AtomicBool::new(true),
+                locations: Mutex::new(Vec::new()),
+            },
             next_id: 2,
             player_loc: Point::new(0, 0),
             default: super::new_obj(ObjectName::StoneWall),
             default_oids: vec![Oid(1)],
+            extent: Extent::Bounded,
+            top_left: Point::new(i32::MAX, i32::MAX),
+            bottom_right: Point::new(i32::MIN, i32::MIN),
             changed: Point::new(0, 0),
+            edits: Edits::default(),
             constructing: true,
             #[cfg(debug_assertions)]
             invariants: false,
@@ -56,10 +107,51 @@ impl Level {
         self.invariants = enable;
     }
 
+    pub fn extent(&self) -> Extent {
+        self.extent
+    }
+
+    pub fn set_extent(&mut self, extent: Extent) {
+        self.extent = extent;
+    }
+
+    /// Returns the top-left and bottom-right locations of the level.
+    pub fn bounds(&self) -> (Point, Point) {
+        (self.top_left, self.bottom_right)
+    }
+
+    pub fn width(&self) -> i32 {
+        (self.bottom_right.x - self.top_left.x + 1).max(0)
+    }
+
+    pub fn height(&self) -> i32 {
+        (self.bottom_right.y - self.top_left.y + 1).max(0)
+    }
+
+    /// Unbounded levels are always in bounds.
+    pub fn in_bounds(&self, loc: &Point) -> bool {
+        self.extent == Extent::Unbounded
+            || (self.top_left.x..=self.bottom_right.x).contains(&loc.x)
+                && (self.top_left.y..=self.bottom_right.y).contains(&loc.y)
+    }
+
     pub fn player_loc(&self) -> Point {
         self.player_loc
     }
 
+    /// Clears the recorded edits and starts (or stops) recording the locations of cells
+    /// that are modified.
+    pub fn record_edits(&mut self, enable: bool) {
+        self.edits.recording = enable;
+        self.edits.locs.clear();
+    }
+
+    /// Locations of the cells modified since record_edits was called (this may include
+    /// duplicates).
+    pub fn edits(&self) -> &[Point] {
+        &self.edits.locs
+    }
+
     pub fn get(&self, loc: &Point, tag: Tid) -> Option<(Oid, &Object)> {
         if let Some(oids) = self.cells.get(loc) {
             for oid in oids.iter().rev() {
@@ -83,7 +175,13 @@ impl Level {
 
     pub fn get_mut(&mut self, loc: &Point, tag: Tid) -> Option<(Oid, &mut Object)> {
         if !self.cells.contains_key(loc) {
-            self.add_default(loc);
+            if !self.constructing && !self.in_bounds(loc) {
+                return None; // hard edge so don't grow the level
+            }
+            match self.extent {
+                Extent::Bounded => self.add_default(loc),
+                Extent::Unbounded => self.add_chunk(loc),
+            }
         }
 
         let mut oid = None;
@@ -148,6 +246,15 @@ impl Level {
         (&entry.obj, entry.loc)
     }
 
+    /// Note that this shouldn't be used to change tags that affect Level, e.g. Character.
+    pub fn obj_mut(&mut self, oid: Oid) -> &mut Object {
+        let entry = self
+            .objects
+            .get_mut(&oid)
+            .unwrap_or_else(|| panic!("oid {oid} isn't in objects"));
+        &mut entry.obj
+    }
+
     pub fn try_obj(&self, oid: Oid) -> Option<&Object> {
         let entry = self.objects.get(&oid);
         entry.map(|e| &e.obj)
@@ -169,29 +276,36 @@ impl Level {
     /// Note that this is sorted by distance from the player (closest first) and does not
     /// consider PoV.
     pub fn npcs(&self) -> impl Iterator<Item = Oid> + '_ {
-        if !self.sorted.get() {
+        if !self.caches.sorted.load(Ordering::Relaxed) {
             // This will normally be mostly sorted so it should be pretty close to an O(N)
             // operation. Still it's expensive enough that we want to defer sorting until
             // we actually need it.
-            self.npcs.borrow_mut().sort_by(|a, b| {
-                let a = self.obj(*a).1.unwrap();
-                let b = self.obj(*b).1.unwrap();
-                let a = a.distance2(&self.player_loc);
-                let b = b.distance2(&self.player_loc);
-                a.cmp(&b)
+            // Ties are broken by oid so that the order doesn't depend on when we sorted.
+            self.caches.npcs.lock().unwrap().sort_by_key(|oid| {
+                let loc = self.obj(*oid).1.unwrap();
+                (loc.distance2(&self.player_loc), oid.0)
             });
-            self.sorted.set(true);
+            self.caches.sorted.store(true, Ordering::Relaxed);
         }
         NpcsIterator { level: self, index: -1 }
     }
 
     /// Returns a random cell on the map.
     pub fn random_loc(&self, rng: &RefCell<SmallRng>) -> Point {
-        if self.locations.borrow().is_empty() {
-            self.locations.borrow_mut().extend(self.cells.keys());
+        if self.caches.locations.lock().unwrap().is_empty() {
+            let mut locations = self.caches.locations.lock().unwrap();
+            locations.extend(self.cells.keys());
+            locations.sort_by_key(|loc| (loc.x, loc.y)); // so that choose doesn't depend on hash order
         }
         // We could use cells.keys() here but the performance would be O(N) if we did that.
-        *self.locations.borrow().iter().choose(&mut *rng.borrow_mut()).unwrap()
+        *self
+            .caches
+            .locations
+            .lock()
+            .unwrap()
+            .iter()
+            .choose(&mut *rng.borrow_mut())
+            .unwrap()
     }
 
     pub fn add(&mut self, obj: Object, loc: Option<Point>) -> Oid {
@@ -207,8 +321,8 @@ impl Level {
             if oid.0 == 0 {
                 self.player_loc = loc.unwrap();
             } else {
-                self.npcs.borrow_mut().push(oid);
-                self.sorted.set(false);
+                self.caches.npcs.lock().unwrap().push(oid);
+                self.caches.sorted.store(false, Ordering::Relaxed);
             }
         }
 
@@ -218,10 +332,12 @@ impl Level {
         if let Some(loc) = loc {
             let oids = self.cells.entry(loc).or_insert_with(Vec::new);
             if oids.is_empty() {
-                self.locations.borrow_mut().clear();
+                self.caches.locations.lock().unwrap().clear();
             }
             oids.push(oid);
             self.changed = loc;
+            self.edits.push(loc);
+            self.grow_bounds(&loc);
         }
 
         if cfg!(debug_assertions) {
@@ -238,16 +354,18 @@ impl Level {
 
         let oids = self.cells.entry(loc).or_insert_with(Vec::new);
         if oids.is_empty() {
-            self.locations.borrow_mut().clear();
+            self.caches.locations.lock().unwrap().clear();
         }
 
-        if let Some(i) = oids.iter().position(|&o| o.0 == 0) {
-            // TODO: doesn't support NPCs dropping items
+        let objects = &self.objects;
+        if let Some(i) = oids.iter().position(|o| objects[o].obj.has(CHARACTER_ID)) {
             oids.insert(i, oid);
         } else {
             oids.push(oid);
         }
         self.changed = loc;
+        self.edits.push(loc);
+        self.grow_bounds(&loc);
 
         if cfg!(debug_assertions) {
             self.invariant();
@@ -262,13 +380,15 @@ impl Level {
             let index = oids.iter().position(|id| *id == oid).unwrap();
             oids.remove(index);
             self.changed = loc;
+            self.edits.push(loc);
         } else {
             trace!("removing {} {oid} which had no loc", entry.obj);
         }
 
         if oid.0 != 0 && entry.obj.has(CHARACTER_ID) {
-            let index = self.npcs.borrow().iter().position(|id| *id == oid).unwrap();
-            self.npcs.borrow_mut().remove(index);
+            let mut npcs = self.caches.npcs.lock().unwrap();
+            let index = npcs.iter().position(|id| *id == oid).unwrap();
+            npcs.remove(index);
         }
 
         self.objects.remove(&oid);
@@ -289,6 +409,7 @@ impl Level {
             let index = oids.iter().position(|id| *id == oid).unwrap();
             oids.remove(index);
             self.changed = loc;
+            self.edits.push(loc);
         }
         entry.loc = None;
         assert!(!entry.obj.has(CHARACTER_ID));
@@ -308,7 +429,7 @@ impl Level {
         let old_name = old_obj.dname();
         if old_obj.has(CHARACTER_ID) {
             assert!(old_oid.0 > 1);
-            let mut oids = self.npcs.borrow_mut();
+            let mut oids = self.caches.npcs.lock().unwrap();
             let index = oids.iter().position(|id| *id == old_oid).unwrap();
             oids.remove(index);
         }
@@ -317,8 +438,8 @@ impl Level {
         trace!("replacing {old_name} {old_oid} with {new_obj} {new_oid} at {loc}");
         if new_obj.has(CHARACTER_ID) {
             assert!(new_oid.0 > 1);
-            self.npcs.borrow_mut().push(new_oid);
-            self.sorted.set(false);
+            self.caches.npcs.lock().unwrap().push(new_oid);
+            self.caches.sorted.store(false, Ordering::Relaxed);
         }
 
         // Fix up objects.
@@ -339,6 +460,7 @@ impl Level {
         oids[index] = new_oid;
 
         self.changed = *loc;
+        self.edits.push(*loc);
         new_oid
     }
 
@@ -359,12 +481,14 @@ impl Level {
 
         let oids = self.cells.entry(*to).or_insert_with(Vec::new);
         if oids.is_empty() {
-            self.locations.borrow_mut().clear();
+            self.caches.locations.lock().unwrap().clear();
         }
         oids.push(oid);
 
-        self.sorted.set(false); // technically we should do this only if oid has a CHARACTER_ID, but very little moves other than characters
+        self.caches.sorted.store(false, Ordering::Relaxed); // technically we should do this only if oid has a CHARACTER_ID, but very little moves other than characters
         self.changed = *to;
+        self.edits.push(*from);
+        self.edits.push(*to);
 
         if oid.0 == 0 {
             self.player_loc = *to;
@@ -381,11 +505,24 @@ impl Level {
             let new_loc = Point::new(loc.x + delta.0, loc.y + delta.1);
             if !self.cells.contains_key(&new_loc) {
                 self.add_default(&new_loc);
-                self.locations.borrow_mut().clear();
+                self.caches.locations.lock().unwrap().clear();
             }
         }
     }
 
+    /// Used to verify that replayed games are in sync with the original game.
+    pub fn checksum<H: Hasher>(&self, hasher: &mut H) {
+        let mut oids: Vec<&Oid> = self.objects.keys().collect();
+        oids.sort_by_key(|oid| oid.0);
+        for oid in oids {
+            let entry = self.objects.get(oid).unwrap();
+            oid.hash(hasher);
+            entry.loc.hash(hasher);
+            format!("{:?}", entry.obj).hash(hasher);
+        }
+        self.next_id.hash(hasher);
+    }
+
     fn next_oid(&mut self, obj: &Object) -> Oid {
         if obj.has(PLAYER_ID) {
             Oid(0)
@@ -396,6 +533,25 @@ impl Level {
         }
     }
 
+    fn grow_bounds(&mut self, loc: &Point) {
+        self.top_left = Point::new(self.top_left.x.min(loc.x), self.top_left.y.min(loc.y));
+        self.bottom_right = Point::new(self.bottom_right.x.max(loc.x), self.bottom_right.y.max(loc.y));
+    }
+
+    /// Adds default cells for the chunk containing loc (that don't already exist).
+    fn add_chunk(&mut self, loc: &Point) {
+        let x0 = loc.x.div_euclid(CHUNK_SIZE) * CHUNK_SIZE;
+        let y0 = loc.y.div_euclid(CHUNK_SIZE) * CHUNK_SIZE;
+        for y in y0..y0 + CHUNK_SIZE {
+            for x in x0..x0 + CHUNK_SIZE {
+                let candidate = Point::new(x, y);
+                if !self.cells.contains_key(&candidate) {
+                    self.add_default(&candidate);
+                }
+            }
+        }
+    }
+
     fn add_default(&mut self, new_loc: &Point) {
         let oid = Oid(self.next_id);
         self.next_id += 1;
@@ -407,15 +563,76 @@ impl Level {
             },
         );
         if old.is_none() {
-            self.locations.borrow_mut().clear();
+            self.caches.locations.lock().unwrap().clear();
         }
         let old_oids = self.cells.insert(*new_loc, vec![oid]);
         assert!(old_oids.is_none());
+        self.edits.push(*new_loc);
+        self.grow_bounds(new_loc);
     }
 }
 
 // Debugging support
 impl Level {
+    /// Appends a summary of memory use to report. Sizes are the number of bytes each
+    /// section takes when serialized with postcard.
+    pub fn audit(&self, report: &mut Vec<String>) {
+        let mut counts: FnvHashMap<String, (usize, usize)> = FnvHashMap::default();
+        let mut object_bytes = 0;
+        for entry in self.objects.values() {
+            for tag in entry.obj.iter() {
+                let bytes = persistence::encoded_size(tag);
+                let count = counts.entry(format!("{tag}")).or_insert((0, 0));
+                count.0 += 1;
+                count.1 += bytes;
+                object_bytes += bytes;
+            }
+        }
+
+        report.push(format!(
+            "objects: {} (capacity {}, {} bytes)",
+            self.objects.len(),
+            self.objects.capacity(),
+            object_bytes
+        ));
+        report.push(format!(
+            "cells: {} (capacity {}, {} bytes)",
+            self.cells.len(),
+            self.cells.capacity(),
+            persistence::encoded_size(&self.cells)
+        ));
+        report.push(format!(
+            "npcs: {}, locations: {}, next oid: {}",
+            self.caches.npcs.lock().unwrap().len(),
+            self.caches.locations.lock().unwrap().len(),
+            self.next_id
+        ));
+
+        let mut counts: Vec<(String, (usize, usize))> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(&b.0)));
+        report.push("tags:".to_string());
+        for (name, (count, bytes)) in counts {
+            report.push(format!("   {name}: {count} ({bytes} bytes)"));
+        }
+    }
+
+    /// Releases excess memory. Note that objects are removed from the level as soon as
+    /// they are destroyed so there are no tombstones to clean up.
+    pub fn compact(&mut self) {
+        self.objects.shrink_to_fit();
+        for entry in self.objects.values_mut() {
+            entry.obj.shrink_to_fit();
+        }
+        self.cells.shrink_to_fit();
+        for oids in self.cells.values_mut() {
+            oids.shrink_to_fit();
+        }
+        self.caches.npcs.lock().unwrap().shrink_to_fit();
+        self.caches.locations.lock().unwrap().shrink_to_fit();
+    }
+
+    // Note that this must not change anything, including caches, because --invariants
+    // isn't supposed to affect the game.
     #[cfg(debug_assertions)]
     fn invariant(&self) {
         if self.constructing {
@@ -443,7 +660,10 @@ impl Level {
             self.player_loc
         );
 
-        assert!(self.locations.borrow().is_empty() || self.locations.borrow().len() == self.cells.len());
+        assert!(
+            self.caches.locations.lock().unwrap().is_empty()
+                || self.caches.locations.lock().unwrap().len() == self.cells.len()
+        );
 
         self.cheap_invariants(&self.changed);
         if self.invariants {
@@ -513,7 +733,7 @@ impl Level {
             }
         }
 
-        for oid in self.npcs.borrow().iter() {
+        for oid in self.caches.npcs.lock().unwrap().iter() {
             assert!(all_oids.contains(&oid), "{oid} NPC isn't on the map");
         }
 
@@ -628,7 +848,7 @@ impl<'a> Iterator for NpcsIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.index += 1;
-        let npcs = self.level.npcs.borrow();
+        let npcs = self.level.caches.npcs.lock().unwrap();
         let index = self.index as usize;
         if index < npcs.len() {
             Some(npcs[index])
@@ -637,3 +857,50 @@ impl<'a> Iterator for NpcsIterator<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_extent() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        // Bounded levels don't grow.
+        let mut game = Game::new_game(&path, 1);
+        let (top_left, bottom_right) = game.level_bounds();
+        assert!(game.level_size().width > 0 && game.level_size().height > 0);
+        let outside = Point::new(bottom_right.x + 5, top_left.y);
+        assert!(!game.level.in_bounds(&outside));
+        assert!(game.level.get_mut(&outside, TERRAIN_ID).is_none());
+        assert_eq!(game.level_bounds(), (top_left, bottom_right));
+
+        // Unbounded levels grow a chunk at a time.
+        game.set_level_extent(Extent::Unbounded);
+        assert!(game.level.in_bounds(&outside));
+        assert!(game.level.get_mut(&outside, TERRAIN_ID).is_some());
+        let (_, new_bottom_right) = game.level_bounds();
+        assert!(new_bottom_right.x >= outside.x);
+        assert_eq!((new_bottom_right.x + 1).rem_euclid(CHUNK_SIZE), 0);
+        let chunk = Point::new(outside.x.div_euclid(CHUNK_SIZE) * CHUNK_SIZE, outside.y);
+        assert!(game.level.cells.contains_key(&chunk));
+    }
+
+    #[test]
+    fn test_audit() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        // Sizes are what the sections would take up when serialized.
+        let game = Game::new_game(&path, 1);
+        let mut report = Vec::new();
+        game.level.audit(&mut report);
+        let cells = report.iter().find(|line| line.starts_with("cells:")).unwrap();
+        let bytes = persistence::encoded_size(&game.level.cells);
+        assert!(bytes > 0);
+        assert!(cells.ends_with(&format!("{bytes} bytes)")), "{cells}");
+        assert!(report.iter().any(|line| line.starts_with("   Terrain: ")));
+    }
+}
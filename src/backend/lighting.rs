@@ -0,0 +1,201 @@
+//! Parts of a level can be dark, e.g. `region = 1,32 60,38` in the darkness section of a
+//! level file. The player can only see into dark cells that are lit by a torch or lantern
+//! wielded by the player or by an NPC (so NPCs carrying lights can be spotted from afar).
+//! Lights burn a turn of fuel each time the player acts and can be topped up with oil.
+//! Note that darkness only limits what the player sees: NPCs still use line of sight.
+use super::catalog;
+use super::sound::{self, Sound};
+use super::tag::*;
+use super::time;
+use super::{Game, Message, Oid, Point, Time, Topic};
+
+/// Fuel levels at which the player is warned that their light is running low.
+const FLICKER: i32 = 20;
+const SPUTTER: i32 = 5;
+
+pub struct Lighting {
+    dark: Vec<(Point, Point)>, // inclusive top left and bottom right corners
+}
+
+impl Lighting {
+    pub fn new() -> Lighting {
+        Lighting { dark: Vec::new() }
+    }
+
+    fn in_dark(&self, loc: &Point) -> bool {
+        self.dark
+            .iter()
+            .any(|(tl, br)| tl.x <= loc.x && loc.x <= br.x && tl.y <= loc.y && loc.y <= br.y)
+    }
+}
+
+impl Game {
+    /// Used by make::level with the darkness section of the map.
+    pub(super) fn add_dark_region(&mut self, top_left: Point, bottom_right: Point) {
+        self.lighting.dark.push((top_left, bottom_right));
+        self.pov.dirty();
+    }
+
+    /// Returns the locations and radii of the lights carried by the player and NPCs.
+    pub(super) fn lights(&self) -> Vec<(Point, i32)> {
+        if self.lighting.dark.is_empty() {
+            return Vec::new();
+        }
+        std::iter::once(Oid(0))
+            .chain(self.level.npcs())
+            .filter_map(|oid| {
+                let light = self.wielded_light(oid, true)?;
+                let loc = self.level.try_loc(oid)?;
+                Some((loc, self.level.obj(light).0.light_value().unwrap()))
+            })
+            .collect()
+    }
+
+    /// Returns true if loc is in a dark region and isn't lit by any of lights.
+    pub(super) fn is_dark(&self, loc: &Point, lights: &[(Point, i32)]) -> bool {
+        self.lighting.in_dark(loc)
+            && !lights
+                .iter()
+                .any(|(center, radius)| loc.distance2(center) <= radius * radius)
+    }
+
+    /// Called each time the player does something that takes time.
+    pub(super) fn burn_fuel(&mut self) {
+        let burning: Vec<(Oid, Oid)> = std::iter::once(Oid(0))
+            .chain(self.level.npcs())
+            .filter_map(|oid| self.wielded_light(oid, true).map(|light| (oid, light)))
+            .collect();
+        for (oid, light) in burning {
+            let obj = self.level.obj_mut(light);
+            let fuel = match obj.fuel_value() {
+                Some(fuel) => fuel,
+                None => continue,
+            };
+            let current = fuel.current - 1;
+            obj.replace(Tag::Fuel(Durability { current, ..fuel }));
+
+            let key = match current {
+                0 => "light.out",
+                SPUTTER => "light.sputters",
+                FLICKER => "light.flickers",
+                _ => continue,
+            };
+            debug!("{oid}'s {light} has {current} fuel left");
+            if oid.0 == 0 {
+                let text = catalog::text(key, &[("item", &self.item_name(light))]);
+                let topic = if current == 0 { Topic::Important } else { Topic::Warning };
+                self.messages.push(Message::new(topic, &text));
+            }
+        }
+
+        // Lights move around with the characters carrying them.
+        if !self.lighting.dark.is_empty() {
+            self.pov.dirty();
+        }
+    }
+
+    // If burning is set then only lights with fuel left are returned.
+    fn wielded_light(&self, oid: Oid, burning: bool) -> Option<Oid> {
+        let equipped = self.level.obj(oid).0.equipped_value()?;
+        [Slot::MainHand, Slot::OffHand]
+            .iter()
+            .filter_map(|slot| equipped[*slot])
+            .find(|item| {
+                let light = self.level.obj(*item).0;
+                light.light_value().is_some() && (!burning || light.fuel_value().is_none_or(|fuel| fuel.current > 0))
+            })
+    }
+}
+
+/// Use handler for oil flasks and the like: refuels the light the player is wielding.
+pub(super) fn refuel_vs_player(game: &mut Game, item: Oid, _loc: &Point) -> Option<(Time, Sound)> {
+    let light = game.wielded_light(Oid(0), false)?;
+    let fuel = game.level.obj(light).0.fuel_value()?;
+    let amount = game.level.obj(item).0.refuel_value().unwrap();
+    let current = (fuel.current + amount).min(fuel.max);
+    game.level
+        .obj_mut(light)
+        .replace(Tag::Fuel(Durability { current, ..fuel }));
+
+    let text = catalog::text("light.refueled", &[("item", &game.item_name(light))]);
+    game.messages.push(Message::new(Topic::Normal, &text));
+    let player = game.level.obj_mut(Oid(0));
+    player.inventory_value_mut().unwrap().retain(|&oid| oid != item);
+    game.level.remove(item);
+    game.pov.dirty();
+    Some((time::DIAGNOL_MOVE, sound::QUIET))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pov::PoV;
+    use super::super::{new_obj, Action, ObjectName};
+    use super::*;
+    use crate::backend::test_game;
+
+    #[test]
+    fn test_lighting() {
+        let mut game = test_game();
+
+        // Cells in the dark can't be seen without a light (though the player can always
+        // see their own cell).
+        let player_loc = game.player_loc();
+        let near = Point::new(player_loc.x + 2, player_loc.y);
+        let far = Point::new(player_loc.x + 7, player_loc.y);
+        game.add_dark_region(
+            Point::new(player_loc.x - 10, player_loc.y - 10),
+            Point::new(player_loc.x + 10, player_loc.y + 10),
+        );
+        PoV::refresh(&mut game);
+        assert!(game.pov.visible(&game, &player_loc));
+        assert!(!game.pov.visible(&game, &near));
+
+        // A torch lights up the cells around the player.
+        let torch = game.level.add(new_obj(ObjectName::Torch), None);
+        let player = game.level.obj_mut(Oid(0));
+        player.inventory_value_mut().unwrap().push(torch);
+        game.player_acted(Action::WieldOffHand(torch));
+        assert!(game.pov.visible(&game, &near));
+        assert!(!game.pov.visible(&game, &far));
+
+        // NPCs carrying lights can be seen from further away.
+        let lantern = game.level.add(new_obj(ObjectName::Lantern), None);
+        let guard = game.add_object(&far, new_obj(ObjectName::Guard));
+        let obj = game.level.obj_mut(guard);
+        obj.set(Tag::Inventory(Vec::new()));
+        obj.set(Tag::Equipped(Default::default()));
+        obj.equipped_value_mut().unwrap()[Slot::OffHand] = Some(lantern);
+        game.player_acted(Action::Rest);
+        assert!(game.pov.visible(&game, &far));
+
+        // The player is warned as fuel runs low and the light eventually goes out.
+        game.level
+            .obj_mut(lantern)
+            .replace(Tag::Fuel(Durability { current: 0, max: 600 }));
+        game.level.obj_mut(torch).replace(Tag::Fuel(Durability {
+            current: FLICKER + 1,
+            max: 300,
+        }));
+        game.player_acted(Action::Rest);
+        let messages = game.recent_messages(1).map(|m| m.text.clone()).collect::<Vec<_>>();
+        assert_eq!(messages, vec!["Your torch flickers.".to_string()]);
+        game.level
+            .obj_mut(torch)
+            .replace(Tag::Fuel(Durability { current: 1, max: 300 }));
+        game.player_acted(Action::Rest);
+        assert!(!game.pov.visible(&game, &near));
+
+        // Oil refuels the light.
+        let flask = game.level.add(new_obj(ObjectName::OilFlask), None);
+        let player = game.level.obj_mut(Oid(0));
+        player.inventory_value_mut().unwrap().push(flask);
+        game.player_acted(Action::UseOn {
+            oid: flask,
+            target: game.player_loc(),
+        });
+        let fuel = game.level.obj(torch).0.fuel_value().unwrap();
+        assert_eq!(fuel.current, 299); // refilling takes a turn
+        assert!(game.level.try_obj(flask).is_none());
+        assert!(game.pov.visible(&game, &near));
+    }
+}
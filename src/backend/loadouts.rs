@@ -0,0 +1,208 @@
+//! Equipment sets that the player can quickly swap between, e.g. a pick-axe for digging
+//! and a sword and armor for fighting. Sets are saved using actions so they are rebuilt
+//! when a saved game is replayed. Swapping is done with an EquipSet intent which expands
+//! into Remove, Wear, and Wield actions (old saved games use SwapLoadout).
+use super::tag::*;
+use super::time::{self, Time};
+use super::{catalog, Action, Conduct, Game, Message, Oid, Topic};
+use enum_map::EnumMap;
+use std::fmt::Display;
+
+pub const MAX_LOADOUTS: usize = 3;
+
+struct Loadout {
+    name: &'static str, // name of the main hand item
+    items: EnumMap<Slot, Option<Oid>>,
+}
+
+pub struct Loadouts {
+    sets: [Option<Loadout>; MAX_LOADOUTS],
+    current: Option<usize>, // set that was last saved or swapped to
+}
+
+impl Loadouts {
+    pub fn new() -> Loadouts {
+        Loadouts {
+            sets: [None, None, None],
+            current: None,
+        }
+    }
+}
+
+impl Game {
+    /// Returns the names of each equipment set (None if the set hasn't been saved).
+    pub fn loadouts(&self) -> Vec<Option<&'static str>> {
+        self.loadouts.sets.iter().map(|s| s.as_ref().map(|s| s.name)).collect()
+    }
+
+    /// Returns the set after the one last used (if there is one).
+    pub fn next_loadout(&self) -> Option<u8> {
+        let current = self.equipped_loadout().or(self.loadouts.current);
+        let start = current.map_or(0, |i| i + 1);
+        (0..MAX_LOADOUTS)
+            .map(|i| (start + i) % MAX_LOADOUTS)
+            .find(|&i| self.loadouts.sets[i].is_some())
+            .map(|i| i as u8)
+    }
+
+    fn equipped_loadout(&self) -> Option<usize> {
+        let player = self.level.get(&self.player_loc(), CHARACTER_ID).unwrap().1;
+        let current = player.equipped_value().unwrap();
+        self.loadouts
+            .sets
+            .iter()
+            .position(|s| s.as_ref().is_some_and(|s| s.items == *current))
+    }
+
+    /// Returns the next action needed to equip a set (or None if there is nothing more
+    /// to do). Like swap_loadout everything is removed before anything is equipped so
+    /// that items can move between slots.
+    pub(super) fn loadout_step(&self, index: u8) -> Option<Action> {
+        let target = self.loadouts.sets.get(index as usize)?.as_ref()?.items;
+        let player = self.level.get(&self.player_loc(), CHARACTER_ID).unwrap().1;
+        let current = *player.equipped_value().unwrap();
+        for (slot, oid) in current.iter() {
+            if let Some(oid) = oid {
+                if target[slot] != Some(*oid) {
+                    return Some(Action::Remove(*oid));
+                }
+            }
+        }
+
+        // Items may have been dropped since the set was saved.
+        let inv = player.inventory_value().unwrap();
+        for (slot, oid) in target.iter() {
+            if let Some(oid) = oid {
+                if current[slot] != Some(*oid) && inv.contains(oid) {
+                    return Some(match slot {
+                        Slot::MainHand => Action::WieldMainHand(*oid),
+                        Slot::OffHand => Action::WieldOffHand(*oid),
+                        _ => Action::Wear(*oid),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    pub(super) fn save_loadout(&mut self, index: u8) {
+        let index = index as usize;
+        if index < MAX_LOADOUTS {
+            let player = self.level.get(&self.player_loc(), CHARACTER_ID).unwrap().1;
+            let items = *player.equipped_value().unwrap();
+            let name = match items[Slot::MainHand] {
+                Some(oid) => self.level.obj(oid).0.name_value().unwrap(),
+                None => "unarmed",
+            };
+            self.loadouts.sets[index] = Some(Loadout { name, items });
+            self.loadouts.current = Some(index);
+
+            let args: [(&str, &dyn Display); 2] = [("index", &(index + 1)), ("name", &name)];
+            let text = catalog::text("loadout.saved", &args);
+            let mesg = Message::new(Topic::Normal, &text);
+            self.messages.push(mesg);
+        }
+    }
+
+    /// Only used when replaying old saved games. Returns the time taken which depends on
+    /// how many items had to be changed.
+    pub(super) fn swap_loadout(&mut self, index: u8) -> Time {
+        let index = index as usize;
+        let target = match self.loadouts.sets.get(index) {
+            Some(Some(loadout)) => loadout.items,
+            _ => return Time::zero(),
+        };
+        self.loadouts.current = Some(index);
+
+        let player = self.level.get(&self.player_loc(), CHARACTER_ID).unwrap().1;
+        let current = *player.equipped_value().unwrap();
+        let changed: Vec<Slot> = target
+            .iter()
+            .filter_map(|(slot, oid)| if current[slot] != *oid { Some(slot) } else { None })
+            .collect();
+        if changed.is_empty() {
+            let mesg = Message::new(Topic::Normal, &catalog::text("loadout.already_using", &[]));
+            self.messages.push(mesg);
+            return Time::zero();
+        }
+
+        // Remove everything first so that items can move between slots.
+        let mut delay = Time::zero();
+        for &slot in changed.iter() {
+            if let Some(oid) = current[slot] {
+                self.remove(oid);
+            }
+            delay += if matches!(slot, Slot::MainHand | Slot::OffHand) {
+                time::DIAGNOL_MOVE / 2
+            } else {
+                time::DIAGNOL_MOVE
+            };
+        }
+
+        // Items may have been dropped since the set was saved.
+        for &slot in changed.iter() {
+            if let Some(oid) = target[slot] {
+                let player = self.level.get(&self.player_loc(), CHARACTER_ID).unwrap().1;
+                if player.inventory_value().unwrap().contains(&oid) {
+                    match slot {
+                        Slot::MainHand | Slot::OffHand => self.wield(oid, slot),
+                        _ => {
+                            self.wear(oid);
+                            self.break_conduct(Conduct::NoArmor);
+                        }
+                    }
+                }
+            }
+        }
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::make::new_obj;
+    use super::super::{Action, Intent, ObjectName};
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_sets() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        assert_eq!(game.next_loadout(), None);
+
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+        game.player_acted(Action::SaveLoadout(1));
+        assert_eq!(game.loadouts(), vec![None, Some("unarmed"), None]);
+        assert_eq!(game.next_loadout(), Some(1));
+
+        // Nothing has changed so swapping should be free.
+        assert_eq!(game.swap_loadout(1), Time::zero());
+        assert_eq!(game.swap_loadout(0), Time::zero());
+
+        // Swapping to a set expands into primitive actions.
+        let spear = game.level.add(new_obj(ObjectName::Spear), None);
+        let loc = game.player_loc();
+        let player = game.level.get_mut(&loc, CHARACTER_ID).unwrap().1;
+        player.inventory_value_mut().unwrap().push(spear);
+        game.player_acted(Action::WieldMainHand(spear));
+        game.player_acted(Action::SaveLoadout(0));
+        assert_eq!(game.next_loadout(), Some(1));
+
+        let mut plan = game.start_intent(Intent::EquipSet(1));
+        let mut actions = Vec::new();
+        while let Some(action) = game.intent_step(&mut plan) {
+            actions.push(action.clone());
+            game.player_acted(action);
+        }
+        assert_eq!(actions, vec![Action::Remove(spear)]);
+        assert_eq!(game.next_loadout(), Some(0));
+
+        let mut plan = game.start_intent(Intent::EquipSet(0));
+        assert_eq!(game.intent_step(&mut plan), Some(Action::WieldMainHand(spear)));
+    }
+}
@@ -0,0 +1,128 @@
+//! Lore entries are longer descriptions of uniques, signs, special terrain, etc that can
+//! be viewed when examining a cell. The built in entries are in lore.txt and a lore file
+//! can add to or replace them. Lore files look like:
+//! ```text
+//! # Comments start with #.
+//! [Rhulad]
+//! Lines are shown as is, including blank lines.
+//! ```
+//! where the name in brackets is an ObjectName.
+use super::{Game, ObjectName, Point};
+use fnv::FnvHashMap;
+use std::cell::RefCell;
+use std::fs;
+
+thread_local!(static LORE: RefCell<FnvHashMap<ObjectName, Vec<String>>> = RefCell::new(parse_lore(include_str!("lore.txt")).0));
+
+/// Adds entries from a lore file (replacing built in entries with the same name). Returns
+/// warnings for problems that didn't prevent the file from being used (e.g. unknown names).
+pub fn load_lore(path: &str) -> Result<Vec<String>, String> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("Couldn't read {path}: {err}"))?;
+    let (entries, warnings) = parse_lore(&contents);
+    if entries.is_empty() {
+        return Err(format!("{path} has no lore entries"));
+    }
+    LORE.with(|lore| lore.borrow_mut().extend(entries));
+    Ok(warnings)
+}
+
+pub fn has_lore(name: ObjectName) -> bool {
+    LORE.with(|lore| lore.borrow().contains_key(&name))
+}
+
+pub fn lore(name: ObjectName) -> Option<Vec<String>> {
+    LORE.with(|lore| lore.borrow().get(&name).cloned())
+}
+
+impl Game {
+    /// Returns the lore for the topmost object at loc that has lore (if the player can
+    /// see loc).
+    pub fn lore_at(&self, loc: &Point) -> Option<Vec<String>> {
+        if !self.pov.visible(self, loc) {
+            return None;
+        }
+        self.level
+            .cell_iter(loc)
+            .map(|(_, obj)| obj.object_name())
+            .find(|name| has_lore(*name))
+            .and_then(lore)
+    }
+}
+
+fn parse_lore(contents: &str) -> (FnvHashMap<ObjectName, Vec<String>>, Vec<String>) {
+    let mut entries = FnvHashMap::default();
+    let mut warnings = Vec::new();
+    let mut current: Option<(ObjectName, Vec<String>)> = None;
+    let mut skipping = false; // true if the current entry had a bad name
+    for (i, line) in contents.lines().enumerate() {
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.trim().strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            if let Some((name, lines)) = current.take() {
+                entries.insert(name, trim_blank_lines(lines));
+            }
+            match serde_json::from_str::<ObjectName>(&format!("\"{name}\"")) {
+                Ok(name) => {
+                    current = Some((name, Vec::new()));
+                    skipping = false;
+                }
+                Err(_) => {
+                    warnings.push(format!("line {}: unknown object name '{name}'", i + 1));
+                    skipping = true;
+                }
+            }
+        } else if let Some((_, lines)) = current.as_mut() {
+            lines.push(line.trim_end().to_string());
+        } else if !skipping && !line.trim().is_empty() {
+            warnings.push(format!("line {}: text before the first entry", i + 1));
+        }
+    }
+    if let Some((name, lines)) = current.take() {
+        entries.insert(name, trim_blank_lines(lines));
+    }
+    (entries, warnings)
+}
+
+fn trim_blank_lines(mut lines: Vec<String>) -> Vec<String> {
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    lines.split_off(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in() {
+        let (entries, warnings) = parse_lore(include_str!("lore.txt"));
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert!(entries.len() >= 5);
+        for (name, lines) in entries.iter() {
+            assert!(!lines.is_empty(), "{name:?} has no text");
+            assert!(!lines[0].is_empty() && !lines.last().unwrap().is_empty());
+            for line in lines {
+                assert!(line.chars().count() <= 80, "{name:?} has a long line: {line}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let path = format!("/tmp/lore-{}.txt", line!());
+        fs::write(
+            &path,
+            "stray\n[Rhulad]\n\nBetter text.\n\n[Rhulad the Great]\nignored\n[Bell]\nIt rings.\n",
+        )
+        .unwrap();
+        let warnings = load_lore(&path).unwrap();
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert_eq!(lore(ObjectName::Rhulad), Some(vec!["Better text.".to_string()]));
+        assert_eq!(lore(ObjectName::Bell), Some(vec!["It rings.".to_string()]));
+        assert!(lore(ObjectName::Vitr).is_some()); // built in entries are kept
+        assert!(lore(ObjectName::Dirt).is_none());
+    }
+}
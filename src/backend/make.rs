@@ -2,7 +2,42 @@ use super::*;
 use enum_map::EnumMap;
 use rand::prelude::*;
 
+/// Circuits follow the map, see circuits.rs.
+const CIRCUITS_HEADER: &str = "[circuits]\n";
+
+/// Graffiti goes after circuits (if present).
+const GRAFFITI_HEADER: &str = "[graffiti]\n";
+
+/// Crowd regions go after graffiti (if present), see crowd.rs.
+const CROWD_HEADER: &str = "[crowd]\n";
+
+/// Dark regions go after crowd regions (if present), see lighting.rs.
+const DARKNESS_HEADER: &str = "[darkness]\n";
+
+/// Ambient events go at the end, see ambience.rs.
+const AMBIENCE_HEADER: &str = "[ambience]\n";
+
 pub fn level(game: &mut Game, map: &str) {
+    let (map, ambience) = match map.split_once(AMBIENCE_HEADER) {
+        Some((map, ambience)) => (map, Some(ambience)),
+        None => (map, None),
+    };
+    let (map, darkness) = match map.split_once(DARKNESS_HEADER) {
+        Some((map, darkness)) => (map, Some(darkness)),
+        None => (map, None),
+    };
+    let (map, crowd) = match map.split_once(CROWD_HEADER) {
+        Some((map, crowd)) => (map, Some(crowd)),
+        None => (map, None),
+    };
+    let (map, graffiti) = match map.split_once(GRAFFITI_HEADER) {
+        Some((map, graffiti)) => (map, Some(graffiti)),
+        None => (map, None),
+    };
+    let (map, circuits) = match map.split_once(CIRCUITS_HEADER) {
+        Some((map, circuits)) => (map, Some(circuits)),
+        None => (map, None),
+    };
     let mut loc = Point::origin();
     for ch in map.chars() {
         // TODO: If we keep these level files we may want to add a symbol
@@ -61,6 +96,31 @@ pub fn level(game: &mut Game, map: &str) {
                 game.add_object(&loc, new_obj(ObjectName::Dirt));
                 game.add_object(&loc, new_obj(ObjectName::GreaterArmorySign))
             }
+            '=' => game.add_object(&loc, new_obj(ObjectName::LockedDoor)),
+            'k' => {
+                game.add_object(&loc, new_obj(ObjectName::Dirt));
+                game.add_object(&loc, new_obj(ObjectName::IronKey))
+            }
+            'L' => {
+                game.add_object(&loc, new_obj(ObjectName::Dirt));
+                game.add_object(&loc, new_obj(ObjectName::Lever))
+            }
+            '_' => {
+                game.add_object(&loc, new_obj(ObjectName::Dirt));
+                game.add_object(&loc, new_obj(ObjectName::PressurePlate))
+            }
+            't' => {
+                game.add_object(&loc, new_obj(ObjectName::Dirt));
+                game.add_object(&loc, new_obj(ObjectName::Torch))
+            }
+            'f' => {
+                game.add_object(&loc, new_obj(ObjectName::Dirt));
+                game.add_object(&loc, new_obj(ObjectName::OilFlask))
+            }
+            'l' => {
+                game.add_object(&loc, new_obj(ObjectName::Dirt));
+                lantern_guard(game, &loc)
+            }
             '\n' => Oid(0),
             _ => {
                 game.messages.push(Message {
@@ -76,15 +136,129 @@ pub fn level(game: &mut Game, map: &str) {
             loc = Point::new(loc.x + 1, loc.y);
         }
     }
+    if let Some(circuits) = circuits {
+        add_circuits(game, circuits);
+    }
+    if let Some(graffiti) = graffiti {
+        add_graffiti(game, graffiti);
+    }
+    if let Some(crowd) = crowd {
+        add_crowd(game, crowd);
+    }
+    if let Some(darkness) = darkness {
+        add_darkness(game, darkness);
+    }
+    if let Some(ambience) = ambience {
+        add_ambience(game, ambience);
+    }
     add_extras(game);
 }
 
+// Lines look like "gate = 10,5 12,5".
+fn add_circuits(game: &mut Game, circuits: &str) {
+    for line in circuits.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        let locs = line.split_once('=').and_then(|(name, locs)| {
+            let locs: Option<Vec<Point>> = locs
+                .split_whitespace()
+                .map(|loc| {
+                    let (x, y) = loc.split_once(',')?;
+                    Some(Point::new(x.parse().ok()?, y.parse().ok()?))
+                })
+                .collect();
+            Some((name.trim(), locs?))
+        });
+        match locs {
+            Some((name, locs)) => {
+                for loc in locs {
+                    game.add_to_circuit(name, &loc);
+                }
+            }
+            None => game.messages.push(Message {
+                topic: Topic::Error,
+                text: format!("Ignoring circuit '{line}'"),
+            }),
+        }
+    }
+}
+
+// Lines look like "10,5 = Kilroy was here".
+fn add_graffiti(game: &mut Game, graffiti: &str) {
+    for line in graffiti.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        let entry = line.split_once('=').and_then(|(loc, text)| {
+            let (x, y) = loc.trim().split_once(',')?;
+            Some((Point::new(x.parse().ok()?, y.parse().ok()?), text.trim()))
+        });
+        match entry {
+            Some((loc, text)) => game.decorate(&loc, Decoration::Graffiti(text.to_string())),
+            None => game.messages.push(Message {
+                topic: Topic::Error,
+                text: format!("Ignoring graffiti '{line}'"),
+            }),
+        }
+    }
+}
+
+// Lines look like "region = 72,21 86,22" or "exit = 71,25".
+fn add_crowd(game: &mut Game, crowd: &str) {
+    let parse_loc = |loc: &str| {
+        let (x, y) = loc.split_once(',')?;
+        Some(Point::new(x.parse().ok()?, y.parse().ok()?))
+    };
+    for line in crowd.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        let locs: Option<Vec<Point>> = line
+            .split_once('=')
+            .and_then(|(_, locs)| locs.split_whitespace().map(parse_loc).collect());
+        match (line.split_once('=').map(|(key, _)| key.trim()), locs.as_deref()) {
+            (Some("region"), Some([top_left, bottom_right])) => game.add_crowd_region(*top_left, *bottom_right),
+            (Some("exit"), Some([loc])) => game.set_crowd_exit(*loc),
+            _ => game.messages.push(Message {
+                topic: Topic::Error,
+                text: format!("Ignoring crowd '{line}'"),
+            }),
+        }
+    }
+}
+
+// Lines look like "region = 1,32 60,38".
+fn add_darkness(game: &mut Game, darkness: &str) {
+    let parse_loc = |loc: &str| {
+        let (x, y) = loc.split_once(',')?;
+        Some(Point::new(x.parse().ok()?, y.parse().ok()?))
+    };
+    for line in darkness.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        let locs: Option<Vec<Point>> = line
+            .split_once('=')
+            .and_then(|(_, locs)| locs.split_whitespace().map(parse_loc).collect());
+        match (line.split_once('=').map(|(key, _)| key.trim()), locs.as_deref()) {
+            (Some("region"), Some([top_left, bottom_right])) => game.add_dark_region(*top_left, *bottom_right),
+            _ => game.messages.push(Message {
+                topic: Topic::Error,
+                text: format!("Ignoring darkness '{line}'"),
+            }),
+        }
+    }
+}
+
+// Each line is the text for an event.
+fn add_ambience(game: &mut Game, ambience: &str) {
+    let events = ambience
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect();
+    game.set_ambience(events);
+}
+
 fn add_extras(game: &mut Game) {
     add_extra(game, new_obj(ObjectName::LeatherHat));
     add_extra(game, new_obj(ObjectName::LeatherChest));
     add_extra(game, new_obj(ObjectName::LeatherGloves));
     add_extra(game, new_obj(ObjectName::LeatherLegs));
     add_extra(game, new_obj(ObjectName::LeatherSandals));
+    add_extra(game, new_obj(ObjectName::Bell));
+    add_extra(game, new_obj(ObjectName::Rock));
+    add_extra(game, new_obj(ObjectName::Rock));
 }
 
 fn add_extra(game: &mut Game, obj: Object) {
@@ -112,12 +286,24 @@ fn add_extra(game: &mut Game, obj: Object) {
     }
 }
 
+// Guards that patrol the dark carry a lantern so the player can see them coming.
+fn lantern_guard(game: &mut Game, loc: &Point) -> Oid {
+    let lantern = game.level.add(new_obj(ObjectName::Lantern), None);
+    let mut guard = new_obj(ObjectName::Guard);
+    let mut equipped: EnumMap<Slot, Option<Oid>> = EnumMap::default();
+    equipped[Slot::OffHand] = Some(lantern);
+    guard.set(Tag::Inventory(Vec::new()));
+    guard.set(Tag::Equipped(equipped));
+    game.add_object(loc, guard)
+}
+
 fn weak_sword(game: &Game) -> Object {
     let swords = vec![
         ObjectName::LongSword,
         ObjectName::Broadsword,
         ObjectName::LongKnife,
         ObjectName::Dagger,
+        ObjectName::Spear,
     ];
     let sword = swords.iter().choose(&mut *game.rng()).unwrap();
     new_obj(*sword)
@@ -145,7 +331,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         // TODO: plate armor should be 15, 12, and 9%
         LeatherChest => Object::new(
             name,
-            "a leather chest",
+            "object.leather_chest",
             Symbol::Armor,
             Color::SandyBrown,
             vec![
@@ -157,7 +343,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         LeatherGloves => Object::new(
             name,
-            "a leather gloves",
+            "object.leather_gloves",
             Symbol::Armor,
             Color::SandyBrown,
             vec![
@@ -169,7 +355,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         LeatherHat => Object::new(
             name,
-            "a leather hat",
+            "object.leather_hat",
             Symbol::Armor,
             Color::SandyBrown,
             vec![
@@ -181,7 +367,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         LeatherLegs => Object::new(
             name,
-            "leather shin guard",
+            "object.leather_legs",
             Symbol::Armor,
             Color::SandyBrown,
             vec![
@@ -193,7 +379,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         LeatherSandals => Object::new(
             name,
-            "a leather sandals",
+            "object.leather_sandals",
             Symbol::Armor,
             Color::SandyBrown,
             vec![
@@ -205,23 +391,58 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
 
         // Misc Items
+        Bell => Object::new(
+            name,
+            "object.bell",
+            Symbol::Bell,
+            Color::Gold,
+            vec![Tag::Name("bell"), Tag::Portable, Tag::Noise(sound::LOUD)],
+        ),
         GreaterArmorySign => Object::new(
             name,
-            "a sign that says 'the Greater Armory'",
+            "object.greater_armory_sign",
             Symbol::Sign,
             Color::Pink,
             vec![Tag::Sign],
         ),
         LesserArmorySign => Object::new(
             name,
-            "a sign that says 'the Lesser Armory'",
+            "object.lesser_armory_sign",
             Symbol::Sign,
             Color::Pink,
             vec![Tag::Sign],
         ),
+        IronKey => Object::new(
+            name,
+            "object.iron_key",
+            Symbol::Key,
+            Color::Gray,
+            vec![Tag::Name("iron key"), Tag::Portable, Tag::Key("iron")],
+        ),
+        Lever => Object::new(
+            name,
+            "object.lever",
+            Symbol::Lever,
+            Color::Silver,
+            vec![Tag::Name("lever"), Tag::Lever],
+        ),
+        PressurePlate => Object::new(
+            name,
+            "object.pressure_plate",
+            Symbol::PressurePlate,
+            Color::Gray,
+            vec![Tag::PressurePlate],
+        ),
+        Rock => Object::new(
+            name,
+            "object.rock",
+            Symbol::Rock,
+            Color::Gray,
+            vec![Tag::Name("rock"), Tag::Portable, Tag::Noise(sound::QUIET)],
+        ),
         PickAxe => Object::new(
             name,
-            "a pick-axe",
+            "object.pick_axe",
             Symbol::PickAxe,
             Color::Tan,
             vec![
@@ -232,12 +453,35 @@ pub fn new_obj(name: ObjectName) -> Object {
             ],
         ),
 
+        // Lights
+        Lantern => Object::new(
+            name,
+            "object.lantern",
+            Symbol::Light,
+            Color::Gold,
+            light_tags("lantern", 5, 600),
+        ),
+        OilFlask => Object::new(
+            name,
+            "object.oil_flask",
+            Symbol::Light,
+            Color::Tan,
+            vec![Tag::Name("oil flask"), Tag::Portable, Tag::Refuel(300)],
+        ),
+        Torch => Object::new(
+            name,
+            "object.torch",
+            Symbol::Light,
+            Color::Orange,
+            light_tags("torch", 3, 300),
+        ),
+
         // NPCs
         // https://malazan.fandom.com/wiki/The_Seven_Faces_in_the_Rock
         BerokeSoftVoice | HaladRackBearer | ImrothTheCruel | KahlbTheSilentHunter | SiballeTheUnfound
         | ThenikTheShattered | UrugalTheWoven => Object::new(
             name,
-            "One of seven broken Logros T'lan Imass worshipped as gods by the Teblor.",
+            "object.broken_god",
             Symbol::Npc('u'),
             Color::Red,
             vec![
@@ -249,13 +493,16 @@ pub fn new_obj(name: ObjectName) -> Object {
                 Tag::Delay(time::secs(5)),
                 Tag::Durability(Durability { current: 170, max: 170 }),
                 Tag::Name(broken_name(name)),
+                Tag::Size(BodySize::Large),
+                Tag::Abilities(vec![Ability::new(AbilityKind::FearAura, time::secs(90))]),
+                Tag::Charges,
                 Tag::Scheduled,
                 Tag::Character,
             ],
         ),
         Doorman => Object::new(
             name,
-            "a royal guard",
+            "object.doorman",
             Symbol::Npc('D'),
             Color::Green,
             vec![
@@ -267,7 +514,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         Guard => Object::new(
             name,
-            "a low level guard",
+            "object.guard",
             Symbol::Npc('g'),
             Color::Green,
             vec![
@@ -281,14 +528,18 @@ pub fn new_obj(name: ObjectName) -> Object {
                 Tag::Hearing(0),
                 Tag::Durability(Durability { current: 30, max: 30 }),
                 Tag::Name("a guard"),
+                Tag::Abilities(vec![Ability::new(AbilityKind::ShieldBash, time::secs(30))]),
+                Tag::Block(15),
+                Tag::CanOpenDoor,
                 Tag::Guard,
+                Tag::Reach,
                 Tag::Scheduled,
                 Tag::Character,
             ],
         ),
         Icarium => Object::new(
             name,
-            "Icarium Lifestealer, a mixed blood Jahgut. He looks extremely dangerous",
+            "object.icarium",
             Symbol::Npc('I'),
             Color::LightGrey,
             vec![
@@ -300,6 +551,7 @@ pub fn new_obj(name: ObjectName) -> Object {
                 Tag::Delay(time::secs(3)),
                 Tag::Durability(Durability { current: 500, max: 500 }),
                 Tag::Name("Icarium"),
+                Tag::Abilities(vec![Ability::new(AbilityKind::Rage, time::secs(60))]),
                 Tag::Icarium,
                 Tag::Scheduled,
                 Tag::Character,
@@ -307,7 +559,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         Player => Object::new(
             name,
-            "yourself",
+            "object.player",
             Symbol::Player,
             Color::Linen,
             vec![
@@ -316,6 +568,7 @@ pub fn new_obj(name: ObjectName) -> Object {
                 Tag::Durability(Durability { current: 100, max: 100 }),
                 Tag::Damage(6),
                 Tag::Delay(time::secs(2)),
+                Tag::Vision(pov::RADIUS),
                 Tag::Inventory(Vec::new()),
                 Tag::Equipped(EnumMap::default()),
                 Tag::Name("yourself"),
@@ -327,7 +580,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         Rhulad => Object::new(
             name,
-            "the Emperor of a Thousand Deaths",
+            "object.rhulad",
             Symbol::Npc('R'),
             Color::Red,
             vec![
@@ -346,17 +599,18 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         Spectator => Object::new(
             name,
-            "a spectator",
+            "object.spectator",
             Symbol::Npc('s'),
             Color::Plum,
             vec![
                 Tag::Strength(10),
                 Tag::Dexterity(10),
                 Tag::Disposition(Disposition::Neutral),
-                Tag::Behavior(Behavior::Sleeping),
+                Tag::Behavior(Behavior::Wandering(Time::max())), // see crowd.rs
                 Tag::Hearing(0),
                 Tag::Durability(Durability { current: 33, max: 33 }),
                 Tag::Name("Spectator"),
+                Tag::Size(BodySize::Small),
                 Tag::Spectator,
                 Tag::Scheduled,
                 Tag::Character,
@@ -366,14 +620,19 @@ pub fn new_obj(name: ObjectName) -> Object {
         // Terrain
         ClosedDoor => Object::new(
             name,
-            "a closed door",
+            "object.closed_door",
             Symbol::ClosedDoor,
             Color::Yellow,
             door_tags(Color::Black, Material::Stone, false),
         ),
+        LockedDoor => {
+            let mut tags = door_tags(Color::Black, Material::Stone, false);
+            tags.push(Tag::Lock("iron"));
+            Object::new(name, "object.locked_door", Symbol::ClosedDoor, Color::Yellow, tags)
+        }
         DeepWater => Object::new(
             name,
-            "deep water",
+            "object.deep_water",
             Symbol::DeepLiquid,
             Color::Blue,
             vec![
@@ -384,35 +643,35 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         Dirt => Object::new(
             name,
-            "a patch of dirt",
+            "object.dirt",
             Symbol::Dirt,
             Color::LightSlateGray,
             vec![Tag::Terrain(Terrain::Ground), Tag::Background(Color::Black)],
         ),
         MetalWall => Object::new(
             name,
-            "a metal wall",
+            "object.metal_wall",
             Symbol::Wall,
             Color::Silver,
             wall_tags(Color::Black, Material::Metal),
         ),
         OpenDoor => Object::new(
             name,
-            "an open door",
+            "object.open_door",
             Symbol::OpenDoor,
             Color::Yellow,
             door_tags(Color::Black, Material::Stone, true),
         ),
         Rubble => Object::new(
             name,
-            "a destroyed wall",
+            "object.rubble",
             Symbol::Rubble,
             Color::Chocolate,
             vec![Tag::Terrain(Terrain::Ground), Tag::Background(Color::Black)],
         ),
         ShallowWater => Object::new(
             name,
-            "shallow water",
+            "object.shallow_water",
             Symbol::ShallowLiquid,
             Color::Blue,
             vec![
@@ -423,21 +682,21 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         StoneWall => Object::new(
             name,
-            "a stone wall",
+            "object.stone_wall",
             Symbol::Wall,
             Color::Chocolate,
             wall_tags(Color::Black, Material::Stone),
         ),
         Tree => Object::new(
             name,
-            "a tree",
+            "object.tree",
             Symbol::Tree,
             Color::ForestGreen,
             vec![Tag::Terrain(Terrain::Tree), Tag::Background(Color::Black)],
         ),
         Vitr => Object::new(
             name,
-            "a pool of chaotic acid",
+            "object.vitr",
             Symbol::DeepLiquid,
             Color::Gold,
             vec![Tag::Terrain(Terrain::Vitr), Tag::Background(Color::Black)],
@@ -446,7 +705,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         // Weapons
         Broadsword => Object::new(
             name,
-            "a dull broadsword",
+            "object.broadsword",
             Symbol::WeakSword,
             Color::Silver,
             vec![
@@ -463,7 +722,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         Dagger => Object::new(
             // TODO: need to re-balance these (and differentiate the weak swords)
             name,
-            "a pointy dagger",
+            "object.dagger",
             Symbol::WeakSword,
             Color::Silver,
             vec![
@@ -479,7 +738,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         EmperorSword => Object::new(
             name,
-            "the Sword of the Crippled God",
+            "object.emperor_sword",
             Symbol::StrongSword,
             Color::Silver,
             vec![
@@ -496,7 +755,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         LongKnife => Object::new(
             name,
-            "a shiny long knife",
+            "object.long_knife",
             Symbol::WeakSword,
             Color::Silver,
             vec![
@@ -512,7 +771,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         LongSword => Object::new(
             name,
-            "a nicked long sword",
+            "object.long_sword",
             Symbol::WeakSword,
             Color::Silver,
             vec![
@@ -528,7 +787,7 @@ pub fn new_obj(name: ObjectName) -> Object {
         ),
         MightySword => Object::new(
             name,
-            "the Sword of Impending Doom",
+            "object.mighty_sword",
             Symbol::StrongSword,
             Color::Silver,
             vec![
@@ -542,9 +801,45 @@ pub fn new_obj(name: ObjectName) -> Object {
                 Tag::Crit(2),
             ],
         ),
+        Spear => Object::new(
+            name,
+            "object.spear",
+            Symbol::Spear,
+            Color::Silver,
+            vec![
+                Tag::Name("spear"),
+                Tag::Portable,
+                Tag::Weapon(Weapon::TwoHander),
+                Tag::Reach,
+                Tag::Damage(10),
+                Tag::Delay(time::secs(4)),
+                Tag::Strength(5),
+                Tag::Dexterity(8),
+                Tag::Crit(5),
+            ],
+        ),
     }
 }
 
+// Lights are wielded like (rather poor) weapons so that they can be carried in either hand.
+fn light_tags(name: &'static str, radius: i32, fuel: i32) -> Vec<Tag> {
+    vec![
+        Tag::Name(name),
+        Tag::Portable,
+        Tag::Weapon(Weapon::OneHand),
+        Tag::Light(radius),
+        Tag::Fuel(Durability {
+            current: fuel,
+            max: fuel,
+        }),
+        Tag::Damage(4),
+        Tag::Delay(time::secs(3)),
+        Tag::Strength(1),
+        Tag::Dexterity(1),
+        Tag::Crit(5),
+    ]
+}
+
 fn wall_tags(bg: Color, material: Material) -> Vec<Tag> {
     let durability = 5 * to_durability(material); // walls are quite a bit tougher than something like a door
     vec![
@@ -1,6 +1,51 @@
+use super::catalog;
 use super::*;
+use std::fmt::Display;
 
-const MAX_STAT: i32 = 30; // this is a soft limit: stats can go higher than this but with diminishing (or no) returns
+pub(super) const MAX_STAT: i32 = 30; // this is a soft limit: stats can go higher than this but with diminishing (or no) returns
+
+/// Armor mitigation that costs one point of evasion.
+const ENCUMBRANCE_PER_DEX: i32 = 5;
+
+/// Range of parry chances, the higher chances require a lot more dexterity than the weapon needs.
+const MIN_PARRY: f64 = 0.05;
+const MAX_PARRY: f64 = 0.25;
+
+/// Cap on block chances so that stacking shields doesn't make characters invulnerable.
+const MAX_BLOCK: i32 = 50;
+
+/// How an attack was resolved, see resolve_defense.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Defense {
+    Hit,
+    Evaded,
+    Parried,
+    Blocked,
+}
+
+/// Chances that an attack is stopped by each of the defender's active defenses. These are
+/// checked in order: evade, then parry, and then block.
+pub struct DefenseProbs {
+    pub hit: f64, // chance that the defender fails to evade
+    pub parry: f64,
+    pub block: f64,
+}
+
+impl DefenseProbs {
+    /// Chance that the attack gets through all the defenses.
+    pub fn land(&self) -> f64 {
+        self.hit * (1.0 - self.parry) * (1.0 - self.block)
+    }
+}
+
+/// The pieces that go into a damage roll, see base_damage.
+struct DamageRoll {
+    base: i32,            // weapon (or unarmed) damage
+    scaling: Option<f64>, // None if the weapon doesn't care about strength
+    crit_prob: f64,
+    crit: bool,
+    damage: i32, // after scaling, crits, and the random spread
+}
 
 impl Game {
     pub fn melee_delay(&self, attacker_loc: &Point) -> Time {
@@ -19,10 +64,38 @@ impl Game {
     }
 
     pub fn do_melee_attack(&mut self, attacker_loc: &Point, defender_loc: &Point) {
+        self.do_scaled_melee_attack(attacker_loc, defender_loc, 100);
+    }
+
+    /// Like do_melee_attack except that damage is scaled by percent.
+    pub(super) fn do_scaled_melee_attack(&mut self, attacker_loc: &Point, defender_loc: &Point, percent: i32) {
         let attacker_id = self.level.get(attacker_loc, CHARACTER_ID).unwrap().0;
         let defender_id = self.level.get_mut(defender_loc, CHARACTER_ID).unwrap().0;
+        let spectator = self.level.obj(defender_id).0.has(SPECTATOR_ID);
         debug!("{attacker_id} is meleeing {defender_id}");
+        if defender_id.0 == 0 {
+            self.add_hint(Hint::Attacked);
+        } else if attacker_id.0 == 0 {
+            let defender = self.level.obj(defender_id).0;
+            if let Some(Disposition::Neutral) = defender.disposition_value() {
+                self.player_attacked_neutral(defender_id);
+            }
+        }
+        if attacker_id.0 == 0 || defender_id.0 == 0 {
+            let other = if attacker_id.0 == 0 { defender_id } else { attacker_id };
+            let name = self.level.obj(other).0.object_name();
+            self.discoveries.identify(name);
+        } else if self.pov.visible(self, attacker_loc) || self.pov.visible(self, defender_loc) {
+            for oid in [attacker_id, defender_id] {
+                let name = self.level.obj(oid).0.object_name();
+                self.discoveries.saw_fighting(name);
+            }
+        }
         self.react_to_attack(attacker_loc, attacker_id, defender_loc);
+        self.player_hears(attacker_loc, sound::QUIET, "sound.fighting");
+        if self.fumbled(attacker_id, attacker_loc) {
+            return;
+        }
 
         let mut damage = 0;
         let mut text = String::new();
@@ -31,7 +104,7 @@ impl Game {
             let attacker = self.level.obj(attacker_id).0;
             attacker.equipped_value().map(|e| e[Slot::MainHand]).flatten()
         };
-        let (dam, msg) = self.do_attack(attacker_id, defender_id, defender_loc, weapon);
+        let (dam, msg) = self.do_attack(attacker_id, defender_id, defender_loc, weapon, percent);
         damage += dam;
         text += &msg;
 
@@ -49,35 +122,132 @@ impl Game {
             if off_hand {
                 // TODO: probability should depend on skill (very low at no skill)
                 // TODO: may want to allow an off hand unarmed attack for some races (or for a skill?)
-                let (dam, msg) = self.do_attack(attacker_id, defender_id, defender_loc, weapon);
+                let (dam, msg) = self.do_attack(attacker_id, defender_id, defender_loc, weapon, percent);
                 damage += dam;
                 text += &format!(" {msg}");
             }
         }
 
-        let topic = self.topic(attacker_id, defender_id, damage);
-        let mesg = Message::new(topic, &text);
-        self.messages.push(mesg);
+        if damage > 0 {
+            self.decorate(defender_loc, Decoration::Blood);
+            self.push_combat_event(CombatEvent::Damaged {
+                loc: *defender_loc,
+                amount: damage,
+            });
+        }
+        self.push_attack(attacker_id, defender_id, damage, &text);
+        self.crowd_reacts(attacker_id, defender_id, spectator, damage);
+        self.heavy_hit_knockback(attacker_loc, defender_loc, damage);
+    }
+
+    /// Returns the location of the character two cells away in the (dx, dy) direction if
+    /// the attacker can hit it with a reach weapon. The cell in between must not block
+    /// line of sight but it may have a character in it (so NPCs can attack from behind
+    /// their allies).
+    pub fn reach_target(&self, attacker_loc: &Point, dx: i32, dy: i32) -> Option<Point> {
+        let attacker = self.level.get(attacker_loc, CHARACTER_ID).unwrap().1;
+        let reach = match self.find_main_hand(attacker) {
+            Some(weapon) => weapon.has(REACH_ID),
+            None => attacker.has(REACH_ID),
+        };
+        if !reach {
+            return None;
+        }
+
+        let middle = Point::new(attacker_loc.x + dx, attacker_loc.y + dy);
+        if self.level.get_bottom(&middle).1.blocks_los() {
+            return None;
+        }
+
+        let target = Point::new(attacker_loc.x + 2 * dx, attacker_loc.y + 2 * dy);
+        self.level.get(&target, CHARACTER_ID).map(|_| target)
+    }
+
+    /// Moving towards an aggressive character two cells away while wielding a reach weapon
+    /// attacks it instead of moving. Characters next to the player are handled by the
+    /// usual bump interactions.
+    pub(super) fn player_reach_attack(&mut self, dx: i32, dy: i32) -> Option<Time> {
+        let player_loc = self.player_loc();
+        let middle = Point::new(player_loc.x + dx, player_loc.y + dy);
+        if self.level.get(&middle, CHARACTER_ID).is_some() {
+            return None;
+        }
+
+        let target = self.reach_target(&player_loc, dx, dy)?;
+        let defender = self.level.get(&target, CHARACTER_ID).unwrap().1;
+        if !matches!(defender.disposition_value(), Some(Disposition::Aggressive)) {
+            return None;
+        }
+
+        let delay = self.melee_delay(&player_loc);
+        self.do_melee_attack(&player_loc, &target);
+        Some(delay)
     }
 }
 
 impl Game {
+    /// Thrown items do their normal melee damage (or unarmed damage if they aren't a
+    /// weapon) but there is no chance of an off hand attack.
+    pub fn do_throw_attack(&mut self, attacker_loc: &Point, defender_loc: &Point, item: Oid) {
+        let attacker_id = self.level.get(attacker_loc, CHARACTER_ID).unwrap().0;
+        let defender_id = self.level.get(defender_loc, CHARACTER_ID).unwrap().0;
+        debug!("{attacker_id} threw {item} at {defender_id}");
+        if attacker_id.0 == 0 {
+            let defender = self.level.obj(defender_id).0;
+            if let Some(Disposition::Neutral) = defender.disposition_value() {
+                self.player_attacked_neutral(defender_id);
+            }
+        }
+        self.react_to_attack(attacker_loc, attacker_id, defender_loc);
+
+        let weapon = if self.level.obj(item).0.has(WEAPON_ID) {
+            Some(item)
+        } else {
+            None
+        };
+        let (damage, text) = self.do_attack(attacker_id, defender_id, defender_loc, weapon, 100);
+        if damage > 0 {
+            self.push_combat_event(CombatEvent::Damaged {
+                loc: *defender_loc,
+                amount: damage,
+            });
+        }
+        self.push_attack(attacker_id, defender_id, damage, &text);
+    }
+
+    fn push_attack(&mut self, attacker_id: Oid, defender_id: Oid, damage: i32, text: &str) {
+        let topic = self.topic(attacker_id, defender_id, damage);
+        self.messages.push(Message::new(topic, text));
+        if topic == Topic::Fatal {
+            let text = catalog::text("combat.killed_by", &[("attacker", &self.attacker_name(attacker_id))]);
+            self.messages.push(Message::new(Topic::Fatal, &text));
+        }
+    }
+
     fn do_attack(
         &mut self,
         attacker_id: Oid,
         defender_id: Oid,
         defender_loc: &Point,
         weapon: Option<Oid>,
+        percent: i32,
     ) -> (i32, String) {
         // It'd be more efficient to use Objects here but the borrow checker whines a lot.
         let attacker_name = self.attacker_name(attacker_id);
         let defender_name = self.defender_name(defender_id);
-        if let Some((damage, crit)) = self.do_strike(attacker_id, defender_id, weapon) {
+        let strike = self.do_strike(attacker_id, defender_id, weapon, percent);
+        if let Ok((damage, crit)) = strike {
             let (new_hps, max_hps) = self.hps(defender_id, damage);
-            let hit = if crit { "critcally hit" } else { "hit" };
+            let hit = if crit { "crit" } else { "hit" };
             debug!("   {hit} for {damage}, new HPs are {new_hps}");
+            let args: [(&str, &dyn Display); 4] = [
+                ("attacker", &attacker_name),
+                ("defender", &defender_name),
+                ("damage", &damage),
+                ("over", &-new_hps),
+            ];
             let msg = if damage == 0 {
-                format!("{attacker_name} {hit} {defender_name} for no damage.")
+                catalog::text(&format!("combat.{hit}_no_damage"), &args)
             } else {
                 let (oid, defender) = self.level.get_mut(defender_loc, CHARACTER_ID).unwrap();
                 let durability = Tag::Durability(Durability {
@@ -88,46 +258,130 @@ impl Game {
 
                 if new_hps <= 0 {
                     if oid.0 == 0 {
-                        let msg = "You've lost the game!";
-                        let mesg = Message::new(Topic::Important, msg);
+                        let mesg = Message::new(Topic::Important, &catalog::text("game.lost", &[]));
                         self.messages.push(mesg);
                         self.state = State::LostGame;
                     } else {
+                        if attacker_id.0 == 0 {
+                            self.player_killed(oid);
+                        }
                         self.npc_died(defender_loc, oid);
                     }
                     if new_hps < 0 {
-                        format!(
-                            "{attacker_name} {hit} {defender_name} for {damage} damage ({} over kill).",
-                            -new_hps
-                        )
+                        catalog::text(&format!("combat.{hit}_over_kill"), &args)
                     } else {
-                        format!("{attacker_name} {hit} {defender_name} for {damage} damage.",)
+                        catalog::text(&format!("combat.{hit}"), &args)
                     }
                 } else {
-                    format!("{attacker_name} {hit} {defender_name} for {damage} damage.")
+                    catalog::text(&format!("combat.{hit}"), &args)
                 }
             };
 
             (damage, msg)
         } else {
-            let msg = format!("{attacker_name} missed {defender_name}.");
+            // Parries and blocks are something the defender did so it's the subject.
+            let msg = match strike {
+                Err(Defense::Parried) | Err(Defense::Blocked) => {
+                    let key = if strike == Err(Defense::Parried) {
+                        "combat.parried"
+                    } else {
+                        "combat.blocked"
+                    };
+                    let attacker = self.defender_name(attacker_id);
+                    let defender = self.attacker_name(defender_id);
+                    catalog::text(key, &[("attacker", &attacker), ("defender", &defender)])
+                }
+                _ => catalog::text(
+                    "combat.missed",
+                    &[("attacker", &attacker_name), ("defender", &defender_name)],
+                ),
+            };
             (0, msg)
         }
     }
 
-    fn do_strike(&mut self, attacker_id: Oid, defender_id: Oid, weapon: Option<Oid>) -> Option<(i32, bool)> {
-        let (damage, crit) = self.base_damage(attacker_id, weapon);
-        if self.hit_defender(attacker_id, defender_id) {
-            let damage = self.mitigate_damage(attacker_id, defender_id, damage);
-            Some((damage, crit))
+    fn do_strike(
+        &mut self,
+        attacker_id: Oid,
+        defender_id: Oid,
+        weapon: Option<Oid>,
+        percent: i32,
+    ) -> Result<(i32, bool), Defense> {
+        let roll = self.damage_roll(attacker_id, weapon);
+        let damage = roll.damage * percent / 100;
+        let defense = self.resolve_defense(attacker_id, defender_id);
+        if defense == Defense::Parried {
+            self.scheduler.force_acted(defender_id, time::PARRY, &self.rng);
+        }
+        let mitigated = if defense == Defense::Hit {
+            Ok(self.mitigate_damage(attacker_id, defender_id, damage))
         } else {
-            None
+            Err(defense)
+        };
+        if self.combat_details {
+            self.push_strike_details(attacker_id, defender_id, weapon, &roll, percent, mitigated);
+        }
+
+        let mitigated = mitigated?;
+        self.note_strike(attacker_id, defender_id, damage, mitigated);
+        Ok((mitigated, roll.crit))
+    }
+
+    // Note that this has to be careful not to use the rng (combat details aren't saved
+    // so replays would diverge).
+    fn push_strike_details(
+        &mut self,
+        attacker_id: Oid,
+        defender_id: Oid,
+        weapon: Option<Oid>,
+        roll: &DamageRoll,
+        percent: i32,
+        mitigated: Result<i32, Defense>,
+    ) {
+        let mut parts = Vec::new();
+        let source = match weapon {
+            Some(weapon) => format!("{}", self.level.obj(weapon).0),
+            None => "unarmed".to_string(),
+        };
+        parts.push(format!("{} base damage from {source}", roll.base));
+        if let Some(scaling) = roll.scaling {
+            parts.push(format!("x{scaling:.2} for strength"));
         }
+        let crit = if roll.crit { "crit so x2" } else { "no crit" };
+        parts.push(format!("{:.0}% crit chance ({crit})", 100.0 * roll.crit_prob));
+        parts.push(format!("rolled {}", roll.damage));
+        if percent != 100 {
+            parts.push(format!("scaled to {percent}%"));
+        }
+        let probs = self.defense_probs(attacker_id, defender_id);
+        parts.push(format!(
+            "{:.0}% chance to hit ({:.0}% parry, {:.0}% block)",
+            100.0 * probs.hit,
+            100.0 * probs.parry,
+            100.0 * probs.block
+        ));
+        match mitigated {
+            Ok(mitigated) => {
+                for (item, mitigation) in self.armor_mitigation(defender_id) {
+                    parts.push(format!("{item} mitigates {mitigation}%"));
+                }
+                parts.push(format!("{mitigated} damage"));
+            }
+            Err(defense) => parts.push(format!("{defense:?}").to_lowercase()),
+        }
+
+        let text = format!(
+            "{} vs {}: {}.",
+            self.attacker_name(attacker_id),
+            self.defender_name(defender_id),
+            parts.join(", ")
+        );
+        self.messages.push(Message::new(Topic::Normal, &text));
     }
 
     fn attacker_name(&self, attacker_id: Oid) -> String {
         if attacker_id.0 == 0 {
-            "You".to_string()
+            catalog::text("combat.you", &[])
         } else {
             let attacker = self.level.obj(attacker_id).0;
             let name: &'static str = attacker.name_value().unwrap();
@@ -136,6 +390,11 @@ impl Game {
     }
 
     pub fn base_damage(&self, attacker_id: Oid, weapon: Option<Oid>) -> (i32, bool) {
+        let roll = self.damage_roll(attacker_id, weapon);
+        (roll.damage, roll.crit)
+    }
+
+    fn damage_roll(&self, attacker_id: Oid, weapon: Option<Oid>) -> DamageRoll {
         let attacker = self.level.obj(attacker_id).0;
         let (damage, min_str) = if let Some(weapon) = weapon.map(|w| self.level.obj(w).0) {
             (weapon.damage_value().unwrap(), weapon.strength_value())
@@ -153,9 +412,12 @@ impl Game {
         // help light weapons. Also there can be significant penalties for using weapons
         // that are too heavy for a character. TODO: need some sort of indication for these
         // penalties, maybe status effect warning.
-        let mut damage = if let Some(min_str) = min_str {
+        let base = damage;
+        let scaling = min_str.map(|min_str| {
             let cur_str = attacker.strength_value().unwrap();
-            let scaling = f64::max((cur_str as f64) / (min_str as f64), 2.0);
+            f64::max((cur_str as f64) / (min_str as f64), 2.0)
+        });
+        let mut damage = if let Some(scaling) = scaling {
             ((damage as f64) * scaling) as i32
         } else {
             damage
@@ -169,7 +431,13 @@ impl Game {
         if crit {
             damage *= 2;
         }
-        (super::rand_normal32(damage, 20, &self.rng), crit)
+        DamageRoll {
+            base,
+            scaling,
+            crit_prob: p,
+            crit,
+            damage: super::rand_normal32(damage, 20, &self.rng),
+        }
     }
 
     pub fn crit_prob(&self, attacker_id: Oid, weapon: Option<Oid>) -> f64 {
@@ -194,7 +462,7 @@ impl Game {
 
     fn defender_name(&self, defender_id: Oid) -> String {
         if defender_id.0 == 0 {
-            "you".to_string()
+            catalog::text("combat.you_object", &[])
         } else {
             let defender = self.level.obj(defender_id).0;
             format!("{defender}")
@@ -223,21 +491,76 @@ impl Game {
         (durability.current - damage, durability.max)
     }
 
-    fn hit_defender(&self, attacker_id: Oid, defender_id: Oid) -> bool {
-        let p = self.hit_prob(attacker_id, defender_id);
+    /// This is the single place where the defender's active defenses are rolled.
+    fn resolve_defense(&self, attacker_id: Oid, defender_id: Oid) -> Defense {
+        let probs = self.defense_probs(attacker_id, defender_id);
+
+        // Parry and block are only rolled if possible so that characters without them use
+        // the same rolls as before they were added.
         let rng = &mut *self.rng();
-        rng.gen_bool(p)
+        if !rng.gen_bool(probs.hit) {
+            Defense::Evaded
+        } else if probs.parry > 0.0 && rng.gen_bool(probs.parry) {
+            Defense::Parried
+        } else if probs.block > 0.0 && rng.gen_bool(probs.block) {
+            Defense::Blocked
+        } else {
+            Defense::Hit
+        }
+    }
+
+    pub fn defense_probs(&self, attacker_id: Oid, defender_id: Oid) -> DefenseProbs {
+        DefenseProbs {
+            hit: self.hit_prob(attacker_id, defender_id),
+            parry: self.parry_prob(defender_id),
+            block: self.block_prob(defender_id),
+        }
     }
 
-    // TODO: use dexterity/evasion
+    /// Chance that the attacker isn't evaded.
     pub fn hit_prob(&self, attacker_id: Oid, defender_id: Oid) -> f64 {
         let attacker = self.level.obj(attacker_id).0;
-        let defender = self.level.obj(defender_id).0;
 
         let adex = attacker.dexterity_value().unwrap(); // TODO: this should be adjusted by heavy gear
-        let ddex = defender.dexterity_value().unwrap();
         let max_delta = (2 * MAX_STAT) / 3;
-        linear_scale(adex - ddex, -max_delta, max_delta, 0.1, 1.0)
+        let p = linear_scale(adex - self.evasion(defender_id), -max_delta, max_delta, 0.1, 1.0);
+        if self.has_status(attacker_id, Status::Frightened) {
+            0.75 * p
+        } else {
+            p
+        }
+    }
+
+    /// Dexterity less a penalty for wearing heavy armor.
+    pub fn evasion(&self, oid: Oid) -> i32 {
+        let obj = self.level.obj(oid).0;
+        obj.dexterity_value().unwrap() - self.encumbrance(oid) / ENCUMBRANCE_PER_DEX
+    }
+
+    fn encumbrance(&self, oid: Oid) -> i32 {
+        self.armor_mitigation(oid).iter().map(|(_, m)| m).sum()
+    }
+
+    /// Characters can parry if they are wielding a weapon and have at least the dexterity
+    /// the weapon requires.
+    pub fn parry_prob(&self, defender_id: Oid) -> f64 {
+        let defender = self.level.obj(defender_id).0;
+        if let Some(weapon) = self.find_main_hand(defender) {
+            let dex = defender.dexterity_value().unwrap() - weapon.dexterity_value().unwrap_or(0);
+            if dex >= 0 {
+                return linear_scale(dex, 0, MAX_STAT, MIN_PARRY, MAX_PARRY);
+            }
+        }
+        0.0
+    }
+
+    pub fn block_prob(&self, defender_id: Oid) -> f64 {
+        let defender = self.level.obj(defender_id).0;
+        let mut percent = defender.block_value().unwrap_or(0);
+        if let Some(shield) = self.find_off_hand(defender) {
+            percent += shield.block_value().unwrap_or(0);
+        }
+        (percent.min(MAX_BLOCK) as f64) / 100.0
     }
 
     // TODO: use skill
@@ -265,24 +588,45 @@ impl Game {
         }
     }
 
-    fn npc_died(&mut self, defender_loc: &Point, defender_id: Oid) {
+    // Returns the name and mitigation percent of each piece of armor the defender has on.
+    fn armor_mitigation(&self, defender_id: Oid) -> Vec<(String, i32)> {
+        let defender = self.level.obj(defender_id).0;
+        let mut result = Vec::new();
+        if let Some(equipped) = defender.equipped_value() {
+            for oid in equipped.values().flatten() {
+                let obj = self.level.obj(*oid).0;
+                if let Some(m) = obj.mitigation_value() {
+                    result.push((format!("{obj}"), m));
+                }
+            }
+        }
+        result
+    }
+
+    pub(super) fn npc_died(&mut self, defender_loc: &Point, defender_id: Oid) {
         let defender = self.level.obj(defender_id).0;
         let is_rhulad = defender.has(RHULAD_ID);
 
         self.destroy_object(defender_loc, defender_id);
+        self.routes.remove(defender_id);
 
         if is_rhulad {
             self.add_object(defender_loc, new_obj(ObjectName::EmperorSword)); // TODO: should drop inv items
             self.state = State::KilledRhulad;
+            let text = catalog::text("quest.kill_rhulad.done", &[]);
+            self.journal.complete(QuestId::KillRhulad, &text);
+            let text = catalog::text("quest.destroy_sword.fallen", &[]);
+            self.journal.set_hint(QuestId::DestroySword, &text);
 
-            let msg = "The Crippled God whispers, 'You shall pay for this mortal'.";
-            let mesg = Message::new(Topic::Important, &msg);
+            let mesg = Message::new(Topic::Important, &catalog::text("rhulad.killed", &[]));
             self.messages.push(mesg);
+            self.pay_bets();
             self.spawn_the_broken();
+            self.crowd_flees();
         }
     }
 
-    fn react_to_attack(&mut self, attacker_loc: &Point, attacker_id: Oid, defender_loc: &Point) {
+    pub(super) fn react_to_attack(&mut self, attacker_loc: &Point, attacker_id: Oid, defender_loc: &Point) {
         let defender = self.level.get_mut(defender_loc, CHARACTER_ID).unwrap().1;
         let attack = match defender.behavior_value() {
             Some(Behavior::Sleeping) => true,
@@ -344,7 +688,9 @@ impl Game {
                 Topic::PlayerDidNoDamage
             }
         } else if defender.0 == 0 {
-            if damage > 0 {
+            if damage > 0 && self.state == State::LostGame {
+                Topic::Fatal
+            } else if damage > 0 {
                 Topic::PlayerIsDamaged
             } else {
                 Topic::PlayerIsNotDamaged
@@ -359,7 +705,7 @@ impl Game {
     }
 }
 
-fn linear_scale(x: i32, min_x: i32, max_x: i32, min_p: f64, max_p: f64) -> f64 {
+pub(super) fn linear_scale(x: i32, min_x: i32, max_x: i32, min_p: f64, max_p: f64) -> f64 {
     assert!(min_x < max_x);
     assert!(min_p < max_p);
 
@@ -377,3 +723,144 @@ fn linear_scale(x: i32, min_x: i32, max_x: i32, min_p: f64, max_p: f64) -> f64 {
 
     p
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_game;
+    use std::fs;
+
+    #[test]
+    fn test_reach() {
+        let mut game = test_game();
+
+        let player_loc = game.player_loc();
+        let deltas = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+        let (dx, dy) = *deltas
+            .iter()
+            .find(|(dx, dy)| {
+                let near = Point::new(player_loc.x + dx, player_loc.y + dy);
+                let far = Point::new(player_loc.x + 2 * dx, player_loc.y + 2 * dy);
+                game.can_teleport_to(Oid(0), &near) && game.can_teleport_to(Oid(0), &far)
+            })
+            .unwrap();
+        let near = Point::new(player_loc.x + dx, player_loc.y + dy);
+        let far = Point::new(player_loc.x + 2 * dx, player_loc.y + 2 * dy);
+
+        // Without a reach weapon the player just moves.
+        assert!(game.reach_target(&player_loc, dx, dy).is_none());
+
+        let spear = game.level.add(new_obj(ObjectName::Spear), None);
+        let player = game.level.get_mut(&player_loc, CHARACTER_ID).unwrap().1;
+        player.inventory_value_mut().unwrap().push(spear);
+        game.player_acted(Action::WieldMainHand(spear));
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+
+        // With one moving towards an enemy attacks it instead.
+        let enemy = game.add_object(&far, new_obj(ObjectName::ImrothTheCruel));
+        game.player_acted(Action::Move { dx, dy });
+        assert_eq!(game.player_loc(), player_loc);
+        let behavior = game.level.obj(enemy).0.behavior_value();
+        assert!(matches!(behavior, Some(Behavior::Attacking(Oid(0), _))));
+
+        // Guards can poke the player from behind another character.
+        game.add_object(&near, new_obj(ObjectName::Spectator));
+        game.level.remove(enemy);
+        game.add_object(&far, new_obj(ObjectName::Guard));
+        assert_eq!(game.reach_target(&far, -dx, -dy), Some(player_loc));
+    }
+
+    #[test]
+    fn test_combat_details() {
+        // Details shouldn't change the outcome of attacks.
+        let mut outcomes = Vec::new();
+        for details in [false, true] {
+            let path = format!("/tmp/saved-{}-{details}.game", line!());
+            let _ = fs::remove_file(&path);
+
+            let mut game = Game::new_game(&path, 1);
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.set_combat_details(details);
+
+            let player_loc = game.player_loc();
+            let loc = Point::new(player_loc.x + 1, player_loc.y);
+            let guard = game.add_object(&loc, new_obj(ObjectName::Guard));
+            let count = game.messages.len();
+            for _ in 0..5 {
+                game.do_melee_attack(&loc, &player_loc);
+            }
+
+            let detailed = game.messages[count..]
+                .iter()
+                .filter(|m| m.text.contains("chance to hit"))
+                .count();
+            if details {
+                assert_eq!(detailed, 5);
+            } else {
+                assert_eq!(detailed, 0);
+            }
+            outcomes.push((game.player_hps(), game.level.obj(guard).0.durability_value()));
+        }
+        assert_eq!(outcomes[0], outcomes[1]);
+    }
+
+    #[test]
+    fn test_defense() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        let loc = Point::new(player_loc.x + 1, player_loc.y);
+        let guard = game.add_object(&loc, new_obj(ObjectName::Guard));
+
+        // Probabilities stay in range and more dexterity makes the player harder to hit.
+        let mut last = 1.0;
+        for dex in 0..=2 * MAX_STAT {
+            game.level.obj_mut(Oid(0)).replace(Tag::Dexterity(dex));
+            let probs = game.defense_probs(guard, Oid(0));
+            assert!((0.1..=1.0).contains(&probs.hit), "dex {dex} has hit {}", probs.hit);
+            assert!(probs.hit <= last);
+            assert_eq!(probs.parry, 0.0); // the player is unarmed
+            assert_eq!(probs.block, 0.0);
+            last = probs.hit;
+
+            // Guards have shields.
+            let probs = game.defense_probs(Oid(0), guard);
+            assert_eq!(probs.block, 0.15);
+            assert!(probs.land() > 0.0 && probs.land() < probs.hit);
+        }
+
+        // Armor makes the player easier to hit.
+        game.level.obj_mut(Oid(0)).replace(Tag::Dexterity(10));
+        let unarmored = game.defense_probs(guard, Oid(0)).hit;
+        for name in [
+            ObjectName::LeatherChest,
+            ObjectName::LeatherLegs,
+            ObjectName::LeatherHat,
+        ] {
+            let oid = game.level.add(new_obj(name), None);
+            let player = game.level.get_mut(&player_loc, CHARACTER_ID).unwrap().1;
+            player.inventory_value_mut().unwrap().push(oid);
+            game.player_acted(Action::Wear(oid));
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+        }
+        assert!(game.encumbrance(Oid(0)) > 0);
+        assert!(game.defense_probs(guard, Oid(0)).hit > unarmored);
+
+        // Wielding a weapon allows parries but only if the character is dexterous enough.
+        let sword = game.level.add(new_obj(ObjectName::LongSword), None);
+        let player = game.level.get_mut(&player_loc, CHARACTER_ID).unwrap().1;
+        player.inventory_value_mut().unwrap().push(sword);
+        game.player_acted(Action::WieldMainHand(sword));
+        let parry = game.parry_prob(Oid(0));
+        assert!((MIN_PARRY..=MAX_PARRY).contains(&parry));
+        game.level.obj_mut(Oid(0)).replace(Tag::Dexterity(2 * MAX_STAT));
+        assert!((game.parry_prob(Oid(0)) - MAX_PARRY).abs() < 1e-9);
+        game.level.obj_mut(Oid(0)).replace(Tag::Dexterity(4));
+        assert_eq!(game.parry_prob(Oid(0)), 0.0);
+    }
+}
@@ -1,6 +1,6 @@
 use derive_more::Display;
 
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Display, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum Topic {
     /// An operation could not be completed.
     Error,
@@ -15,6 +15,9 @@ pub enum Topic {
     /// A significant announcement.
     Important,
 
+    /// Tutorial hint.
+    Hint,
+
     NPCSpeaks,
 
     /// NPC was damaged (but not by the player).
@@ -42,9 +45,36 @@ pub enum Topic {
     // PlayerIsThreatened,
     /// An operation was not completely successful.
     Warning,
+
+    /// Whatever it was that killed the player.
+    Fatal,
+
+    /// Flavor text that doesn't affect game play, e.g. a distant roar.
+    Environmental,
+}
+
+impl Topic {
+    /// All of the topics, e.g. so that frontends can configure each one.
+    pub const ALL: [Topic; 15] = [
+        Topic::Error,
+        Topic::Normal,
+        Topic::Failed,
+        Topic::Hint,
+        Topic::Important,
+        Topic::NpcIsDamaged,
+        Topic::NpcIsNotDamaged,
+        Topic::NPCSpeaks,
+        Topic::PlayerDidDamage,
+        Topic::PlayerDidNoDamage,
+        Topic::PlayerIsDamaged,
+        Topic::PlayerIsNotDamaged,
+        Topic::Warning,
+        Topic::Fatal,
+        Topic::Environmental,
+    ];
 }
 
-#[derive(Clone, Debug, Display, Eq, PartialEq)]
+#[derive(Clone, Debug, Display, Eq, PartialEq, Serialize, Deserialize)]
 #[display(fmt = "{} {}", topic, text)]
 pub struct Message {
     pub topic: Topic,
@@ -0,0 +1,1413 @@
+//! Allows a remote frontend to drive a headless backend over TCP. Messages are framed
+//! using a little endian u32 length followed by a postcard encoded Request or Response
+//! (the same encoding used by saved games).
+//!
+//! The first request on a connection must be a Hello with the server's shared token.
+//! Requests are only acted upon once the entire frame has arrived so a client that
+//! disconnects mid-turn leaves the game in a consistent state: any actions that were
+//! received are saved and a new client can connect and pick up where the old one left off.
+//! Frontends that reconnect should use Client::resume which returns a Snapshot of the game
+//! in progress so that they can redraw themselves.
+//!
+//! Clients have typed methods for each query. Queries are retried on a new connection if
+//! they fail and all requests time out, see Policy.
+//!
+//! Clients can also save a copy of the game and later load it back in (replacing the game
+//! the server was running). Saved games are named with bare file names which live in the
+//! saves folder of the data directory so clients can't touch arbitrary files. Loaded games
+//! continue to be saved to the file they were loaded from.
+//!
+//! Clients can also Observe the game: they're sent a new Snapshot whenever the game changes
+//! but can't send any more requests (e.g. a second frontend used to watch an AI or to stream
+//! a game).
+//!
+//! While a server runs it holds a lock file next to the saved game with a heartbeat so that
+//! two servers don't append to the same game. Locks left behind by a server that crashed
+//! stop being refreshed and are reclaimed by the next server.
+//!
+//! Servers use a Multiplexer so that they can listen on more than one channel (e.g. a
+//! control channel in addition to the game channel) without blocking on any one client.
+//! The multiplexer also answers Metrics queries itself with counters for each channel.
+use super::loadouts::MAX_LOADOUTS;
+use super::persistence;
+use super::tag::*;
+use super::targeting::THROW_RANGE;
+use super::{data_dir, Action, Game, Message, Oid, Point, Topic};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io::{Cursor, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Component, Path};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Folder within the data directory for games saved by clients.
+const SAVES_DIR: &str = "saves";
+
+/// Frames larger than this are assumed to be garbage.
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// Messages sent to clients are trimmed (oldest first) so that they fit into this many
+/// bytes. This leaves room in the frame for everything else in a Snapshot.
+const MAX_MESSAGE_BYTES: usize = MAX_FRAME_LEN - 1024;
+
+/// Peers that don't accept a response within this long are dropped (so that one stalled
+/// observer can't block everyone else).
+const SEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long the multiplexer sleeps when there is nothing to do.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long serve waits for requests before checking again.
+const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How often the server refreshes its save lock.
+const HEARTBEAT: Duration = Duration::from_secs(5);
+
+/// Locks that haven't been refreshed for this long are assumed to belong to a server that
+/// crashed.
+const STALE_LOCK: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Request {
+    Hello { token: String },
+    Act(Action),
+    Query(Query),
+    SaveGame(String), // arg is the file name (within SAVES_DIR) to write a copy of the game to
+    LoadGame(String), // arg is the file name (within SAVES_DIR) of a saved game to switch to
+    Observe(usize),   // arg is the max number of messages to send with each update
+    Shutdown,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Query {
+    PlayerLoc,
+    PlayerHps,
+    RecentMessages(usize),
+    Snapshot(usize), // arg is the max number of messages to return
+    Topics,
+    Metrics,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Response {
+    Welcome,
+    Denied,
+    Acted,
+    Saved,
+    Loaded,
+    Loc(Point),
+    Hps(i32, i32),
+    Messages(Vec<Message>),
+    Snapshot(Snapshot),
+    Topics(Vec<Topic>),
+    Metrics(Vec<Metrics>),
+    Error(String),
+}
+
+/// The state a frontend needs to redraw itself, e.g. after re-connecting.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Number of actions recorded for the game (including object actions), zero for a
+    /// brand new game.
+    pub num_actions: u64,
+    pub player_loc: Point,
+    pub player_hps: (i32, i32),
+
+    /// Total number of messages, including those no longer in messages.
+    pub num_messages: usize,
+    pub messages: Vec<Message>,
+}
+
+/// Counters for one of the server's channels.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub addr: SocketAddr,
+    pub connections: usize,
+
+    /// Requests handled since the server started (not counting Hello).
+    pub requests: u64,
+
+    /// Requests that were answered with an Error.
+    pub errors: u64,
+
+    /// Average time spent handling a request (including sending the response).
+    pub latency: Duration,
+
+    /// Bytes that have arrived but haven't been handled yet.
+    pub queued: usize,
+}
+
+/// Serves clients until a client sends Shutdown. If control is set then clients can also
+/// connect to that listener (with its token) to query the game or shut the server down
+/// (but not to act). Lock should have been acquired for game's path before the game was
+/// loaded and should outlive the game.
+pub fn serve(
+    game: &mut Game,
+    lock: &mut SaveLock,
+    listener: TcpListener,
+    token: &str,
+    control: Option<(TcpListener, &str)>,
+) -> Result<(), Box<dyn Error>> {
+    let mut mux = Multiplexer::new();
+    mux.listen(listener, token, Box::new(handle_request))?;
+    if let Some((listener, token)) = control {
+        mux.listen(listener, token, Box::new(handle_control))?;
+    }
+
+    let mut published = (game.num_actions, game.num_messages());
+    loop {
+        match mux.poll(game, POLL_TIMEOUT)? {
+            Polled::Shutdown => {
+                game.save_actions();
+                return Ok(());
+            }
+            Polled::Disconnected => game.save_actions(),
+            Polled::Handled | Polled::Idle => (),
+        }
+
+        lock.refresh(&game.path)?;
+        let current = (game.num_actions, game.num_messages());
+        if current != published {
+            published = current;
+            mux.publish(|limit| Response::Snapshot(snapshot(game, limit)));
+        }
+    }
+}
+
+/// How long clients wait on the server and how many times they reconnect to retry a
+/// query (actions aren't retried because they may have been applied before the failure).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Policy {
+    pub timeout: Duration,
+    pub retries: u32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            timeout: Duration::from_secs(5),
+            retries: 2,
+        }
+    }
+}
+
+/// Used by frontends to talk to a server.
+pub struct Client {
+    stream: TcpStream,
+    addr: SocketAddr,
+    token: String,
+    policy: Policy,
+}
+
+impl Client {
+    pub fn connect<A: ToSocketAddrs>(addr: A, token: &str) -> Result<Client, Box<dyn Error>> {
+        Client::connect_with(addr, token, Policy::default())
+    }
+
+    pub fn connect_with<A: ToSocketAddrs>(addr: A, token: &str, policy: Policy) -> Result<Client, Box<dyn Error>> {
+        let addr = addr.to_socket_addrs()?.next().ok_or("no address to connect to")?;
+        let mut client = Client {
+            stream: open_stream(&addr, &policy)?,
+            addr,
+            token: token.to_string(),
+            policy,
+        };
+        match client.send(&Request::Hello {
+            token: token.to_string(),
+        })? {
+            Response::Welcome => Ok(client),
+            Response::Denied => Err("server denied access".into()),
+            response => Err(unexpected("hello", response)),
+        }
+    }
+
+    /// Connects to a server that may already have a game in progress and returns the
+    /// state of that game (with up to max_messages messages).
+    pub fn resume<A: ToSocketAddrs>(
+        addr: A,
+        token: &str,
+        max_messages: usize,
+    ) -> Result<(Client, Snapshot), Box<dyn Error>> {
+        let mut client = Client::connect(addr, token)?;
+        let snapshot = client.snapshot(max_messages)?;
+        Ok((client, snapshot))
+    }
+
+    pub fn act(&mut self, action: Action) -> Result<(), Box<dyn Error>> {
+        match self.send(&Request::Act(action))? {
+            Response::Acted => Ok(()),
+            response => Err(unexpected("act", response)),
+        }
+    }
+
+    pub fn save_game(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        match self.send(&Request::SaveGame(name.to_string()))? {
+            Response::Saved => Ok(()),
+            response => Err(unexpected("save", response)),
+        }
+    }
+
+    /// Replaces the server's game with the one saved as name and returns its state (with
+    /// up to max_messages messages).
+    pub fn load_game(&mut self, name: &str, max_messages: usize) -> Result<Snapshot, Box<dyn Error>> {
+        match self.send(&Request::LoadGame(name.to_string()))? {
+            Response::Loaded => self.snapshot(max_messages),
+            response => Err(unexpected("load", response)),
+        }
+    }
+
+    pub fn player_loc(&mut self) -> Result<Point, Box<dyn Error>> {
+        match self.query(Query::PlayerLoc)? {
+            Response::Loc(loc) => Ok(loc),
+            response => Err(unexpected("player loc", response)),
+        }
+    }
+
+    /// Returns current and max hit points.
+    pub fn player_hps(&mut self) -> Result<(i32, i32), Box<dyn Error>> {
+        match self.query(Query::PlayerHps)? {
+            Response::Hps(current, max) => Ok((current, max)),
+            response => Err(unexpected("player hps", response)),
+        }
+    }
+
+    pub fn recent_messages(&mut self, limit: usize) -> Result<Vec<Message>, Box<dyn Error>> {
+        match self.query(Query::RecentMessages(limit))? {
+            Response::Messages(messages) => Ok(messages),
+            response => Err(unexpected("recent messages", response)),
+        }
+    }
+
+    pub fn snapshot(&mut self, max_messages: usize) -> Result<Snapshot, Box<dyn Error>> {
+        match self.query(Query::Snapshot(max_messages))? {
+            Response::Snapshot(snapshot) => Ok(snapshot),
+            response => Err(unexpected("snapshot", response)),
+        }
+    }
+
+    pub fn topics(&mut self) -> Result<Vec<Topic>, Box<dyn Error>> {
+        match self.query(Query::Topics)? {
+            Response::Topics(topics) => Ok(topics),
+            response => Err(unexpected("topics", response)),
+        }
+    }
+
+    pub fn metrics(&mut self) -> Result<Vec<Metrics>, Box<dyn Error>> {
+        match self.query(Query::Metrics)? {
+            Response::Metrics(metrics) => Ok(metrics),
+            response => Err(unexpected("metrics", response)),
+        }
+    }
+
+    /// Queries are retried (on a new connection) if they fail, see Policy.
+    pub fn query(&mut self, query: Query) -> Result<Response, Box<dyn Error>> {
+        let request = Request::Query(query);
+        let mut result = self.send(&request);
+        for attempt in 1..=self.policy.retries {
+            match result {
+                Ok(response) => return Ok(response),
+                Err(err) => warn!("{query:?} failed (attempt {attempt}): {err}"),
+            }
+            result = self.reconnect().and_then(|_| self.send(&request));
+        }
+        result
+    }
+
+    pub fn shutdown(mut self) -> Result<(), Box<dyn Error>> {
+        write_frame(&mut self.stream, &Request::Shutdown)
+    }
+
+    fn reconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        self.stream = open_stream(&self.addr, &self.policy)?;
+        match self.send(&Request::Hello {
+            token: self.token.clone(),
+        })? {
+            Response::Welcome => Ok(()),
+            response => Err(unexpected("hello", response)),
+        }
+    }
+
+    fn send(&mut self, request: &Request) -> Result<Response, Box<dyn Error>> {
+        write_frame(&mut self.stream, request)?;
+        read_frame(&mut self.stream)?.ok_or_else(|| "server closed the connection".into())
+    }
+}
+
+/// Used by frontends that want to watch a game without being able to change it.
+pub struct Observer {
+    stream: TcpStream,
+}
+
+impl Observer {
+    /// Returns the state of the game when the observer connected (with up to max_messages
+    /// messages).
+    pub fn connect<A: ToSocketAddrs>(
+        addr: A,
+        token: &str,
+        max_messages: usize,
+    ) -> Result<(Observer, Snapshot), Box<dyn Error>> {
+        let mut client = Client::connect(addr, token)?;
+        match client.send(&Request::Observe(max_messages))? {
+            Response::Snapshot(snapshot) => {
+                // Updates only arrive when the game changes so there's no timeout.
+                client.stream.set_read_timeout(None)?;
+                Ok((Observer { stream: client.stream }, snapshot))
+            }
+            response => Err(unexpected("observe", response)),
+        }
+    }
+
+    /// Blocks until the game changes.
+    pub fn next_update(&mut self) -> Result<Snapshot, Box<dyn Error>> {
+        match read_frame(&mut self.stream)? {
+            Some(Response::Snapshot(snapshot)) => Ok(snapshot),
+            Some(response) => Err(unexpected("observe", response)),
+            None => Err("server closed the connection".into()),
+        }
+    }
+
+    /// Calls update with each change to the game until update returns false.
+    pub fn watch<F: FnMut(Snapshot) -> bool>(&mut self, mut update: F) -> Result<(), Box<dyn Error>> {
+        while update(self.next_update()?) {}
+        Ok(())
+    }
+}
+
+fn open_stream(addr: &SocketAddr, policy: &Policy) -> Result<TcpStream, Box<dyn Error>> {
+    let stream = TcpStream::connect_timeout(addr, policy.timeout)?;
+    stream.set_read_timeout(Some(policy.timeout))?;
+    stream.set_write_timeout(Some(policy.timeout))?;
+    Ok(stream)
+}
+
+// Errors from the server are passed along as is.
+fn unexpected(what: &str, response: Response) -> Box<dyn Error> {
+    match response {
+        Response::Error(err) => err.into(),
+        response => format!("unexpected response to {what}: {response:?}").into(),
+    }
+}
+
+fn handle_request(game: &mut Game, request: Request) -> Dispatch {
+    let response = match request {
+        Request::Hello { .. } => Response::Error("already connected".to_string()),
+        Request::Act(action) => match check_action(game, &action) {
+            Ok(()) if action.is_free() => {
+                game.free_action(action);
+                Response::Acted
+            }
+            Ok(()) => {
+                while !game.players_turn() {
+                    game.advance_time(false);
+                }
+                game.player_acted(action);
+                Response::Acted
+            }
+            Err(err) => Response::Error(err),
+        },
+        Request::Query(query) => handle_query(game, query),
+        Request::SaveGame(name) => match saves_path(&name) {
+            Ok(path) => match game.save_copy(&path) {
+                Ok(()) => Response::Saved,
+                Err(err) => Response::Error(format!("couldn't save to {name}: {err}")),
+            },
+            Err(err) => Response::Error(err),
+        },
+        Request::LoadGame(name) => match saves_path(&name) {
+            Ok(path) if SaveLock::held(&path) => Response::Error(format!("{name} is being used by another server")),
+            Ok(path) => match load_game(&path) {
+                Ok(loaded) => {
+                    game.save_actions();
+                    *game = loaded;
+                    Response::Loaded
+                }
+                Err(err) => Response::Error(format!("couldn't load {name}: {err}")),
+            },
+            Err(err) => Response::Error(err),
+        },
+        Request::Observe(limit) => {
+            return Dispatch::Observe {
+                limit,
+                reply: Response::Snapshot(snapshot(game, limit)),
+            }
+        }
+        Request::Shutdown => return Dispatch::Shutdown,
+    };
+    Dispatch::Reply(response)
+}
+
+fn handle_control(game: &mut Game, request: Request) -> Dispatch {
+    match request {
+        Request::Act(_) => Dispatch::Reply(Response::Error("control clients can't act".to_string())),
+        Request::SaveGame(_) => Dispatch::Reply(Response::Error("control clients can't save games".to_string())),
+        Request::LoadGame(_) => Dispatch::Reply(Response::Error("control clients can't load games".to_string())),
+        _ => handle_request(game, request),
+    }
+}
+
+/// Returns where the saved game called name lives. Names have to be bare file names so
+/// that clients can't read or write files outside of the saves folder.
+fn saves_path(name: &str) -> Result<String, String> {
+    let mut components = Path::new(name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(format!("'{name}' isn't a bare file name"));
+    }
+    let dir = data_dir().join(SAVES_DIR);
+    fs::create_dir_all(&dir).map_err(|err| format!("couldn't create {}: {err}", dir.display()))?;
+    Ok(dir.join(name).to_string_lossy().to_string())
+}
+
+/// Game::player_acted asserts if it's given an action that the UI would never produce so
+/// actions from external programs have to be checked first. Items have to be carried by
+/// the player and targets have to be within range.
+pub(super) fn check_action(game: &Game, action: &Action) -> Result<(), String> {
+    match action {
+        Action::Object
+        | Action::WizardMode
+        | Action::Polymorph { .. }
+        | Action::Teleport { .. }
+        | Action::Examine { wizard: true, .. } => Err(format!("{action:?} can't be used by external programs")),
+        Action::Examine { loc, .. } if !game.level.in_bounds(loc) => Err(format!("{loc} isn't on the level")),
+        Action::Move { dx, dy }
+        | Action::Shove { dx, dy }
+        | Action::Charge { dx, dy }
+        | Action::Withdraw { dx, dy }
+            if !(-1..=1).contains(dx) || !(-1..=1).contains(dy) || (*dx == 0 && *dy == 0) =>
+        {
+            Err(format!("{action:?} should have dx and dy in [-1, 1] and not both zero"))
+        }
+        Action::Drop(oid) | Action::Call { oid, .. } => check_carried(game, *oid),
+        Action::DropMany(oids) => oids.iter().try_for_each(|&oid| check_carried(game, oid)),
+        Action::Remove(oid) => {
+            check_carried(game, *oid)?;
+            if game
+                .inventory()
+                .iter()
+                .any(|item| item.oid == *oid && item.equipped.is_some())
+            {
+                Ok(())
+            } else {
+                Err(format!("{oid} isn't equipped"))
+            }
+        }
+        Action::Wear(oid) => {
+            check_carried(game, *oid)?;
+            check_tag(game, *oid, ARMOR_ID)
+        }
+        Action::WieldMainHand(oid) => {
+            check_carried(game, *oid)?;
+            check_tag(game, *oid, WEAPON_ID)
+        }
+        Action::WieldOffHand(oid) => {
+            check_carried(game, *oid)?;
+            match game.level.obj(*oid).0.weapon_value() {
+                Some(Weapon::OneHand) => Ok(()),
+                _ => Err(format!("{oid} isn't a one handed weapon")),
+            }
+        }
+        Action::Throw { oid, target } => {
+            check_carried(game, *oid)?;
+            if target.distance2(&game.player_loc()) <= THROW_RANGE * THROW_RANGE {
+                Ok(())
+            } else {
+                Err(format!("{target} is out of range"))
+            }
+        }
+        Action::UseOn { oid, target } => {
+            check_carried(game, *oid)?;
+            let player_loc = game.player_loc();
+            if player_loc.adjacent(target) || player_loc == *target {
+                Ok(())
+            } else {
+                Err(format!("{target} isn't next to the player"))
+            }
+        }
+        Action::PickUpMany(oids) => {
+            let player_loc = game.player_loc();
+            let cell = game.level.cell(&player_loc);
+            match oids
+                .iter()
+                .find(|&oid| !cell.contains(oid) || !game.level.obj(*oid).0.has(PORTABLE_ID))
+            {
+                Some(oid) => Err(format!("{oid} isn't an item in the player's cell")),
+                None => Ok(()),
+            }
+        }
+        Action::SaveLoadout(index) | Action::SwapLoadout(index) if *index as usize >= MAX_LOADOUTS => {
+            Err(format!("loadout {index} should be less than {MAX_LOADOUTS}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Items are either in the player's inventory or equipped.
+fn check_carried(game: &Game, oid: Oid) -> Result<(), String> {
+    if game.inventory().iter().any(|item| item.oid == oid) {
+        Ok(())
+    } else {
+        Err(format!("{oid} isn't carried by the player"))
+    }
+}
+
+fn check_tag(game: &Game, oid: Oid, tid: Tid) -> Result<(), String> {
+    if game.level.obj(oid).0.has(tid) {
+        Ok(())
+    } else {
+        Err(format!("{oid} can't be used that way"))
+    }
+}
+
+// Game::old_game starts a new game if the load fails so we check the file first.
+fn load_game(path: &str) -> Result<Game, Box<dyn Error>> {
+    persistence::read_info(path)?;
+    let (mut game, actions) = Game::old_game(path, Vec::new());
+    for action in actions {
+        game.replay_action(action);
+    }
+    Ok(game)
+}
+
+fn handle_query(game: &Game, query: Query) -> Response {
+    match query {
+        Query::PlayerLoc => Response::Loc(game.player_loc()),
+        Query::PlayerHps => {
+            let (current, max) = game.player_hps();
+            Response::Hps(current, max)
+        }
+        Query::RecentMessages(limit) => Response::Messages(recent_messages(game, limit)),
+        Query::Snapshot(limit) => Response::Snapshot(snapshot(game, limit)),
+        Query::Topics => Response::Topics(Topic::ALL.to_vec()),
+        Query::Metrics => Response::Error("metrics are handled by the multiplexer".to_string()),
+    }
+}
+
+fn snapshot(game: &Game, limit: usize) -> Snapshot {
+    Snapshot {
+        num_actions: game.num_actions,
+        player_loc: game.player_loc(),
+        player_hps: game.player_hps(),
+        num_messages: game.num_messages(),
+        messages: recent_messages(game, limit),
+    }
+}
+
+// Clients can ask for any number of messages so newer messages are kept when they won't
+// all fit into a frame.
+fn recent_messages(game: &Game, limit: usize) -> Vec<Message> {
+    let mut budget = MAX_MESSAGE_BYTES;
+    let mut messages: Vec<Message> = game
+        .recent_messages(limit)
+        .rev()
+        .take_while(|message| {
+            let len = message.text.len() + 8; // postcard adds the topic and length
+            budget = budget.saturating_sub(len);
+            budget > 0
+        })
+        .cloned()
+        .collect();
+    messages.reverse();
+    messages
+}
+
+/// Lock file for the game that a server is appending to. The file has the time of the
+/// last heartbeat so that locks from servers that crashed can be detected. Servers take
+/// the lock before loading the game (loading may truncate a torn chunk) and hold it until
+/// the Game has been dropped (dropping saves the game).
+pub struct SaveLock {
+    path: String, // of the saved game
+    refreshed: Instant,
+}
+
+impl SaveLock {
+    pub fn acquire(path: &str) -> Result<SaveLock, Box<dyn Error>> {
+        if let Some(age) = SaveLock::age(path) {
+            if age < STALE_LOCK {
+                return Err(format!("{path} is being used by another server").into());
+            }
+            info!(
+                "reclaimed stale lock for {path} (last heartbeat was {}s ago)",
+                age.as_secs()
+            );
+        }
+        let lock = SaveLock {
+            path: path.to_string(),
+            refreshed: Instant::now(),
+        };
+        lock.write()?;
+        Ok(lock)
+    }
+
+    /// Returns true if another server has a live lock on path.
+    fn held(path: &str) -> bool {
+        SaveLock::age(path).is_some_and(|age| age < STALE_LOCK)
+    }
+
+    /// Called periodically with the path of the game the server is using (which changes
+    /// when clients load a game).
+    fn refresh(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        if path != self.path {
+            let _ = fs::remove_file(SaveLock::lock_path(&self.path));
+            self.path = path.to_string();
+        } else if self.refreshed.elapsed() < HEARTBEAT {
+            return Ok(());
+        }
+        self.refreshed = Instant::now();
+        self.write()
+    }
+
+    fn write(&self) -> Result<(), Box<dyn Error>> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        fs::write(SaveLock::lock_path(&self.path), format!("{}\n", now.as_secs()))?;
+        Ok(())
+    }
+
+    // Returns None if there is no lock (or it's unreadable which can happen if the server
+    // crashed while writing it).
+    fn age(path: &str) -> Option<Duration> {
+        let text = fs::read_to_string(SaveLock::lock_path(path)).ok()?;
+        let heartbeat = Duration::from_secs(text.trim().parse().ok()?);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+        Some(now.saturating_sub(heartbeat))
+    }
+
+    fn lock_path(path: &str) -> String {
+        format!("{path}.lock")
+    }
+}
+
+impl Drop for SaveLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(SaveLock::lock_path(&self.path));
+    }
+}
+
+fn write_frame<T: Serialize>(stream: &mut TcpStream, value: &T) -> Result<(), Box<dyn Error>> {
+    let payload: Vec<u8> = postcard::to_stdvec(value)?;
+    let mut bytes = Vec::with_capacity(4 + payload.len());
+    bytes.write_u32::<LittleEndian>(payload.len() as u32)?;
+    bytes.extend_from_slice(&payload);
+    stream.write_all(&bytes)?;
+    Ok(())
+}
+
+// Returns None if the peer closed the connection between frames.
+fn read_frame<T: for<'de> Deserialize<'de>>(stream: &mut TcpStream) -> Result<Option<T>, Box<dyn Error>> {
+    let mut bytes = vec![0u8; 4];
+    match stream.read_exact(&mut bytes) {
+        Ok(_) => (),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(Box::new(err)),
+    }
+    let len = std::io::Cursor::new(bytes).read_u32::<LittleEndian>()? as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(format!("frame length {len} is too large").into());
+    }
+
+    let mut bytes = vec![0u8; len];
+    stream.read_exact(&mut bytes)?;
+    Ok(Some(postcard::from_bytes(&bytes)?))
+}
+
+// ---- Multiplexer ----------------------------------------------------------------------
+/// What a handler wants done after processing a request.
+pub enum Dispatch {
+    Reply(Response),
+
+    /// Reply and then send the client whatever is published (see Multiplexer::publish).
+    Observe {
+        limit: usize,
+        reply: Response,
+    },
+
+    Shutdown,
+}
+
+/// Handlers are given requests from clients that have said Hello with the right token.
+pub type Handler<S> = Box<dyn FnMut(&mut S, Request) -> Dispatch>;
+
+/// The result of a poll, from least to most important.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Polled {
+    /// Nothing arrived before the timeout.
+    Idle,
+
+    /// At least one request was handled.
+    Handled,
+
+    /// At least one client went away.
+    Disconnected,
+
+    /// A handler asked for a shutdown.
+    Shutdown,
+}
+
+/// Polls any number of listeners, and the clients connected to them, without blocking on
+/// any one of them. Each listener is a channel with its own token and handler.
+pub struct Multiplexer<S> {
+    channels: Vec<Channel<S>>,
+}
+
+struct Channel<S> {
+    listener: TcpListener,
+    addr: SocketAddr,
+    token: String,
+    handler: Handler<S>,
+    connections: Vec<Connection>,
+    counters: Counters,
+}
+
+#[derive(Default)]
+struct Counters {
+    requests: u64,
+    errors: u64,
+    busy: Duration, // total time spent handling requests
+}
+
+struct Connection {
+    stream: TcpStream,
+    peer: SocketAddr,
+    pending: Vec<u8>, // bytes for frames that haven't entirely arrived yet
+    welcomed: bool,
+    observing: Option<usize>, // limit from the Observe request
+}
+
+impl<S> Multiplexer<S> {
+    pub fn new() -> Multiplexer<S> {
+        Multiplexer { channels: Vec::new() }
+    }
+
+    pub fn listen(&mut self, listener: TcpListener, token: &str, handler: Handler<S>) -> Result<(), Box<dyn Error>> {
+        let addr = listener.local_addr()?;
+        info!("listening on {addr}");
+        listener.set_nonblocking(true)?;
+        self.channels.push(Channel {
+            listener,
+            addr,
+            token: token.to_string(),
+            handler,
+            connections: Vec::new(),
+            counters: Counters::default(),
+        });
+        Ok(())
+    }
+
+    pub fn metrics(&self) -> Vec<Metrics> {
+        self.channels
+            .iter()
+            .map(|channel| {
+                let counters = &channel.counters;
+                Metrics {
+                    addr: channel.addr,
+                    connections: channel.connections.len(),
+                    requests: counters.requests,
+                    errors: counters.errors,
+                    latency: if counters.requests > 0 {
+                        counters.busy / counters.requests as u32
+                    } else {
+                        Duration::ZERO
+                    },
+                    queued: channel.connections.iter().map(|c| c.pending.len()).sum(),
+                }
+            })
+            .collect()
+    }
+
+    /// Accepts new clients and dispatches whatever requests have arrived, waiting up to
+    /// timeout for something to happen.
+    pub fn poll(&mut self, state: &mut S, timeout: Duration) -> Result<Polled, Box<dyn Error>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut polled = Polled::Idle;
+            for i in 0..self.channels.len() {
+                self.channels[i].accept()?;
+                let metrics = self.metrics();
+                polled = polled.max(self.channels[i].service(state, &metrics));
+                if polled == Polled::Shutdown {
+                    return Ok(polled);
+                }
+            }
+
+            let now = Instant::now();
+            if polled != Polled::Idle || now >= deadline {
+                return Ok(polled);
+            }
+            thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// Sends a response to every observer, update is called with the observer's limit.
+    /// Observers that can't be written to are dropped.
+    pub fn publish<F: Fn(usize) -> Response>(&mut self, update: F) {
+        for channel in self.channels.iter_mut() {
+            channel.connections.retain_mut(|connection| match connection.observing {
+                Some(limit) => match connection.send(&update(limit)) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        warn!("{} disconnected: {err}", connection.peer);
+                        false
+                    }
+                },
+                None => true,
+            });
+        }
+    }
+}
+
+impl<S> Default for Multiplexer<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Channel<S> {
+    fn accept(&mut self) -> Result<(), Box<dyn Error>> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    info!("{peer} connected");
+                    stream.set_nonblocking(true)?;
+                    stream.set_write_timeout(Some(SEND_TIMEOUT))?;
+                    self.connections.push(Connection {
+                        stream,
+                        peer,
+                        pending: Vec::new(),
+                        welcomed: false,
+                        observing: None,
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(Box::new(err)),
+            }
+        }
+    }
+
+    fn service(&mut self, state: &mut S, metrics: &[Metrics]) -> Polled {
+        let mut polled = Polled::Idle;
+        let mut i = 0;
+        while i < self.connections.len() {
+            let connection = &mut self.connections[i];
+            let result = connection.service(&self.token, &mut self.handler, state, metrics, &mut self.counters);
+            let peer = connection.peer;
+            let outcome = match result {
+                Ok(Polled::Disconnected) => {
+                    info!("{peer} disconnected");
+                    Polled::Disconnected
+                }
+                Ok(Polled::Shutdown) => {
+                    info!("{peer} shut the server down");
+                    Polled::Shutdown
+                }
+                Ok(p) => {
+                    polled = polled.max(p);
+                    i += 1;
+                    continue;
+                }
+                Err(err) => {
+                    warn!("{peer} disconnected: {err}");
+                    Polled::Disconnected
+                }
+            };
+            self.connections.remove(i);
+            polled = polled.max(outcome);
+            if polled == Polled::Shutdown {
+                break;
+            }
+        }
+        polled
+    }
+}
+
+impl Connection {
+    // Disconnected and Shutdown mean that the connection should be dropped.
+    fn service<S>(
+        &mut self,
+        token: &str,
+        handler: &mut Handler<S>,
+        state: &mut S,
+        metrics: &[Metrics],
+        counters: &mut Counters,
+    ) -> Result<Polled, Box<dyn Error>> {
+        let closed = self.receive()?;
+        let mut polled = Polled::Idle;
+        while let Some(request) = self.next_request()? {
+            polled = Polled::Handled;
+            if !self.welcomed {
+                match request {
+                    Request::Hello { token: t } if t == token => {
+                        self.welcomed = true;
+                        self.send(&Response::Welcome)?;
+                    }
+                    _ => {
+                        self.send(&Response::Denied)?;
+                        return Ok(Polled::Disconnected);
+                    }
+                }
+                continue;
+            }
+
+            let start = Instant::now();
+            let response = match request {
+                Request::Hello { .. } => Response::Error("already connected".to_string()),
+                _ if self.observing.is_some() => Response::Error("observers can't make requests".to_string()),
+                Request::Query(Query::Metrics) => Response::Metrics(metrics.to_vec()),
+                request => match handler(state, request) {
+                    Dispatch::Reply(response) => response,
+                    Dispatch::Observe { limit, reply } => {
+                        self.observing = Some(limit);
+                        reply
+                    }
+                    Dispatch::Shutdown => return Ok(Polled::Shutdown),
+                },
+            };
+            let result = self.send(&response);
+            counters.requests += 1;
+            if matches!(response, Response::Error(_)) {
+                counters.errors += 1;
+            }
+            counters.busy += start.elapsed();
+            result?;
+        }
+        if closed {
+            Ok(Polled::Disconnected)
+        } else {
+            Ok(polled)
+        }
+    }
+
+    // Reads whatever has arrived. Returns true if the peer closed the connection.
+    fn receive(&mut self) -> Result<bool, Box<dyn Error>> {
+        let mut bytes = [0u8; 4096];
+        loop {
+            match self.stream.read(&mut bytes) {
+                Ok(0) => return Ok(true),
+                Ok(count) => self.pending.extend_from_slice(&bytes[..count]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(false),
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err) => return Err(Box::new(err)),
+            }
+        }
+    }
+
+    // Returns None if a complete frame hasn't arrived yet.
+    fn next_request(&mut self) -> Result<Option<Request>, Box<dyn Error>> {
+        if self.pending.len() < 4 {
+            return Ok(None);
+        }
+        let len = Cursor::new(&self.pending[..4]).read_u32::<LittleEndian>()? as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(format!("frame length {len} is too large").into());
+        }
+        if self.pending.len() < 4 + len {
+            return Ok(None);
+        }
+
+        let request = postcard::from_bytes(&self.pending[4..4 + len])?;
+        self.pending.drain(..4 + len);
+        Ok(Some(request))
+    }
+
+    // Responses are small so it's simpler to block while they are written. Peers that
+    // stop reading time out (see SEND_TIMEOUT) and are dropped.
+    fn send(&mut self, response: &Response) -> Result<(), Box<dyn Error>> {
+        self.stream.set_nonblocking(false)?;
+        let result = write_frame(&mut self.stream, response);
+        self.stream.set_nonblocking(true)?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{set_data_dir, test_game};
+    use super::*;
+    use std::fs;
+
+    /// Runs a server in a thread with its own saved game (in the temp dir) so that tests
+    /// can drive it end to end with real clients.
+    struct Harness {
+        addr: SocketAddr,
+        control_addr: SocketAddr,
+        path: String,
+        server: Option<thread::JoinHandle<u64>>, // returns the number of actions
+    }
+
+    impl Harness {
+        // Name has to be unique across tests since they run in parallel.
+        fn start(name: &str) -> Harness {
+            let path = std::env::temp_dir().join(format!("1k-deaths-{name}-{}.game", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+            Harness::remove_files(&path);
+
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let control = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let control_addr = control.local_addr().unwrap();
+            let game_path = path.clone();
+            let server = thread::spawn(move || {
+                let mut lock = SaveLock::acquire(&game_path).unwrap();
+                let mut game = Game::new_game(&game_path, 1);
+                serve(&mut game, &mut lock, listener, "secret", Some((control, "wizard"))).unwrap();
+                game.num_actions
+            });
+            Harness {
+                addr,
+                control_addr,
+                path,
+                server: Some(server),
+            }
+        }
+
+        fn client(&self) -> Client {
+            Client::connect(self.addr, "secret").unwrap()
+        }
+
+        fn control(&self) -> Client {
+            Client::connect(self.control_addr, "wizard").unwrap()
+        }
+
+        /// Does each action and returns the resulting state.
+        fn play(&self, actions: &[Action]) -> Snapshot {
+            let mut client = self.client();
+            for action in actions {
+                client.act(action.clone()).unwrap();
+            }
+            client.snapshot(100).unwrap()
+        }
+
+        /// Shuts the server down and returns the number of actions in the game.
+        fn stop(mut self) -> u64 {
+            self.control().shutdown().unwrap();
+            self.server.take().unwrap().join().unwrap()
+        }
+
+        fn remove_files(path: &str) {
+            let _ = fs::remove_file(path);
+            let _ = fs::remove_file(SaveLock::lock_path(path));
+        }
+    }
+
+    impl Drop for Harness {
+        fn drop(&mut self) {
+            if let Some(server) = self.server.take() {
+                if let Ok(control) = Client::connect(self.control_addr, "wizard") {
+                    let _ = control.shutdown();
+                }
+                let _ = server.join();
+            }
+            Harness::remove_files(&self.path);
+        }
+    }
+
+    #[test]
+    fn test_harness() {
+        let harness = Harness::start("harness");
+        let (mut observer, _) = Observer::connect(harness.addr, "secret", 100).unwrap();
+
+        // Scripts are played on their own connection.
+        let start = harness.play(&[]);
+        let end = harness.play(&[
+            Action::Move { dx: 1, dy: 0 },
+            Action::Rest,
+            Action::Move { dx: -1, dy: 0 },
+        ]);
+        assert!(end.num_actions >= start.num_actions + 3);
+        assert_eq!(end.player_hps, start.player_hps);
+        assert!(end.num_messages >= start.num_messages);
+
+        // Observers and control clients see the same game.
+        let mut seen = 0;
+        observer
+            .watch(|snapshot| {
+                seen = snapshot.num_actions;
+                seen < end.num_actions
+            })
+            .unwrap();
+        assert_eq!(seen, end.num_actions);
+        assert_eq!(harness.control().snapshot(100).unwrap(), end);
+
+        assert_eq!(harness.stop(), end.num_actions);
+    }
+
+    #[test]
+    fn test_session() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut lock = SaveLock::acquire(&path).unwrap();
+            let mut game = Game::new_game(&path, 1);
+            serve(&mut game, &mut lock, listener, "secret", None).unwrap();
+            game.num_actions
+        });
+
+        let err = Client::connect(addr, "wrong").err().unwrap();
+        assert!(err.to_string().contains("denied"));
+
+        // Disconnecting should allow another client to connect.
+        let mut client = Client::connect(addr, "secret").unwrap();
+        client.act(Action::Rest).unwrap();
+        drop(client);
+
+        let mut client = Client::connect(addr, "secret").unwrap();
+        let start = client.query(Query::PlayerLoc).unwrap();
+        client.act(Action::Move { dx: 1, dy: 0 }).unwrap();
+        assert!(matches!(client.query(Query::PlayerLoc).unwrap(), Response::Loc(_)));
+        assert!(matches!(start, Response::Loc(_)));
+        assert!(client.act(Action::Object).is_err());
+
+        // Free actions aren't recorded.
+        let before = match client.query(Query::Snapshot(0)).unwrap() {
+            Response::Snapshot(snapshot) => snapshot,
+            response => panic!("expected a snapshot but got {response:?}"),
+        };
+        let examine = Action::Examine {
+            loc: before.player_loc,
+            wizard: false,
+        };
+        client.act(examine).unwrap();
+        match client.query(Query::Snapshot(0)).unwrap() {
+            Response::Snapshot(snapshot) => {
+                assert_eq!(snapshot.num_actions, before.num_actions);
+                assert!(snapshot.num_messages > before.num_messages);
+            }
+            response => panic!("expected a snapshot but got {response:?}"),
+        }
+
+        // Bad actions are errors rather than server panics.
+        assert!(client.act(Action::Move { dx: 5, dy: 0 }).is_err());
+        assert!(client.act(Action::Shove { dx: 0, dy: 0 }).is_err());
+        assert!(client.act(Action::WizardMode).is_err());
+        assert!(client.act(Action::Teleport { loc: Point::new(1, 1) }).is_err());
+        assert!(matches!(client.query(Query::PlayerLoc).unwrap(), Response::Loc(_)));
+
+        // Messages keep their topics so that frontends can color them.
+        match client.query(Query::RecentMessages(100)).unwrap() {
+            Response::Messages(messages) => assert!(messages.iter().any(|m| m.topic == Topic::Important)),
+            response => panic!("expected messages but got {response:?}"),
+        }
+        match client.query(Query::Topics).unwrap() {
+            Response::Topics(topics) => assert_eq!(topics.len(), Topic::ALL.len()),
+            response => panic!("expected topics but got {response:?}"),
+        }
+        client.shutdown().unwrap();
+
+        let num_actions = server.join().unwrap();
+        assert!(num_actions >= 2);
+    }
+
+    #[test]
+    fn test_resume() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut lock = SaveLock::acquire(&path).unwrap();
+            let mut game = Game::new_game(&path, 1);
+            serve(&mut game, &mut lock, listener, "secret", None).unwrap();
+        });
+
+        let (mut client, snapshot) = Client::resume(addr, "secret", 10).unwrap();
+        assert_eq!(snapshot.num_actions, 0);
+        assert!(!snapshot.messages.is_empty());
+        client.act(Action::Rest).unwrap();
+        client.act(Action::Rest).unwrap();
+        drop(client);
+
+        // Re-connecting should pick up the game in progress instead of starting over.
+        let (client, resumed) = Client::resume(addr, "secret", 10).unwrap();
+        assert!(resumed.num_actions >= 2);
+        assert!(resumed.num_messages >= snapshot.num_messages);
+        client.shutdown().unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_control() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let control_addr = control.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut lock = SaveLock::acquire(&path).unwrap();
+            let mut game = Game::new_game(&path, 1);
+            serve(&mut game, &mut lock, listener, "secret", Some((control, "wizard"))).unwrap();
+        });
+
+        // Both channels can be used at the same time.
+        let mut client = Client::connect(addr, "secret").unwrap();
+        client.act(Action::Rest).unwrap();
+        assert!(Client::connect(control_addr, "secret").is_err());
+        let mut wizard = Client::connect(control_addr, "wizard").unwrap();
+        let loc = wizard.query(Query::PlayerLoc).unwrap();
+        assert_eq!(client.query(Query::PlayerLoc).unwrap(), loc);
+
+        // But control clients can't act.
+        assert!(wizard.act(Action::Rest).is_err());
+        client.act(Action::Rest).unwrap();
+
+        // Both channels are included in the metrics.
+        match wizard.query(Query::Metrics).unwrap() {
+            Response::Metrics(metrics) => {
+                assert_eq!(metrics.len(), 2);
+                assert_eq!(metrics[0].addr, addr);
+                assert_eq!(
+                    (metrics[0].connections, metrics[0].requests, metrics[0].errors),
+                    (1, 3, 0)
+                );
+                assert_eq!(metrics[1].addr, control_addr);
+                assert_eq!(
+                    (metrics[1].connections, metrics[1].requests, metrics[1].errors),
+                    (1, 2, 1)
+                );
+                assert_eq!(metrics[1].queued, 0);
+            }
+            response => panic!("expected metrics but got {response:?}"),
+        }
+
+        // Nor can they save or load games.
+        assert!(wizard.save_game("wizard.game").is_err());
+        assert!(wizard.load_game("wizard.game", 0).is_err());
+
+        wizard.shutdown().unwrap();
+        server.join().unwrap();
+        drop(client);
+    }
+
+    #[test]
+    fn test_client() {
+        let harness = Harness::start("client");
+        let policy = Policy {
+            timeout: Duration::from_secs(2),
+            retries: 1,
+        };
+        let mut client = Client::connect_with(harness.addr, "secret", policy).unwrap();
+        let loc = client.player_loc().unwrap();
+        assert!(client.player_hps().unwrap().0 > 0);
+        assert_eq!(client.topics().unwrap().len(), Topic::ALL.len());
+
+        // Queries survive a dropped connection but actions don't.
+        client.stream.shutdown(std::net::Shutdown::Both).unwrap();
+        assert_eq!(client.player_loc().unwrap(), loc);
+        client.stream.shutdown(std::net::Shutdown::Both).unwrap();
+        assert!(client.act(Action::Rest).is_err());
+        assert!(harness.play(&[Action::Rest]).num_actions > 0);
+    }
+
+    #[test]
+    fn test_save_lock() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let moved = format!("/tmp/saved-{}.game", line!());
+        let lock_path = SaveLock::lock_path(&path);
+
+        // Locks from servers that crashed are reclaimed.
+        let stale = SystemTime::now().duration_since(UNIX_EPOCH).unwrap() - 2 * STALE_LOCK;
+        fs::write(&lock_path, format!("{}\n", stale.as_secs())).unwrap();
+        assert!(!SaveLock::held(&path));
+        let mut lock = SaveLock::acquire(&path).unwrap();
+
+        // But live locks are not.
+        assert!(SaveLock::held(&path));
+        assert!(SaveLock::acquire(&path).is_err());
+
+        // Locks follow the game the server is using.
+        lock.refresh(&moved).unwrap();
+        assert!(!SaveLock::held(&path));
+        assert!(SaveLock::held(&moved));
+
+        drop(lock);
+        assert!(!SaveLock::held(&moved));
+    }
+
+    #[test]
+    fn test_observe() {
+        let harness = Harness::start("observe");
+        let (mut observer, start) = Observer::connect(harness.addr, "secret", 5).unwrap();
+        assert!(Observer::connect(harness.addr, "wrong", 5).is_err());
+        assert_eq!(start.num_actions, 0);
+
+        // Observers see the changes made by other clients.
+        let mut client = harness.client();
+        client.act(Action::Move { dx: 1, dy: 0 }).unwrap();
+        let update = observer.next_update().unwrap();
+        assert!(update.num_actions > start.num_actions);
+        assert!(update.messages.len() <= 5);
+        assert_eq!(client.player_loc().unwrap(), update.player_loc);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let data_dir = std::env::temp_dir().join(format!("1k-deaths-saves-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_dir_all(&data_dir);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let dir = data_dir.clone();
+        let server = thread::spawn(move || {
+            set_data_dir(dir.to_str()).unwrap();
+            let mut lock = SaveLock::acquire(&path).unwrap();
+            let mut game = Game::new_game(&path, 1);
+            serve(&mut game, &mut lock, listener, "secret", None).unwrap();
+        });
+
+        let mut client = Client::connect(addr, "secret").unwrap();
+        client.act(Action::Rest).unwrap();
+        client.act(Action::Rest).unwrap();
+        let saved = match client.query(Query::Snapshot(0)).unwrap() {
+            Response::Snapshot(snapshot) => snapshot,
+            response => panic!("expected a snapshot but got {response:?}"),
+        };
+        client.save_game("copy.game").unwrap();
+        assert!(data_dir.join(SAVES_DIR).join("copy.game").is_file());
+
+        // Loading should discard everything done after the save.
+        for _ in 0..5 {
+            client.act(Action::Rest).unwrap();
+        }
+        let loaded = client.load_game("copy.game", 0).unwrap();
+        assert_eq!(loaded.num_actions, saved.num_actions);
+        assert_eq!(loaded.player_loc, saved.player_loc);
+
+        // Bad names are reported and leave the game alone.
+        assert!(client.load_game("no-such-saved.game", 0).is_err());
+        assert!(client.load_game("/tmp/copy.game", 0).is_err());
+        assert!(client.save_game("../copy.game").is_err());
+        assert!(client.save_game("..").is_err());
+        assert!(!data_dir.join("copy.game").exists());
+        client.act(Action::Rest).unwrap();
+
+        client.shutdown().unwrap();
+        server.join().unwrap();
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_message_limits() {
+        let mut game = test_game();
+        for i in 0..200 {
+            let text = format!("{i} {}", "x".repeat(1000));
+            game.messages.push(Message::new(Topic::Normal, &text));
+        }
+
+        // Clients can ask for more messages than will fit into a frame.
+        let snapshot = snapshot(&game, usize::MAX);
+        let bytes = postcard::to_stdvec(&Response::Snapshot(snapshot.clone())).unwrap();
+        assert!(bytes.len() <= MAX_FRAME_LEN);
+        assert!(snapshot.messages.len() < 200);
+        assert!(snapshot.messages.last().unwrap().text.starts_with("199 "));
+    }
+}
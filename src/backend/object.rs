@@ -1,31 +1,70 @@
+use super::sound::Sound;
 use super::tag::*;
-use super::{Color, Material, Message, Oid, Tag, Time, Topic};
+use super::time;
+use super::{catalog, Color, Material, Message, Oid, Tag, Time, Topic};
 use enum_map::EnumMap;
 #[cfg(debug_assertions)]
 use fnv::FnvHashSet;
 use std::fmt::{self, Formatter};
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Serialize)]
 pub enum Symbol {
     Armor,
+    Bell,
     ClosedDoor,
     DeepLiquid,
     Dirt,
+    Key,
+    Lever,
     Npc(char),
     OpenDoor,
     PickAxe,
     Player,
+    PressurePlate,
+    Rock,
     Rubble,
     ShallowLiquid,
     Sign,
+    Spear,
     StrongSword,
     Tree,
     Unseen,
     Wall,
     WeakSword,
+    Light,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+impl Symbol {
+    /// Plain ASCII version of the symbol, used for observations and checkpoints.
+    pub fn to_ascii(self) -> char {
+        use Symbol::*;
+        match self {
+            Armor => '[',
+            Bell => '!',
+            ClosedDoor => '+',
+            DeepLiquid => 'W',
+            Dirt => '.',
+            Key => '-',
+            Lever => '/',
+            Light => '%',
+            Npc(ch) => ch,
+            OpenDoor => ':',
+            PickAxe => '(',
+            Player => '@',
+            PressurePlate => '^',
+            Rock => '*',
+            Rubble => ',',
+            ShallowLiquid => '~',
+            Sign => '?',
+            Spear | StrongSword | WeakSword => ')',
+            Tree => 'T',
+            Unseen => ' ',
+            Wall => '#',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum ObjectName {
     // Armor
     LeatherChest,
@@ -73,6 +112,24 @@ pub enum ObjectName {
     LongKnife,
     LongSword,
     MightySword,
+    Spear,
+
+    // Noisemakers
+    Bell,
+    Rock,
+
+    // Mechanisms
+    Lever,
+    PressurePlate,
+
+    // Keys and locks
+    IronKey,
+    LockedDoor,
+
+    // Lights
+    Torch,
+    Lantern,
+    OilFlask,
 }
 
 // TODO: Should define a custom Clone for Object (and probably Tag) because stuff like
@@ -91,6 +148,7 @@ pub struct Object {
     symbol: Symbol,
     color: Color,
 
+    // Catalog key for the description, e.g. "object.dirt".
     // If we ever need a dynamic string we can continue to optimize for the common case
     // by using a special static str to cause a DyanmicDesc tag to be used instead (or
     // maybe just use that tag if it's present).
@@ -108,18 +166,26 @@ impl Object {
         }
     }
 
+    pub fn object_name(&self) -> ObjectName {
+        self.name
+    }
+
     pub fn dname(&self) -> String {
         format!("{:?}", self.name)
     }
 
-    pub fn description(&self) -> &'static str {
-        &self.description
+    pub fn description(&self) -> String {
+        catalog::text(self.description, &[])
     }
 
     pub fn iter(&self) -> std::slice::Iter<Tag> {
         self.tags.iter()
     }
 
+    pub fn shrink_to_fit(&mut self) {
+        self.tags.shrink_to_fit();
+    }
+
     pub fn replace(&mut self, tag: Tag) {
         let id = tag.to_id();
         let index = self.tags.iter().position(|candidate| candidate.to_id() == id).unwrap();
@@ -130,6 +196,49 @@ impl Object {
         }
     }
 
+    /// Like replace except that the tag is added if it isn't already present.
+    pub fn set(&mut self, tag: Tag) {
+        let id = tag.to_id();
+        if let Some(index) = self.tags.iter().position(|candidate| candidate.to_id() == id) {
+            self.tags[index] = tag;
+        } else {
+            let index = self.character_index();
+            self.tags.insert(index, tag);
+        }
+
+        if cfg!(debug_assertions) {
+            self.invariant();
+        }
+    }
+
+    pub fn remove(&mut self, tid: Tid) {
+        self.tags.retain(|candidate| candidate.to_id() != tid);
+    }
+
+    /// Replaces the tags in tids, and the appearance, with those from form. Other tags
+    /// are left as is. This is used by polymorph so that things like inventory are kept.
+    pub fn take_form(&mut self, form: &Object, tids: &[Tid]) {
+        self.tags.retain(|candidate| !tids.contains(&candidate.to_id()));
+        let index = self.character_index();
+        let tags = form.tags.iter().filter(|tag| tids.contains(&tag.to_id())).cloned();
+        self.tags.splice(index..index, tags);
+        self.symbol = form.symbol;
+        self.color = form.color;
+        self.description = form.description;
+
+        if cfg!(debug_assertions) {
+            self.invariant();
+        }
+    }
+
+    // Character has to be the last tag so new tags go before it.
+    fn character_index(&self) -> usize {
+        match self.tags.last() {
+            Some(tag) if tag.to_id() == CHARACTER_ID => self.tags.len() - 1,
+            _ => self.tags.len(),
+        }
+    }
+
     // We use this instead of as_mut_ref to make it easier to call the invariant.
     // pub fn pick_up(&mut self, item: Object) {
     //     let inv = self.as_mut_ref(INVENTORY_ID).unwrap();
@@ -165,30 +274,58 @@ impl Object {
 
     pub fn impassible_terrain(&self, obj: &Object) -> Option<Message> {
         let terrain = obj.terrain_value().unwrap();
-        obj.impassible_terrain_type(terrain)
+        self.impassible_terrain_type(terrain)
     }
 
     pub fn impassible_terrain_type(&self, terrain: Terrain) -> Option<Message> {
+        match (self.size(), terrain) {
+            (BodySize::Aquatic, Terrain::DeepWater | Terrain::ShallowWater) => return None,
+            (BodySize::Aquatic, _) => return Some(failed("impassible.leave_water")),
+            (BodySize::Large, Terrain::ClosedDoor | Terrain::OpenDoor) => return Some(failed("impassible.too_big")),
+            _ => (),
+        }
         match terrain {
-            Terrain::ClosedDoor if !self.has(CAN_OPEN_DOOR_ID) => {
-                Some(Message::new(Topic::Failed, "You fail to open the door."))
-            }
+            Terrain::ClosedDoor if !self.has(CAN_OPEN_DOOR_ID) => Some(failed("impassible.door")),
             Terrain::ClosedDoor => None,
-            Terrain::DeepWater => Some(Message::new(Topic::Failed, "The water is too deep.")),
+            Terrain::DeepWater => Some(failed("impassible.deep_water")),
             Terrain::Ground => None,
             Terrain::OpenDoor => None,
             Terrain::Rubble => None,
             Terrain::ShallowWater => None,
-            Terrain::Tree => Some(Message::new(
-                Topic::Failed,
-                "The tree's are too thick to travel through.",
-            )),
-            Terrain::Vitr => Some(Message::new(Topic::Failed, "Do you have a death wish?")),
-            Terrain::Wall => Some(Message::new(Topic::Failed, "You bump into the wall.")),
+            Terrain::Tree => Some(failed("impassible.tree")),
+            Terrain::Vitr => Some(failed("impassible.vitr")),
+            Terrain::Wall => Some(failed("impassible.wall")),
         }
     }
 }
 
+fn failed(key: &str) -> Message {
+    Message::new(Topic::Failed, &catalog::text(key, &[]))
+}
+
+// Size support
+impl Object {
+    pub fn size(&self) -> BodySize {
+        self.size_value().unwrap_or(BodySize::Medium)
+    }
+
+    /// Extra time it takes the character to move into terrain.
+    pub fn terrain_delay(&self, terrain: Terrain) -> Time {
+        match (terrain, self.size()) {
+            (Terrain::Rubble, BodySize::Large) => time::MOVE_THRU_SHALLOW_WATER,
+            (Terrain::Rubble, _) => time::MOVE_THRU_SHALLOW_WATER * 2,
+            (Terrain::ShallowWater, BodySize::Small) => time::MOVE_THRU_SHALLOW_WATER * 2,
+            (Terrain::ShallowWater, BodySize::Medium) => time::MOVE_THRU_SHALLOW_WATER,
+            _ => Time::zero(),
+        }
+    }
+
+    /// Large characters can't be shoved, knocked back, or made to swap places.
+    pub fn can_be_displaced(&self) -> bool {
+        self.size() != BodySize::Large
+    }
+}
+
 // Debug support
 impl Object {
     #[cfg(debug_assertions)]
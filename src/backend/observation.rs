@@ -0,0 +1,297 @@
+//! A stable machine-readable view of what the player can see. This is intended for
+//! external programs (e.g. AI agents, tutorials, or test scripts written in other languages)
+//! that want to drive the game without linking to Rust. Observations are written as one
+//! line of JSON each turn and actions are read back as JSON using Action's serde format,
+//! e.g. `{"Move": {"dx": 1, "dy": 0}}` or `"Rest"`. Bad actions are answered with a
+//! `{"error":"..."}` line followed by a fresh observation.
+//!
+//! The grid uses ASCII so that it's easy to parse:
+//! ```text
+//!    @ player           # wall            . dirt             + closed door
+//!    : open door        ~ shallow water   W deep water       T tree
+//!    , rubble           [ armor           ) weapon           ( pick-axe
+//!    - key              * rock            ? sign             / lever
+//!    ^ pressure plate   ! bell            % light            space not visible
+//!    letters are NPCs
+//! ```
+use super::network;
+use super::pov;
+use super::{Action, Game, Point, State, Tile};
+use serde::Serialize;
+use std::error::Error;
+use std::io::{BufRead, Write};
+
+/// Incremented when fields are removed or their meaning changes (adding fields doesn't
+/// change the version).
+pub const OBSERVATION_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct Observation {
+    pub version: u32,
+
+    /// Game time in milliseconds.
+    pub time: i64,
+    pub state: State,
+    pub game_over: bool,
+    pub player: PlayerObservation,
+
+    /// Location of the first character in the first row of grid.
+    pub origin: Point,
+
+    /// Rows of the visible cells centered on the player.
+    pub grid: Vec<String>,
+
+    /// Visible NPCs sorted by distance from the player.
+    pub npcs: Vec<NpcObservation>,
+
+    /// Messages added since the last observation.
+    pub messages: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerObservation {
+    pub loc: Point,
+    pub hps: i32,
+    pub max_hps: i32,
+
+    /// Temporary effects on the player, e.g. "Frightened" or "Hasted".
+    pub statuses: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NpcObservation {
+    pub loc: Point,
+    pub name: String,
+    pub disposition: String,
+
+    /// Current and max where max is in [1, 10], like Npc::observed_hps.
+    pub hps: (i32, i32),
+    pub sleeping: bool,
+}
+
+impl Observation {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("observations should always be serializable")
+    }
+}
+
+impl Game {
+    /// seen_messages should be the num_messages value from the last call (or zero). It's
+    /// updated so that only new messages are included in the next observation.
+    pub fn observe(&self, seen_messages: &mut usize) -> Observation {
+        let player = self.player_loc();
+        let (hps, max_hps) = self.player_hps();
+
+        let origin = Point::new(player.x - pov::RADIUS, player.y - pov::RADIUS);
+        let size = 2 * pov::RADIUS + 1;
+        let grid = (0..size)
+            .map(|dy| {
+                (0..size)
+                    .map(|dx| match self.tile(&Point::new(origin.x + dx, origin.y + dy)) {
+                        Tile::Visible { symbol, .. } => symbol.to_ascii(),
+                        _ => ' ',
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let npcs = self
+            .level
+            .npcs()
+            .map(|oid| self.level.obj(oid).1.unwrap())
+            .take_while(|loc| loc.distance2(&player) <= pov::RADIUS * pov::RADIUS)
+            .filter(|loc| self.pov.visible(self, loc))
+            .map(|loc| {
+                let npc = self.to_npc(&loc, false);
+                NpcObservation {
+                    loc,
+                    name: npc.name.to_string(),
+                    disposition: npc.disposition.to_string(),
+                    hps: npc.observed_hps,
+                    sleeping: npc.is_sleeping,
+                }
+            })
+            .collect();
+
+        let total = self.num_messages();
+        let count = total.saturating_sub(*seen_messages);
+        let messages = self.recent_messages(count).map(|m| m.text.clone()).collect();
+        *seen_messages = total;
+
+        Observation {
+            version: OBSERVATION_VERSION,
+            time: self.scheduler.now().as_ms(),
+            state: self.state,
+            game_over: self.game_over(),
+            player: PlayerObservation {
+                loc: player,
+                hps,
+                max_hps,
+                statuses: self.player_statuses().iter().map(|s| s.to_string()).collect(),
+            },
+            origin,
+            grid,
+            npcs,
+            messages,
+        }
+    }
+}
+
+/// Writes an observation each turn to writer and reads actions from reader until reader
+/// is closed or the game is over.
+pub fn observe_session<R: BufRead, W: Write>(game: &mut Game, reader: R, mut writer: W) -> Result<(), Box<dyn Error>> {
+    let mut seen_messages = 0;
+    let mut lines = reader.lines();
+    loop {
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+        writeln!(writer, "{}", game.observe(&mut seen_messages).to_json())?;
+        writer.flush()?;
+        if game.game_over() {
+            break;
+        }
+
+        let line = match lines.find(|line| !line.as_ref().is_ok_and(|l| l.trim().is_empty())) {
+            Some(line) => line?,
+            None => break,
+        };
+        match parse_action(game, &line) {
+            Ok(action) if action.is_free() => game.free_action(action),
+            Ok(action) => game.player_acted(action),
+            Err(err) => writeln!(writer, "{}", serde_json::to_string(&ObserveError { error: err })?)?,
+        }
+    }
+    game.save_actions();
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ObserveError {
+    error: String,
+}
+
+/// Parses an action written by an external program. Actions that only make sense
+/// internally (or in wizard mode) are rejected, as are bad move deltas and actions that
+/// use items the player isn't carrying.
+pub fn parse_action(game: &Game, text: &str) -> Result<Action, String> {
+    let action: Action = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    network::check_action(game, &action)?;
+    Ok(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::statuses::Status;
+    use crate::backend::tag::*;
+    use crate::backend::{new_obj, test_game, time, ObjectName, Oid};
+    use crate::ItemKind;
+    use std::fs;
+
+    #[test]
+    fn test_observe() {
+        let path = format!("/tmp/observe-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+
+        let mut seen = 0;
+        let obs = game.observe(&mut seen);
+        let size = (2 * pov::RADIUS + 1) as usize;
+        assert_eq!(obs.grid.len(), size);
+        assert!(obs.grid.iter().all(|row| row.chars().count() == size));
+        assert_eq!(obs.grid[size / 2].chars().nth(size / 2), Some('@'));
+        assert_eq!(obs.player.loc, game.player_loc());
+        assert!(obs.player.statuses.is_empty());
+        assert!(!obs.messages.is_empty()); // the intro messages
+        assert_eq!(seen, game.num_messages());
+        let num_intro = obs.messages.len();
+
+        let text = obs.to_json();
+        assert!(!text.contains('\n'));
+        assert!(text.starts_with("{\"version\":1,\"time\":"));
+
+        // Only new messages are included.
+        game.free_action(Action::Examine {
+            loc: game.player_loc(),
+            wizard: false,
+        });
+        let obs = game.observe(&mut seen);
+        assert!(obs.messages[0].starts_with("You see"));
+        assert_eq!(obs.messages.len(), game.recent_messages(100).count() - num_intro);
+
+        game.add_status(Oid(0), Status::Hasted, time::HASTED);
+        let obs = game.observe(&mut seen);
+        assert_eq!(obs.player.statuses, vec!["Hasted".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_action() {
+        let game = test_game();
+        assert_eq!(parse_action(&game, "\"Rest\""), Ok(Action::Rest));
+        assert_eq!(
+            parse_action(&game, "{\"Move\": {\"dx\": 1, \"dy\": -1}}"),
+            Ok(Action::Move { dx: 1, dy: -1 })
+        );
+        assert!(parse_action(&game, "{\"Move\": {\"dx\": 2, \"dy\": 0}}").is_err());
+        assert!(parse_action(&game, "{\"Shove\": {\"dx\": 0, \"dy\": 0}}").is_err());
+        assert!(parse_action(&game, "\"Object\"").is_err());
+        assert!(parse_action(&game, "\"WizardMode\"").is_err());
+        assert!(parse_action(&game, "\"Dance\"").is_err());
+
+        let loc = game.player_loc();
+        let examine = format!(
+            "{{\"Examine\": {{\"loc\": {{\"x\": {}, \"y\": {}}}, \"wizard\": true}}}}",
+            loc.x, loc.y
+        );
+        assert!(parse_action(&game, &examine).is_err());
+    }
+
+    #[test]
+    fn test_parse_item_actions() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        for name in [ObjectName::LeatherChest, ObjectName::LongSword, ObjectName::Dagger] {
+            let oid = game.level.add(new_obj(name), None);
+            let player = game.level.get_mut(&player_loc, CHARACTER_ID).unwrap().1;
+            player.inventory_value_mut().unwrap().push(oid);
+        }
+        assert!(parse_action(&game, "{\"Drop\": 999999}").is_err());
+        assert!(parse_action(&game, "{\"Call\": {\"oid\": 999999, \"name\": \"x\"}}").is_err());
+
+        // Items have to be the right kind for the action.
+        for item in game.inventory() {
+            let oid = serde_json::to_string(&item.oid).unwrap();
+            let wear = parse_action(&game, &format!("{{\"Wear\": {oid}}}"));
+            assert_eq!(wear.is_ok(), item.kind == ItemKind::Armor, "{}", item.name);
+            let wield = parse_action(&game, &format!("{{\"WieldOffHand\": {oid}}}"));
+            assert_eq!(wield.is_ok(), item.kind == ItemKind::OneHandWeapon, "{}", item.name);
+            assert!(parse_action(&game, &format!("{{\"Drop\": {oid}}}")).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_observe_loop() {
+        let path = format!("/tmp/observe-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        let input = "\"Rest\"\n\n{\"Move\": {\"dx\": 5, \"dy\": 0}}\n\"Rest\"\n";
+        let mut output = Vec::new();
+        observe_session(&mut game, input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 5); // initial, rest, error, bad move, rest
+        assert!(lines[2].starts_with("{\"error\":"));
+        assert!(lines
+            .iter()
+            .filter(|l| !l.contains("error"))
+            .all(|l| l.contains("\"grid\"")));
+        assert_eq!(game.unsaved_actions().len(), 0); // saved when reader was closed
+    }
+}
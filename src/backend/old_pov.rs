@@ -1,13 +1,15 @@
-use super::{Game, Point, Symbol};
+use super::time::{self, Time};
+use super::{persistence, Conduct, Game, Point, Symbol};
 use fnv::FnvHashMap;
 
 /// Locations that were visible to a character. Note that PoV overrides
 /// this so, as an optimization, this may include locations that are actually
 /// visible. Current;y this is only used for the Player to render locations
-/// that he has seen before.
+/// that he has seen before. With the fog of memory challenge locations that haven't
+/// been seen for a long time first fade and are then forgotten.
 pub struct OldPoV {
-    old: FnvHashMap<Point, Symbol>, // may not match the current Level state
-    edition: u32,                   // current PoV edition
+    old: FnvHashMap<Point, (Symbol, Time)>, // may not match the current Level state, time is when it was last seen
+    edition: u32,                           // current PoV edition
 }
 
 impl OldPoV {
@@ -21,16 +23,129 @@ impl OldPoV {
     // This can't be an ordinary method or we run into all sorts of borrowing grief.
     pub fn update(game: &mut Game) {
         if game.pov.edition() != game.old_pov.edition {
+            let now = game.now();
             for loc in game.pov.locations() {
                 let (_, obj) = game.level.get_top(loc);
                 let (_, symbol) = obj.to_fg_symbol();
-                game.old_pov.old.insert(*loc, symbol);
+                game.old_pov.old.insert(*loc, (symbol, now));
+            }
+            if game.challenges().contains(&Conduct::FogOfMemory) {
+                game.old_pov.old.retain(|_, (_, seen)| now - *seen < time::MEMORY_DECAY);
             }
             game.old_pov.edition = game.pov.edition();
         }
     }
 
     pub fn get(&self, loc: &Point) -> Option<&Symbol> {
-        self.old.get(loc)
+        self.old.get(loc).map(|(symbol, _)| symbol)
+    }
+
+    /// Returns how long it has been since loc was last seen.
+    pub fn age(&self, loc: &Point, now: Time) -> Option<Time> {
+        self.old.get(loc).map(|(_, seen)| now - *seen)
+    }
+
+    /// Returns the top-left and bottom-right locations the player has seen.
+    pub fn extents(&self) -> Option<(Point, Point)> {
+        let mut locs = self.old.keys();
+        let first = *locs.next()?;
+        let (mut top_left, mut bottom_right) = (first, first);
+        for loc in locs {
+            top_left = Point::new(top_left.x.min(loc.x), top_left.y.min(loc.y));
+            bottom_right = Point::new(bottom_right.x.max(loc.x), bottom_right.y.max(loc.y));
+        }
+        Some((top_left, bottom_right))
+    }
+
+    pub fn audit(&self, report: &mut Vec<String>) {
+        report.push(format!(
+            "old pov: {} (capacity {}, {} bytes)",
+            self.old.len(),
+            self.old.capacity(),
+            persistence::encoded_size(&self.old)
+        ));
+    }
+
+    pub fn compact(&mut self) {
+        self.old.shrink_to_fit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ai::{self, Acted};
+    use crate::backend::make::new_obj;
+    use crate::backend::tag::{Terrain, DOORMAN_ID};
+    use crate::backend::test_game;
+    use crate::backend::{ObjectName, PoV, Tile};
+    use std::fs;
+
+    #[test]
+    fn test_fog_of_memory() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        let far = Point::new(game.player_loc().x + 200, game.player_loc().y + 200);
+        let symbol = Symbol::Wall;
+        let now = game.now();
+
+        // Without the challenge memories never fade.
+        game.old_pov.old.insert(far, (symbol, now - time::MEMORY_DECAY * 2));
+        assert!(game.tile(&far) == Tile::Stale(symbol));
+
+        game.add_challenge(Conduct::FogOfMemory);
+        let now = game.now();
+        game.old_pov.old.insert(far, (symbol, now - time::MEMORY_DECAY / 4));
+        assert!(game.tile(&far) == Tile::Stale(symbol));
+
+        game.old_pov.old.insert(far, (symbol, now - time::MEMORY_DECAY * 3 / 4));
+        assert!(game.tile(&far) == Tile::Faded(symbol));
+
+        game.old_pov.old.insert(far, (symbol, now - time::MEMORY_DECAY * 2));
+        assert!(game.tile(&far) == Tile::NotVisible);
+
+        // Forgotten locations are dropped the next time the PoV changes.
+        game.old_pov.edition = game.pov.edition().wrapping_add(1);
+        OldPoV::update(&mut game);
+        assert!(game.old_pov.get(&far).is_none());
+    }
+
+    #[test]
+    fn test_stale_doors() {
+        let mut game = test_game();
+        let doorman = game
+            .level
+            .npcs()
+            .find(|&oid| game.level.obj(oid).0.has(DOORMAN_ID))
+            .unwrap();
+        let doorman_loc = game.loc(doorman).unwrap();
+        let door_loc = Point::new(doorman_loc.x + 1, doorman_loc.y);
+        let terrain = |game: &Game| game.level.get_bottom(&door_loc).1.terrain_value().unwrap();
+        assert_eq!(terrain(&game), Terrain::ClosedDoor);
+        assert!(!game.pov.visible(&game, &door_loc));
+
+        // The player remembers the door as closed,
+        let now = game.now();
+        game.old_pov.old.insert(door_loc, (Symbol::ClosedDoor, now));
+
+        // and continues to after a guard opens it out of sight,
+        let guard_loc = Point::new(door_loc.x + 1, door_loc.y);
+        let guard = game.add_object(&guard_loc, new_obj(ObjectName::Guard));
+        game.do_npc_open_door(guard, &door_loc);
+        PoV::refresh(&mut game);
+        assert_eq!(terrain(&game), Terrain::OpenDoor);
+        assert!(game.tile(&door_loc) == Tile::Stale(Symbol::ClosedDoor));
+        assert!(!game.recent_messages(3).any(|m| m.text.contains("opens a door")));
+
+        // and after the Doorman reopens it.
+        game.do_npc_close_door(guard, &door_loc);
+        assert_eq!(terrain(&game), Terrain::ClosedDoor);
+        let acted = ai::acted(&mut game, doorman, time::OPEN_DOOR);
+        assert!(matches!(acted, Acted::Acted(_)));
+        assert_eq!(terrain(&game), Terrain::OpenDoor);
+        PoV::refresh(&mut game);
+        assert!(game.tile(&door_loc) == Tile::Stale(Symbol::ClosedDoor));
     }
 }
@@ -0,0 +1,138 @@
+//! Moving out of a cell next to an enemy gives that enemy a free (but weaker) attack so
+//! positioning matters. The player can withdraw instead, which is slower but safe. NPCs
+//! that back away from the player give the player the same free attack.
+use super::catalog;
+use super::tag::*;
+use super::time::{self, Time};
+use super::{Game, Message, Oid, Point, Topic};
+
+/// Percent of normal damage done by an attack of opportunity.
+const OPPORTUNITY_DAMAGE: i32 = 50;
+
+/// Withdrawing takes this many times longer than a normal move.
+const WITHDRAW_SCALING: i64 = 2;
+
+impl Game {
+    /// Called before the character at old_loc moves to new_loc. Enemies that will no
+    /// longer be adjacent get a free attack. Returns true if the character is still at
+    /// old_loc and able to move (heavy hits can kill it or knock it back).
+    pub(super) fn opportunity_attacks(&mut self, old_loc: &Point, new_loc: &Point) -> bool {
+        let oid = self.level.get(old_loc, CHARACTER_ID).unwrap().0;
+        for attacker_loc in self.disengaged_from(oid, old_loc, new_loc) {
+            if !self.can_still_move(oid, old_loc) {
+                break;
+            }
+
+            let attacker = self.level.get(&attacker_loc, CHARACTER_ID).unwrap().1;
+            let text = if oid.0 == 0 {
+                catalog::text("opportunity.player", &[("name", &attacker.name_value().unwrap())])
+            } else {
+                let name = self.level.obj(oid).0.name_value().unwrap();
+                catalog::text("opportunity.npc", &[("name", &name)])
+            };
+            self.messages.push(Message::new(Topic::Normal, &text));
+            self.do_scaled_melee_attack(&attacker_loc, old_loc, OPPORTUNITY_DAMAGE);
+        }
+        self.can_still_move(oid, old_loc)
+    }
+
+    /// Moves the player without provoking attacks of opportunity.
+    pub(super) fn do_withdraw(&mut self, dx: i32, dy: i32) -> Time {
+        let old_loc = self.player_loc();
+        let new_loc = Point::new(old_loc.x + dx, old_loc.y + dy);
+        if !self.can_teleport_to(Oid(0), &new_loc) {
+            let mesg = Message::new(Topic::Failed, &catalog::text("withdraw.no_room", &[]));
+            self.messages.push(mesg);
+            return Time::zero();
+        }
+
+        self.do_move(Oid(0), &old_loc, &new_loc);
+        let (duration, _) = self.interact_post_move(&new_loc);
+        let taken = if old_loc.diagnol(&new_loc) {
+            time::DIAGNOL_MOVE
+        } else {
+            time::CARDINAL_MOVE
+        };
+        taken * WITHDRAW_SCALING + duration
+    }
+
+    // Returns the locations of the characters that can attack oid when it moves from
+    // old_loc to new_loc. Only awake enemies get attacks: aggressive NPCs fighting the
+    // player or the player when an aggressive NPC moves away.
+    fn disengaged_from(&self, oid: Oid, old_loc: &Point, new_loc: &Point) -> Vec<Point> {
+        let mut attackers = Vec::new();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let loc = Point::new(old_loc.x + dx, old_loc.y + dy);
+                if loc == *old_loc || loc.adjacent(new_loc) {
+                    continue;
+                }
+                if let Some((attacker, _)) = self.level.get(&loc, CHARACTER_ID) {
+                    if self.is_engaged(attacker, oid) {
+                        attackers.push(loc);
+                    }
+                }
+            }
+        }
+        attackers
+    }
+
+    fn can_still_move(&self, oid: Oid, loc: &Point) -> bool {
+        !self.game_over() && self.level.try_loc(oid) == Some(*loc)
+    }
+
+    fn is_engaged(&self, attacker: Oid, defender: Oid) -> bool {
+        let npc = if attacker.0 == 0 {
+            defender
+        } else if defender.0 == 0 {
+            attacker
+        } else {
+            return false;
+        };
+        let npc = self.level.obj(npc).0;
+        let aggressive = matches!(npc.disposition_value(), Some(Disposition::Aggressive));
+        let awake = !matches!(npc.behavior_value(), Some(Behavior::Sleeping));
+        aggressive && awake
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::test_game;
+    use crate::backend::{Action, ObjectName};
+
+    #[test]
+    fn test_opportunity() {
+        let mut game = test_game();
+
+        // Find a spot for an enemy with room to move away from it.
+        let player_loc = game.player_loc();
+        let deltas = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+        let (dx, dy) = *deltas
+            .iter()
+            .find(|(dx, dy)| {
+                let behind = Point::new(player_loc.x - dx, player_loc.y - dy);
+                let away = Point::new(player_loc.x + dx, player_loc.y + dy);
+                game.can_teleport_to(Oid(0), &behind) && game.can_teleport_to(Oid(0), &away)
+            })
+            .unwrap();
+        let enemy_loc = Point::new(player_loc.x - dx, player_loc.y - dy);
+        let away = Point::new(player_loc.x + dx, player_loc.y + dy);
+        game.add_object(&enemy_loc, new_obj(ObjectName::ImrothTheCruel));
+        assert_eq!(game.disengaged_from(Oid(0), &player_loc, &away), vec![enemy_loc]);
+
+        // Withdrawing doesn't give the enemy a free attack.
+        let count = game.num_messages();
+        game.player_acted(Action::Withdraw { dx, dy });
+        assert_eq!(game.player_loc(), away);
+        let count = game.num_messages() - count;
+        assert!(!game.recent_messages(count).any(|m| m.text.contains("as you disengage")));
+
+        // But moving away does.
+        game.do_move(Oid(0), &away, &player_loc);
+        game.player_acted(Action::Move { dx, dy });
+        assert!(game.recent_messages(4).any(|m| m.text.contains("as you disengage")));
+    }
+}
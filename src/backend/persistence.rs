@@ -58,7 +58,9 @@
 //
 // borsh, nachricht, prost, and maybe rkyv are also options but, based on the benchmark
 // link above they are unlikely to be better than postcard.
-use super::Action;
+use super::catalog;
+use super::checksum::Checkpoint;
+use super::{Action, FovAlgorithm};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use postcard::from_bytes;
 use serde::{Deserialize, Serialize};
@@ -67,16 +69,22 @@ use std::error::Error;
 use std::fmt::{self};
 use std::fs::{File, OpenOptions};
 use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
 use std::io::Write;
 use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
 
 #[cfg(test)]
 use super::Point;
 #[cfg(test)]
 use std::fs;
 
+const MAX_PENDING_CHUNKS: usize = 4; // Saver will block if the worker falls this far behind
+
 const MAJOR_VERSION: u8 = 2;
-const MINOR_VERSION: u8 = 0;
+const MINOR_VERSION: u8 = 5; // 1 added checkpoints to chunks, 2 added timestamps and play time, 3 added chunk CRCs, 4 added turn counts, 5 added the FoV algorithm
 
 #[derive(Debug, Clone)]
 pub struct BadVersionError {
@@ -95,6 +103,7 @@ impl fmt::Display for BadVersionError {
 
 impl std::error::Error for BadVersionError {}
 
+/// Note that files before version 2.5 don't have fov (and use the Permissive algorithm).
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
 struct Header {
     app_version: String, // from Cargo.toml
@@ -103,10 +112,21 @@ struct Header {
     date: String,
     os: String,
     seed: u64,
+    fov: FovAlgorithm, // affects what the player sees so replays have to use the same one
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header4 {
+    app_version: String,
+    major_version: u8,
+    minor_version: u8,
+    date: String,
+    os: String,
+    seed: u64,
 }
 
 impl Header {
-    fn new(seed: u64) -> Header {
+    fn new(seed: u64, fov: FovAlgorithm) -> Header {
         let local = chrono::Local::now();
         info!("version: {}", env!("CARGO_PKG_VERSION"));
         Header {
@@ -116,10 +136,123 @@ impl Header {
             date: local.to_rfc2822(),
             os: env::consts::OS.to_string(),
             seed,
+            fov,
         }
     }
 }
 
+/// How long the player has spent playing a game.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlayTime {
+    pub played: u64,   // ms of wall-clock time across all sessions
+    pub sessions: u32, // number of times the game was started or loaded
+}
+
+impl fmt::Display for PlayTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mins = self.played / (60 * 1000);
+        let (hours, mins) = (mins / 60, mins % 60);
+        let suffix = if self.sessions == 1 { "" } else { "s" };
+        if hours > 0 {
+            write!(f, "played {hours}h{mins:02}m over {} session{suffix}", self.sessions)
+        } else {
+            write!(f, "played {mins}m over {} session{suffix}", self.sessions)
+        }
+    }
+}
+
+/// Actions are saved in chunks along with any checkpoints that were recorded while those
+/// actions were executed. Note that version 2.0 files have just the actions and version
+/// 2.1 files don't have timestamp and play_time. Starting with 2.3 each chunk is followed
+/// by a CRC of its bytes so that a chunk that was only partially written (e.g. because the
+/// game was killed while saving) can be detected and dropped. 2.4 added the number of
+/// turns so far so that the turn count can be read from the last chunk.
+#[derive(Serialize)]
+struct Chunk<'a> {
+    actions: &'a [Action],
+    checkpoints: &'a [Checkpoint],
+    timestamp: i64, // ms since the Unix epoch when the chunk was written
+    play_time: PlayTime,
+    turns: u64, // player actions in this chunk and all the chunks before it
+}
+
+#[derive(Serialize)]
+struct Chunk3<'a> {
+    actions: &'a [Action],
+    checkpoints: &'a [Checkpoint],
+    timestamp: i64,
+    play_time: PlayTime,
+}
+
+#[derive(Serialize)]
+struct Chunk1<'a> {
+    actions: &'a [Action],
+    checkpoints: &'a [Checkpoint],
+}
+
+#[derive(Serialize, Deserialize)]
+struct LoadedChunk {
+    actions: Vec<Action>,
+    checkpoints: Vec<Checkpoint>,
+    timestamp: i64,
+    play_time: PlayTime,
+    turns: u64,
+}
+
+#[derive(Deserialize)]
+struct LoadedChunk3 {
+    actions: Vec<Action>,
+    checkpoints: Vec<Checkpoint>,
+    timestamp: i64,
+    play_time: PlayTime,
+}
+
+#[derive(Deserialize)]
+struct LoadedChunk1 {
+    actions: Vec<Action>,
+    checkpoints: Vec<Checkpoint>,
+}
+
+/// The contents of a saved game file.
+#[derive(Debug)]
+pub struct SavedGame {
+    pub seed: u64,
+    pub fov: FovAlgorithm,
+    pub actions: Vec<Action>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub timestamps: Vec<i64>,  // when each chunk was written (empty for old files)
+    pub play_time: PlayTime,   // as of the last chunk
+    pub warnings: Vec<String>, // e.g. the last chunk was torn and had to be dropped
+}
+
+/// The final chunk of a saved game that couldn't be read.
+struct TornChunk {
+    offset: u64, // where the chunk starts (the file is truncated to this when appending)
+    reason: String,
+}
+
+/// What read_chunks found: the header, the good chunks, and a torn final chunk.
+type Chunks = (Header, Vec<LoadedChunk>, Option<TornChunk>);
+
+/// Summary of a saved game, used to allow the player to choose which game to load.
+#[derive(Clone, Debug)]
+pub struct SaveInfo {
+    pub path: String,
+    pub app_version: String, // version of the game that created the file
+    pub version: String,     // version of the saved game format, e.g. "2.2"
+    pub seed: u64,
+    pub date: String, // when the game was started
+    pub play_time: PlayTime,
+    pub turns: usize, // number of player actions
+}
+
+/// Saved game that can be appended to.
+pub struct SaveFile {
+    file: File,
+    minor_version: u8, // chunks are written using the format of the file's version
+    turns: u64,        // player actions written so far
+}
+
 impl fmt::Display for Header {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "version: {} date: {} os: {}", self.app_version, self.date, self.os)
@@ -134,76 +267,492 @@ fn write_len(file: &mut File, len: usize) -> Result<(), Box<dyn Error>> {
 }
 
 fn read_len(file: &mut File) -> Result<usize, Box<dyn Error>> {
+    Ok(read_u32(file)? as usize)
+}
+
+fn read_u32(file: &mut File) -> Result<u32, Box<dyn Error>> {
     let mut bytes = vec![0u8; 4];
     file.read_exact(&mut bytes)?;
     let mut cursor = std::io::Cursor::new(bytes);
-    let len = cursor.read_u32::<LittleEndian>()?;
-    Ok(len as usize)
+    let value = cursor.read_u32::<LittleEndian>()?;
+    Ok(value)
+}
+
+/// Standard CRC-32 (as used by zip and png).
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+// The chunk is written with one call so that it's less likely to be torn.
+fn write_chunk(file: &mut SaveFile, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut buffer = Vec::with_capacity(bytes.len() + 8);
+    buffer.write_u32::<LittleEndian>(bytes.len() as u32)?;
+    buffer.extend_from_slice(bytes);
+    if file.minor_version >= 3 {
+        buffer.write_u32::<LittleEndian>(crc32(bytes))?;
+    }
+    file.file.write_all(&buffer)?;
+    Ok(())
+}
+
+// The fields in Header4 are a prefix of the fields in Header so the version can be checked
+// before we know which one was written.
+fn read_header(file: &mut File) -> Result<Header, Box<dyn Error>> {
+    let len = read_len(file)?;
+    let mut bytes = vec![0u8; len];
+    file.read_exact(&mut bytes)?;
+    let old: Header4 = from_bytes(&bytes)?;
+    if old.major_version != MAJOR_VERSION {
+        return Err(Box::new(BadVersionError {
+            major: old.major_version,
+        }));
+    }
+    if old.minor_version >= 5 {
+        Ok(from_bytes(&bytes)?)
+    } else {
+        Ok(Header {
+            app_version: old.app_version,
+            major_version: old.major_version,
+            minor_version: old.minor_version,
+            date: old.date,
+            os: old.os,
+            seed: old.seed,
+            fov: FovAlgorithm::Permissive,
+        })
+    }
 }
 
 // TODO: We might also want to save the entire game state (maybe in a separate file).
 // Loading that could be quite a bit faster than loading and replaying actions. That would
 // also isolate us from logic changes that could hose replay.
-fn new_with_header(path: &str, header: Header) -> Result<File, Box<dyn Error>> {
+fn new_with_header(path: &str, header: Header) -> Result<SaveFile, Box<dyn Error>> {
     let path = Path::new(path);
     let mut file = File::create(&path)?;
 
-    let bytes: Vec<u8> = postcard::to_stdvec(&header)?;
+    let bytes: Vec<u8> = if header.minor_version >= 5 {
+        postcard::to_stdvec(&header)?
+    } else {
+        postcard::to_stdvec(&Header4 {
+            app_version: header.app_version.clone(),
+            major_version: header.major_version,
+            minor_version: header.minor_version,
+            date: header.date.clone(),
+            os: header.os.clone(),
+            seed: header.seed,
+        })?
+    };
     write_len(&mut file, bytes.len())?;
     file.write_all(&bytes)?;
 
-    Ok(file)
+    Ok(SaveFile {
+        file,
+        minor_version: header.minor_version,
+        turns: 0,
+    })
 }
 
 /// Create a brand new saved game at path (overwriting any existing game).
-pub fn new_game(path: &str, seed: u64) -> Result<File, Box<dyn Error>> {
-    let header = Header::new(seed);
+pub fn new_game(path: &str, seed: u64, fov: FovAlgorithm) -> Result<SaveFile, Box<dyn Error>> {
+    let header = Header::new(seed, fov);
     new_with_header(path, header)
 }
 
-/// Append onto an existing game (which must exist).
-pub fn open_game(path: &str) -> Result<File, Box<dyn Error>> {
+/// Append onto an existing game (which must exist). If the last chunk was torn it's
+/// removed so that new chunks aren't written after garbage.
+pub fn open_game(path: &str) -> Result<SaveFile, Box<dyn Error>> {
+    let (header, chunks, torn) = read_chunks(path)?;
     let file = OpenOptions::new().append(true).open(path)?;
-    Ok(file)
+    if let Some(torn) = torn {
+        warn!("truncating {path} to {} bytes ({})", torn.offset, torn.reason);
+        file.set_len(torn.offset)?;
+    }
+    Ok(SaveFile {
+        file,
+        minor_version: header.minor_version,
+        turns: chunks.last().map_or(0, |chunk| chunk.turns),
+    })
 }
 
-pub fn append_game(file: &mut File, actions: &[Action]) -> Result<(), Box<dyn Error>> {
-    let bytes: Vec<u8> = postcard::to_stdvec(actions)?; // TODO: compress actions?
-    write_len(file, bytes.len())?;
-    file.write_all(&bytes)?;
+pub fn append_game(
+    file: &mut SaveFile,
+    actions: &[Action],
+    checkpoints: &[Checkpoint],
+    play_time: PlayTime,
+) -> Result<(), Box<dyn Error>> {
+    // TODO: compress actions?
+    let turns = file.turns + count_turns(actions);
+    let bytes: Vec<u8> = match file.minor_version {
+        0 => postcard::to_stdvec(actions)?, // old files don't support checkpoints
+        1 => postcard::to_stdvec(&Chunk1 { actions, checkpoints })?,
+        2 | 3 => postcard::to_stdvec(&Chunk3 {
+            actions,
+            checkpoints,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            play_time,
+        })?,
+        _ => postcard::to_stdvec(&Chunk {
+            actions,
+            checkpoints,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            play_time,
+            turns,
+        })?,
+    };
+    write_chunk(file, &bytes)?;
+    file.turns = turns;
     Ok(())
 }
 
+// Object actions are done by the game, not the player, so they don't count as turns.
+fn count_turns(actions: &[Action]) -> u64 {
+    actions.iter().filter(|a| !matches!(a, Action::Object)).count() as u64
+}
+
+type SaveRequest = (Vec<Action>, Vec<Checkpoint>, PlayTime);
+
+/// Appends chunks onto a saved game using a worker thread so that the UI doesn't hitch
+/// while saving. Dropping the Saver blocks until all pending chunks have been written.
+pub struct Saver {
+    sender: Option<SyncSender<SaveRequest>>,
+    errors: Receiver<String>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Saver {
+    pub fn new(mut file: SaveFile) -> Saver {
+        let (sender, requests) = mpsc::sync_channel::<SaveRequest>(MAX_PENDING_CHUNKS);
+        let (error_sender, errors) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            for (actions, checkpoints, play_time) in requests {
+                if let Err(err) = append_game(&mut file, &actions, &checkpoints, play_time) {
+                    // Box<dyn Error> isn't Send so we have to convert it into a string.
+                    let _ = error_sender.send(format!("{err}"));
+                }
+            }
+        });
+        Saver {
+            sender: Some(sender),
+            errors,
+            worker: Some(worker),
+        }
+    }
+
+    pub fn append(&self, actions: Vec<Action>, checkpoints: Vec<Checkpoint>, play_time: PlayTime) {
+        let sender = self.sender.as_ref().unwrap();
+        if sender.send((actions, checkpoints, play_time)).is_err() {
+            error!("save worker has exited");
+        }
+    }
+
+    /// Returns any errors that happened since the last call.
+    pub fn errors(&self) -> impl Iterator<Item = String> + '_ {
+        self.errors.try_iter()
+    }
+}
+
+impl Drop for Saver {
+    fn drop(&mut self) {
+        // Closing the channel will cause the worker to exit once it has written everything.
+        self.sender = None;
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("save worker panicked");
+            }
+        }
+        for err in self.errors.try_iter() {
+            error!("Couldn't save game: {err}");
+        }
+    }
+}
+
+/// Returns the number of bytes value takes up when encoded the same way as saved games.
+pub fn encoded_size<T: Serialize + ?Sized>(value: &T) -> usize {
+    postcard::to_stdvec(value).map_or(0, |bytes| bytes.len())
+}
+
 // TODO: Would be a lot better to return these a chunk at a time.
-pub fn load_game(path: &str) -> Result<(u64, Vec<Action>), Box<dyn Error>> {
+pub fn load_game(path: &str) -> Result<SavedGame, Box<dyn Error>> {
+    let (header, saved) = read_game(path)?;
+    info!("loaded file, {header}");
+    Ok(saved)
+}
+
+/// Returns a summary of the saved game at path. This is called for every game in the
+/// saves directory so, for current files, only the last chunk is decoded.
+pub fn read_info(path: &str) -> Result<SaveInfo, Box<dyn Error>> {
+    let (header, last) = read_last_chunk(path)?;
+    let (play_time, turns) = if header.minor_version >= 4 {
+        last.map_or((PlayTime::default(), 0), |chunk| (chunk.play_time, chunk.turns))
+    } else {
+        // Older files don't record the turn count so all the actions have to be read.
+        let (_, saved) = read_game(path)?;
+        (saved.play_time, count_turns(&saved.actions))
+    };
+    Ok(SaveInfo {
+        path: path.to_string(),
+        app_version: header.app_version,
+        version: format!("{}.{}", header.major_version, header.minor_version),
+        seed: header.seed,
+        date: header.date,
+        play_time,
+        turns: turns as usize,
+    })
+}
+
+/// Uses the chunk lengths to skip to the last chunk which is the only one decoded. As
+/// with read_chunks a final chunk that can't be read is dropped.
+fn read_last_chunk(path: &str) -> Result<(Header, Option<LoadedChunk>), Box<dyn Error>> {
+    let mut file = File::open(path)?;
+    let header = read_header(&mut file)?;
+
+    let file_len = file.metadata()?.len();
+    let crc_len = if header.minor_version >= 3 { 4 } else { 0 };
+    let mut offsets = Vec::new();
+    let mut offset = file.stream_position()?;
+    while offset + 4 <= file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        let end = offset + 4 + read_len(&mut file)? as u64 + crc_len;
+        if end > file_len {
+            break;
+        }
+        offsets.push(offset);
+        offset = end;
+    }
+
+    for &offset in offsets.iter().rev().take(2) {
+        file.seek(SeekFrom::Start(offset))?;
+        let len = read_len(&mut file)?;
+        if let Ok(chunk) = decode_chunk(&mut file, header.minor_version, len) {
+            return Ok((header, Some(chunk)));
+        }
+    }
+    Ok((header, None))
+}
+
+fn read_game(path: &str) -> Result<(Header, SavedGame), Box<dyn Error>> {
+    let (header, chunks, torn) = read_chunks(path)?;
+
+    let mut warnings = Vec::new();
+    if let Some(torn) = torn {
+        warn!("dropping the last chunk of {path}: {}", torn.reason);
+        let text = catalog::text("save.torn", &[("path", &path), ("reason", &torn.reason.as_str())]);
+        warnings.push(text);
+    }
+
+    let mut actions = Vec::new();
+    let mut checkpoints = Vec::new();
+    let mut timestamps = Vec::new();
+    let mut play_time = PlayTime::default();
+    for mut chunk in chunks {
+        actions.append(&mut chunk.actions);
+        checkpoints.append(&mut chunk.checkpoints);
+        if header.minor_version >= 2 {
+            timestamps.push(chunk.timestamp);
+            play_time = chunk.play_time;
+        }
+    }
+
+    let seed = header.seed;
+    let fov = header.fov;
+    Ok((
+        header,
+        SavedGame {
+            seed,
+            fov,
+            actions,
+            checkpoints,
+            timestamps,
+            play_time,
+            warnings,
+        },
+    ))
+}
+
+/// Chunks from older files are upgraded to the current format (with a zero timestamp and
+/// play time). If the final chunk can't be read it's assumed to have been torn while it
+/// was being appended and is returned separately (bad chunks elsewhere are an error).
+fn read_chunks(path: &str) -> Result<Chunks, Box<dyn Error>> {
     let path = Path::new(path);
     let mut file = File::open(&path)?;
 
-    let len = read_len(&mut file)?;
+    let header = read_header(&mut file)?;
+
+    let mut chunks = Vec::new();
+    let file_len = file.metadata()?.len();
+    let mut offset = file.stream_position()?;
+    while offset < file_len {
+        let (result, end) = read_chunk(&mut file, header.minor_version, offset, file_len);
+        match result {
+            Ok(mut chunk) => {
+                if header.minor_version < 4 {
+                    let turns = chunks.last().map_or(0, |chunk: &LoadedChunk| chunk.turns);
+                    chunk.turns = turns + count_turns(&chunk.actions);
+                }
+                chunks.push(chunk)
+            }
+            Err(err) if end >= file_len => {
+                let reason = format!("{err}");
+                return Ok((header, chunks, Some(TornChunk { offset, reason })));
+            }
+            Err(err) => return Err(err),
+        }
+        offset = end;
+    }
+    Ok((header, chunks, None))
+}
+
+// Returns the chunk starting at offset along with where the next chunk starts.
+fn read_chunk(
+    file: &mut File,
+    minor_version: u8,
+    offset: u64,
+    file_len: u64,
+) -> (Result<LoadedChunk, Box<dyn Error>>, u64) {
+    if file_len - offset < 4 {
+        return (Err("chunk length was cut off".into()), file_len);
+    }
+    let len = match read_len(file) {
+        Ok(len) => len,
+        Err(err) => return (Err(err), file_len),
+    };
+    let crc_len = if minor_version >= 3 { 4 } else { 0 };
+    let end = offset + 4 + len as u64 + crc_len;
+    if end > file_len {
+        let err = format!(
+            "chunk needs {} bytes but only {} are left",
+            end - offset,
+            file_len - offset
+        );
+        return (Err(err.into()), file_len);
+    }
+    (decode_chunk(file, minor_version, len), end)
+}
+
+fn decode_chunk(file: &mut File, minor_version: u8, len: usize) -> Result<LoadedChunk, Box<dyn Error>> {
     let mut bytes = vec![0u8; len];
     file.read_exact(&mut bytes)?;
-    let header: Header = from_bytes(&bytes)?;
+    if minor_version >= 3 && read_u32(file)? != crc32(&bytes) {
+        return Err("chunk CRC doesn't match".into());
+    }
+    let chunk = match minor_version {
+        0 => LoadedChunk {
+            actions: from_bytes(&bytes)?,
+            checkpoints: Vec::new(),
+            timestamp: 0,
+            play_time: PlayTime::default(),
+            turns: 0,
+        },
+        1 => {
+            let chunk: LoadedChunk1 = from_bytes(&bytes)?;
+            LoadedChunk {
+                actions: chunk.actions,
+                checkpoints: chunk.checkpoints,
+                timestamp: 0,
+                play_time: PlayTime::default(),
+                turns: 0,
+            }
+        }
+        2 | 3 => {
+            let chunk: LoadedChunk3 = from_bytes(&bytes)?;
+            LoadedChunk {
+                actions: chunk.actions,
+                checkpoints: chunk.checkpoints,
+                timestamp: chunk.timestamp,
+                play_time: chunk.play_time,
+                turns: 0,
+            }
+        }
+        _ => from_bytes(&bytes)?,
+    };
+    Ok(chunk)
+}
+
+/// Readable form of a saved game, see export_json.
+#[derive(Serialize, Deserialize)]
+struct JsonGame {
+    header: Header,
+    chunks: Vec<LoadedChunk>,
+}
+
+/// Writes the saved game at save_path out as JSON so that it can be attached to bug
+/// reports or hand edited. Older files are upgraded to the current format.
+pub fn export_json(save_path: &str, json_path: &str) -> Result<(), Box<dyn Error>> {
+    let (mut header, chunks, torn) = read_chunks(save_path)?;
+    if let Some(torn) = torn {
+        warn!("dropping the last chunk of {save_path}: {}", torn.reason);
+    }
+    header.minor_version = MINOR_VERSION;
+    let mut text = serde_json::to_string_pretty(&JsonGame { header, chunks })?;
+    text.push('\n');
+    std::fs::write(json_path, text)?;
+    Ok(())
+}
+
+/// Converts a file written by export_json (and possibly edited) back into a saved game.
+/// The JSON is validated before save_path is touched.
+pub fn import_json(json_path: &str, save_path: &str) -> Result<(), Box<dyn Error>> {
+    let text = std::fs::read_to_string(json_path)?;
+    let game: JsonGame = serde_json::from_str(&text)?;
+    validate_json(&game)?;
+
+    // Turn counts are recomputed in case the actions were edited.
+    let mut file = new_with_header(save_path, game.header)?;
+    for chunk in game.chunks {
+        file.turns += count_turns(&chunk.actions);
+        let bytes: Vec<u8> = postcard::to_stdvec(&Chunk {
+            actions: &chunk.actions,
+            checkpoints: &chunk.checkpoints,
+            timestamp: chunk.timestamp,
+            play_time: chunk.play_time,
+            turns: file.turns,
+        })?;
+        write_chunk(&mut file, &bytes)?;
+    }
+    Ok(())
+}
+
+fn validate_json(game: &JsonGame) -> Result<(), Box<dyn Error>> {
+    let header = &game.header;
     if header.major_version != MAJOR_VERSION {
         return Err(Box::new(BadVersionError {
             major: header.major_version,
         }));
     }
-    info!("loaded file, {header}");
-
-    let mut actions = Vec::new();
-    while let Ok(len) = read_len(&mut file) {
-        let mut bytes = vec![0u8; len];
-        file.read_exact(&mut bytes)?;
-        let mut chunk: Vec<Action> = from_bytes(&bytes)?;
-        actions.append(&mut chunk);
+    if header.minor_version != MINOR_VERSION {
+        return Err(format!("expected minor_version {MINOR_VERSION} not {}", header.minor_version).into());
     }
 
-    Ok((header.seed, actions))
+    // Checkpoints are used to detect replay divergences so they need to line up with the
+    // actions.
+    let mut num_actions = 0;
+    let mut last_index = None;
+    for (i, chunk) in game.chunks.iter().enumerate() {
+        num_actions += chunk.actions.len() as u64;
+        for checkpoint in chunk.checkpoints.iter() {
+            if checkpoint.index >= num_actions {
+                return Err(format!("chunk {i} has a checkpoint past its actions").into());
+            }
+            if last_index.is_some_and(|last| checkpoint.index <= last) {
+                return Err(format!("chunk {i} has an out of order checkpoint").into());
+            }
+            last_index = Some(checkpoint.index);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backend::test_game;
 
     #[test]
     fn test_save_load() {
@@ -225,13 +774,13 @@ mod tests {
 
         {
             // save, close
-            let mut serializer = new_game(&path, 1).unwrap();
-            append_game(&mut serializer, &actions1).unwrap();
-            append_game(&mut serializer, &actions2).unwrap();
+            let mut serializer = new_game(&path, 1, FovAlgorithm::Permissive).unwrap();
+            append_game(&mut serializer, &actions1, &[], PlayTime::default()).unwrap();
+            append_game(&mut serializer, &actions2, &[], PlayTime::default()).unwrap();
         }
 
         // load
-        let actions = load_game(&path).unwrap().1;
+        let actions = load_game(&path).unwrap().actions;
 
         assert_eq!(actions.len(), 4);
         assert_eq!(actions[0], actions1[0]);
@@ -261,14 +810,14 @@ mod tests {
 
         {
             // save, close
-            let mut serializer = new_game(&path, 1).unwrap();
-            append_game(&mut serializer, &actions1).unwrap();
-            append_game(&mut serializer, &actions2).unwrap();
+            let mut serializer = new_game(&path, 1, FovAlgorithm::Permissive).unwrap();
+            append_game(&mut serializer, &actions1, &[], PlayTime::default()).unwrap();
+            append_game(&mut serializer, &actions2, &[], PlayTime::default()).unwrap();
         }
 
         {
             // load 1
-            let actions = load_game(&path).unwrap().1;
+            let actions = load_game(&path).unwrap().actions;
 
             assert_eq!(actions.len(), 4);
             assert_eq!(actions[0], actions1[0]);
@@ -280,11 +829,11 @@ mod tests {
         {
             // open, append, close
             let mut serializer = open_game(&path).unwrap();
-            append_game(&mut serializer, &actions3).unwrap();
+            append_game(&mut serializer, &actions3, &[], PlayTime::default()).unwrap();
         }
 
         // load 2
-        let actions = load_game(&path).unwrap().1;
+        let actions = load_game(&path).unwrap().actions;
 
         assert_eq!(actions.len(), 5);
         assert_eq!(actions[0], actions1[0]);
@@ -294,17 +843,66 @@ mod tests {
         assert_eq!(actions[4], actions3[0]);
     }
 
+    #[test]
+    fn test_torn_chunk() {
+        // If the game is killed while appending the partial chunk should be dropped.
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let actions1 = vec![Action::Rest, Action::Move { dx: 1, dy: 0 }];
+        let actions2 = vec![Action::Move { dx: 0, dy: 1 }];
+        {
+            let mut serializer = new_game(&path, 1, FovAlgorithm::Permissive).unwrap();
+            append_game(&mut serializer, &actions1, &[], PlayTime::default()).unwrap();
+            append_game(&mut serializer, &actions2, &[], PlayTime::default()).unwrap();
+        }
+        let bytes = fs::read(&path).unwrap();
+
+        for cut in [1, 3, 5] {
+            fs::write(&path, &bytes[..bytes.len() - cut]).unwrap();
+            let saved = load_game(&path).unwrap();
+            assert_eq!(saved.actions, actions1);
+            assert_eq!(saved.warnings.len(), 1);
+        }
+
+        // A chunk with a bad CRC is also dropped.
+        let mut bad = bytes.clone();
+        let last = bad.len() - 5;
+        bad[last] ^= 0xFF;
+        fs::write(&path, &bad).unwrap();
+        let saved = load_game(&path).unwrap();
+        assert_eq!(saved.actions, actions1);
+        assert_eq!(saved.warnings.len(), 1);
+
+        // Appending should replace the torn chunk.
+        {
+            let mut serializer = open_game(&path).unwrap();
+            append_game(&mut serializer, &actions2, &[], PlayTime::default()).unwrap();
+        }
+        let saved = load_game(&path).unwrap();
+        assert_eq!(saved.actions.len(), 3);
+        assert_eq!(saved.actions[2], actions2[0]);
+        assert!(saved.warnings.is_empty());
+
+        // But damage before the last chunk is still an error.
+        let mut bad = fs::read(&path).unwrap();
+        let header_len = u32::from_le_bytes(bad[0..4].try_into().unwrap()) as usize;
+        bad[header_len + 4 + 4] ^= 0xFF;
+        fs::write(&path, &bad).unwrap();
+        assert!(load_game(&path).is_err());
+    }
+
     #[test]
     fn test_bad_paths() {
         // File in a non-existent directory.
         let path = "/nothing/there/x.y";
-        assert!(new_game(path, 1).is_err());
+        assert!(new_game(path, 1, FovAlgorithm::Permissive).is_err());
         assert!(open_game(path).is_err());
         assert!(load_game(path).is_err());
 
         // Write to read-only directory.
         let path = "/user/bad.game";
-        assert!(new_game(path, 1).is_err());
+        assert!(new_game(path, 1, FovAlgorithm::Permissive).is_err());
         assert!(open_game(path).is_err());
 
         // Load of missing file.
@@ -319,7 +917,7 @@ mod tests {
         let _ = fs::remove_file(&path);
 
         {
-            let mut header = Header::new(1);
+            let mut header = Header::new(1, FovAlgorithm::Permissive);
             header.major_version = MAJOR_VERSION - 1;
 
             let mut serializer = new_with_header(&path, header).unwrap();
@@ -333,11 +931,433 @@ mod tests {
                     wizard: true,
                 },
             ];
-            append_game(&mut serializer, &actions1).unwrap();
+            append_game(&mut serializer, &actions1, &[], PlayTime::default()).unwrap();
         }
 
         let err = load_game(&path).unwrap_err();
         let desc = format!("{err}");
         assert!(desc.contains("Expected file version"));
     }
+
+    #[test]
+    fn test_checkpoints() {
+        // Are checkpoints saved along with the actions?
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let actions1 = vec![Action::Rest, Action::Move { dx: 1, dy: 0 }];
+        let checkpoints1 = vec![Checkpoint {
+            index: 0,
+            turn: 1000,
+            checksum: 0xDEAD,
+            surroundings: vec!["#.@".to_string()],
+        }];
+        let actions2 = vec![Action::Move { dx: 0, dy: 1 }];
+
+        {
+            let mut serializer = new_game(&path, 1, FovAlgorithm::Permissive).unwrap();
+            append_game(&mut serializer, &actions1, &checkpoints1, PlayTime::default()).unwrap();
+        }
+        {
+            let mut serializer = open_game(&path).unwrap();
+            append_game(&mut serializer, &actions2, &[], PlayTime::default()).unwrap();
+        }
+
+        let saved = load_game(&path).unwrap();
+        assert_eq!(saved.actions.len(), 3);
+        assert_eq!(saved.actions[2], actions2[0]);
+        assert_eq!(saved.checkpoints, checkpoints1);
+    }
+
+    #[test]
+    fn test_json() {
+        // Exporting to JSON and importing back should give us the same game.
+        let path = format!("/tmp/saved-{}.game", line!());
+        let json_path = format!("/tmp/saved-{}.json", line!());
+        let imported_path = format!("/tmp/saved-{}.game", line!());
+
+        let actions = vec![Action::Rest, Action::Move { dx: 1, dy: 0 }];
+        let checkpoints = vec![Checkpoint {
+            index: 1,
+            turn: 1000,
+            checksum: u64::MAX,
+            surroundings: vec!["#.@".to_string()],
+        }];
+        let play_time = PlayTime {
+            played: 5000,
+            sessions: 2,
+        };
+        {
+            let mut serializer = new_game(&path, 42, FovAlgorithm::Symmetric).unwrap();
+            append_game(&mut serializer, &actions, &checkpoints, play_time).unwrap();
+        }
+
+        export_json(&path, &json_path).unwrap();
+        import_json(&json_path, &imported_path).unwrap();
+
+        let saved = load_game(&path).unwrap();
+        let imported = load_game(&imported_path).unwrap();
+        assert_eq!(imported.seed, 42);
+        assert_eq!(imported.fov, FovAlgorithm::Symmetric);
+        assert_eq!(imported.actions, saved.actions);
+        assert_eq!(imported.checkpoints, saved.checkpoints);
+        assert_eq!(imported.timestamps, saved.timestamps);
+        assert_eq!(imported.play_time, play_time);
+
+        // Bad edits should be rejected without touching the saved game.
+        let text = fs::read_to_string(&json_path).unwrap();
+        for (old, new) in [
+            ("\"Rest\"", "\"Sleep\""),
+            ("\"index\": 1", "\"index\": 2"),
+            ("\"seed\": 42", "\"seed\": -42"),
+            ("\"sessions\"", "\"sesions\""),
+        ] {
+            assert!(text.contains(old), "{old}");
+            fs::write(&json_path, text.replace(old, new)).unwrap();
+            assert!(import_json(&json_path, &imported_path).is_err(), "{new}");
+        }
+        assert_eq!(load_game(&imported_path).unwrap().actions, saved.actions);
+    }
+
+    #[test]
+    fn test_minor_version0() {
+        // Can we still load and append onto files written before checkpoints were added?
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let actions1 = vec![Action::Rest, Action::Move { dx: 1, dy: 0 }];
+        let checkpoints1 = vec![Checkpoint {
+            index: 0,
+            turn: 1000,
+            checksum: 0xDEAD,
+            surroundings: vec!["#.@".to_string()],
+        }];
+
+        {
+            let mut header = Header::new(1, FovAlgorithm::Permissive);
+            header.minor_version = 0;
+
+            let mut serializer = new_with_header(&path, header).unwrap();
+            append_game(&mut serializer, &actions1, &[], PlayTime::default()).unwrap();
+        }
+        {
+            let mut serializer = open_game(&path).unwrap();
+            append_game(&mut serializer, &actions1, &checkpoints1, PlayTime::default()).unwrap();
+        }
+
+        let saved = load_game(&path).unwrap();
+        assert_eq!(saved.actions.len(), 4);
+        assert!(saved.checkpoints.is_empty());
+    }
+
+    #[test]
+    fn test_play_time() {
+        // Is play time saved with each chunk?
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let actions1 = vec![Action::Rest, Action::Move { dx: 1, dy: 0 }];
+        let time1 = PlayTime {
+            played: 5 * 60 * 1000,
+            sessions: 1,
+        };
+        let time2 = PlayTime {
+            played: (3 * 60 + 12) * 60 * 1000,
+            sessions: 5,
+        };
+
+        {
+            let mut serializer = new_game(&path, 1, FovAlgorithm::Permissive).unwrap();
+            append_game(&mut serializer, &actions1, &[], time1).unwrap();
+            append_game(&mut serializer, &actions1, &[], time2).unwrap();
+        }
+
+        let saved = load_game(&path).unwrap();
+        assert_eq!(saved.timestamps.len(), 2);
+        assert!(saved.timestamps[0] <= saved.timestamps[1]);
+        assert_eq!(saved.play_time, time2);
+        assert_eq!(format!("{time1}"), "played 5m over 1 session");
+        assert_eq!(format!("{time2}"), "played 3h12m over 5 sessions");
+    }
+
+    #[test]
+    fn test_read_info() {
+        // Can we get a summary of a saved game?
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let actions1 = vec![Action::Rest, Action::Object, Action::Move { dx: 1, dy: 0 }];
+        let time1 = PlayTime {
+            played: 5 * 60 * 1000,
+            sessions: 2,
+        };
+
+        {
+            let mut serializer = new_game(&path, 7, FovAlgorithm::Permissive).unwrap();
+            append_game(&mut serializer, &actions1, &[], time1).unwrap();
+        }
+        {
+            let mut serializer = open_game(&path).unwrap();
+            append_game(&mut serializer, &actions1, &[], time1).unwrap();
+        }
+
+        let info = read_info(&path).unwrap();
+        assert_eq!(info.path, path);
+        assert_eq!(info.version, format!("{MAJOR_VERSION}.{MINOR_VERSION}"));
+        assert_eq!(info.seed, 7);
+        assert_eq!(info.play_time, time1);
+        assert_eq!(info.turns, 4);
+
+        // A torn final chunk doesn't count.
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        assert_eq!(read_info(&path).unwrap().turns, 2);
+
+        // Older files don't have turn counts so they have to be computed.
+        let _ = fs::remove_file(&path);
+        {
+            let mut header = Header::new(7, FovAlgorithm::Permissive);
+            header.minor_version = 3;
+            let mut serializer = new_with_header(&path, header).unwrap();
+            append_game(&mut serializer, &actions1, &[], time1).unwrap();
+            append_game(&mut serializer, &actions1, &[], time1).unwrap();
+        }
+        let info = read_info(&path).unwrap();
+        assert_eq!(info.version, format!("{MAJOR_VERSION}.3"));
+        assert_eq!(info.play_time, time1);
+        assert_eq!(info.turns, 4);
+    }
+
+    #[test]
+    fn test_no_empty_chunks() {
+        // Saving when nothing has happened shouldn't append a chunk.
+        let mut game = test_game();
+        let path = game.path.clone();
+        game.player_acted(Action::Rest);
+        game.save_actions();
+        game.save_actions();
+        drop(game); // also saves
+
+        assert_eq!(load_game(&path).unwrap().timestamps.len(), 1);
+    }
+
+    #[test]
+    fn test_saver() {
+        // Are chunks written by the worker thread by the time the Saver is dropped?
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let actions1 = vec![Action::Rest, Action::Move { dx: 1, dy: 0 }];
+        let actions2 = vec![Action::Move { dx: 0, dy: 1 }];
+        {
+            let saver = Saver::new(new_game(&path, 1, FovAlgorithm::Permissive).unwrap());
+            for _ in 0..2 * MAX_PENDING_CHUNKS {
+                saver.append(actions1.clone(), Vec::new(), PlayTime::default());
+            }
+            saver.append(actions2.clone(), Vec::new(), PlayTime::default());
+            assert_eq!(saver.errors().count(), 0);
+        }
+
+        let actions = load_game(&path).unwrap().actions;
+        assert_eq!(actions.len(), 4 * MAX_PENDING_CHUNKS + 1);
+        assert_eq!(actions[4 * MAX_PENDING_CHUNKS], actions2[0]);
+    }
+
+    #[test]
+    fn test_review() {
+        // Does review return the last turns without changing the saved game?
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = crate::backend::Game::new_game(&path, 1);
+        for _ in 0..5 {
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.player_acted(Action::Rest);
+        }
+
+        let (review, tail) = game.review(2).unwrap();
+        assert_eq!(tail.iter().filter(|a| !matches!(a, Action::Object)).count(), 2);
+        assert!(matches!(tail[0], Action::Rest));
+        assert_eq!(review.player_loc(), game.player_loc());
+
+        // The game can still be saved.
+        game.player_acted(Action::Rest);
+        let (_, tail) = game.review(100).unwrap();
+        assert_eq!(tail.iter().filter(|a| !matches!(a, Action::Object)).count(), 6);
+    }
+
+    // ---- Compatibility ----------------------------------------------------------------
+    // Saved games are streams of postcard encoded Actions so reordering variants, changing
+    // field types, etc will silently break old saves. To catch that saves/ has a fixture
+    // for every save file version that contains one of each Action (as of when the fixture
+    // was written). Fixtures must never be regenerated: if the encoding has to change then
+    // bump MINOR_VERSION and run `cargo test write_save_fixtures -- --ignored` to add a
+    // fixture for the new version.
+    const SAVES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/backend/saves");
+
+    /// New variants must be added here with the next index (and added to sample_actions).
+    const NUM_ACTIONS: usize = 23;
+
+    fn action_index(action: &Action) -> usize {
+        match action {
+            Action::Drop(_) => 0,
+            Action::Examine { .. } => 1,
+            Action::Move { .. } => 2,
+            Action::Object => 3,
+            Action::Remove(_) => 4,
+            Action::Rest => 5,
+            Action::Wear(_) => 6,
+            Action::WieldMainHand(_) => 7,
+            Action::WieldOffHand(_) => 8,
+            Action::Challenge(_) => 9,
+            Action::DropMany(_) => 10,
+            Action::SaveLoadout(_) => 11,
+            Action::SwapLoadout(_) => 12,
+            Action::Throw { .. } => 13,
+            Action::Call { .. } => 14,
+            Action::Polymorph { .. } => 15,
+            Action::Teleport { .. } => 16,
+            Action::Shove { .. } => 17,
+            Action::WizardMode => 18,
+            Action::PickUpMany(_) => 19,
+            Action::Charge { .. } => 20,
+            Action::Withdraw { .. } => 21,
+            Action::UseOn { .. } => 22,
+        }
+    }
+
+    /// One of each Action in declaration order. Values are chosen to exercise the encoding,
+    /// e.g. negative numbers and the last ObjectName and Conduct variants.
+    fn sample_actions() -> Vec<Action> {
+        use super::super::{Conduct, ObjectName, Oid};
+        vec![
+            Action::Drop(Oid(1)),
+            Action::Examine {
+                loc: Point::new(-3, 7),
+                wizard: true,
+            },
+            Action::Move { dx: -1, dy: 1 },
+            Action::Object,
+            Action::Remove(Oid(u64::MAX)),
+            Action::Rest,
+            Action::Wear(Oid(300)),
+            Action::WieldMainHand(Oid(4)),
+            Action::WieldOffHand(Oid(5)),
+            Action::Challenge(Conduct::FogOfMemory),
+            Action::DropMany(vec![Oid(6), Oid(70000)]),
+            Action::SaveLoadout(2),
+            Action::SwapLoadout(1),
+            Action::Throw {
+                oid: Oid(8),
+                target: Point::new(100, -100),
+            },
+            Action::Call {
+                oid: Oid(9),
+                name: "Sting \u{2694}".to_string(),
+            },
+            Action::Polymorph {
+                loc: Point::new(1, 2),
+                into: ObjectName::LockedDoor,
+            },
+            Action::Teleport {
+                loc: Point::new(i32::MIN, i32::MAX),
+            },
+            Action::Shove { dx: 0, dy: -1 },
+            Action::WizardMode,
+            Action::PickUpMany(vec![]),
+            Action::Charge { dx: 1, dy: 1 },
+            Action::Withdraw { dx: -1, dy: 0 },
+            Action::UseOn {
+                oid: Oid(10),
+                target: Point::new(-5, -6),
+            },
+        ]
+    }
+
+    fn fixture_path(minor: u8) -> String {
+        format!("{SAVES_DIR}/v{MAJOR_VERSION}.{minor}.game")
+    }
+
+    #[test]
+    fn test_sample_actions() {
+        let indexes: Vec<usize> = sample_actions().iter().map(action_index).collect();
+        let expected: Vec<usize> = (0..NUM_ACTIONS).collect();
+        assert_eq!(
+            indexes, expected,
+            "sample_actions should have one of each Action in order"
+        );
+    }
+
+    #[test]
+    fn test_save_fixtures() {
+        let samples = sample_actions();
+        for minor in 0..=MINOR_VERSION {
+            let path = fixture_path(minor);
+            assert!(
+                Path::new(&path).exists(),
+                "missing {path}, run `cargo test write_save_fixtures -- --ignored`"
+            );
+
+            // Old saves have fewer variants but the ones they do have must still decode
+            // to the same thing.
+            let saved = load_game(&path).unwrap_or_else(|err| panic!("couldn't load {path}: {err}"));
+            assert_eq!(saved.seed, 7);
+            let fov = if minor >= 5 {
+                FovAlgorithm::Symmetric
+            } else {
+                FovAlgorithm::Permissive // not written to older files
+            };
+            assert_eq!(saved.fov, fov, "{path}");
+            assert!(!saved.actions.is_empty());
+            assert_eq!(saved.actions, samples[..saved.actions.len()], "{path}");
+            if minor > 0 {
+                assert_eq!(saved.checkpoints.len(), 1, "{path}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        // Going through JSON exercises both decoding and encoding so, for the current
+        // version, we should get back exactly the bytes in the fixture.
+        let path = fixture_path(MINOR_VERSION);
+        let json_path = format!("/tmp/saved-{}.json", line!());
+        let round_trip = format!("/tmp/saved-{}.game", line!());
+        export_json(&path, &json_path).unwrap();
+        import_json(&json_path, &round_trip).unwrap();
+        assert!(fs::read(&path).unwrap() == fs::read(&round_trip).unwrap());
+    }
+
+    #[test]
+    #[ignore]
+    fn write_save_fixtures() {
+        // Writes fixtures for any versions that don't have one yet.
+        let samples = sample_actions();
+        let (first, second) = samples.split_at(samples.len() / 2);
+        let checkpoints = vec![Checkpoint {
+            index: 2,
+            turn: 16000,
+            checksum: 0xFEED_FACE_CAFE_BEEF,
+            surroundings: vec!["#.@".to_string(), "..[".to_string()],
+        }];
+        let play_time = PlayTime {
+            played: 60_000,
+            sessions: 3,
+        };
+        for minor in 0..=MINOR_VERSION {
+            let path = fixture_path(minor);
+            if Path::new(&path).exists() {
+                continue;
+            }
+            let mut header = Header::new(7, FovAlgorithm::Symmetric);
+            header.minor_version = minor;
+            header.date = "Fri, 16 Oct 2026 00:00:00 +0000".to_string();
+            header.os = "linux".to_string();
+            let mut file = new_with_header(&path, header).unwrap();
+            append_game(&mut file, first, &checkpoints, play_time).unwrap();
+            append_game(&mut file, second, &[], play_time).unwrap();
+        }
+    }
 }
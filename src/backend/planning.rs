@@ -0,0 +1,262 @@
+//! With lots of NPCs most of the time spent running a round goes into path finding. So,
+//! when a round starts, the NPCs in the round that are moving towards something are
+//! partitioned into groups that can't interfere with each other (they aren't near each
+//! other and aren't after the same target) and paths for the first NPC in each group are
+//! found in parallel. NPCs still act serially, in the round's order, and a planned path is
+//! only used if nothing that path finding looked at has changed since the path was found.
+//! So the game plays out exactly as if the path was found when the NPC acted which is
+//! what keeps replays in sync.
+use super::ai;
+use super::level::{Extent, Level};
+use super::object::Object;
+use super::primitives::PathFind;
+use super::tag::*;
+use super::{Game, Oid, Point, Time};
+use fnv::{FnvHashMap, FnvHashSet};
+use rayon::prelude::*;
+use std::cell::Cell;
+
+/// NPCs within this distance of each other are assumed to get in each other's way.
+const GROUP_RADIUS: i32 = 8;
+
+struct Request {
+    oid: Oid,
+    ch: Object,
+    start: Point,
+    target: Point,
+    defender: Option<Oid>, // set if the NPC is attacking or searching for a character
+}
+
+struct Plan {
+    start: Point,
+    target: Point,
+    size: BodySize,
+    opens_doors: bool,
+    extent: (Extent, Point, Point),
+    read: (Point, Point), // bounding box of the cells path finding looked at
+    path: Vec<Point>,
+}
+
+pub struct Plans {
+    planned: FnvHashMap<Oid, Plan>,
+}
+
+impl Plans {
+    pub fn new() -> Plans {
+        Plans {
+            planned: FnvHashMap::default(),
+        }
+    }
+}
+
+impl Game {
+    /// Called when a new round starts to find paths for the NPCs in it.
+    pub(super) fn plan_paths(&mut self) {
+        let round: Vec<Oid> = self.scheduler.round().collect();
+        self.plan_paths_for(&round);
+    }
+
+    /// Returns the lowest Time path from start to target (or an empty vector if there is no
+    /// path). This will be the planned path if it's still valid.
+    pub(super) fn find_path(&mut self, oid: Oid, ch: &Object, start: &Point, target: &Point) -> Vec<Point> {
+        if let Some(plan) = self.plans.planned.remove(&oid) {
+            let valid = plan.is_valid(&self.level, ch, start, target);
+            if self.plans.planned.is_empty() {
+                self.level.record_edits(false);
+            }
+            if valid {
+                return plan.path;
+            }
+        }
+
+        let callback =
+            |loc: Point, neighbors: &mut Vec<(Point, Time)>| ai::successors(&self.level, ch, loc, target, neighbors);
+        let find = PathFind::new(*start, *target, callback);
+        find.path().clone()
+    }
+
+    // Oids are in the order that the NPCs will act.
+    fn plan_paths_for(&mut self, oids: &[Oid]) {
+        let requests = self.path_requests(oids);
+        let level = &self.level;
+        let plans: Vec<(Oid, Plan)> = requests
+            .par_iter()
+            .map(|request| (request.oid, plan_path(level, request)))
+            .collect();
+        self.plans.planned = plans.into_iter().collect();
+        self.level.record_edits(!self.plans.planned.is_empty());
+    }
+
+    // Returns requests for the first NPC (in round order) of each group of NPCs that may
+    // interfere with one another. The others will find paths when they act.
+    fn path_requests(&self, oids: &[Oid]) -> Vec<Request> {
+        let candidates: Vec<Request> = oids.iter().filter_map(|&oid| self.path_request(oid)).collect();
+
+        let mut groups: Vec<usize> = (0..candidates.len()).collect();
+        for i in 0..candidates.len() {
+            for j in (i + 1)..candidates.len() {
+                if interferes(&candidates[i], &candidates[j]) {
+                    let (gi, gj) = (group(&groups, i), group(&groups, j));
+                    groups[gi.max(gj)] = gi.min(gj);
+                }
+            }
+        }
+
+        let mut seen = FnvHashSet::default();
+        candidates
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, request)| {
+                if seen.insert(group(&groups, i)) {
+                    Some(request)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // Mirrors the targets that ai::acted moves NPCs towards.
+    fn path_request(&self, oid: Oid) -> Option<Request> {
+        let ch = self.level.try_obj(oid)?;
+        if !ch.has(CHARACTER_ID) || ch.has(DOORMAN_ID) {
+            return None;
+        }
+        let start = self.level.try_loc(oid)?;
+        let (target, defender) = match ch.behavior_value()? {
+            Behavior::Attacking(defender, _) => (self.loc(defender)?, Some(defender)),
+            Behavior::MovingTo(target) => (target, None),
+            Behavior::Searching(defender, origin, seen) => (ai::search_loc(&origin, self.now() - seen), Some(defender)),
+            Behavior::Sleeping | Behavior::Wandering(_) => return None,
+        };
+        if start == target || start.adjacent(&target) {
+            return None;
+        }
+        if self.is_distant(&start) && self.cached_step(oid, ch, &start, &target).is_some() {
+            return None;
+        }
+        Some(Request {
+            oid,
+            ch: ch.clone(),
+            start,
+            target,
+            defender,
+        })
+    }
+}
+
+impl Plan {
+    // Path finding would return the same path if nothing it depends upon has changed.
+    fn is_valid(&self, level: &Level, ch: &Object, start: &Point, target: &Point) -> bool {
+        let (top_left, bottom_right) = level.bounds();
+        self.start == *start
+            && self.target == *target
+            && self.size == ch.size()
+            && self.opens_doors == ch.has(CAN_OPEN_DOOR_ID)
+            && self.extent == (level.extent(), top_left, bottom_right)
+            && !level.edits().iter().any(|loc| self.was_read(loc))
+    }
+
+    fn was_read(&self, loc: &Point) -> bool {
+        let (top_left, bottom_right) = self.read;
+        (top_left.x..=bottom_right.x).contains(&loc.x) && (top_left.y..=bottom_right.y).contains(&loc.y)
+    }
+}
+
+// Note that this runs on rayon's threads so it can only read the level.
+fn plan_path(level: &Level, request: &Request) -> Plan {
+    let read = Cell::new((request.start, request.start));
+    let callback = |loc: Point, neighbors: &mut Vec<(Point, Time)>| {
+        // successors looks at the cells adjacent to loc.
+        let (top_left, bottom_right) = read.get();
+        read.set((
+            Point::new(top_left.x.min(loc.x - 1), top_left.y.min(loc.y - 1)),
+            Point::new(bottom_right.x.max(loc.x + 1), bottom_right.y.max(loc.y + 1)),
+        ));
+        ai::successors(level, &request.ch, loc, &request.target, neighbors);
+    };
+    let path = PathFind::new(request.start, request.target, callback).path().clone();
+
+    let (top_left, bottom_right) = level.bounds();
+    Plan {
+        start: request.start,
+        target: request.target,
+        size: request.ch.size(),
+        opens_doors: request.ch.has(CAN_OPEN_DOOR_ID),
+        extent: (level.extent(), top_left, bottom_right),
+        read: read.get(),
+        path,
+    }
+}
+
+fn interferes(lhs: &Request, rhs: &Request) -> bool {
+    lhs.start.distance2(&rhs.start) <= GROUP_RADIUS * GROUP_RADIUS
+        || lhs.target == rhs.target
+        || (lhs.defender.is_some() && lhs.defender == rhs.defender)
+}
+
+fn group(groups: &[usize], mut i: usize) -> usize {
+    while groups[i] != i {
+        i = groups[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::{test_game, ObjectName};
+
+    #[test]
+    fn test_plans() {
+        let mut game = test_game();
+        game.set_level_extent(Extent::Unbounded);
+
+        // Make two corridors far away from the player and each other.
+        let player = game.player_loc();
+        let origins = [
+            Point::new(player.x + 100, player.y),
+            Point::new(player.x + 100, player.y + 4 * GROUP_RADIUS),
+        ];
+        for origin in origins.iter() {
+            for dx in 0..10 {
+                let loc = Point::new(origin.x + dx, origin.y);
+                let oid = game.level.get_mut(&loc, TERRAIN_ID).unwrap().0;
+                game.replace_object(&loc, oid, new_obj(ObjectName::Dirt));
+            }
+        }
+        let target0 = Point::new(origins[0].x + 9, origins[0].y);
+        let target1 = Point::new(origins[1].x + 9, origins[1].y);
+        let starts = [origins[0], Point::new(origins[0].x, origins[0].y + 2), origins[1]];
+        let targets = [target0, target0, target1];
+        let mut guards = Vec::new();
+        for (start, target) in starts.iter().zip(targets.iter()) {
+            guards.push(game.add_object(start, new_obj(ObjectName::Guard)));
+            game.replace_behavior(start, Behavior::MovingTo(*target));
+        }
+        let ch = game.level.obj(guards[0]).0.clone();
+        let expected = game.find_path(guards[0], &ch, &origins[0], &target0);
+        assert_eq!(expected.len(), 10);
+
+        // The first two guards are in each other's way so only the first gets a plan.
+        game.plan_paths_for(&guards);
+        let planned: FnvHashSet<Oid> = game.plans.planned.keys().copied().collect();
+        assert_eq!(planned, [guards[0], guards[2]].into_iter().collect());
+
+        // Plans are used if nothing they depend on has changed,
+        let plan = game.plans.planned.get(&guards[0]).unwrap();
+        assert!(plan.is_valid(&game.level, &ch, &origins[0], &target0));
+        assert_eq!(game.find_path(guards[0], &ch, &origins[0], &target0), expected);
+
+        // but not if a cell they looked at was changed.
+        let blocker = Point::new(origins[1].x + 5, origins[1].y);
+        game.add_object(&blocker, new_obj(ObjectName::Guard));
+        let plan = game.plans.planned.get(&guards[2]).unwrap();
+        assert!(plan.path.contains(&blocker));
+        assert!(!plan.is_valid(&game.level, &ch, &origins[1], &target1));
+        let path = game.find_path(guards[2], &ch, &origins[1], &target1);
+        assert!(!path.contains(&blocker));
+        assert!(game.plans.planned.is_empty());
+    }
+}
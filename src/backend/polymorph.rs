@@ -0,0 +1,170 @@
+//! Polymorph temporarily gives a character the stats and appearance of another kind of
+//! character. The oid, location, inventory, behavior, and scheduling are all kept so the
+//! rest of the game doesn't need to know that anything happened.
+use super::make::new_obj;
+use super::object::Object;
+use super::tag::*;
+use super::time::{self, Time};
+use super::{catalog, Game, Message, ObjectName, Oid, Point, Topic};
+use fnv::FnvHashMap;
+
+/// The forms that characters can be polymorphed into.
+pub const POLYMORPH_FORMS: [ObjectName; 4] = [
+    ObjectName::Guard,
+    ObjectName::Icarium,
+    ObjectName::Rhulad,
+    ObjectName::Spectator,
+];
+
+// These are the tags that are swapped. Name is also swapped for NPCs.
+const FORM_TAGS: [Tid; 9] = [
+    STRENGTH_ID,
+    DEXTERITY_ID,
+    DAMAGE_ID,
+    DELAY_ID,
+    CRIT_ID,
+    FLEES_ID,
+    HEARING_ID,
+    DURABILITY_ID,
+    SIZE_ID,
+];
+
+struct Polymorphed {
+    original: Object, // used to restore the character's tags when the polymorph expires
+    expires: Time,
+}
+
+pub struct Polymorphs {
+    active: FnvHashMap<Oid, Polymorphed>,
+}
+
+impl Polymorphs {
+    pub fn new() -> Polymorphs {
+        Polymorphs {
+            active: FnvHashMap::default(),
+        }
+    }
+}
+
+impl Game {
+    pub fn is_polymorphed(&self, oid: Oid) -> bool {
+        self.polymorphs.active.contains_key(&oid)
+    }
+
+    /// Changes the character at loc into a new form. If it is already polymorphed then
+    /// its original form will be used when the new polymorph expires.
+    pub(super) fn do_polymorph(&mut self, loc: &Point, into: ObjectName) {
+        let form = new_obj(into);
+        assert!(form.has(CHARACTER_ID), "{into:?} isn't a character");
+        let oid = match self.level.get(loc, CHARACTER_ID) {
+            Some((oid, _)) => oid,
+            None => {
+                self.messages
+                    .push(Message::new(Topic::Failed, &catalog::text("polymorph.nobody", &[])));
+                return;
+            }
+        };
+
+        let expires = self.now() + time::POLYMORPH;
+        let obj = self.level.obj(oid).0;
+        let old_name = self.character_name(oid);
+        match self.polymorphs.active.get_mut(&oid) {
+            Some(entry) => entry.expires = expires,
+            None => {
+                let original = obj.clone();
+                self.polymorphs.active.insert(oid, Polymorphed { original, expires });
+            }
+        }
+
+        take_form(self.level.obj_mut(oid), &form, oid);
+        let new_name = self.character_name(oid);
+        let text = if oid.0 == 0 {
+            catalog::text("polymorph.player", &[("name", &form.name_value().unwrap())])
+        } else {
+            catalog::text("polymorph.npc", &[("old_name", &old_name), ("new_name", &new_name)])
+        };
+        self.messages.push(Message::new(Topic::Important, &text));
+    }
+
+    /// Restores characters whose polymorph has expired.
+    pub(super) fn expire_polymorphs(&mut self) {
+        let now = self.now();
+        let mut expired: Vec<Oid> = self
+            .polymorphs
+            .active
+            .iter()
+            .filter_map(|(oid, entry)| if entry.expires <= now { Some(*oid) } else { None })
+            .collect();
+        expired.sort(); // so that messages are deterministic
+        for oid in expired {
+            let entry = self.polymorphs.active.remove(&oid).unwrap();
+            if self.level.try_obj(oid).is_some() {
+                take_form(self.level.obj_mut(oid), &entry.original, oid);
+                let text = if oid.0 == 0 {
+                    catalog::text("polymorph.end.player", &[])
+                } else {
+                    catalog::text("polymorph.end.npc", &[("name", &self.character_name(oid))])
+                };
+                self.messages.push(Message::new(Topic::Important, &text));
+            }
+        }
+    }
+
+    fn character_name(&self, oid: Oid) -> &'static str {
+        self.level.obj(oid).0.name_value().unwrap()
+    }
+}
+
+// Damage taken in one form carries over to the other form.
+fn take_form(obj: &mut Object, form: &Object, oid: Oid) {
+    let old = obj.durability_value();
+    if oid.0 == 0 {
+        obj.take_form(form, &FORM_TAGS);
+    } else {
+        let mut tids = FORM_TAGS.to_vec();
+        tids.push(NAME_ID);
+        obj.take_form(form, &tids);
+    }
+
+    if let (Some(old), Some(new)) = (old, obj.durability_value()) {
+        let current = (new.max * old.current / old.max.max(1)).max(1);
+        obj.replace(Tag::Durability(Durability { current, max: new.max }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_game;
+    use crate::backend::Action;
+
+    #[test]
+    fn test_polymorph() {
+        let mut game = test_game();
+        let loc = game.player_loc();
+        let max_hps = game.player_hps().1;
+        game.player_acted(Action::Polymorph {
+            loc,
+            into: ObjectName::Rhulad,
+        });
+        assert!(game.is_polymorphed(Oid(0)));
+        assert_eq!(game.player_loc(), loc);
+
+        let player = game.level.obj(Oid(0)).0;
+        assert!(player.has(PLAYER_ID));
+        assert!(player.has(INVENTORY_ID));
+        assert_eq!(player.damage_value(), Some(24));
+
+        // Eventually the polymorph wears off.
+        for _ in 0..100 {
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.player_acted(Action::Rest);
+        }
+        assert!(!game.is_polymorphed(Oid(0)));
+        let player = game.level.obj(Oid(0)).0;
+        assert_eq!(player.damage_value(), Some(6));
+        assert_eq!(game.player_hps().1, max_hps);
+    }
+}
@@ -1,30 +1,72 @@
-use super::primitives::FoV;
+use super::primitives::{FoV, Shadowcast};
 use super::{Game, Object, Oid, Point};
 use fnv::FnvHashSet;
+use std::sync::Mutex;
 
-pub const RADIUS: i32 = 10; // TODO: should this depend on race or perception? or gear?
+/// Vision radius for characters without a Vision tag. This is also used for things that
+/// scale with what the player can typically see, e.g. how far away noises are heard.
+pub const RADIUS: i32 = 10;
+
+/// Algorithms used to decide which cells the player can see. This affects how the game
+/// plays out so it's recorded in saved games.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FovAlgorithm {
+    /// Precise Permissive Field of View: sees more but isn't symmetric.
+    #[default]
+    Permissive,
+
+    /// Symmetric Shadowcasting: if the player can see a cell then someone in that cell can
+    /// see the player.
+    Symmetric,
+}
+
+static NEW_GAME_ALGORITHM: Mutex<FovAlgorithm> = Mutex::new(FovAlgorithm::Permissive);
+
+/// Sets the algorithm used by new games. Saved games continue to use the algorithm they
+/// were started with.
+pub fn set_fov_algorithm(algorithm: FovAlgorithm) {
+    *NEW_GAME_ALGORITHM.lock().unwrap() = algorithm;
+}
+
+pub fn new_game_fov_algorithm() -> FovAlgorithm {
+    *NEW_GAME_ALGORITHM.lock().unwrap()
+}
 
 /// Field of View for a character. These are invalidated for certain events
 /// (e.g. terrain changes).
 pub struct PoV {
+    algorithm: FovAlgorithm,
     edition: u32, // incremented each time visible is updated
     visible: FnvHashSet<Point>,
-    dirty: bool, // true if visible is invalid
+    los: FnvHashSet<Point>, // like visible but ignores things like blindness
+    radius: i32,            // vision radius used for los
+    dirty: bool,            // true if visible is invalid
 }
 
 impl PoV {
-    pub fn new() -> PoV {
+    pub fn new(algorithm: FovAlgorithm) -> PoV {
         PoV {
+            algorithm,
             edition: 0,
             visible: FnvHashSet::default(),
+            los: FnvHashSet::default(),
+            radius: RADIUS,
             dirty: true,
         }
     }
 
+    pub fn algorithm(&self) -> FovAlgorithm {
+        self.algorithm
+    }
+
     pub fn dirty(&mut self) {
         self.dirty = true;
     }
 
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
     pub fn edition(&self) -> u32 {
         self.edition
     }
@@ -37,13 +79,25 @@ impl PoV {
     /// Returns true if loc is visible from origin.
     pub fn visible(&self, game: &Game, loc: &Point) -> bool {
         assert!(!self.dirty);
-        if loc.distance2(&game.player_loc()) <= RADIUS * RADIUS {
+        if loc.distance2(&game.player_loc()) <= self.radius * self.radius {
             self.visible.contains(loc)
         } else {
             false
         }
     }
 
+    /// Returns true if there is a line of sight between the player and loc. Unlike visible
+    /// this ignores effects like blindness that only impair the player (so it's what NPCs
+    /// use to see the player).
+    pub fn in_los(&self, game: &Game, loc: &Point) -> bool {
+        assert!(!self.dirty);
+        if loc.distance2(&game.player_loc()) <= self.radius * self.radius {
+            self.los.contains(loc)
+        } else {
+            false
+        }
+    }
+
     // This can't be an ordinary method or we run into all sorts of borrowing grief.
     pub fn refresh(game: &mut Game) {
         if game.pov.dirty {
@@ -57,25 +111,60 @@ impl PoV {
     // Game is mutable so that we can create a Cell if one isn't already there.
     fn do_refresh(game: &mut Game, origin: &Point) {
         game.pov.visible.clear();
+        game.pov.los.clear();
 
+        let radius = game.vision_radius(Oid(0));
         let mut new_locs = Vec::new();
-        let mut view = FoV {
-            start: *origin,
-            radius: RADIUS,
-            visible_tile: |loc| {
-                new_locs.push(loc);
-            },
-            blocks_los: { |loc| blocks_los(game.level.cell_iter(&loc)) },
-        };
-        view.visit();
+        {
+            let visible_tile = |loc| {
+                if game.level.in_bounds(&loc) {
+                    new_locs.push(loc);
+                }
+            };
+            let blocks = |loc| blocks_los(game.level.cell_iter(&loc));
+            match game.pov.algorithm {
+                FovAlgorithm::Permissive => FoV {
+                    start: *origin,
+                    radius,
+                    visible_tile,
+                    blocks_los: blocks,
+                }
+                .visit(),
+                FovAlgorithm::Symmetric => Shadowcast {
+                    start: *origin,
+                    radius,
+                    visible_tile,
+                    blocks_los: blocks,
+                }
+                .visit(),
+            }
+        }
+        game.pov.radius = radius;
 
+        let sight = game.sight_radius();
+        let lights = game.lights();
         for loc in new_locs {
-            game.pov.visible.insert(loc);
+            if loc.distance2(origin) <= sight * sight && (loc == *origin || !game.is_dark(&loc, &lights)) {
+                game.pov.visible.insert(loc);
+            }
+            game.pov.los.insert(loc);
         }
     }
 }
 
-fn blocks_los<'a>(objs: impl Iterator<Item = (Oid, &'a Object)>) -> bool {
+impl Game {
+    /// Returns how far the player can currently see and how far they can normally see.
+    pub fn player_sight(&self) -> (i32, i32) {
+        (self.sight_radius(), self.vision_radius(Oid(0)))
+    }
+
+    /// How far the character can see when it isn't impaired, see sight_radius.
+    pub(super) fn vision_radius(&self, oid: Oid) -> i32 {
+        self.level.obj(oid).0.vision_value().unwrap_or(RADIUS)
+    }
+}
+
+pub fn blocks_los<'a>(objs: impl Iterator<Item = (Oid, &'a Object)>) -> bool {
     let mut count = 0;
     for obj in objs {
         if obj.1.blocks_los() {
@@ -85,3 +174,56 @@ fn blocks_los<'a>(objs: impl Iterator<Item = (Oid, &'a Object)>) -> bool {
     }
     count == 0 // non-existent cell
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::tag::Tag;
+    use super::super::Action;
+    use super::*;
+    use crate::backend::test_game;
+    use std::fs;
+
+    #[test]
+    fn test_vision() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        assert_eq!(game.player_sight(), (RADIUS, RADIUS));
+
+        // Vision limits how far the player (and NPCs looking at the player) can see.
+        game.level.obj_mut(Oid(0)).replace(Tag::Vision(4));
+        game.pov.dirty();
+        PoV::refresh(&mut game);
+        assert_eq!(game.player_sight(), (4, 4));
+        assert!(game.pov.locations().all(|loc| loc.distance2(&player_loc) <= 4 * 4));
+        assert!(game.pov.locations().any(|loc| loc.distance2(&player_loc) > 3 * 3));
+
+        // The algorithm can be switched.
+        game.level.obj_mut(Oid(0)).replace(Tag::Vision(RADIUS));
+        game.pov.algorithm = FovAlgorithm::Symmetric;
+        game.pov.dirty();
+        PoV::refresh(&mut game);
+        assert!(game.pov.visible(&game, &player_loc));
+        assert!(game
+            .pov
+            .locations()
+            .all(|loc| loc.distance2(&player_loc) <= RADIUS * RADIUS));
+        assert!(game.pov.locations().any(|loc| loc.distance2(&player_loc) > 4 * 4));
+    }
+
+    #[test]
+    fn test_saved_algorithm() {
+        // Loaded games should use the algorithm they were started with.
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::start_game(&path, 1, FovAlgorithm::Symmetric);
+        while !game.players_turn() {
+            game.advance_time(false);
+        }
+        game.player_acted(Action::Rest);
+        drop(game);
+
+        let (game, _) = Game::old_game(&path, Vec::new());
+        assert_eq!(game.pov.algorithm(), FovAlgorithm::Symmetric);
+    }
+}
@@ -2,6 +2,7 @@ mod color;
 mod fov;
 mod path_find;
 mod point;
+mod shadowcast;
 mod size;
 mod vec2d;
 
@@ -9,5 +10,6 @@ pub use color::Color;
 pub use fov::FoV;
 pub use path_find::PathFind;
 pub use point::Point;
+pub use shadowcast::Shadowcast;
 pub use size::Size;
 pub use vec2d::Vec2d;
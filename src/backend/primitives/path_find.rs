@@ -72,7 +72,8 @@ where
         }
     }
 
-    #[cfg(test)]
+    /// Returns the points from start to target (or an empty vector if a path could not be
+    /// found).
     pub fn path(&self) -> &Vec<Point> {
         &self.path
     }
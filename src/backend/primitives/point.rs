@@ -31,6 +31,28 @@ impl Point {
         dx * dx + dy * dy
     }
 
+    /// Returns the compass direction from self to rhs, e.g. "northeast". Note that north
+    /// is towards the top of the map.
+    pub fn direction(&self, rhs: &Point) -> &'static str {
+        let dx = (rhs.x - self.x) as f64;
+        let dy = (self.y - rhs.y) as f64;
+        if dx == 0.0 && dy == 0.0 {
+            return "here";
+        }
+        const NAMES: [&str; 8] = [
+            "east",
+            "northeast",
+            "north",
+            "northwest",
+            "west",
+            "southwest",
+            "south",
+            "southeast",
+        ];
+        let octant = (dy.atan2(dx) / std::f64::consts::FRAC_PI_4).round() as i32;
+        NAMES[octant.rem_euclid(8) as usize]
+    }
+
     pub fn diagnol(&self, new_loc: &Point) -> bool {
         assert!(self != new_loc);
 
@@ -58,3 +80,20 @@ impl Hash for Point {
         s.hash(state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction() {
+        let origin = Point::new(5, 5);
+        assert_eq!(origin.direction(&Point::new(5, 1)), "north");
+        assert_eq!(origin.direction(&Point::new(9, 1)), "northeast");
+        assert_eq!(origin.direction(&Point::new(9, 4)), "east");
+        assert_eq!(origin.direction(&Point::new(1, 9)), "southwest");
+        assert_eq!(origin.direction(&Point::new(4, 9)), "south");
+        assert_eq!(origin.direction(&Point::new(0, 5)), "west");
+        assert_eq!(origin.direction(&origin), "here");
+    }
+}
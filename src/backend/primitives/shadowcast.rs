@@ -0,0 +1,264 @@
+// This is an alternative to FoV using Albert Ford's Symmetric Shadowcasting, see
+// https://www.albertford.com/shadowcasting/. Unlike FoV it guarantees that if A can see B
+// then B can see A which matters because NPCs use the player's view to decide whether
+// they can see the player.
+use super::point::Point;
+#[cfg(test)] // for now this is only used within unit tests
+use super::size::Size;
+#[cfg(test)] // for now this is only used within unit tests
+use super::vec2d::Vec2d;
+use fnv::FnvHashSet;
+
+/// Same interface as FoV.
+pub struct Shadowcast<V, B>
+where
+    V: FnMut(Point),
+    B: Fn(Point) -> bool,
+{
+    /// Where to start checking for visible cells from. Typically the position of a character.
+    pub start: Point,
+
+    /// Maximum distance that LOS can extend to.
+    pub radius: i32,
+
+    /// Called for each visible tile.
+    pub visible_tile: V,
+
+    /// Returns true if the tile blocks LOS.
+    pub blocks_los: B,
+}
+
+impl<V, B> Shadowcast<V, B>
+where
+    V: FnMut(Point),
+    B: Fn(Point) -> bool,
+{
+    /// Calls self.visible_tile for each tile that is visible from start.
+    pub fn visit(&mut self) {
+        // As with FoV if the starting point cannot be seen then the character is blind.
+        if (self.blocks_los)(self.start) {
+            return;
+        }
+
+        let mut visited = FnvHashSet::default();
+        (self.visible_tile)(self.start);
+        visited.insert(self.start);
+
+        for quadrant in [Quadrant::North, Quadrant::East, Quadrant::South, Quadrant::West] {
+            let row = Row {
+                depth: 1,
+                start_slope: Slope::new(-1, 1),
+                end_slope: Slope::new(1, 1),
+            };
+            self.scan(&mut visited, quadrant, row);
+        }
+    }
+
+    fn scan(&mut self, visited: &mut FnvHashSet<Point>, quadrant: Quadrant, row: Row) {
+        if row.depth > self.radius {
+            return;
+        }
+
+        let mut row = row;
+        let mut prev_wall = None;
+        for col in row.min_col()..=row.max_col() {
+            let loc = quadrant.transform(self.start, row.depth, col);
+            let wall = (self.blocks_los)(loc);
+            if (wall || row.is_symmetric(col)) && !visited.contains(&loc) {
+                visited.insert(loc);
+                if loc.distance2(&self.start) <= self.radius * self.radius {
+                    (self.visible_tile)(loc);
+                }
+            }
+
+            if prev_wall == Some(true) && !wall {
+                row.start_slope = Slope::of(row.depth, col);
+            }
+            if prev_wall == Some(false) && wall {
+                let mut next = row.next();
+                next.end_slope = Slope::of(row.depth, col);
+                self.scan(visited, quadrant, next);
+            }
+            prev_wall = Some(wall);
+        }
+        if prev_wall == Some(false) {
+            self.scan(visited, quadrant, row.next());
+        }
+    }
+}
+
+// ---- Private Items -------------------------------------------------------------------
+#[derive(Clone, Copy)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    fn transform(self, start: Point, depth: i32, col: i32) -> Point {
+        match self {
+            Quadrant::North => Point::new(start.x + col, start.y - depth),
+            Quadrant::South => Point::new(start.x + col, start.y + depth),
+            Quadrant::East => Point::new(start.x + depth, start.y + col),
+            Quadrant::West => Point::new(start.x - depth, start.y + col),
+        }
+    }
+}
+
+/// Slopes are kept as fractions so that the results are exact.
+#[derive(Clone, Copy)]
+struct Slope {
+    num: i32,
+    den: i32, // always positive
+}
+
+impl Slope {
+    fn new(num: i32, den: i32) -> Slope {
+        Slope { num, den }
+    }
+
+    /// Slope of the left edge of the tile at col.
+    fn of(depth: i32, col: i32) -> Slope {
+        Slope::new(2 * col - 1, 2 * depth)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Row {
+    depth: i32,
+    start_slope: Slope,
+    end_slope: Slope,
+}
+
+impl Row {
+    // floor(depth * start_slope + 0.5)
+    fn min_col(&self) -> i32 {
+        let s = self.start_slope;
+        (2 * self.depth * s.num + s.den).div_euclid(2 * s.den)
+    }
+
+    // ceil(depth * end_slope - 0.5)
+    fn max_col(&self) -> i32 {
+        let s = self.end_slope;
+        -(s.den - 2 * self.depth * s.num).div_euclid(2 * s.den)
+    }
+
+    fn is_symmetric(&self, col: i32) -> bool {
+        let (start, end) = (self.start_slope, self.end_slope);
+        col * start.den >= self.depth * start.num && col * end.den <= self.depth * end.num
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Do we visit a subset of the cells when there are no obstructions and radius is small?
+    #[test]
+    fn test_small_empty() {
+        let size = Size::new(7, 7);
+        let radius = 2;
+        let original = Vec2d::new(size, '.');
+        let actual = visit_tiles(&original, size, radius);
+
+        let expected = "\n???????\n???.???\n??...??\n?..x..?\n??...??\n???.???\n???????";
+        assert_eq!(actual, expected);
+    }
+
+    // Does a full length wall block los?
+    #[test]
+    fn test_long_wall() {
+        let size = Size::new(7, 7);
+        let radius = 10;
+        let mut original = Vec2d::new(size, '.');
+        for x in 0..size.width {
+            original.set(Point::new(x, 1), '#');
+        }
+        let actual = visit_tiles(&original, size, radius);
+
+        let expected = "\n???????\n#######\n.......\n...x...\n.......\n.......\n.......";
+        assert_eq!(actual, expected);
+    }
+
+    // Does a pillar block los?
+    #[test]
+    fn test_pillar() {
+        let size = Size::new(8, 8);
+        let radius = 10;
+        let mut original = Vec2d::new(size, '.');
+        original.set(Point::new(size.width / 2, 2), '#');
+        let actual = visit_tiles(&original, size, radius);
+
+        let expected = "\n....?...\n....?...\n....#...\n........\n....x...\n........\n........\n........";
+        assert_eq!(actual, expected);
+    }
+
+    // Is everything blocked if nothing can be seen?
+    #[test]
+    fn test_blinded() {
+        let size = Size::new(6, 6);
+        let radius = 10;
+        let original = Vec2d::new(size, '#');
+        let actual = visit_tiles(&original, size, radius);
+
+        let expected = "\n??????\n??????\n??????\n??????\n??????\n??????";
+        assert_eq!(actual, expected);
+    }
+
+    // If the start can see a cell then that cell can see the start.
+    #[test]
+    fn test_symmetry() {
+        let size = Size::new(9, 9);
+        let mut cells = Vec2d::new(size, '.');
+        for loc in [(2, 2), (5, 3), (6, 6), (3, 5), (4, 1), (1, 6)] {
+            cells.set(Point::new(loc.0, loc.1), '#');
+        }
+        let start = Point::new(size.width / 2, size.height / 2);
+        for loc in visible(&cells, start, 10) {
+            if get_symbol(&cells, &loc) != '#' {
+                assert!(visible(&cells, loc, 10).contains(&start), "{loc} can't see {start}");
+            }
+        }
+    }
+
+    fn get_symbol(cells: &Vec2d<char>, loc: &Point) -> char {
+        if loc.x >= 0 && loc.y >= 0 && loc.x < cells.size().width && loc.y < cells.size().height {
+            *cells.get(*loc)
+        } else {
+            '#'
+        }
+    }
+
+    fn visible(cells: &Vec2d<char>, start: Point, radius: i32) -> Vec<Point> {
+        let mut result = Vec::new();
+        let mut view = super::Shadowcast {
+            start,
+            radius,
+            visible_tile: |loc| result.push(loc),
+            blocks_los: |loc| get_symbol(cells, &loc) == '#',
+        };
+        view.visit();
+        result
+    }
+
+    fn visit_tiles(old_cells: &Vec2d<char>, size: Size, radius: i32) -> String {
+        let mut new_cells = Vec2d::new(size, '?');
+        let start = Point::new(size.width / 2, size.height / 2);
+        for loc in visible(old_cells, start, radius) {
+            let value = if loc == start { 'x' } else { get_symbol(old_cells, &loc) };
+            if loc.x >= 0 && loc.y >= 0 && loc.x < new_cells.size().width && loc.y < new_cells.size().height {
+                new_cells.set(loc, value);
+            }
+        }
+        format!("{}", new_cells)
+    }
+}
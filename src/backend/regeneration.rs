@@ -0,0 +1,89 @@
+//! Characters slowly regain hit points as time passes. NPCs only regenerate when they
+//! aren't fighting so a wounded NPC that gets away will come back healthier (and one
+//! that is chased down won't).
+use super::statuses::Status;
+use super::tag::*;
+use super::{Game, Oid};
+
+/// Extra hit points regained each tick by characters with the Healing status.
+const HEALING_RATE: i32 = 3;
+
+impl Game {
+    /// True if the player is currently regaining hit points.
+    pub fn player_regenerating(&self) -> bool {
+        let (current, max) = self.player_hps();
+        current < max && self.regen_rate(Oid(0)) > 0
+    }
+
+    /// Called by the scheduler each time it advances time.
+    pub(super) fn regenerate(&mut self, oids: &[Oid]) {
+        for &oid in oids {
+            let rate = self.regen_rate(oid);
+            if rate == 0 {
+                continue;
+            }
+            let durability = match self.level.try_obj(oid) {
+                Some(obj) if obj.has(CHARACTER_ID) => obj.durability_value(),
+                _ => None,
+            };
+            if let Some(durability) = durability {
+                if durability.current < durability.max {
+                    self.level.obj_mut(oid).replace(Tag::Durability(Durability {
+                        current: (durability.current + rate).min(durability.max),
+                        ..durability
+                    }));
+                }
+            }
+        }
+    }
+
+    fn regen_rate(&self, oid: Oid) -> i32 {
+        let in_combat = matches!(
+            self.level.try_obj(oid).and_then(|obj| obj.behavior_value()),
+            Some(Behavior::Attacking(..))
+        );
+        let mut rate = if in_combat { 0 } else { 1 };
+        if self.has_status(oid, Status::Healing) {
+            rate += HEALING_RATE;
+        }
+        rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::test_game;
+    use crate::backend::time;
+    use crate::backend::{ObjectName, Point};
+
+    fn wound(game: &mut Game, oid: Oid) -> i32 {
+        let obj = game.level.obj_mut(oid);
+        let max = obj.durability_value().unwrap().max;
+        obj.replace(Tag::Durability(Durability { current: max / 2, max }));
+        max / 2
+    }
+
+    #[test]
+    fn test_regeneration() {
+        let mut game = test_game();
+        let loc = game.player_loc();
+        let guard = game.add_object(&Point::new(loc.x + 100, loc.y), new_obj(ObjectName::Guard));
+
+        // Wounded characters slowly heal (but NPCs only when they aren't fighting).
+        let hps = wound(&mut game, Oid(0));
+        assert!(game.player_regenerating());
+        wound(&mut game, guard);
+        game.replace_behavior(&Point::new(loc.x + 100, loc.y), Behavior::Attacking(Oid(0), loc));
+        game.regenerate(&[Oid(0), guard]);
+        assert_eq!(game.player_hps().0, hps + 1);
+        let durability = game.level.obj(guard).0.durability_value().unwrap();
+        assert_eq!(durability.current, durability.max / 2);
+
+        // Healing effects speed that up.
+        game.add_status(Oid(0), Status::Healing, time::HASTED);
+        game.regenerate(&[Oid(0)]);
+        assert_eq!(game.player_hps().0, hps + 2 + HEALING_RATE);
+    }
+}
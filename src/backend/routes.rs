@@ -0,0 +1,146 @@
+//! NPCs that are far from the player use a cheaper simulation ("world ticks"): instead
+//! of path finding every time they move they follow a cached route. The route is only
+//! recomputed when it no longer leads to the target or the next step is blocked. Once
+//! an NPC gets close to the player it switches back to full path finding.
+use super::ai;
+use super::object::Object;
+use super::pov;
+use super::tag::CHARACTER_ID;
+use super::{Game, Oid, Point};
+use fnv::FnvHashMap;
+
+/// NPCs within this distance of the player always use full path finding.
+pub const FULL_SIM_RADIUS: i32 = 2 * pov::RADIUS;
+
+struct Route {
+    target: Point,
+    path: Vec<Point>, // reversed so that the next step is at the end
+}
+
+pub struct Routes {
+    cached: FnvHashMap<Oid, Route>,
+}
+
+impl Routes {
+    pub fn new() -> Routes {
+        Routes {
+            cached: FnvHashMap::default(),
+        }
+    }
+
+    pub fn remove(&mut self, oid: Oid) {
+        self.cached.remove(&oid);
+    }
+}
+
+impl Game {
+    /// Returns true if the NPC at loc should use a cached route instead of path finding.
+    pub(super) fn is_distant(&self, loc: &Point) -> bool {
+        loc.distance2(&self.player_loc()) > FULL_SIM_RADIUS * FULL_SIM_RADIUS
+    }
+
+    /// Returns the next location from start to target using the lowest Time path. Distant
+    /// NPCs use a cached route instead of finding a new path each time they move.
+    pub(super) fn next_route_loc(&mut self, oid: Oid, ch: &Object, start: &Point, target: &Point) -> Option<Point> {
+        if !self.is_distant(start) {
+            self.routes.remove(oid);
+            let next = *self.find_path(oid, ch, start, target).get(1)?; // first entry is start
+            if self.level.get(&next, CHARACTER_ID).is_some() {
+                // We want to allow moving towards a character, but not into it.
+                return None;
+            }
+            return Some(next);
+        }
+
+        if let Some(next) = self.cached_step(oid, ch, start, target) {
+            let route = self.routes.cached.get_mut(&oid).unwrap();
+            route.path.pop();
+            if route.path.is_empty() {
+                self.routes.remove(oid);
+            }
+            return Some(next);
+        }
+
+        // Note that, as above, we don't want to move into a character.
+        self.routes.remove(oid);
+        let mut path: Vec<Point> = self.find_path(oid, ch, start, target).into_iter().skip(1).collect();
+        path.reverse();
+        let next = path.pop()?;
+        if self.level.get(&next, CHARACTER_ID).is_some() {
+            return None;
+        }
+        if !path.is_empty() {
+            self.routes.cached.insert(oid, Route { target: *target, path });
+        }
+        Some(next)
+    }
+
+    /// Returns the next step in the cached route if it's still usable.
+    pub(super) fn cached_step(&self, oid: Oid, ch: &Object, start: &Point, target: &Point) -> Option<Point> {
+        let route = self.routes.cached.get(&oid)?;
+        let next = *route.path.last()?;
+        if route.target != *target || !start.adjacent(&next) {
+            return None;
+        }
+
+        let mut neighbors = Vec::new();
+        ai::successors(&self.level, ch, *start, target, &mut neighbors);
+        if neighbors.iter().any(|(loc, _)| *loc == next) && self.level.get(&next, CHARACTER_ID).is_none() {
+            Some(next)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::tag::*;
+    use crate::backend::ObjectName;
+    use std::fs;
+
+    #[test]
+    fn test_distant_routes() {
+        let path = format!("/tmp/saved-{}.game", line!());
+        let _ = fs::remove_file(&path);
+
+        let mut game = Game::new_game(&path, 1);
+        game.set_level_extent(crate::backend::Extent::Unbounded);
+
+        // Make an open area far away from the player.
+        let player = game.player_loc();
+        let origin = Point::new(player.x + 10 * FULL_SIM_RADIUS, player.y);
+        for dx in 0..10 {
+            let loc = Point::new(origin.x + dx, origin.y);
+            let oid = game.level.get_mut(&loc, TERRAIN_ID).unwrap().0;
+            game.replace_object(&loc, oid, new_obj(ObjectName::Dirt));
+        }
+        let npc = game.add_object(&origin, new_obj(ObjectName::Guard));
+        let ch = game.level.obj(npc).0.clone();
+        let target = Point::new(origin.x + 9, origin.y);
+
+        // Distant NPCs cache their route,
+        assert!(game.is_distant(&origin));
+        let next = game.next_route_loc(npc, &ch, &origin, &target).unwrap();
+        assert_eq!(next, Point::new(origin.x + 1, origin.y));
+        assert_eq!(game.routes.cached.get(&npc).unwrap().path.len(), 8);
+
+        // and follow it.
+        game.do_move(npc, &origin, &next);
+        let next2 = game.next_route_loc(npc, &ch, &next, &target).unwrap();
+        assert_eq!(next2, Point::new(origin.x + 2, origin.y));
+        assert_eq!(game.routes.cached.get(&npc).unwrap().path.len(), 7);
+
+        // Blocked routes are recomputed (here there's no other way to go).
+        let blocker = Point::new(origin.x + 3, origin.y);
+        game.add_object(&blocker, new_obj(ObjectName::Guard));
+        game.do_move(npc, &next, &next2);
+        assert!(game.next_route_loc(npc, &ch, &next2, &target).is_none());
+        assert!(!game.routes.cached.contains_key(&npc));
+
+        // NPCs close to the player use full path finding.
+        assert!(!game.is_distant(&player));
+    }
+}
@@ -0,0 +1,122 @@
+//! Lets the player move repeatedly in one direction until something interesting happens
+//! (the UI calls run_step once per turn and stops when it returns None).
+use super::tag::*;
+use super::{Action, Game, Oid, Point, Topic};
+
+/// State for a run in progress, see Game::start_run.
+pub struct Run {
+    dx: i32,
+    dy: i32,
+    terrain: Terrain,     // terrain the run started on
+    exits: Option<usize>, // number of open cells around the player (set after the first step)
+    seen_messages: usize, // from Game::num_messages
+}
+
+impl Game {
+    pub fn start_run(&self, dx: i32, dy: i32) -> Run {
+        assert!((-1..=1).contains(&dx));
+        assert!((-1..=1).contains(&dy));
+        let loc = self.player_loc();
+        Run {
+            dx,
+            dy,
+            terrain: self.level.get_bottom(&loc).1.terrain_value().unwrap(),
+            exits: None,
+            seen_messages: self.num_messages(),
+        }
+    }
+
+    /// Returns the next action for the run or None if the player should stop running.
+    /// Runs stop when the way is blocked, at forks, when the terrain changes, when an
+    /// enemy is visible, and when a message other than a normal message is added.
+    pub fn run_step(&self, run: &mut Run) -> Option<Action> {
+        if self.interrupted(&mut run.seen_messages) {
+            return None;
+        }
+
+        let loc = self.player_loc();
+        if self.level.get_bottom(&loc).1.terrain_value().unwrap() != run.terrain {
+            return None;
+        }
+
+        let next = Point::new(loc.x + run.dx, loc.y + run.dy);
+        if !self.can_teleport_to(Oid(0), &next) {
+            return None;
+        }
+
+        // The number of open cells around the player stays the same while running down a
+        // corridor or along a wall so if it changes we've reached a fork or an opening.
+        let exits = self.num_exits(&loc, run);
+        match run.exits {
+            Some(old) if old != exits => return None,
+            _ => run.exits = Some(exits),
+        }
+
+        Some(Action::Move { dx: run.dx, dy: run.dy })
+    }
+
+    /// Used to stop actions that take multiple turns: returns true if the game is over,
+    /// an enemy is visible, or a message other than a normal message was added since
+    /// seen_messages (which is updated).
+    pub(super) fn interrupted(&self, seen_messages: &mut usize) -> bool {
+        if self.game_over() {
+            return true;
+        }
+
+        let count = self.num_messages().saturating_sub(*seen_messages);
+        *seen_messages = self.num_messages();
+        if self.recent_messages(count).any(|m| m.topic != Topic::Normal) {
+            return true;
+        }
+
+        self.npcs(false)
+            .iter()
+            .any(|npc| npc.disposition == Disposition::Aggressive)
+    }
+
+    fn num_exits(&self, loc: &Point, run: &Run) -> usize {
+        let behind = Point::new(loc.x - run.dx, loc.y - run.dy);
+        let mut count = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let candidate = Point::new(loc.x + dx, loc.y + dy);
+                if candidate != *loc && candidate != behind && self.can_teleport_to(Oid(0), &candidate) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::make::new_obj;
+    use crate::backend::ObjectName;
+    use crate::backend::{open_neighbor, test_game};
+
+    #[test]
+    fn test_run() {
+        let mut game = test_game();
+
+        // Runs stop before the player walks into something.
+        let (loc, neighbor) = (game.player_loc(), open_neighbor(&game));
+        let mut run = game.start_run(neighbor.x - loc.x, neighbor.y - loc.y);
+        let mut steps = 0;
+        while let Some(action) = game.run_step(&mut run) {
+            game.player_acted(action);
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            steps += 1;
+            assert!(steps < 500, "run never stopped");
+        }
+
+        // Runs don't start when an enemy is in view.
+        let loc = game.player_loc();
+        let enemy_loc = open_neighbor(&game);
+        let mut run = game.start_run(loc.x - enemy_loc.x, loc.y - enemy_loc.y);
+        game.add_object(&enemy_loc, new_obj(ObjectName::ImrothTheCruel));
+        assert!(game.run_step(&mut run).is_none());
+    }
+}
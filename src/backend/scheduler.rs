@@ -24,22 +24,58 @@
 // that bit of extra time. So a wizard who casts a long spell may have to wait a while to
 // cast it and once it goes off everything else will be able to do quite a lot while the
 // wizard is recovering.
+//
+// Fairness works like this: every object with at least MIN_TIME units is put into a round
+// and each object in the round gets exactly one chance to act before time advances. So no
+// matter how fast, or how many, NPCs are they cannot starve the player (or each other) of
+// turns. Objects that are slower than a round simply keep accumulating units until they
+// have enough to do something. To keep things from being too predictable the order of
+// objects within a round is shuffled and the time actions take is fuzzed a bit. That
+// jitter can be disabled (e.g. for tests) in which case objects with more units go first
+// and ties are broken by oid.
 use super::ai::{self, Acted};
-use super::tag::CHARACTER_ID;
 use super::time;
 use super::{Action, Game, Oid, Time};
-use crate::backend::{Durability, Tag};
 use fnv::FnvHashMap;
 use rand::prelude::SliceRandom;
 use rand::rngs::SmallRng;
 use std::cell::RefCell;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
+use std::collections::VecDeque;
+use std::fmt::{self, Formatter};
+use std::hash::{Hash, Hasher};
 use std::io::{Error, Write};
 
+const MAX_HISTORY: usize = 5;
+
+/// The result of giving an object a chance to act.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Acted(Time),
+    DidntAct,
+}
+
+/// Used by wizard mode to see what the scheduler is doing.
+pub struct ScheduledObj {
+    pub oid: Oid,
+    pub name: String,
+
+    /// Objects can act once they have at least MIN_TIME units.
+    pub units: Time,
+
+    /// Approximate time at which the object will next be able to act.
+    pub next_act: Time,
+
+    /// The scheduler time and outcome of recent chances to act, oldest first.
+    pub recent: Vec<(Time, Outcome)>,
+}
+
 pub struct Scheduler {
     entries: FnvHashMap<Oid, Time>,
     now: Time,
     round: Vec<Entry>, // objects who are given a chance to move before time advances
+    history: FnvHashMap<Oid, VecDeque<(Time, Outcome)>>, // only used for debugging so not part of the checksum
+    jitter: bool,      // if false rounds aren't shuffled and action times aren't fuzzed
 }
 
 impl Scheduler {
@@ -48,13 +84,31 @@ impl Scheduler {
             entries: FnvHashMap::default(),
             now: Time::zero(),
             round: Vec::new(),
+            history: FnvHashMap::default(),
+            jitter: true,
         }
     }
 
+    /// Note that saved games don't record this so it should only be disabled for games
+    /// that won't be replayed.
+    #[cfg(test)]
+    pub fn set_jitter(&mut self, enabled: bool) {
+        self.jitter = enabled;
+    }
+
     pub fn now(&self) -> Time {
         self.now
     }
 
+    pub fn num_entries(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Objects that have yet to act in the current round in the order they will act.
+    pub fn round(&self) -> impl Iterator<Item = Oid> + '_ {
+        self.round.iter().rev().map(|entry| entry.oid)
+    }
+
     /// Player starts with a small amount of time units. NPCs start out with zero time
     /// units. That way the player will always have the first move. Other objects may
     /// start out with a negative time so that they execute some time in the future.
@@ -79,31 +133,21 @@ impl Scheduler {
         // Note that objects can remove themselves from scheduling if they have nothing
         // left to do so this may be a no-op.
         self.entries.remove(&oid);
+        self.history.remove(&oid);
     }
 
     /// Iterates through all objects in the current round until one performs an action.
-    pub fn player_is_ready(game: &mut Game) -> bool {
+    /// When replaying the Action::Object entries have already been counted so they aren't
+    /// pushed again.
+    pub fn player_is_ready(game: &mut Game, replay: bool) -> bool {
         // To ensure fairness all objects with the minimum action time are collected
         // together into a "round". Once they have all had a chance to move time advances
         // and a new round starts.
         if game.scheduler.round.is_empty() {
-            let mut items: Vec<Entry> = game
-                .scheduler
-                .entries
-                .iter()
-                .filter_map(|(&oid, &units)| {
-                    if units >= time::MIN_TIME {
-                        Some(Entry { oid, units })
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-            items.shuffle(&mut *game.rng());
-            game.scheduler.round = items;
+            game.scheduler.start_round(&game.rng);
+            game.plan_paths();
         }
-        while !game.scheduler.round.is_empty() {
-            let entry = game.scheduler.round.pop().unwrap();
+        while let Some(entry) = game.scheduler.round.pop() {
             if entry.oid.0 == 0 {
                 // The player can move whenever he has a bit of time. This may once in a
                 // while matter but he will go into negative time units which will allow
@@ -115,11 +159,14 @@ impl Scheduler {
                         assert!(duration >= time::MIN_TIME);
                         assert!(duration <= entry.units);
                         game.scheduler.obj_acted(entry.oid, duration, &game.rng);
+                        game.scheduler.record(entry.oid, Outcome::Acted(duration));
                         return false;
                     }
-                    Acted::DidntAct => (),
+                    Acted::DidntAct => game.scheduler.record(entry.oid, Outcome::DidntAct),
                     Acted::Removed => {
-                        game.stream.push(Action::Object);
+                        if !replay {
+                            game.push_action(Action::Object);
+                        }
                         return false; // there's been some sort of state change so the UI may need to update
                     }
                 }
@@ -136,7 +183,7 @@ impl Scheduler {
         // execute, but that doesn't affect scheduler time. It merely reduces the amount
         // of time that object has to do things. Time only advances after all the objects
         // have had a chance to move.
-        let taken = taken.fuzz(rng);
+        let taken = self.jittered(taken, rng);
         let units = self.entries.get_mut(&Oid(0)).unwrap();
         *units -= taken;
         trace!("   player acted for {taken} and has {units}");
@@ -147,18 +194,32 @@ impl Scheduler {
     /// weaker one out of the way.
     pub fn force_acted(&mut self, oid: Oid, taken: Time, rng: &RefCell<SmallRng>) {
         assert!(taken >= time::MIN_TIME);
-        let taken = taken.fuzz(rng);
+        let taken = self.jittered(taken, rng);
         if let Some(units) = self.entries.get_mut(&oid) {
             *units -= taken;
             trace!("   {oid} forced acted for {taken} and has {units}");
         }
     }
 
+    /// Used to verify that replayed games are in sync with the original game.
+    pub fn checksum<H: Hasher>(&self, hasher: &mut H) {
+        let mut items: Vec<Entry> = self.entries.iter().map(|(&oid, &units)| Entry { oid, units }).collect();
+        items.sort_by_key(|entry| entry.oid.0);
+        for entry in items {
+            entry.oid.hash(hasher);
+            hasher.write_i64(entry.units.as_ms());
+        }
+        hasher.write_i64(self.now.as_ms());
+        for entry in self.round.iter() {
+            entry.oid.hash(hasher);
+        }
+    }
+
     pub fn dump<W: Write>(&self, writer: &mut W, game: &Game) -> Result<(), Error> {
         write!(writer, "scheduler is at {}\n", self.now)?;
 
         let mut items: Vec<Entry> = self.entries.iter().map(|(&oid, &units)| Entry { oid, units }).collect();
-        items.sort_by(|a, b| a.units.partial_cmp(&b.units).unwrap());
+        items.sort_by(|a, b| a.units.cmp(&b.units).then_with(|| a.oid.0.cmp(&b.oid.0)));
 
         write!(writer, "   oid  units dname\n")?;
         for entry in items.iter().rev() {
@@ -169,41 +230,121 @@ impl Scheduler {
     }
 }
 
+impl Game {
+    pub fn now(&self) -> Time {
+        self.scheduler.now
+    }
+
+    /// Returns all the scheduled objects sorted by when they will next act.
+    pub fn scheduled(&self) -> Vec<ScheduledObj> {
+        let now = self.scheduler.now;
+        let mut objs: Vec<ScheduledObj> = self
+            .scheduler
+            .entries
+            .iter()
+            .map(|(&oid, &units)| {
+                let next_act = if units >= time::MIN_TIME {
+                    now
+                } else {
+                    now + (time::MIN_TIME - units)
+                };
+                let recent = self
+                    .scheduler
+                    .history
+                    .get(&oid)
+                    .map_or(Vec::new(), |h| h.iter().copied().collect());
+                ScheduledObj {
+                    oid,
+                    name: self.level.obj(oid).0.dname(),
+                    units,
+                    next_act,
+                    recent,
+                }
+            })
+            .collect();
+        objs.sort_by_key(|obj| (obj.next_act, obj.oid));
+        objs
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Acted(duration) => write!(f, "acted for {duration}"),
+            Outcome::DidntAct => write!(f, "didn't act"),
+        }
+    }
+}
+
 // ---- Private methods ------------------------------------------------------------------
 impl Scheduler {
+    fn record(&mut self, oid: Oid, outcome: Outcome) {
+        let history = self.history.entry(oid).or_default();
+        if history.len() == MAX_HISTORY {
+            history.pop_front();
+        }
+        history.push_back((self.now, outcome));
+    }
+
     fn obj_acted(&mut self, oid: Oid, taken: Time, rng: &RefCell<SmallRng>) {
         assert!(taken >= time::MIN_TIME);
         assert!(oid.0 != 0);
 
-        let taken = taken.fuzz(rng);
+        let taken = self.jittered(taken, rng);
         let units = self.entries.get_mut(&oid).unwrap();
         *units -= taken;
         trace!("   {oid} acted for {taken} and has {units}");
     }
-}
 
-fn advance_time(game: &mut Game) {
-    game.scheduler.now += time::DIAGNOL_MOVE;
-    for units in game.scheduler.entries.values_mut() {
-        *units += time::DIAGNOL_MOVE;
-    }
-
-    for oid in game.scheduler.entries.keys() {
-        if let Some(loc) = game.loc(*oid) {
-            if let Some((_, obj)) = game.level.get_mut(&loc, CHARACTER_ID) {
-                if let Some(durability) = obj.durability_value() {
-                    if durability.current < durability.max {
-                        obj.replace(Tag::Durability(Durability {
-                            current: durability.current + 1, // TODO: should scale differently
-                            ..durability
-                        }));
-                    }
+    fn jittered(&self, taken: Time, rng: &RefCell<SmallRng>) -> Time {
+        if self.jitter {
+            taken.fuzz(rng)
+        } else {
+            taken
+        }
+    }
+
+    // Objects with enough time units to act are given one chance to do so in the round.
+    // Note that round is popped so the last entry goes first.
+    fn start_round(&mut self, rng: &RefCell<SmallRng>) {
+        let mut items: Vec<Entry> = self
+            .entries
+            .iter()
+            .filter_map(|(&oid, &units)| {
+                if units >= time::MIN_TIME {
+                    Some(Entry { oid, units })
+                } else {
+                    None
                 }
-            }
+            })
+            .collect();
+        if self.jitter {
+            items.sort_by_key(|entry| entry.oid.0); // so that the shuffle doesn't depend on hash order
+            items.shuffle(&mut *rng.borrow_mut());
+        } else {
+            items.sort_by_key(|entry| (entry.units, Reverse(entry.oid.0)));
+        }
+        self.round = items;
+    }
+
+    fn advance(&mut self, speed_percent: impl Fn(Oid) -> i64) {
+        self.now += time::DIAGNOL_MOVE;
+        for (oid, units) in self.entries.iter_mut() {
+            *units += time::DIAGNOL_MOVE * speed_percent(*oid) / 100;
         }
     }
 }
 
+fn advance_time(game: &mut Game) {
+    let statuses = &game.statuses;
+    game.scheduler.advance(|oid| statuses.speed_percent(oid));
+
+    let mut oids: Vec<Oid> = game.scheduler.entries.keys().copied().collect();
+    oids.sort();
+    game.regenerate(&oids);
+    game.ambient_events();
+}
+
 // ---- Entry struct ---------------------------------------------------------------------
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 struct Entry {
@@ -223,3 +364,69 @@ impl PartialOrd for Entry {
         Some(self.cmp(rhs))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    // Runs rounds the same way player_is_ready does except that objects always want to do
+    // something that takes delay time. Returns the objects that acted in each round.
+    fn run_rounds(scheduler: &mut Scheduler, delays: &[(Oid, Time)], rounds: usize) -> Vec<Vec<Oid>> {
+        let rng = RefCell::new(SmallRng::seed_from_u64(1));
+        let mut result = Vec::new();
+        for _ in 0..rounds {
+            let mut acted = Vec::new();
+            scheduler.start_round(&rng);
+            while let Some(entry) = scheduler.round.pop() {
+                let delay = delays.iter().find(|(oid, _)| *oid == entry.oid).unwrap().1;
+                if entry.oid.0 == 0 {
+                    scheduler.player_acted(delay, &rng);
+                    acted.push(entry.oid);
+                } else if delay <= entry.units {
+                    scheduler.obj_acted(entry.oid, delay, &rng);
+                    acted.push(entry.oid);
+                }
+            }
+            scheduler.advance(|_| 100);
+            result.push(acted);
+        }
+        result
+    }
+
+    #[test]
+    fn test_identical_delays_alternate() {
+        let mut scheduler = Scheduler::new();
+        scheduler.set_jitter(false);
+        scheduler.add(Oid(1), Time::zero());
+        scheduler.add(Oid(2), Time::zero());
+
+        let delays = [(Oid(1), time::CARDINAL_MOVE), (Oid(2), time::CARDINAL_MOVE)];
+        let acted: Vec<Oid> = run_rounds(&mut scheduler, &delays, 50).into_iter().flatten().collect();
+        assert!(acted.len() > 50);
+        for pair in acted.windows(2) {
+            assert_ne!(pair[0], pair[1], "NPCs didn't alternate: {acted:?}");
+        }
+    }
+
+    #[test]
+    fn test_player_isnt_starved() {
+        for jitter in [false, true] {
+            let mut scheduler = Scheduler::new();
+            scheduler.set_jitter(jitter);
+            scheduler.add(Oid(0), time::DIAGNOL_MOVE);
+            let mut delays = vec![(Oid(0), time::CARDINAL_MOVE)];
+            for i in 1..=30 {
+                scheduler.add(Oid(i), Time::zero());
+                delays.push((Oid(i), time::MIN_TIME));
+            }
+
+            for (i, acted) in run_rounds(&mut scheduler, &delays, 50).iter().enumerate() {
+                assert!(
+                    acted.contains(&Oid(0)),
+                    "player didn't act in round {i} (jitter {jitter})"
+                );
+            }
+        }
+    }
+}
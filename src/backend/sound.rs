@@ -1,15 +1,18 @@
 // Note that the probabilities listed below were computed with scripts/sound_prob.py.
 use super::primitives::PathFind;
 use super::*;
+use fnv::FnvHashMap;
 use rand::rngs::SmallRng;
 use rand::Rng;
 use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::ops::{Add, AddAssign, Mul};
 
 /// Volume represents the percent chance that an NPC will wake up if it is on top of the
 /// noise source. The probability goes down according to 1/distance^1.2 so there is always
 /// a chance that an NPC will wake up (inside the cutoff point anyway).
-#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Sound {
     volume: i32,
 }
@@ -40,17 +43,40 @@ pub const LOUD: Sound = Sound { volume: 500 };
 // /// 100.0 100.0 100.0 100.0 100.0 100.0 96.8 82.5 71.6 63.1 56.3 50.7 46.1.
 // pub const VERY_LOUD: Sound = Sound { volume: 1000 };
 
+// The player hears noises that they can't see if the loudness is at least this. Note that,
+// unlike NPCs, this is deterministic so that replays don't consume extra random numbers.
+// This works out to about 9 squares for QUIET and 26 for LOUD.
+const PLAYER_HEARING: f64 = 10.0;
+
+// Cells where an NPC would have less than this percent chance of hearing a noise are left
+// out of the noise map.
+const MIN_NOISE_PERCENT: f64 = 5.0;
+
 impl Sound {
     fn was_heard(&self, rng: &RefCell<SmallRng>, distance10: i32, hearing: i32) -> (bool, f64) {
-        let scaling = (hearing as f64) / 100.0;
-        let distance = (distance10 as f64) / 10.0;
-        let p = (self.volume as f64) / distance.powf(1.2);
-        let p = p * scaling;
+        let p = self.loudness(distance10, hearing);
 
         let rng = &mut *rng.borrow_mut();
         let x: f64 = rng.gen();
         (x <= p, p)
     }
+
+    fn loudness(&self, distance10: i32, hearing: i32) -> f64 {
+        let scaling = (hearing as f64) / 100.0;
+        let distance = (distance10 as f64) / 10.0;
+        let p = (self.volume as f64) / distance.powf(1.2);
+        p * scaling
+    }
+
+    // Max straight line distance at which the player can hear this.
+    fn player_range(&self) -> i32 {
+        self.range(PLAYER_HEARING)
+    }
+
+    // Max distance at which the loudness is at least threshold.
+    fn range(&self, threshold: f64) -> i32 {
+        ((self.volume as f64) / threshold).powf(1.0 / 1.2).ceil() as i32
+    }
 }
 
 impl Mul<f64> for Sound {
@@ -88,6 +114,9 @@ impl Game {
         // TODO: if this becomes an issue we could look at using the rstar crate to find
         // the NPCs near an arbitrary location (not sure how well that'd work with lots
         // of movement though).
+        debug!(target: "noise", "noise with volume {} at {origin}", noise.volume);
+        self.noises.push((*origin, noise));
+
         let delta2 = origin.distance2(&self.player_loc());
         let npcs: Vec<Point> = self
             .level
@@ -123,27 +152,100 @@ impl Game {
                             // time we handle MovingTo so there's little point in doing that
                             // here too.
                             debug!(
+                                target: "noise",
                                 "{obj} heard a noise and is now moving to {origin}, prob={p:.2}, dist={:.1}",
                                 (distance10 as f64) / 10.0
                             );
                             self.replace_behavior(&loc, Behavior::MovingTo(*origin));
-                            // } else {
-                            //     info!(
-                            //         "{obj} heard a noise but ignored it, prob={p:.2}, dist={:.1}",
-                            //         (distance10 as f64) / 10.0
-                            //     );
+                        } else {
+                            debug!(
+                                target: "noise",
+                                "{obj} heard a noise but ignored it, prob={p:.2}, dist={:.1}",
+                                (distance10 as f64) / 10.0
+                            );
                         }
                     }
-                    // } else {
-                    //     if let Some((_, obj)) = self.level.get(&loc, CHARACTER_ID) {
-                    //         info!(
-                    //             "{obj} did not hear a noise, prob={p:.2}, dist={:.1}",
-                    //             (distance10 as f64) / 10.0
-                    //         );
-                    //     }
+                } else if let Some((_, obj)) = self.level.get(loc, CHARACTER_ID) {
+                    debug!(
+                        target: "noise",
+                        "{obj} did not hear a noise, prob={p:.2}, dist={:.1}",
+                        (distance10 as f64) / 10.0
+                    );
+                }
+            }
+        }
+    }
+
+    /// Adds a message like "You hear fighting to the northeast." if the player is close
+    /// enough to hear a noise that they cannot see. This is used to give players some
+    /// idea of what's happening off-screen without revealing the map.
+    /// what is the catalog key for the noise, e.g. "sound.door".
+    pub fn player_hears(&mut self, origin: &Point, noise: Sound, what: &str) {
+        let player_loc = self.player_loc();
+        let range = noise.player_range();
+        if origin.distance2(&player_loc) > range * range {
+            return;
+        }
+        if self.pov.is_dirty() || self.pov.visible(self, origin) {
+            return; // the player will get a better message if they can see what happened
+        }
+        if let Some(distance10) = self.find_distance10(&player_loc, origin) {
+            if noise.loudness(distance10, 100) >= PLAYER_HEARING {
+                let what = catalog::text(what, &[]);
+                let dir = catalog::text(&format!("direction.{}", player_loc.direction(origin)), &[]);
+                let mesg = Message::new(
+                    Topic::Normal,
+                    &catalog::text("sound.heard", &[("what", &what), ("dir", &dir)]),
+                );
+                self.messages.push(mesg);
+            }
+        }
+    }
+
+    /// Returns the percent chance that an NPC with normal hearing would hear the noises
+    /// made since the player's last turn (using the loudest noise for each cell). This is
+    /// used by wizard mode to figure out why NPCs do or don't wake up.
+    pub fn noise_map(&self) -> FnvHashMap<Point, i32> {
+        let mut map = FnvHashMap::default();
+        for (origin, noise) in self.noises.iter() {
+            for (loc, distance10) in self.sound_distances(origin, noise.range(MIN_NOISE_PERCENT)) {
+                let percent = if distance10 == 0 {
+                    100.0
+                } else {
+                    noise.loudness(distance10, 100).min(100.0)
+                };
+                if percent >= MIN_NOISE_PERCENT {
+                    let entry = map.entry(loc).or_insert(0);
+                    *entry = (*entry).max(percent as i32);
                 }
             }
         }
+        map
+    }
+
+    // Returns the distance10 from origin to each cell that sound can reach within range.
+    fn sound_distances(&self, origin: &Point, range: i32) -> Vec<(Point, i32)> {
+        let mut distances = FnvHashMap::default();
+        let mut heap = BinaryHeap::new();
+        let mut neighbors = Vec::new();
+        distances.insert(*origin, 0);
+        heap.push(Reverse((0, origin.x, origin.y)));
+        while let Some(Reverse((distance10, x, y))) = heap.pop() {
+            let loc = Point::new(x, y);
+            if distances.get(&loc).is_some_and(|&d| d < distance10) {
+                continue; // already found a shorter path to loc
+            }
+            neighbors.clear();
+            self.successors(loc, &mut neighbors);
+            for &(new_loc, d) in neighbors.iter() {
+                let new_distance = distance10 + d;
+                if new_distance <= 10 * range && distances.get(&new_loc).is_none_or(|&old| old > new_distance) {
+                    distances.insert(new_loc, new_distance);
+                    heap.push(Reverse((new_distance, new_loc.x, new_loc.y)));
+                }
+            }
+        }
+        distances.into_iter().collect()
     }
 
     // Returns the distance sound must travel to reach target from origin. Note that this
@@ -160,6 +262,9 @@ impl Game {
         let deltas = vec![(-1, -1), (-1, 1), (-1, 0), (1, -1), (1, 1), (1, 0), (0, -1), (0, 1)];
         for delta in deltas {
             let new_loc = Point::new(loc.x + delta.0, loc.y + delta.1);
+            if !self.level.in_bounds(&new_loc) {
+                continue;
+            }
             let (_, obj) = self.level.get_bottom(&new_loc);
             let mut d = match obj.terrain_value().unwrap() {
                 // sound travels through everything but can be very attenuated
@@ -188,6 +293,10 @@ fn responded_to_noise(obj: &Object, origin: &Point) -> bool {
     match obj.behavior_value() {
         Some(Behavior::Attacking(_, _)) => false,
         Some(Behavior::MovingTo(_)) => false, // TODO: change target if the new noise is louder?
+        Some(Behavior::Searching(..)) => {
+            debug!("{obj} stopped searching and is moving towards noise at {origin}");
+            true
+        }
         Some(Behavior::Sleeping) => {
             debug!("{obj} stopped sleeping and is moving towards noise at {origin}");
             true
@@ -199,3 +308,27 @@ fn responded_to_noise(obj: &Object, origin: &Point) -> bool {
         None => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_game;
+
+    #[test]
+    fn test_noise_map() {
+        let mut game = test_game();
+        let loc = game.player_loc();
+
+        // Noises are loudest at their origin and fade with distance.
+        game.handle_noise(&loc, LOUD);
+        let map = game.noise_map();
+        assert_eq!(map.get(&loc), Some(&100));
+        let far = map.keys().map(|pt| loc.distance2(pt)).max().unwrap();
+        let faint = map.iter().find(|(pt, _)| loc.distance2(pt) == far).unwrap().1;
+        assert!(*faint < 100);
+
+        // The map only covers noises since the player's last turn.
+        game.player_acted(Action::Rest);
+        assert!(!game.noises.contains(&(loc, LOUD)));
+    }
+}
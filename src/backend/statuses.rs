@@ -0,0 +1,257 @@
+//! Temporary effects on characters, e.g. fear. Statuses begin and end with a message and
+//! expire after a duration (adding a status the character already has extends it).
+use super::catalog;
+use super::combat_events::CombatEvent;
+use super::time::Time;
+use super::{Game, Message, Oid, Point, Topic};
+use derive_more::Display;
+use fnv::FnvHashMap;
+use rand::Rng;
+
+#[derive(Clone, Copy, Debug, Display, Eq, Hash, PartialEq)]
+pub enum Status {
+    /// Attacks are less likely to hit and NPCs flee.
+    Frightened,
+
+    /// Moves sometimes go in a random direction.
+    Confused,
+
+    /// Gains time units faster.
+    Hasted,
+
+    /// Gains time units slower.
+    Slowed,
+
+    /// Regains hit points faster.
+    Healing,
+
+    /// Player can only see adjacent cells.
+    Blind,
+
+    /// Player can't see as far as usual.
+    Dazzled,
+}
+
+pub struct Statuses {
+    active: FnvHashMap<Oid, Vec<(Status, Time)>>, // status and when it expires
+}
+
+impl Statuses {
+    pub fn new() -> Statuses {
+        Statuses {
+            active: FnvHashMap::default(),
+        }
+    }
+
+    /// Percentage of the normal rate at which oid gains time units.
+    pub fn speed_percent(&self, oid: Oid) -> i64 {
+        let has = |status| {
+            self.active
+                .get(&oid)
+                .is_some_and(|entries| entries.iter().any(|(s, _)| *s == status))
+        };
+        match (has(Status::Hasted), has(Status::Slowed)) {
+            (true, false) => 150,
+            (false, true) => 50,
+            _ => 100, // haste and slow cancel out
+        }
+    }
+}
+
+impl Game {
+    pub fn has_status(&self, oid: Oid, status: Status) -> bool {
+        self.statuses
+            .active
+            .get(&oid)
+            .is_some_and(|entries| entries.iter().any(|(s, _)| *s == status))
+    }
+
+    /// Returns the statuses currently affecting the player.
+    pub fn player_statuses(&self) -> Vec<Status> {
+        self.statuses
+            .active
+            .get(&Oid(0))
+            .map_or(Vec::new(), |entries| entries.iter().map(|(s, _)| *s).collect())
+    }
+
+    /// Confused characters stagger into a random neighboring cell half the time. Returns
+    /// the cell the character actually moves into.
+    pub(super) fn stagger(&mut self, oid: Oid, loc: &Point, intended: Point) -> Point {
+        if self.has_status(oid, Status::Confused) && self.rng().gen_bool(0.5) {
+            let ch = self.level.obj(oid).0;
+            if let Some(new_loc) = self.find_empty_cell(ch, loc) {
+                debug!("{oid} staggered to {new_loc} instead of {intended}");
+                if oid.0 == 0 {
+                    let text = catalog::text("status.confused.stagger", &[]);
+                    self.messages.push(Message::new(Topic::Normal, &text));
+                }
+                return new_loc;
+            }
+        }
+        intended
+    }
+
+    /// How far the player can currently see (this is less than vision_radius when the
+    /// player is blinded or dazzled).
+    pub(super) fn sight_radius(&self) -> i32 {
+        if self.has_status(Oid(0), Status::Blind) {
+            1
+        } else if self.has_status(Oid(0), Status::Dazzled) {
+            self.vision_radius(Oid(0)) / 3
+        } else {
+            self.vision_radius(Oid(0))
+        }
+    }
+
+    pub(super) fn add_status(&mut self, oid: Oid, status: Status, duration: Time) {
+        let expires = self.now() + duration;
+        let entries = self.statuses.active.entry(oid).or_default();
+        if let Some(entry) = entries.iter_mut().find(|(s, _)| *s == status) {
+            entry.1 = entry.1.max(expires);
+        } else {
+            entries.push((status, expires));
+            if affects_sight(oid, status) {
+                self.pov.dirty();
+            }
+            let text = self.status_text(oid, status, "start");
+            self.messages.push(Message::new(Topic::Normal, &text));
+            if let Some(loc) = self.level.try_loc(oid) {
+                self.push_combat_event(CombatEvent::StatusAdded { loc, status });
+            }
+        }
+    }
+
+    /// Removes statuses that have run their course (and those of characters that have
+    /// died).
+    pub(super) fn expire_statuses(&mut self) {
+        let now = self.now();
+        let mut oids: Vec<Oid> = self.statuses.active.keys().copied().collect();
+        oids.sort(); // so that messages are deterministic
+        for oid in oids {
+            let alive = self.level.try_obj(oid).is_some();
+            let entries = self.statuses.active.get_mut(&oid).unwrap();
+            let mut expired = Vec::new();
+            entries.retain(|(status, expires)| {
+                if *expires <= now || !alive {
+                    expired.push(*status);
+                    false
+                } else {
+                    true
+                }
+            });
+            if entries.is_empty() {
+                self.statuses.active.remove(&oid);
+            }
+            if alive {
+                for status in expired {
+                    if affects_sight(oid, status) {
+                        self.pov.dirty();
+                    }
+                    let text = self.status_text(oid, status, "end");
+                    self.messages.push(Message::new(Topic::Normal, &text));
+                }
+            }
+        }
+    }
+
+    fn status_text(&self, oid: Oid, status: Status, when: &str) -> String {
+        let status = status.to_string().to_lowercase();
+        if oid.0 == 0 {
+            catalog::text(&format!("status.{status}.{when}.player"), &[])
+        } else {
+            let name = self.level.obj(oid).0.name_value().unwrap();
+            catalog::text(&format!("status.{status}.{when}.npc"), &[("name", &name)])
+        }
+    }
+}
+
+// The renderer needs to show the new field of view right away.
+fn affects_sight(oid: Oid, status: Status) -> bool {
+    oid.0 == 0 && matches!(status, Status::Blind | Status::Dazzled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::pov;
+    use crate::backend::test_game;
+    use crate::backend::time;
+    use crate::backend::{Action, OldPoV, PoV, CHARACTER_ID};
+
+    #[test]
+    fn test_statuses() {
+        let mut game = test_game();
+
+        // Haste and slow cancel each other out.
+        game.add_status(Oid(0), Status::Hasted, time::HASTED);
+        assert_eq!(game.statuses.speed_percent(Oid(0)), 150);
+        game.add_status(Oid(0), Status::Slowed, time::HASTED);
+        assert_eq!(game.statuses.speed_percent(Oid(0)), 100);
+        assert_eq!(game.player_statuses(), vec![Status::Hasted, Status::Slowed]);
+
+        // Confused characters sometimes stagger but only into cells they can enter.
+        game.add_status(Oid(0), Status::Confused, time::CONFUSED);
+        let loc = game.player_loc();
+        let intended = Point::new(loc.x + 100, loc.y); // so we can tell when we staggered
+        let mut staggered = 0;
+        for _ in 0..20 {
+            let new_loc = game.stagger(Oid(0), &loc, intended);
+            if new_loc != intended {
+                assert!(loc.adjacent(&new_loc));
+                assert!(game.level.get(&new_loc, CHARACTER_ID).is_none());
+                staggered += 1;
+            }
+        }
+        assert!(staggered > 0 && staggered < 20);
+
+        // Statuses expire with a message.
+        let end = game.now() + time::HASTED;
+        while game.now() <= end {
+            game.player_acted(Action::Rest);
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+        }
+        assert!(game.player_statuses().is_empty());
+        assert!(game.recent_messages(10).any(|m| m.text.contains("Your head clears")));
+    }
+
+    #[test]
+    fn test_blindness() {
+        let mut game = test_game();
+        OldPoV::update(&mut game);
+        let player_loc = game.player_loc();
+        let far = *game
+            .pov
+            .locations()
+            .find(|loc| loc.distance2(&player_loc) > 4 * 4)
+            .unwrap();
+
+        // Blindness limits what the player can see,
+        game.add_status(Oid(0), Status::Blind, time::secs(60));
+        assert!(game.pov.is_dirty());
+        PoV::refresh(&mut game);
+        OldPoV::update(&mut game);
+        assert!(!game.pov.visible(&game, &far));
+        assert!(game
+            .pov
+            .locations()
+            .all(|loc| player_loc.adjacent(loc) || *loc == player_loc));
+
+        // but the player still remembers what was seen before and NPCs can still see the
+        // player.
+        assert!(game.old_pov.get(&far).is_some());
+        assert!(game.pov.in_los(&game, &far));
+
+        // Being dazzled isn't as bad.
+        game.statuses.active.clear();
+        game.add_status(Oid(0), Status::Dazzled, time::secs(60));
+        PoV::refresh(&mut game);
+        assert_eq!(game.sight_radius(), pov::RADIUS / 3);
+        assert!(game
+            .pov
+            .locations()
+            .any(|loc| !player_loc.adjacent(loc) && *loc != player_loc));
+        assert!(!game.pov.visible(&game, &far));
+    }
+}
@@ -1,16 +1,17 @@
+use super::sound::Sound;
 use super::{Color, Oid, Point, Time};
 use derive_more::Display;
 use enum_map::{Enum, EnumMap};
 use std::fmt::{self, Formatter};
 
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Serialize)]
 pub enum Weapon {
     TwoHander,
     OneHand,
     //Ranged,
 }
 
-#[derive(Clone, Copy, Debug, Display, Enum, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Display, Enum, Eq, PartialEq, Serialize)]
 pub enum Slot {
     MainHand,
     OffHand,
@@ -23,14 +24,14 @@ pub enum Slot {
 
 /// Affects behavior of items like burning oil or a pick axe. Also affects
 /// spell behavior and whether characters can move through terrain.
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Serialize)]
 pub enum Material {
     // Wood,
     Stone,
     Metal,
 }
 
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Serialize)]
 pub enum Disposition {
     /// Player cannot attack these.
     Friendly,
@@ -42,7 +43,7 @@ pub enum Disposition {
     Aggressive,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
 pub enum Behavior {
     /// NPC is attempting to attack oid at its last known location.
     Attacking(Oid, Point),
@@ -51,6 +52,10 @@ pub enum Behavior {
     /// there.
     MovingTo(Point),
 
+    /// NPC lost sight of oid at the point at the specified time and is searching around
+    /// there (see Persistence).
+    Searching(Oid, Point, Time),
+
     /// NPC isn't doing anything but may wake up if there are noises.
     Sleeping,
 
@@ -58,13 +63,55 @@ pub enum Behavior {
     Wandering(Time),
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Affects how quickly characters move through terrain, which terrain they can enter, and
+/// whether they can be pushed around.
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Serialize)]
+pub enum BodySize {
+    /// Slowed down more by shallow water.
+    Small,
+
+    Medium,
+
+    /// Wades through water, can't fit through doors, and can't be shoved or knocked back.
+    Large,
+
+    /// Swims through deep water but can't leave the water.
+    Aquatic,
+}
+
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Serialize)]
+pub enum AbilityKind {
+    /// Frightens nearby enemies.
+    FearAura,
+
+    /// A telegraphed attack that does a lot of extra damage. Used when badly hurt.
+    Rage,
+
+    /// A weaker attack that knocks the defender back.
+    ShieldBash,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct Ability {
+    pub kind: AbilityKind,
+
+    /// Minimum time between uses.
+    pub cooldown: Time,
+
+    /// The ability can't be used before this time.
+    pub ready: Time,
+
+    /// Set when a telegraphed ability has been announced (it'll be used next).
+    pub primed: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
 pub struct Durability {
     pub current: i32,
     pub max: i32,
 }
 
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Serialize)]
 pub enum Terrain {
     /// Will have Durability (and usually Material) if the door can be broken down.
     /// If it has a Binding tag then it can only be opened by characters that
@@ -102,6 +149,7 @@ impl fmt::Display for Behavior {
         match self {
             Behavior::Attacking(oid, pt) => write!(f, "Behavior::Attacking({oid}, {pt})"),
             Behavior::MovingTo(pt) => write!(f, "Behavior::MovingTo({pt})"),
+            Behavior::Searching(oid, pt, t) => write!(f, "Behavior::Searching({oid}, {pt}, {t})"),
             Behavior::Sleeping => write!(f, "Behavior::Sleeping"),
             Behavior::Wandering(t) => write!(f, "Behavior::Wandering({t})"),
         }
@@ -110,7 +158,7 @@ impl fmt::Display for Behavior {
 
 // Generated by build.rs, will be at a path like ./target/debug/build/one-thousand-deaths-f4f54e60e59b18ad/out/tag.rs
 // It contains:
-// #[derive(Clone, Debug, Eq, PartialEq)]
+// #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 // pub enum Tag {
 //     Character,
 //     Player,
@@ -0,0 +1,201 @@
+//! Support for actions that affect a distant cell, e.g. throwing an item. Frontends use
+//! targets and projected_path to allow the player to pick a cell and to preview what
+//! will happen when the action is performed.
+use super::catalog;
+use super::tag::*;
+use super::time::{self, Time};
+use super::{pov, Game, Message, Oid, Point, Topic};
+
+/// Maximum distance that items can be thrown.
+pub const THROW_RANGE: i32 = 6;
+
+/// The cells that a projectile will pass through on its way to a target.
+pub struct Projection {
+    /// Cells starting next to the player and ending where the projectile stops. This will
+    /// be the target unless the projectile was blocked or the target is out of range.
+    pub path: Vec<Point>,
+
+    /// The cell (a wall, a closed door, a character, etc) that stopped the projectile
+    /// before it reached the target.
+    pub blocker: Option<Point>,
+}
+
+impl Game {
+    /// Returns the locations of visible hostile characters, closest first.
+    pub fn targets(&self) -> Vec<Point> {
+        let player_loc = self.player_loc();
+        let mut targets: Vec<Point> = self
+            .level
+            .npcs()
+            .filter_map(|oid| {
+                let (obj, loc) = self.level.obj(oid);
+                let loc = loc.unwrap();
+                if obj.disposition_value() == Some(Disposition::Aggressive) && self.pov.visible(self, &loc) {
+                    Some(loc)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        targets.sort_by_key(|loc| loc.distance2(&player_loc));
+        targets
+    }
+
+    /// Returns the cells a projectile thrown (or fired or cast) at target would pass
+    /// through.
+    pub fn projected_path(&self, target: &Point, range: i32) -> Projection {
+        let player_loc = self.player_loc();
+        let mut path = Vec::new();
+        let mut blocker = None;
+        for loc in line(&player_loc, target).into_iter().skip(1) {
+            if loc.distance2(&player_loc) > range * range {
+                break;
+            }
+            path.push(loc);
+            if loc == *target {
+                break;
+            }
+            if self.blocks_projectile(&loc) {
+                blocker = Some(loc);
+                break;
+            }
+        }
+        Projection { path, blocker }
+    }
+
+    pub(super) fn do_throw(&mut self, oid: Oid, target: &Point) -> Time {
+        let projection = self.projected_path(target, THROW_RANGE);
+        let end = match projection.path.last() {
+            Some(&loc) => loc,
+            None => return Time::zero(),
+        };
+
+        self.take_item(oid);
+        self.manage_item_mesg(oid, "item.throw");
+
+        // Items that hit something fall into the cell just before the obstruction.
+        let player_loc = self.player_loc();
+        let landing = if self.blocks_projectile(&end) {
+            if self.level.get(&end, CHARACTER_ID).is_some() {
+                self.do_throw_attack(&player_loc, &end, oid);
+            }
+            let n = projection.path.len();
+            if n > 1 {
+                projection.path[n - 2]
+            } else {
+                player_loc
+            }
+        } else {
+            end
+        };
+        self.level.add_oid(oid, landing);
+        self.pov.dirty();
+
+        // Noisemakers can be used to lure NPCs away.
+        if let Some(noise) = self.level.obj(oid).0.noise_value() {
+            let text = catalog::text("noise.landed", &[("name", &self.item_name(oid))]);
+            self.messages.push(Message::new(Topic::Normal, &text));
+            self.handle_noise(&landing, noise);
+        }
+        time::THROW
+    }
+
+    fn blocks_projectile(&self, loc: &Point) -> bool {
+        let terrain = self.level.get(loc, TERRAIN_ID).unwrap().1;
+        terrain.blocks_los() || self.level.get(loc, CHARACTER_ID).is_some()
+    }
+
+    // Like drop_item except that the item isn't placed into the level.
+    fn take_item(&mut self, oid: Oid) {
+        let player = self.level.get_mut(&self.player_loc(), CHARACTER_ID).unwrap().1;
+        let equipped = player.equipped_value_mut().unwrap();
+        if let Some(value) = equipped.values_mut().find(|value| **value == Some(oid)) {
+            *value = None;
+        } else {
+            let inv = player.inventory_value_mut().unwrap();
+            let index = inv.iter().position(|&o| o == oid).unwrap();
+            inv.remove(index);
+        }
+    }
+}
+
+// Bresenham's line algorithm, includes both start and end.
+fn line(start: &Point, end: &Point) -> Vec<Point> {
+    let dx = (end.x - start.x).abs();
+    let dy = -(end.y - start.y).abs();
+    let sx = if start.x < end.x { 1 } else { -1 };
+    let sy = if start.y < end.y { 1 } else { -1 };
+
+    let mut points = Vec::with_capacity((dx - dy + 1) as usize);
+    let mut loc = *start;
+    let mut err = dx + dy;
+    loop {
+        points.push(loc);
+        if loc == *end || points.len() as i32 > 2 * pov::RADIUS {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            loc.x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            loc.y += sy;
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::make::new_obj;
+    use crate::backend::test_game;
+    use crate::backend::{Action, ObjectName};
+
+    #[test]
+    fn test_line() {
+        let points = line(&Point::new(0, 0), &Point::new(4, 2));
+        let expected = vec![
+            Point::new(0, 0),
+            Point::new(1, 1),
+            Point::new(2, 1),
+            Point::new(3, 2),
+            Point::new(4, 2),
+        ];
+        assert_eq!(points, expected);
+
+        let points = line(&Point::new(2, 2), &Point::new(2, -1));
+        assert_eq!(points.len(), 4);
+        assert_eq!(points.last(), Some(&Point::new(2, -1)));
+    }
+
+    #[test]
+    fn test_noisemaker() {
+        let mut game = test_game();
+        let player_loc = game.player_loc();
+        let at = |n: i32, (dx, dy): (i32, i32)| Point::new(player_loc.x + n * dx, player_loc.y + n * dy);
+        let delta = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .find(|&delta| (1..=4).all(|n| game.can_teleport_to(Oid(0), &at(n, delta))))
+            .unwrap();
+
+        let npc_loc = at(4, delta);
+        game.add_object(&npc_loc, new_obj(ObjectName::ImrothTheCruel));
+
+        let bell = game.level.add(new_obj(ObjectName::Bell), None);
+        let player = game.level.get_mut(&player_loc, CHARACTER_ID).unwrap().1;
+        player.inventory_value_mut().unwrap().push(bell);
+
+        // The NPC stops wandering and goes to see what made the noise.
+        let landing = at(2, delta);
+        game.player_acted(Action::Throw {
+            oid: bell,
+            target: landing,
+        });
+        assert!(game.recent_messages(2).any(|m| m.text.contains("bell clatters")));
+        let npc = game.level.get(&npc_loc, CHARACTER_ID).unwrap().1;
+        assert_eq!(npc.behavior_value(), Some(Behavior::MovingTo(landing)));
+    }
+}
@@ -0,0 +1,71 @@
+//! Moves characters directly to a location without crossing the cells in between. This
+//! should be used instead of do_move for things like teleporting so that destinations are
+//! checked and post-move interactions happen.
+use super::sound;
+use super::tag::*;
+use super::{catalog, Game, Message, Oid, Point, Topic};
+
+impl Game {
+    /// Returns true if oid could stand at loc.
+    pub fn can_teleport_to(&self, oid: Oid, loc: &Point) -> bool {
+        if self.level.get(loc, CHARACTER_ID).is_some() {
+            return false;
+        }
+        let ch = self.level.obj(oid).0;
+        let terrain = self.level.get_bottom(loc).1.terrain_value().unwrap();
+        terrain != Terrain::ClosedDoor && ch.impassible_terrain_type(terrain).is_none()
+    }
+
+    /// Moves oid to loc returning false if oid can't stand there. If oid is the player
+    /// then post-move interactions (like splashing through water) happen as usual.
+    pub(super) fn do_teleport(&mut self, oid: Oid, loc: &Point) -> bool {
+        if !self.can_teleport_to(oid, loc) {
+            if oid.0 == 0 {
+                self.messages
+                    .push(Message::new(Topic::Failed, &catalog::text("teleport.cant", &[])));
+            }
+            return false;
+        }
+
+        let old_loc = self.loc(oid).unwrap();
+        debug!("{oid} is teleporting from {old_loc} to {loc}");
+        self.do_move(oid, &old_loc, loc);
+        if oid.0 == 0 {
+            let (_, volume) = self.interact_post_move(loc);
+            self.handle_noise(loc, sound::QUIET + volume);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_game;
+    use crate::backend::Action;
+
+    #[test]
+    fn test_teleport() {
+        let mut game = test_game();
+
+        // Find somewhere nearby the player can go (and somewhere they can't).
+        let start = game.player_loc();
+        let nearby: Vec<Point> = (-5..=5)
+            .flat_map(|dy| (-5..=5).map(move |dx| Point::new(start.x + dx, start.y + dy)))
+            .filter(|loc| *loc != start)
+            .collect();
+        let good = *nearby.iter().find(|loc| game.can_teleport_to(Oid(0), loc)).unwrap();
+        let bad = nearby.iter().find(|loc| !game.can_teleport_to(Oid(0), loc)).copied();
+
+        game.player_acted(Action::Teleport { loc: good });
+        assert_eq!(game.player_loc(), good);
+
+        if let Some(bad) = bad {
+            while !game.players_turn() {
+                game.advance_time(false);
+            }
+            game.player_acted(Action::Teleport { loc: bad });
+            assert_eq!(game.player_loc(), good);
+        }
+    }
+}
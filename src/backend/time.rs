@@ -7,17 +7,28 @@ pub const CARDINAL_MOVE: Time = Time { t: 8 * SECS_TO_TIME };
 pub const DIAGNOL_MOVE: Time = Time {
     t: 11 * SECS_TO_TIME + 314 * MS_TO_TIME,
 };
+pub const CONFUSED: Time = Time { t: 24 * SECS_TO_TIME };
 pub const DESTROY_EMP_SWORD: Time = Time { t: 24 * SECS_TO_TIME };
 pub const FLOOD: Time = Time { t: 32 * SECS_TO_TIME };
+pub const FRIGHTENED: Time = Time { t: 30 * SECS_TO_TIME };
+pub const MEMORY_DECAY: Time = Time { t: 3200 * SECS_TO_TIME }; // ~400 turns
+pub const HASTED: Time = Time { t: 40 * SECS_TO_TIME };
 pub const MOVE_THRU_SHALLOW_WATER: Time = Time { t: 2 * SECS_TO_TIME };
+pub const PARRY: Time = Time { t: 2 * SECS_TO_TIME };
 pub const OPEN_DOOR: Time = Time { t: 10 * SECS_TO_TIME };
+pub const UNLOCK: Time = Time { t: 6 * SECS_TO_TIME };
+pub const POLYMORPH: Time = Time { t: 200 * SECS_TO_TIME };
+pub const PULL_LEVER: Time = Time { t: 4 * SECS_TO_TIME };
+pub const SEARCH: Time = Time { t: 120 * SECS_TO_TIME }; // ~15 turns
 pub const PICK_UP: Time = Time { t: 4 * SECS_TO_TIME };
+pub const SHOVE: Time = Time { t: 6 * SECS_TO_TIME };
 pub const SHOVE_DOORMAN: Time = Time { t: 16 * SECS_TO_TIME };
 pub const SPEAK_TO_SPECTATOR: Time = Time { t: 2 * SECS_TO_TIME };
+pub const THROW: Time = Time { t: 6 * SECS_TO_TIME };
 
 pub const MIN_TIME: Time = Time { t: 1 * SECS_TO_TIME };
 
-#[derive(Copy, Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Time {
     t: i64,
 }
@@ -0,0 +1,152 @@
+#[macro_use]
+extern crate log;
+extern crate simplelog;
+
+use clap::{ArgEnum, Parser};
+use one_thousand_deaths::{data_path, set_data_dir, Client, Game, SaveLock};
+use simplelog::{CombinedLogger, ConfigBuilder, LevelFilter, WriteLogger};
+use std::error::Error;
+use std::fs::File;
+use std::net::TcpListener;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum LoggingLevel {
+    // can't use simplelog::Level because it doesn't derive ArgEnum
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = "Run a headless game that remote frontends can drive over TCP."
+)]
+struct Args {
+    /// Address to listen on
+    #[clap(long, value_name = "ADDR", default_value = "127.0.0.1:7878")]
+    addr: String,
+
+    /// Path to saved file, a new game is started if it doesn't exist (defaults to
+    /// server.game in the data directory)
+    #[clap(long, value_name = "PATH")]
+    load: Option<String>,
+
+    /// Directory for the log and games saved by clients (defaults to a platform specific
+    /// location, e.g. ~/.local/share/1k-deaths)
+    #[clap(long, value_name = "DIR")]
+    data_dir: Option<String>,
+
+    /// Logging verbosity
+    #[clap(long, arg_enum, value_name = "NAME", default_value_t = LoggingLevel::Info)]
+    log_level: LoggingLevel,
+
+    /// Random number seed used for new games
+    #[clap(long, value_name = "N", default_value_t = 1)]
+    seed: u64,
+
+    /// Shared secret that clients must provide
+    #[clap(long, value_name = "TOKEN")]
+    token: String,
+
+    /// Address to listen on for control clients (they can query and shutdown but not act)
+    #[clap(long, value_name = "ADDR")]
+    control_addr: Option<String>,
+
+    /// Shared secret that control clients must provide
+    #[clap(long, value_name = "TOKEN")]
+    control_token: Option<String>,
+
+    /// Print metrics from a running server and exit (uses the control channel if one is set)
+    #[clap(long)]
+    report: bool,
+}
+
+fn to_filter(level: LoggingLevel) -> LevelFilter {
+    match level {
+        LoggingLevel::Error => LevelFilter::Error,
+        LoggingLevel::Warn => LevelFilter::Warn,
+        LoggingLevel::Info => LevelFilter::Info,
+        LoggingLevel::Debug => LevelFilter::Debug,
+        LoggingLevel::Trace => LevelFilter::Trace,
+    }
+}
+
+fn configure_logging(level: LevelFilter) {
+    let logging = ConfigBuilder::new()
+        .set_target_level(LevelFilter::Off)
+        .set_thread_level(LevelFilter::Off)
+        .set_location_level(LevelFilter::Off)
+        .build();
+    let file = File::create(data_path("1k-deaths-server.log")).unwrap();
+    CombinedLogger::init(vec![WriteLogger::new(level, logging, file)]).unwrap();
+
+    let local = chrono::Local::now();
+    info!(
+        "started up on {} with version {} ----------------------------------------------",
+        local.to_rfc2822(),
+        env!("CARGO_PKG_VERSION")
+    );
+}
+
+fn report(options: &Args) -> Result<(), Box<dyn Error>> {
+    let mut client = match (&options.control_addr, &options.control_token) {
+        (Some(addr), Some(token)) => Client::connect(addr, token)?,
+        _ => Client::connect(&options.addr, &options.token)?,
+    };
+    let metrics = client.metrics()?;
+    println!(
+        "{:<22} {:>5} {:>9} {:>7} {:>10} {:>7}",
+        "channel", "conns", "requests", "errors", "latency", "queued"
+    );
+    for m in metrics {
+        println!(
+            "{:<22} {:>5} {:>9} {:>7} {:>8.2}ms {:>7}",
+            m.addr.to_string(),
+            m.connections,
+            m.requests,
+            m.errors,
+            m.latency.as_secs_f64() * 1000.0,
+            m.queued
+        );
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Args::parse();
+    if options.report {
+        return report(&options);
+    }
+    let data_dir = set_data_dir(options.data_dir.as_deref())?;
+    configure_logging(to_filter(options.log_level));
+    info!("data directory is {}", data_dir.display());
+
+    let path = options.load.clone().unwrap_or_else(|| data_path("server.game"));
+    let mut lock = SaveLock::acquire(&path)?; // declared before game so that it's dropped after game
+    let mut game = if std::path::Path::new(&path).is_file() {
+        let (mut game, actions) = Game::old_game(&path, Vec::new());
+        for action in actions {
+            game.replay_action(action);
+        }
+        game
+    } else {
+        Game::new_game(&path, options.seed)
+    };
+
+    let listener = TcpListener::bind(&options.addr)?;
+    println!("listening on {}", options.addr);
+    let control = match (&options.control_addr, &options.control_token) {
+        (Some(addr), Some(token)) => {
+            println!("listening for control clients on {addr}");
+            Some((TcpListener::bind(addr)?, token.as_str()))
+        }
+        (Some(_), None) => return Err("--control-addr requires --control-token".into()),
+        _ => None,
+    };
+    one_thousand_deaths::serve(&mut game, &mut lock, listener, &options.token, control)
+}
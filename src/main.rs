@@ -6,10 +6,13 @@ extern crate simplelog;
 mod terminal;
 
 use clap::{ArgEnum, Parser};
-use one_thousand_deaths::Game;
+use one_thousand_deaths::{
+    data_path, export_json, import_json, load_catalog, load_lore, migrate_legacy_files, observe_session, set_data_dir,
+    set_fov_algorithm, set_pseudo_locale, Conduct, FovAlgorithm, Game,
+};
 use simplelog::{CombinedLogger, ConfigBuilder, LevelFilter, WriteLogger};
 use std::fs::File;
-use std::path::Path;
+use terminal::Profile;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
 pub enum LoggingLevel {
@@ -21,26 +24,103 @@ pub enum LoggingLevel {
     Trace,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum Challenge {
+    NoArmor,
+    NoNeutralKills,
+    Pacifist,
+    FogOfMemory,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ArgEnum)]
+pub enum Fov {
+    Permissive,
+    Symmetric,
+}
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)] // TODO: could do better here but terminal support wil go away at some point
 struct Args {
+    /// Conduct to commit to when starting a new game (may be repeated)
+    #[clap(long, arg_enum, value_name = "NAME", multiple_occurrences = true)]
+    challenge: Vec<Challenge>,
+
+    /// Directory for saved games, options, and logs (defaults to a platform specific
+    /// location, e.g. ~/.local/share/1k-deaths)
+    #[clap(long, value_name = "DIR")]
+    data_dir: Option<String>,
+
+    /// Convert the --load saved game into JSON (e.g. for bug reports) and exit
+    #[clap(long, value_name = "PATH")]
+    export_json: Option<String>,
+
+    /// Algorithm used to decide what the player can see in new games (defaults to
+    /// permissive, saved games use the algorithm they were started with)
+    #[clap(long, arg_enum, value_name = "NAME")]
+    fov: Option<Fov>,
+
+    /// Convert a JSON file written by --export-json into the --load saved game and exit
+    #[clap(long, value_name = "PATH")]
+    import_json: Option<String>,
+
     /// Enable slow debug checks
     #[cfg(debug_assertions)]
     #[clap(long)]
     invariants: bool,
 
+    /// Path to a language file with translated game text
+    #[clap(long, value_name = "PATH")]
+    language: Option<String>,
+
     /// Path to saved file
     #[clap(long, value_name = "PATH")]
     load: Option<String>,
 
+    /// Path to a file with additional lore entries (shown when examining)
+    #[clap(long, value_name = "PATH")]
+    lore: Option<String>,
+
+    /// Only log messages whose target starts with this, e.g. "noise" for noise events or
+    /// "one_thousand_deaths::backend::ai" (may be repeated)
+    #[clap(long, value_name = "TARGET", multiple_occurrences = true)]
+    log_filter: Vec<String>,
+
     /// Logging verbosity
     #[clap(long, arg_enum, value_name = "NAME", default_value_t = LoggingLevel::Info)]
     log_level: LoggingLevel,
 
+    /// Limit how often the screen is redrawn while animating, e.g. when running or
+    /// replaying (useful for slow terminals and ssh, 0 means no limit)
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    max_fps: u32,
+
     /// Ignore any saved files
     #[clap(long)]
     new_game: bool,
 
+    /// Don't show what was noticed about monsters in earlier games (in discoveries)
+    #[clap(long)]
+    no_monster_memory: bool,
+
+    /// Write JSON observations to stdout and read JSON actions from stdin (instead of
+    /// using the terminal)
+    #[clap(long)]
+    observe: bool,
+
+    /// Accent and bracket translatable text (to find text that can't be translated)
+    #[clap(long)]
+    pseudo_locale: bool,
+
+    /// Add a text summary of what the player can see each turn and leave the cursor on the
+    /// current selection (for use with screen readers)
+    #[clap(long)]
+    screen_reader: bool,
+
+    /// Save an HTML snapshot of the map and recent messages to this directory whenever
+    /// something important happens
+    #[clap(long, value_name = "DIR")]
+    snapshots: Option<String>,
+
     /// Fixed random number seed (defaults to random)
     #[clap(long, value_name = "N")]
     seed: Option<u64>,
@@ -60,13 +140,52 @@ fn to_filter(level: LoggingLevel) -> LevelFilter {
     }
 }
 
-fn configure_logging(level: LevelFilter) {
-    let logging = ConfigBuilder::new()
+fn to_conduct(challenge: Challenge) -> Conduct {
+    match challenge {
+        Challenge::NoArmor => Conduct::NoArmor,
+        Challenge::NoNeutralKills => Conduct::NoNeutralKills,
+        Challenge::Pacifist => Conduct::Pacifist,
+        Challenge::FogOfMemory => Conduct::FogOfMemory,
+    }
+}
+
+fn to_fov(fov: Fov) -> FovAlgorithm {
+    match fov {
+        Fov::Permissive => FovAlgorithm::Permissive,
+        Fov::Symmetric => FovAlgorithm::Symmetric,
+    }
+}
+
+fn convert_json(options: &Args) {
+    let save_path = match options.load {
+        Some(ref path) => path,
+        None => {
+            eprintln!("--export-json and --import-json require --load");
+            std::process::exit(1);
+        }
+    };
+    let result = match (&options.export_json, &options.import_json) {
+        (Some(json_path), None) => export_json(save_path, json_path),
+        (None, Some(json_path)) => import_json(json_path, save_path),
+        _ => Err("--export-json and --import-json can't be used together".into()),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn configure_logging(level: LevelFilter, filters: &[String]) {
+    let mut builder = ConfigBuilder::new();
+    builder
         .set_target_level(LevelFilter::Off)
         .set_thread_level(LevelFilter::Off)
-        .set_location_level(LevelFilter::Off)
-        .build();
-    let file = File::create("1k-deaths.log").unwrap();
+        .set_location_level(LevelFilter::Off);
+    for filter in filters {
+        builder.add_filter_allow(filter.clone());
+    }
+    let logging = builder.build();
+    let file = File::create(data_path("1k-deaths.log")).unwrap();
     CombinedLogger::init(vec![WriteLogger::new(level, logging, file)]).unwrap();
 
     let local = chrono::Local::now();
@@ -79,34 +198,110 @@ fn configure_logging(level: LevelFilter) {
 
 fn main() {
     let options = Args::parse();
-    configure_logging(to_filter(options.log_level));
+    let data_dir = match set_data_dir(options.data_dir.as_deref()) {
+        Ok(dir) => dir,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    configure_logging(to_filter(options.log_level), &options.log_filter);
+    info!("data directory is {}", data_dir.display());
+    for moved in migrate_legacy_files() {
+        info!("{moved}");
+    }
 
-    if options.wizard {
-        terminal::WIZARD_MODE.with(|w| {
-            *w.borrow_mut() = true;
+    if let Some(ref dir) = options.snapshots {
+        terminal::SNAPSHOT_DIR.with(|d| {
+            *d.borrow_mut() = Some(dir.clone());
+        })
+    }
+    terminal::set_max_fps(options.max_fps);
+    if options.screen_reader {
+        terminal::SCREEN_READER.with(|s| {
+            *s.borrow_mut() = true;
         })
     }
+    if options.wizard {
+        terminal::grant_wizard_capabilities();
+    }
+
+    if let Some(ref path) = options.language {
+        match load_catalog(path) {
+            Ok(problems) => problems.iter().for_each(|problem| warn!("{problem}")),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+    }
+    set_pseudo_locale(options.pseudo_locale);
+    if let Some(fov) = options.fov {
+        set_fov_algorithm(to_fov(fov));
+    }
+    if let Some(ref path) = options.lore {
+        match load_lore(path) {
+            Ok(problems) => problems.iter().for_each(|problem| warn!("{path}: {problem}")),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if options.export_json.is_some() || options.import_json.is_some() {
+        convert_json(&options);
+        return;
+    }
 
     let mut warnings = Vec::new();
-    if options.seed.is_some() && (options.load.is_some() || Path::new("saved.game").is_file()) && !options.new_game {
+    if options.seed.is_some() && options.load.is_some() && !options.new_game {
         // --new-game --load is a bit odd but means start a new game saved to the specified
         // path. But --seed --load without the --new-game is wrong because we need to replay
         // saved games using the original seed (we could reset the seed once we're finished
-        // replaying but that's kind of a pain).
+        // replaying but that's kind of a pain). Note that these are only used for old games.
         warnings.push("Ignoring --seed (game is beiing replayed so the original seed is being used.)".to_string());
     }
+    if options.fov.is_some() && options.load.is_some() && !options.new_game {
+        warnings.push("Ignoring --fov (game is being replayed so the original algorithm is being used.)".to_string());
+    }
 
     // TODO: probably need to make --seed and old_game into a warning
     // (can't just set the seed because we'd have to do it after replay finishes)
 
+    let mut profile = Profile::load();
+    profile.remember_monsters = !options.no_monster_memory;
+    if options.load.is_none() && !options.new_game && !options.observe {
+        // The title screen lets the player choose what to play.
+        {
+            #[cfg(debug_assertions)]
+            if options.invariants {
+                warn!("ignoring --invariants (use it with --load or --new-game)");
+            }
+        }
+        let challenges = options.challenge.iter().map(|c| to_conduct(*c)).collect();
+        let mut terminal = terminal::Terminal::title(options.seed, challenges, profile);
+        terminal.run();
+        return;
+    }
+
     // Timestamps are a poor seed but should be fine for our purposes.
     let seed = options.seed.unwrap_or(chrono::Utc::now().timestamp_millis() as u64);
     let (mut game, actions) = match options.load {
         Some(ref path) if options.new_game => (Game::new_game(path, seed), Vec::new()),
         Some(ref path) => Game::old_game(path, warnings),
-        None if Path::new("saved.game").is_file() && !options.new_game => Game::old_game("saved.game", warnings),
-        None => (Game::new_game("saved.game", seed), Vec::new()),
+        None => (Game::new_game(&terminal::new_save_path(), seed), Vec::new()),
     };
+    if actions.is_empty() && !options.wizard && !options.observe {
+        profile.started_game();
+    }
+    if actions.is_empty() {
+        for challenge in options.challenge.iter() {
+            game.add_challenge(to_conduct(*challenge));
+        }
+    } else if !options.challenge.is_empty() {
+        warn!("ignoring --challenge (challenges can only be added to new games)");
+    }
     {
         #[cfg(debug_assertions)]
         if options.invariants {
@@ -114,6 +309,19 @@ fn main() {
         }
     }
 
-    let mut terminal = terminal::Terminal::new(game, actions);
+    if options.observe {
+        for action in actions {
+            game.replay_action(action);
+        }
+        let stdin = std::io::stdin();
+        let stdout = std::io::stdout();
+        if let Err(err) = observe_session(&mut game, stdin.lock(), stdout.lock()) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut terminal = terminal::Terminal::new(game, actions, profile);
     terminal.run();
 }
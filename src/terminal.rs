@@ -1,87 +1,253 @@
 //! Rendering and UI using termion terminal module.
+mod auto_play_mode;
+mod camera;
 mod color;
 mod context_menu;
+mod cutscene_mode;
 mod details_view;
+mod discoveries_mode;
 mod examine_mode;
+mod frame;
 mod help;
+mod html_export;
+mod indicators;
+mod intent_mode;
+mod interact_mode;
 mod inventory_mode;
 mod inventory_view;
+mod layout;
 mod main_mode;
 mod map_view;
+mod message_options;
 mod messages_view;
 mod mode;
+mod pick_up_mode;
+mod profile;
 mod replay_mode;
+mod review_mode;
+mod run_mode;
+mod scheduler_mode;
+mod screen;
+mod screen_reader;
+mod snapshots;
+mod sound_effects;
+mod stack_mode;
+mod symbol_options;
+mod target_mode;
 mod text_mode;
 mod text_view;
+mod title_mode;
+mod tutorial;
 mod ui;
+mod wizard;
 
-use one_thousand_deaths::{Action, Game};
-use std::cell::RefCell;
+use cutscene_mode::CutsceneMode;
+pub use frame::set_max_fps;
+use mode::Launch;
+use one_thousand_deaths::{catalog_text, data_path, Action, Conduct, Game, Message, State, Topic};
+pub use profile::Profile;
+use screen_reader::ScreenReader;
+pub use screen_reader::SCREEN_READER;
+use snapshots::Snapshots;
+pub use snapshots::SNAPSHOT_DIR;
+use sound_effects::SoundEffects;
 use std::io::{self, Write};
+use std::path::Path;
 use std::process;
+use std::time::Instant;
 use termion::raw::IntoRawMode;
+use title_mode::TitleMode;
+use tutorial::Tutorial;
 use ui::UI;
+pub use wizard::grant_all as grant_wizard_capabilities;
 
-thread_local!(pub static WIZARD_MODE: RefCell<bool> = RefCell::new(false));
-
+/// Returns true if wizard information (e.g. exact NPC HPs) should be displayed.
 pub fn wizard_mode() -> bool {
-    WIZARD_MODE.with(|w| *w.borrow())
+    wizard::granted(wizard::Capability::Inspect)
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 enum GameState {
     Running,
+    Launching, // title screen picked a game to play
     Exiting,
 }
 
+// Don't want to clobber existing saved games when the player starts a new game.
+pub fn new_save_path() -> String {
+    let path = data_path("saved.game");
+    if !Path::new(&path).is_file() {
+        return path;
+    }
+    for i in 2..1000 {
+        let candidate = data_path(&format!("saved-{i}.game"));
+        if !Path::new(&candidate).is_file() {
+            return candidate;
+        }
+    }
+    panic!("Couldn't find a path for a new saved game in 1K tries!");
+}
+
 pub struct Terminal {
     ui: UI,
     game: Game,
+    tutorial: Tutorial,
+    sounds: SoundEffects,
+    reader: ScreenReader,
+    snapshots: Snapshots,
+    profile: Profile,
     stdout: Box<dyn Write>,
+    last_render: Instant,
 }
 
 impl Terminal {
-    pub fn new(game: Game, replay: Vec<Action>) -> Terminal {
+    pub fn new(mut game: Game, replay: Vec<Action>, profile: Profile) -> Terminal {
+        let (stdout, width, height) = Terminal::init_stdout();
+        Terminal::load_symbols(&mut game);
+        profile.share_monsters(&mut game);
+        Terminal {
+            ui: UI::new(width, height, replay),
+            snapshots: Snapshots::load(&game),
+            game,
+            tutorial: Tutorial::new(tutorial::hints_enabled()),
+            sounds: SoundEffects::load(),
+            reader: ScreenReader::new(),
+            profile,
+            stdout,
+            last_render: Instant::now(),
+        }
+    }
+
+    /// Starts with the title screen which creates the game the player wants to play.
+    pub fn title(seed: Option<u64>, challenges: Vec<Conduct>, profile: Profile) -> Terminal {
+        let (stdout, width, height) = Terminal::init_stdout();
+        let title = TitleMode::create(profile.summary(), seed, challenges);
+        let game = Game::new_unsaved(seed.unwrap_or(1));
+        Terminal {
+            ui: UI::title(width, height, title),
+            snapshots: Snapshots::load(&game),
+            game,
+            tutorial: Tutorial::new(tutorial::hints_enabled()),
+            sounds: SoundEffects::load(),
+            reader: ScreenReader::new(),
+            profile,
+            stdout,
+            last_render: Instant::now(),
+        }
+    }
+
+    fn init_stdout() -> (Box<dyn Write>, i32, i32) {
         let stdout = io::stdout();
         let mut stdout = stdout.into_raw_mode().unwrap();
-        write!(
-            stdout,
-            "{}{}{}",
-            termion::style::Reset,
-            termion::cursor::Hide,
-            termion::clear::All
-        )
-        .unwrap();
+        write!(stdout, "{}{}", termion::style::Reset, termion::clear::All).unwrap();
+        if !screen_reader::enabled() {
+            write!(stdout, "{}", termion::cursor::Hide).unwrap(); // screen readers follow the cursor
+        }
 
         let (width, height) = termion::terminal_size().expect("couldn't get terminal size");
         let width = width as i32;
         let height = height as i32;
         info!("terminal size is {} x {}", width, height);
-
-        Terminal {
-            ui: UI::new(width, height, replay),
-            game,
-            stdout: Box::new(stdout),
-        }
+        (Box::new(stdout), width, height)
     }
 
     pub fn run(&mut self) {
         let mut state = GameState::Running;
+        let mut game_state = self.game.state();
 
         while state != GameState::Exiting {
-            self.render();
+            // Always render when waiting on the player but otherwise honor --max-fps.
+            let animating = !self.game.players_turn() || self.ui.animating();
+            if !animating || frame::frame_due(self.last_render) {
+                self.render();
+            }
             if self.game.players_turn() {
-                state = self.ui.handle_input(&mut self.stdout, &mut self.game);
+                // Wizard information is displayed without using a command so it counts
+                // as soon as the player can act.
+                if wizard_mode() && !self.ui.replaying() {
+                    wizard::record_use(&mut self.game);
+                }
+                state = self.ui.handle_input(&mut self.game);
+                if state == GameState::Launching {
+                    self.launch();
+                    game_state = self.game.state();
+                    state = GameState::Running;
+                }
             } else {
                 let replaying = self.ui.replaying();
                 self.game.advance_time(replaying);
             }
+            self.tutorial.update(&mut self.game, self.ui.replaying());
+            self.reader.update(&mut self.game, self.ui.replaying());
+            self.sounds.update(&self.game, &mut self.stdout, self.ui.replaying());
+
+            if self.game.state() != game_state {
+                game_state = self.game.state();
+                if !self.ui.replaying() {
+                    for title in self.profile.finished_game(&self.game) {
+                        let text = catalog_text("profile.title", &[("title", &title)]);
+                        self.game.add_mesg(Message::new(Topic::Important, &text));
+                    }
+                    match game_state {
+                        State::WonGame => self.ui.push(CutsceneMode::create(cutscene_mode::WON)),
+                        State::LostGame => {
+                            let text = catalog_text("review.press_r", &[("turns", &main_mode::REVIEW_TURNS)]);
+                            self.game.add_mesg(Message::new(Topic::Important, &text));
+                            self.ui.push(CutsceneMode::create(cutscene_mode::LOST))
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            self.snapshots.update(&self.game, self.ui.replaying());
+        }
+    }
+
+    fn launch(&mut self) {
+        let (game, replay, hints) = match self.ui.take_launch().unwrap() {
+            Launch::NewGame {
+                seed,
+                challenges,
+                hints,
+            } => {
+                // Timestamps are a poor seed but should be fine for our purposes.
+                let seed = seed.unwrap_or(chrono::Utc::now().timestamp_millis() as u64);
+                let mut game = Game::new_game(&new_save_path(), seed);
+                for conduct in challenges {
+                    game.add_challenge(conduct);
+                }
+                if !wizard_mode() {
+                    self.profile.started_game();
+                }
+                (game, Vec::new(), hints)
+            }
+            Launch::Load { path, hints } => {
+                let (game, replay) = Game::old_game(&path, Vec::new());
+                (game, replay, hints)
+            }
+        };
+
+        let (width, height) = termion::terminal_size().expect("couldn't get terminal size");
+        self.game = game;
+        Terminal::load_symbols(&mut self.game);
+        self.profile.share_monsters(&mut self.game);
+        self.ui.restart(width as i32, height as i32, replay);
+        self.tutorial = Tutorial::new(hints);
+        self.sounds = SoundEffects::load();
+        self.reader = ScreenReader::new();
+        self.snapshots = Snapshots::load(&self.game);
+    }
+
+    fn load_symbols(game: &mut Game) {
+        for warning in symbol_options::load() {
+            game.add_mesg(Message::new(Topic::Warning, &warning));
         }
     }
 
     fn render(&mut self) {
         self.ui.render(&mut self.stdout, &mut self.game);
         self.stdout.flush().unwrap();
+        self.last_render = Instant::now();
     }
 }
 
@@ -0,0 +1,154 @@
+use super::help::{format_help, validate_help};
+use super::mode::{InputAction, Mode, RenderContext};
+use super::text_mode::TextMode;
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Action, Game, Message, State, Topic};
+use rand::prelude::*;
+use termion::event::Key;
+
+type KeyHandler = fn(&mut AutoPlayMode, &mut Game) -> InputAction;
+type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
+
+/// What the player does while auto-playing.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Policy {
+    Rest,
+    Wander,
+    Hunt, // move towards the closest visible enemy (and attack it)
+}
+
+const SPEEDS: [i32; 6] = [1000, 500, 200, 100, 20, 0]; // ms per player turn
+
+/// Wizard mode that lets the game run on its own so that developers can watch how things
+/// like flooding and NPCs behave over many turns.
+pub struct AutoPlayMode {
+    policy: Policy,
+    speed: usize, // index into SPEEDS
+    paused: bool,
+    commands: CommandTable,
+}
+
+impl AutoPlayMode {
+    pub fn create(game: &mut Game) -> Box<dyn Mode> {
+        let mut commands: CommandTable = FnvHashMap::default();
+        commands.insert(Key::Char(' '), Box::new(|s, game| s.do_toggle(game)));
+        commands.insert(Key::Char('p'), Box::new(|s, game| s.do_policy(game)));
+        commands.insert(Key::Char('+'), Box::new(|s, game| s.do_speed(game, 1)));
+        commands.insert(Key::Char('-'), Box::new(|s, game| s.do_speed(game, -1)));
+        commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
+        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
+        commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+
+        let mode = AutoPlayMode {
+            policy: Policy::Rest,
+            speed: 3,
+            paused: false,
+            commands,
+        };
+        mode.report(game);
+        Box::new(mode)
+    }
+}
+
+impl Mode for AutoPlayMode {
+    fn render(&self, _context: &mut RenderContext) -> bool {
+        false
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        if self.paused {
+            None
+        } else {
+            Some(SPEEDS[self.speed])
+        }
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        if game.state() == State::LostGame || game.state() == State::WonGame {
+            InputAction::Pop
+        } else if key == Key::Null {
+            let action = self.next_action(game);
+            game.player_acted(action);
+            InputAction::UpdatedGame
+        } else {
+            match self.commands.get(&key).cloned() {
+                Some(handler) => handler(self, game),
+                None => InputAction::NotHandled,
+            }
+        }
+    }
+}
+
+impl AutoPlayMode {
+    fn next_action(&self, game: &Game) -> Action {
+        let mut rng = thread_rng(); // these actions are saved so this doesn't break replay
+        let random_move = |rng: &mut ThreadRng| loop {
+            let dx = rng.gen_range(-1..=1);
+            let dy = rng.gen_range(-1..=1);
+            if dx != 0 || dy != 0 {
+                return Action::Move { dx, dy };
+            }
+        };
+        match self.policy {
+            Policy::Rest => Action::Rest,
+            Policy::Wander => random_move(&mut rng),
+            Policy::Hunt => match game.targets().first() {
+                Some(target) => {
+                    let loc = game.player_loc();
+                    Action::Move {
+                        dx: (target.x - loc.x).signum(),
+                        dy: (target.y - loc.y).signum(),
+                    }
+                }
+                None => random_move(&mut rng),
+            },
+        }
+    }
+
+    fn report(&self, game: &mut Game) {
+        let state = if self.paused { " (paused)" } else { "" };
+        let policy = match self.policy {
+            Policy::Rest => "resting",
+            Policy::Wander => "wandering",
+            Policy::Hunt => "hunting",
+        };
+        game.add_mesg(Message {
+            topic: Topic::Normal,
+            text: format!("Auto-play is {policy} with {} ms per turn{state}.", SPEEDS[self.speed]),
+        });
+    }
+
+    fn do_help(&mut self, _game: &mut Game) -> InputAction {
+        let help = catalog_text("help.auto_play", &[]);
+        validate_help("auto-play", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_policy(&mut self, game: &mut Game) -> InputAction {
+        self.policy = match self.policy {
+            Policy::Rest => Policy::Wander,
+            Policy::Wander => Policy::Hunt,
+            Policy::Hunt => Policy::Rest,
+        };
+        self.report(game);
+        InputAction::UpdatedGame
+    }
+
+    fn do_pop(&mut self, _game: &mut Game) -> InputAction {
+        InputAction::Pop
+    }
+
+    fn do_speed(&mut self, game: &mut Game, delta: i32) -> InputAction {
+        self.speed = (self.speed as i32 + delta).clamp(0, SPEEDS.len() as i32 - 1) as usize;
+        self.report(game);
+        InputAction::UpdatedGame
+    }
+
+    fn do_toggle(&mut self, game: &mut Game) -> InputAction {
+        self.paused = !self.paused;
+        self.report(game);
+        InputAction::UpdatedGame
+    }
+}
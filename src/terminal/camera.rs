@@ -0,0 +1,67 @@
+//! Levels and the terminal both use x/y coordinates so it's easy to use one where the other
+//! was intended. To avoid that level locations are always Points and terminal cells are
+//! always ScreenPoints with a Camera used to convert between the two.
+use one_thousand_deaths::{Point, Size};
+use termion::cursor;
+
+/// A terminal cell, (0, 0) is the top-left cell. Note that termion is 1-based so use goto,
+/// h, and v instead of adding one by hand.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ScreenPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl ScreenPoint {
+    pub fn new(x: i32, y: i32) -> ScreenPoint {
+        ScreenPoint { x, y }
+    }
+
+    pub fn origin() -> ScreenPoint {
+        ScreenPoint { x: 0, y: 0 }
+    }
+
+    /// Returns the termion column.
+    pub fn h(&self) -> u16 {
+        (self.x + 1) as u16
+    }
+
+    /// Returns the termion row.
+    pub fn v(&self) -> u16 {
+        (self.y + 1) as u16
+    }
+
+    pub fn goto(&self) -> cursor::Goto {
+        cursor::Goto(self.h(), self.v())
+    }
+}
+
+/// Maps a rectangle of the terminal onto part of the level.
+pub struct Camera {
+    origin: ScreenPoint, // top-left terminal cell of the view
+    top_left: Point,     // level location drawn at origin
+}
+
+impl Camera {
+    /// Returns a camera for a view at origin with size that is centered on loc.
+    pub fn centered(origin: ScreenPoint, size: Size, loc: Point) -> Camera {
+        Camera {
+            origin,
+            top_left: Point::new(loc.x - size.width / 2, loc.y - size.height / 2),
+        }
+    }
+
+    pub fn to_screen(&self, loc: Point) -> ScreenPoint {
+        ScreenPoint::new(
+            self.origin.x + loc.x - self.top_left.x,
+            self.origin.y + loc.y - self.top_left.y,
+        )
+    }
+
+    pub fn to_world(&self, pt: ScreenPoint) -> Point {
+        Point::new(
+            self.top_left.x + pt.x - self.origin.x,
+            self.top_left.y + pt.y - self.origin.y,
+        )
+    }
+}
@@ -1,5 +1,6 @@
+use super::camera::ScreenPoint;
 use super::color;
-use one_thousand_deaths::{Color, Point, Size};
+use one_thousand_deaths::{Color, Size};
 use std::fmt::Display;
 use std::io::Write;
 use termion::event::Key;
@@ -13,7 +14,7 @@ pub enum ContextResult<T: Copy + Display> {
 
 /// Modal menu rendered on top of a parent view.
 pub struct ContextMenu<T: Copy + Display> {
-    pub parent_origin: Point,
+    pub parent_origin: ScreenPoint,
     pub parent_size: Size,
     pub items: Vec<T>,
     pub suffix: String,
@@ -33,11 +34,12 @@ impl<T: Copy + Display> ContextMenu<T> {
             .max()
             .unwrap_or(0) as i32;
 
-        let h = self.parent_origin.x + (self.parent_size.width - (max_item_width + 4)) / 2;
-        let v = self.parent_origin.y + (self.parent_size.height - (item_height + 2)) / 2;
-
-        let h = h as u16;
-        let mut v = v as u16;
+        let top_left = ScreenPoint::new(
+            self.parent_origin.x + (self.parent_size.width - (max_item_width + 4)) / 2,
+            self.parent_origin.y + (self.parent_size.height - (item_height + 2)) / 2,
+        );
+        let h = top_left.h();
+        let mut v = top_left.v();
         let max_item_width = max_item_width as usize;
 
         let stars = "*".repeat(max_item_width + 4);
@@ -64,6 +66,9 @@ impl<T: Copy + Display> ContextMenu<T> {
                 Color::White
             };
             let name = self.to_name(i, *tag);
+            if i == self.selected {
+                let _ = write!(stdout, "{}", termion::cursor::Save); // for screen readers
+            }
             let _ = write!(stdout, "{}{}", termion::color::Fg(color::to_termion(fg)), name);
             if name.len() < max_item_width {
                 let _ = write!(stdout, "{}", " ".repeat(max_item_width - name.len()),);
@@ -0,0 +1,133 @@
+use super::color;
+use super::help::{format_help, validate_help};
+use super::mode::{InputAction, Mode, RenderContext};
+use super::text_mode::TextMode;
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Color, Game};
+use std::io::Write;
+use termion::event::Key;
+
+type KeyHandler = fn(&mut CutsceneMode, &mut Game) -> InputAction;
+type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
+
+pub const INTRO: &str = include_str!("cutscenes/intro.txt");
+pub const WON: &str = include_str!("cutscenes/won.txt");
+pub const LOST: &str = include_str!("cutscenes/lost.txt");
+
+/// Full screen text (and ASCII art) shown at the start and end of the game. Cutscenes are
+/// data files where pages are separated by "----" lines and lines starting with "# " are
+/// headings.
+pub struct CutsceneMode {
+    pages: Vec<Vec<&'static str>>,
+    page: usize,
+    commands: CommandTable,
+}
+
+impl CutsceneMode {
+    pub fn create(text: &'static str) -> Box<dyn Mode> {
+        let mut commands: CommandTable = FnvHashMap::default();
+        commands.insert(Key::Char(' '), Box::new(|s, game| s.do_page(game, 1)));
+        commands.insert(Key::Char('\n'), Box::new(|s, game| s.do_page(game, 1)));
+        commands.insert(Key::Right, Box::new(|s, game| s.do_page(game, 1)));
+        commands.insert(Key::Char('b'), Box::new(|s, game| s.do_page(game, -1)));
+        commands.insert(Key::Left, Box::new(|s, game| s.do_page(game, -1)));
+        commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
+        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
+        commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+
+        let mut pages = vec![Vec::new()];
+        for line in text.lines() {
+            if line == "----" {
+                pages.push(Vec::new());
+            } else {
+                pages.last_mut().unwrap().push(line);
+            }
+        }
+
+        Box::new(CutsceneMode {
+            pages,
+            page: 0,
+            commands,
+        })
+    }
+}
+
+impl Mode for CutsceneMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        let (width, height) = termion::terminal_size().unwrap_or((80, 24)); // no tty when running tests
+        let stdout = &mut context.stdout;
+        let _ = write!(
+            stdout,
+            "{}{}",
+            termion::color::Bg(color::to_termion(Color::Black)),
+            termion::clear::All
+        );
+
+        // Center the page as a block so that ASCII art lines up.
+        let lines = &self.pages[self.page];
+        let block_width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as u16;
+        let h = 1 + width.saturating_sub(block_width) / 2;
+        let top = 1 + height.saturating_sub(lines.len() as u16 + 2) / 2;
+        for (i, line) in lines.iter().enumerate() {
+            let (fg, text) = match line.strip_prefix("# ") {
+                Some(heading) => (Color::Yellow, heading),
+                None => (Color::White, *line),
+            };
+            let _ = write!(
+                stdout,
+                "{}{}{}",
+                termion::cursor::Goto(h, top + i as u16),
+                termion::color::Fg(color::to_termion(fg)),
+                text
+            );
+        }
+
+        let footer = format!(
+            "page {} of {}: space continues, b goes back, q skips, ? for help",
+            self.page + 1,
+            self.pages.len()
+        );
+        let _ = write!(
+            stdout,
+            "{}{}{}",
+            termion::cursor::Goto(1 + width.saturating_sub(footer.len() as u16) / 2, height),
+            termion::color::Fg(color::to_termion(Color::LightGrey)),
+            footer
+        );
+        true
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        None
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        match self.commands.get(&key).cloned() {
+            Some(handler) => handler(self, game),
+            None => InputAction::NotHandled,
+        }
+    }
+}
+
+impl CutsceneMode {
+    fn do_help(&mut self, _game: &mut Game) -> InputAction {
+        let help = catalog_text("help.cutscene", &[]);
+        validate_help("cutscene", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_page(&mut self, _game: &mut Game, delta: i32) -> InputAction {
+        if delta > 0 && self.page + 1 == self.pages.len() {
+            InputAction::Pop
+        } else {
+            self.page = (self.page as i32 + delta).clamp(0, self.pages.len() as i32 - 1) as usize;
+            InputAction::UpdatedGame
+        }
+    }
+
+    fn do_pop(&mut self, _game: &mut Game) -> InputAction {
+        InputAction::Pop
+    }
+}
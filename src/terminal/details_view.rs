@@ -1,17 +1,18 @@
+use super::camera::ScreenPoint;
 use super::color;
-use one_thousand_deaths::{Color, Disposition, Game, Point, Size};
+use one_thousand_deaths::{Color, Disposition, Game, Npc, Size, Wounds};
 use std::io::Write;
 
 /// Shows info about the player and nearby NPCs.
 pub struct DetailsView {
-    pub origin: Point,
+    pub origin: ScreenPoint,
     pub size: Size,
 }
 
 impl DetailsView {
     pub fn render(&self, stdout: &mut Box<dyn Write>, game: &Game) {
-        let h = (self.origin.x + 1) as u16; // termion is 1-based
-        let mut v = 1;
+        let h = self.origin.h();
+        let mut v = self.origin.v();
 
         self.render_player(h, &mut v, stdout, game);
         self.render_npcs(h, &mut v, stdout, game);
@@ -26,12 +27,59 @@ impl DetailsView {
 
         let bar1 = format!(" {}", "*".repeat(n));
         let bar2 = "*".repeat(10 - n);
-        let suffix = format!("{current}/{max}");
-        self.render_char(h, *v, ' ', Color::Black, &bar1, &bar2, &suffix, fg, stdout);
+        let suffix = if game.player_regenerating() {
+            format!("{current}/{max} +")
+        } else {
+            format!("{current}/{max}")
+        };
+        self.render_char(h, *v, ' ', Color::Black, &bar1, fg, &bar2, &suffix, fg, stdout);
         *v += 1;
 
-        self.render_char(h, *v, ' ', Color::Black, "", "", "", fg, stdout);
+        // Let the player know that there's something to pick up (or look at).
+        let items = match game.items_here().len() {
+            0 => String::new(),
+            1 => "1 item here".to_string(),
+            n => format!("{n} items here"),
+        };
+        self.render_char(
+            h,
+            *v,
+            ' ',
+            Color::Black,
+            "",
+            Color::Black,
+            "",
+            &items,
+            Color::Black,
+            stdout,
+        );
         *v += 1;
+
+        let mut statuses: Vec<String> = game
+            .player_statuses()
+            .iter()
+            .map(|s| s.to_string().to_lowercase())
+            .collect();
+        let (sight, vision) = game.player_sight();
+        if sight < vision {
+            statuses.push(format!("sight {sight}/{vision}"));
+        }
+        if !statuses.is_empty() {
+            let statuses = statuses.join(" ");
+            self.render_char(
+                h,
+                *v,
+                ' ',
+                Color::Black,
+                "",
+                Color::Black,
+                "",
+                &statuses,
+                Color::Goldenrod,
+                stdout,
+            );
+            *v += 1;
+        }
     }
 
     // TODO: Should be an indication if the NPC is really dangerous, maybe use bold
@@ -40,25 +88,50 @@ impl DetailsView {
         let npcs = game.npcs(super::wizard_mode());
 
         for npc in npcs.iter().take(5) {
-            let current = npc.observed_hps.0 as usize;
-            let max = npc.observed_hps.1 as usize;
-
-            let (bar1, bar2) = if npc.is_sleeping {
-                (" ".to_string(), "sleeping".to_string())
-            } else {
-                (format!(" {}", "*".repeat(current)), "*".repeat(max - current))
-            };
+            let (bar1, bar_fg, bar2, suffix) = self.npc_health(npc);
             let fg = match npc.disposition {
                 Disposition::Aggressive => Color::Red,
                 Disposition::Neutral => Color::Blue,
                 Disposition::Friendly => Color::Green,
             };
-            self.render_char(h, *v, npc.letter, npc.color, &bar1, &bar2, npc.name, fg, stdout);
+            self.render_char(h, *v, npc.letter, npc.color, &bar1, bar_fg, &bar2, &suffix, fg, stdout);
 
             *v += 1;
         }
     }
 
+    // In wizard mode NPCs get exact HP bars. Otherwise players only see how badly hurt
+    // NPCs are once they've seen that kind of NPC fight.
+    fn npc_health(&self, npc: &Npc) -> (String, Color, String, String) {
+        let name = npc.name.to_string();
+        if npc.is_sleeping {
+            return (" ".to_string(), Color::Black, "sleeping".to_string(), name);
+        }
+        if !super::symbol_options::health() {
+            return (" ".to_string(), Color::Black, String::new(), name);
+        }
+        if let Some((current, max)) = npc.actual_hps {
+            let percent = (current as f64) / (max as f64);
+            let n = (10.0 * percent).round().clamp(0.0, 10.0) as usize;
+            let bar1 = format!(" {}", "*".repeat(n));
+            (
+                bar1,
+                self.player_color(percent),
+                "*".repeat(10 - n),
+                format!("{name} {current}/{max}"),
+            )
+        } else if let Some(wounds) = npc.wounds {
+            let fg = match wounds {
+                Wounds::Unharmed => Color::Green,
+                Wounds::Bloodied => Color::Orange,
+                Wounds::NearDeath => Color::Red,
+            };
+            (format!(" {wounds}"), fg, String::new(), name)
+        } else {
+            (" ".to_string(), Color::Black, String::new(), name)
+        }
+    }
+
     fn render_char(
         &self,
         h: u16,
@@ -66,6 +139,7 @@ impl DetailsView {
         letter: char,
         color: Color,
         bar1: &str,
+        bar_fg: Color,
         bar2: &str,
         suffix: &str,
         fg: Color,
@@ -87,7 +161,7 @@ impl DetailsView {
             0
         };
 
-        let _ = write!(stdout, "{}{}", termion::color::Fg(color::to_termion(fg)), bar1);
+        let _ = write!(stdout, "{}{}", termion::color::Fg(color::to_termion(bar_fg)), bar1);
 
         let _ = write!(
             stdout,
@@ -0,0 +1,170 @@
+use super::help::{format_help, validate_help};
+use super::map_view::glyph;
+use super::mode::{InputAction, Mode, RenderContext};
+use super::text_mode::TextMode;
+use super::text_view::{Line, TextRun, TextView};
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Color, Discovery, Game};
+use termion::event::Key;
+
+type KeyHandler = fn(&mut DiscoveriesMode, &mut Game) -> InputAction;
+type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
+
+/// Lists the kinds of objects the player has seen this game along with whatever stats
+/// the player has learned about them.
+pub struct DiscoveriesMode {
+    text: TextView,
+    filter: String,
+    filtering: bool, // true if the player is typing into filter
+    commands: CommandTable,
+}
+
+impl DiscoveriesMode {
+    pub fn create(game: &Game) -> Box<dyn Mode> {
+        DiscoveriesMode::create_filtered(game, "")
+    }
+
+    /// Starts out showing only the entries matching filter.
+    pub fn create_filtered(game: &Game, filter: &str) -> Box<dyn Mode> {
+        let mut commands: CommandTable = FnvHashMap::default();
+        commands.insert(Key::Char(' '), Box::new(|s, game| s.do_page(game, 1)));
+        commands.insert(Key::Char('b'), Box::new(|s, game| s.do_page(game, -1)));
+        commands.insert(Key::Down, Box::new(|s, game| s.do_scroll(game, 1)));
+        commands.insert(Key::Up, Box::new(|s, game| s.do_scroll(game, -1)));
+        commands.insert(Key::Char('/'), Box::new(|s, game| s.do_filter(game)));
+        commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
+        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
+        commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+
+        Box::new(DiscoveriesMode {
+            text: DiscoveriesMode::view(game, filter, false),
+            filter: filter.to_string(),
+            filtering: false,
+            commands,
+        })
+    }
+
+    fn view(game: &Game, filter: &str, filtering: bool) -> TextView {
+        let pattern = filter.to_lowercase();
+        let discoveries: Vec<Discovery> = game
+            .discoveries()
+            .into_iter()
+            .filter(|d| d.name.to_lowercase().contains(&pattern) || d.description.to_lowercase().contains(&pattern))
+            .collect();
+
+        let mut header = format!("{} discoveries", discoveries.len());
+        if filtering || !filter.is_empty() {
+            header += &format!(", filter: {filter}");
+            if filtering {
+                header += "_";
+            }
+        }
+        let mut lines = vec![vec![TextRun::Color(Color::Yellow), TextRun::Text(header)]];
+        for discovery in discoveries.iter() {
+            lines.extend(DiscoveriesMode::lines(discovery));
+        }
+
+        let num_lines = lines.len() as i32;
+        let mut view = TextView::new(lines, Color::Black);
+        view.scroll(-num_lines);
+        view
+    }
+
+    pub fn lines(discovery: &Discovery) -> Vec<Line> {
+        let mut lines = vec![vec![
+            TextRun::Color(discovery.color),
+            TextRun::Text(format!("{} ", glyph(discovery.symbol))),
+            TextRun::Color(Color::White),
+            TextRun::Text(format!("{} ", discovery.name)),
+            TextRun::Color(Color::DarkGray),
+            TextRun::Text(discovery.description.to_string()),
+        ]];
+        for stat in discovery.stats.iter() {
+            lines.push(vec![
+                TextRun::Color(Color::LightSkyBlue),
+                TextRun::Text(format!("    {stat}")),
+            ]);
+        }
+        if let Some(notes) = discovery.notes {
+            let mut noticed = Vec::new();
+            if notes.max_damage > 0 {
+                noticed.push(format!("It has hit you for up to {} damage.", notes.max_damage));
+            }
+            if notes.blocked > 0 {
+                noticed.push(format!(
+                    "Its armor has blocked up to {}% of your damage.",
+                    notes.blocked
+                ));
+            }
+            for text in noticed {
+                lines.push(vec![TextRun::Color(Color::Plum), TextRun::Text(format!("    {text}"))]);
+            }
+        }
+        lines
+    }
+}
+
+impl Mode for DiscoveriesMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        self.text.render(context.stdout);
+        true
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        None
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        if self.filtering {
+            match key {
+                Key::Char('\n') => self.filtering = false,
+                Key::Esc => {
+                    self.filter.clear();
+                    self.filtering = false;
+                }
+                Key::Backspace => {
+                    self.filter.pop();
+                }
+                Key::Char(c) => self.filter.push(c),
+                _ => return InputAction::NotHandled,
+            }
+            self.text = DiscoveriesMode::view(game, &self.filter, self.filtering);
+            InputAction::UpdatedGame
+        } else {
+            match self.commands.get(&key).cloned() {
+                Some(handler) => handler(self, game),
+                None => InputAction::NotHandled,
+            }
+        }
+    }
+}
+
+impl DiscoveriesMode {
+    fn do_filter(&mut self, game: &mut Game) -> InputAction {
+        self.filtering = true;
+        self.text = DiscoveriesMode::view(game, &self.filter, self.filtering);
+        InputAction::UpdatedGame
+    }
+
+    fn do_help(&mut self, _game: &mut Game) -> InputAction {
+        let help = catalog_text("help.discoveries", &[]);
+        validate_help("discoveries", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_page(&mut self, _game: &mut Game, sign: i32) -> InputAction {
+        self.text.scroll(sign * self.text.size().height);
+        InputAction::UpdatedGame
+    }
+
+    fn do_pop(&mut self, _game: &mut Game) -> InputAction {
+        InputAction::Pop
+    }
+
+    fn do_scroll(&mut self, _game: &mut Game, delta: i32) -> InputAction {
+        self.text.scroll(delta);
+        InputAction::UpdatedGame
+    }
+}
@@ -1,8 +1,14 @@
 use super::help::{format_help, validate_help};
+use super::intent_mode::IntentMode;
 use super::mode::{InputAction, Mode, RenderContext};
 use super::text_mode::TextMode;
+use super::text_view::TextRun;
+use super::wizard::{Capability, WizardCommand, WizardCommands};
 use fnv::FnvHashMap;
-use one_thousand_deaths::{Action, Game, Point};
+use one_thousand_deaths::{
+    catalog_text, Action, Color, Game, Intent, Message, Point, Topic, MAX_EXAMINE_LINES, POLYMORPH_FORMS,
+};
+use rand::prelude::*;
 use termion::event::Key;
 
 type KeyHandler = fn(&mut ExamineMode, &mut Game) -> InputAction;
@@ -11,6 +17,7 @@ type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
 pub struct ExamineMode {
     examined: Point,
     commands: CommandTable,
+    wizard: WizardCommands,
 }
 
 impl ExamineMode {
@@ -28,19 +35,52 @@ impl ExamineMode {
         commands.insert(Key::Char('7'), Box::new(|s, game| s.do_examine(game, -1, -1)));
         commands.insert(Key::Char('8'), Box::new(|s, game| s.do_examine(game, 0, -1)));
         commands.insert(Key::Char('9'), Box::new(|s, game| s.do_examine(game, 1, -1)));
-        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_quit(game)));
+        commands.insert(Key::Char('l'), Box::new(|s, game| s.do_lore(game)));
+        commands.insert(Key::Char('p'), Box::new(|s, game| s.do_stack(game)));
+        commands.insert(Key::Char('t'), Box::new(|s, game| s.do_travel(game)));
         commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
         commands.insert(Key::Char('\t'), Box::new(|s, game| s.do_tab_target(game, 1)));
         commands.insert(Key::BackTab, Box::new(|s, game| s.do_tab_target(game, -1)));
         commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
         commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+        let wizard = WizardCommands::register(
+            &mut commands,
+            vec![
+                WizardCommand {
+                    key: Key::Ctrl('g'),
+                    capability: Capability::Cheat,
+                    help: "help.wizard.examine.control_g",
+                    handler: ExamineMode::do_teleport,
+                },
+                WizardCommand {
+                    key: Key::Ctrl('y'),
+                    capability: Capability::Cheat,
+                    help: "help.wizard.examine.control_y",
+                    handler: ExamineMode::do_polymorph,
+                },
+            ],
+        );
 
-        Box::new(ExamineMode { examined, commands })
+        Box::new(ExamineMode {
+            examined,
+            commands,
+            wizard,
+        })
     }
 }
 
 impl Mode for ExamineMode {
     fn render(&self, context: &mut RenderContext) -> bool {
+        if super::wizard_mode() {
+            if let Some(plan) = context.game.ai_plan(&self.examined) {
+                for loc in plan.path {
+                    context.overlay.insert(loc, Color::DarkSlateBlue);
+                }
+                if let Some(loc) = plan.target {
+                    context.overlay.insert(loc, Color::DarkMagenta);
+                }
+            }
+        }
         context.examined = Some(self.examined);
         false
     }
@@ -51,57 +91,101 @@ impl Mode for ExamineMode {
 
     fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
         match self.commands.get(&key).cloned() {
-            Some(handler) => handler(self, game),
+            Some(handler) => {
+                self.wizard.note_key(game, key);
+                handler(self, game)
+            }
             None => InputAction::NotHandled,
         }
     }
 }
 
 impl ExamineMode {
-    fn do_examine(&mut self, game: &mut Game, dx: i32, dy: i32) -> InputAction {
-        self.examined = Point::new(self.examined.x + dx, self.examined.y + dy);
-        game.player_acted(Action::Examine {
+    fn examine(&self, game: &mut Game) {
+        let wizard = super::wizard_mode();
+        game.free_action(Action::Examine {
             loc: self.examined,
-            wizard: super::wizard_mode(),
+            wizard,
         });
+        if game.examine_stack(&self.examined).len() > MAX_EXAMINE_LINES {
+            game.add_mesg(Message::new(Topic::Normal, &catalog_text("examine.press_p", &[])));
+        }
+        if game.lore_at(&self.examined).is_some() {
+            game.add_mesg(Message::new(Topic::Normal, &catalog_text("examine.press_l", &[])));
+        }
+        if wizard {
+            if let Some(plan) = game.ai_plan(&self.examined) {
+                let text = match plan.target {
+                    Some(target) if plan.path.is_empty() => format!("AI is {} {target} (no path).", plan.behavior),
+                    Some(target) => format!("AI is {} {target} ({} steps).", plan.behavior, plan.path.len()),
+                    None => format!("AI is {}.", plan.behavior),
+                };
+                game.add_mesg(Message {
+                    topic: Topic::Normal,
+                    text,
+                });
+            }
+        }
+    }
+
+    fn do_examine(&mut self, game: &mut Game, dx: i32, dy: i32) -> InputAction {
+        self.examined = Point::new(self.examined.x + dx, self.examined.y + dy);
+        self.examine(game);
         InputAction::UpdatedGame
     }
 
     fn do_help(&mut self, _game: &mut Game) -> InputAction {
-        let help = r#"Move the focus to examine the contents of a cell.
-The focus is drawn with reversed colors.
-
-The focus can be moved with the usual keys:
-[[7]] [[8]] [[9]]                  [[up-arrow]]
-[[4]]   [[6]]           [[left-arrow]]   [[right-arrow]]
-[[1]] [[2]] [[3]]                 [[down-arrow]]
-
-[[tab]] can be used to select the next character.
-[[shift-tab]] can be used to select the previous character.
-[[?]] show this help.
-[[q]] save and quit.
-[[escape]] and [[q]] exit examine mode."#;
-        validate_help("examine", help, self.commands.keys());
-
-        let lines = format_help(help, self.commands.keys());
+        let mut help = catalog_text("help.examine", &[]);
+        help += &self.wizard.help();
+        validate_help("examine", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
         InputAction::Push(TextMode::at_top().create(lines))
     }
 
+    fn do_polymorph(&mut self, game: &mut Game) -> InputAction {
+        let into = *POLYMORPH_FORMS.choose(&mut thread_rng()).unwrap();
+        game.player_acted(Action::Polymorph {
+            loc: self.examined,
+            into,
+        });
+        InputAction::UpdatedGame
+    }
+
+    fn do_lore(&mut self, game: &mut Game) -> InputAction {
+        match game.lore_at(&self.examined) {
+            Some(lore) => {
+                let lines = lore
+                    .into_iter()
+                    .map(|line| vec![TextRun::Color(Color::White), TextRun::Text(line)])
+                    .collect();
+                InputAction::Push(TextMode::at_top().create(lines))
+            }
+            None => InputAction::NotHandled,
+        }
+    }
+
     fn do_pop(&mut self, _game: &mut Game) -> InputAction {
         InputAction::Pop
     }
 
-    fn do_quit(&mut self, _game: &mut Game) -> InputAction {
-        InputAction::Quit
+    fn do_stack(&mut self, game: &mut Game) -> InputAction {
+        InputAction::Push(super::stack_mode::StackMode::create(game, self.examined))
+    }
+
+    fn do_teleport(&mut self, game: &mut Game) -> InputAction {
+        game.player_acted(Action::Teleport { loc: self.examined });
+        InputAction::UpdatedGame
+    }
+
+    fn do_travel(&mut self, game: &mut Game) -> InputAction {
+        InputAction::Replace(IntentMode::create(game, Intent::TravelTo(self.examined)))
     }
 
     fn do_tab_target(&mut self, game: &mut Game, delta: i32) -> InputAction {
         if let Some(loc) = game.target_next(&self.examined, delta) {
             self.examined = loc;
-            game.player_acted(Action::Examine {
-                loc,
-                wizard: super::wizard_mode(),
-            });
+            self.examine(game);
         }
         InputAction::UpdatedGame
     }
@@ -0,0 +1,17 @@
+//! Optionally limits how often the screen is redrawn while animating (e.g. when running
+//! or replaying). This is mostly useful for slow terminals and ssh sessions.
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+thread_local!(static MAX_FPS: Cell<u32> = const { Cell::new(0) });
+
+/// Zero means that there's no limit.
+pub fn set_max_fps(fps: u32) {
+    MAX_FPS.with(|m| m.set(fps));
+}
+
+/// Returns true if enough time has passed since last_render to render another frame.
+pub fn frame_due(last_render: Instant) -> bool {
+    let fps = MAX_FPS.with(|m| m.get());
+    fps == 0 || last_render.elapsed() >= Duration::from_secs(1) / fps
+}
@@ -0,0 +1,121 @@
+use super::map_view::{glyph, tile_colors};
+use one_thousand_deaths::{Color, Game, Point, Symbol};
+use std::io::{Error, Write};
+
+/// Writes the parts of the level that the player has seen to a standalone HTML page.
+/// This uses the same glyphs and colors as MapView (X11 color names are also valid CSS
+/// color names).
+pub fn export_html<W: Write>(writer: &mut W, game: &Game) -> Result<(), Error> {
+    write_page(writer, game, "1k-deaths map", &[])
+}
+
+/// Like export_html except that caption and messages are included. This is used to save
+/// a record of important events.
+pub fn export_snapshot<W: Write>(writer: &mut W, game: &Game, caption: &str, messages: &[String]) -> Result<(), Error> {
+    write_page(writer, game, caption, messages)
+}
+
+fn write_page<W: Write>(writer: &mut W, game: &Game, title: &str, messages: &[String]) -> Result<(), Error> {
+    write!(
+        writer,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{}</title>"#,
+        escape(title)
+    )?;
+    write!(
+        writer,
+        r#"
+<style>
+body {{ background-color: Black; color: White; }}
+pre {{ font-family: monospace; line-height: 1.0; }}
+</style>
+</head>
+<body>
+<pre>
+"#
+    )?;
+
+    let mut symbols = Vec::new();
+    if let Some((top_left, bottom_right)) = game.explored_extents() {
+        for y in top_left.y..=bottom_right.y {
+            for x in top_left.x..=bottom_right.x {
+                let tile = game.tile(&Point::new(x, y));
+                let (bg, fg, symbol) = tile_colors(&tile);
+                write_cell(writer, bg, fg, &glyph(symbol))?;
+                if symbol != Symbol::Unseen && !symbols.iter().any(|(s, _)| *s == symbol) {
+                    symbols.push((symbol, fg));
+                }
+            }
+            writeln!(writer)?;
+        }
+    }
+    write!(writer, "</pre>\n<h3>Legend</h3>\n<pre>\n")?;
+
+    let npcs = game.npcs(false);
+    for (symbol, fg) in symbols {
+        write_cell(writer, Color::Black, fg, &glyph(symbol))?;
+        let name = match symbol {
+            Symbol::Npc(ch) => npcs
+                .iter()
+                .find(|npc| npc.letter == ch)
+                .map_or("character".to_string(), |npc| npc.name.to_string()),
+            _ => describe(symbol).to_string(),
+        };
+        writeln!(writer, " {}", escape(&name))?;
+    }
+    writeln!(writer, "</pre>")?;
+
+    if !messages.is_empty() {
+        write!(writer, "<h3>Messages</h3>\n<pre>\n")?;
+        for message in messages {
+            writeln!(writer, "{}", escape(message))?;
+        }
+        writeln!(writer, "</pre>")?;
+    }
+    write!(writer, "</body>\n</html>\n")?;
+    Ok(())
+}
+
+fn write_cell<W: Write>(writer: &mut W, bg: Color, fg: Color, text: &str) -> Result<(), Error> {
+    write!(
+        writer,
+        r#"<span style="background-color:{bg};color:{fg}">{}</span>"#,
+        escape(text)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn describe(symbol: Symbol) -> &'static str {
+    use Symbol::*;
+    match symbol {
+        Armor => "armor",
+        Bell => "bell",
+        ClosedDoor => "closed door",
+        DeepLiquid => "deep water",
+        Dirt => "dirt",
+        Key => "key",
+        Lever => "lever",
+        Light => "light",
+        Npc(_) => "character",
+        OpenDoor => "open door",
+        PickAxe => "pick-axe",
+        Player => "you",
+        PressurePlate => "pressure plate",
+        Rock => "rock",
+        Rubble => "rubble",
+        ShallowLiquid => "shallow water",
+        Sign => "sign",
+        Spear => "spear",
+        StrongSword => "strong sword",
+        Tree => "tree",
+        Unseen => "unseen",
+        Wall => "wall",
+        WeakSword => "weak sword",
+    }
+}
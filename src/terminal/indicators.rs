@@ -0,0 +1,63 @@
+use one_thousand_deaths::{Color, CombatEvent, Game, Point, Tile};
+
+/// Number of frames that an indicator is shown for. Only frames rendered while waiting on
+/// the player count (otherwise indicators would vanish while NPCs are taking their turns).
+const INDICATOR_FRAMES: i32 = 1;
+
+/// Transient text drawn over a cell, e.g. "-12" over a character that was just hit.
+#[derive(Clone)]
+pub struct Indicator {
+    pub loc: Point,
+    pub text: String,
+    pub color: Color,
+    frames: i32,
+}
+
+/// Queue of indicators built from the game's combat events.
+pub struct Indicators {
+    seen: usize, // from Game::num_combat_events
+    queue: Vec<Indicator>,
+}
+
+impl Indicators {
+    pub fn new() -> Indicators {
+        Indicators {
+            seen: 0,
+            queue: Vec::new(),
+        }
+    }
+
+    /// Adds indicators for new combat events and returns the ones that should be drawn
+    /// this frame.
+    pub fn frame(&mut self, game: &Game) -> Vec<Indicator> {
+        let count = game.num_combat_events().saturating_sub(self.seen);
+        self.seen = game.num_combat_events();
+        if !super::symbol_options::indicators() {
+            self.queue.clear();
+            return Vec::new();
+        }
+
+        for event in game.recent_combat_events(count) {
+            let (loc, text, color) = match event {
+                CombatEvent::Damaged { loc, amount } => (*loc, format!("-{amount}"), Color::Red),
+                CombatEvent::StatusAdded { loc, .. } => (*loc, "*".to_string(), Color::Yellow),
+            };
+            if matches!(game.tile(&loc), Tile::Visible { .. }) {
+                self.queue.retain(|old| old.loc != loc); // newest wins
+                self.queue.push(Indicator {
+                    loc,
+                    text,
+                    color,
+                    frames: INDICATOR_FRAMES,
+                });
+            }
+        }
+
+        let frame = self.queue.clone();
+        if game.players_turn() {
+            self.queue.iter_mut().for_each(|i| i.frames -= 1);
+            self.queue.retain(|i| i.frames > 0);
+        }
+        frame
+    }
+}
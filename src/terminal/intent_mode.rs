@@ -0,0 +1,42 @@
+use super::mode::{InputAction, Mode, RenderContext};
+use one_thousand_deaths::{Game, Intent, Plan};
+use termion::event::Key;
+
+const STEP_DELAY: i32 = 30; // ms per step so that the player can see what's happening
+
+/// Executes a multi-turn command (e.g. travel) until the backend says it's done (or the
+/// player presses a key).
+pub struct IntentMode {
+    plan: Plan,
+}
+
+impl IntentMode {
+    pub fn create(game: &Game, intent: Intent) -> Box<dyn Mode> {
+        Box::new(IntentMode {
+            plan: game.start_intent(intent),
+        })
+    }
+}
+
+impl Mode for IntentMode {
+    fn render(&self, _context: &mut RenderContext) -> bool {
+        false
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        Some(STEP_DELAY)
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        if key != Key::Null {
+            return InputAction::Pop;
+        }
+        match game.intent_step(&mut self.plan) {
+            Some(action) => {
+                game.player_acted(action);
+                InputAction::UpdatedGame
+            }
+            None => InputAction::Pop,
+        }
+    }
+}
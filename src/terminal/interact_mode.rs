@@ -0,0 +1,115 @@
+use super::help::{format_help, validate_help};
+use super::mode::{InputAction, Mode, RenderContext};
+use super::text_mode::TextMode;
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Action, Color, Game, Interactable, Message, Topic};
+use termion::event::Key;
+
+type KeyHandler = fn(&mut InteractMode, &mut Game) -> InputAction;
+type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
+
+/// Used when there are several things next to the player that can be interacted with so
+/// that the player can pick one instead of bumping in the right direction. Candidates are
+/// drawn with a dark background and the selected candidate with reversed colors.
+pub struct InteractMode {
+    candidates: Vec<Interactable>,
+    selected: usize,
+    commands: CommandTable,
+}
+
+impl InteractMode {
+    /// Returns None (after interacting) if there is zero or one candidate.
+    pub fn create(game: &mut Game) -> Option<Box<dyn Mode>> {
+        let candidates = game.interactables();
+        match candidates.len() {
+            0 => {
+                game.add_mesg(Message::new(Topic::Failed, &catalog_text("interact.nothing", &[])));
+                return None;
+            }
+            1 => {
+                interact(game, &candidates[0]);
+                return None;
+            }
+            _ => (),
+        }
+
+        let mut commands: CommandTable = FnvHashMap::default();
+        commands.insert(Key::Char('\t'), Box::new(|s, game| s.do_select(game, 1)));
+        commands.insert(Key::Down, Box::new(|s, game| s.do_select(game, 1)));
+        commands.insert(Key::BackTab, Box::new(|s, game| s.do_select(game, -1)));
+        commands.insert(Key::Up, Box::new(|s, game| s.do_select(game, -1)));
+        commands.insert(Key::Char('\n'), Box::new(|s, game| s.do_accept(game)));
+        commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
+        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
+        commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+
+        let mode = InteractMode {
+            candidates,
+            selected: 0,
+            commands,
+        };
+        mode.report(game);
+        Some(Box::new(mode))
+    }
+}
+
+impl Mode for InteractMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        for candidate in self.candidates.iter() {
+            context.overlay.insert(candidate.loc, Color::DarkSlateGray);
+        }
+        context.examined = Some(self.candidates[self.selected].loc);
+        false
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        None
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        match self.commands.get(&key).cloned() {
+            Some(handler) => handler(self, game),
+            None => InputAction::NotHandled,
+        }
+    }
+}
+
+impl InteractMode {
+    fn report(&self, game: &mut Game) {
+        let text = catalog_text("interact.candidate", &[("name", &self.candidates[self.selected].name)]);
+        game.add_mesg(Message::new(Topic::Normal, &text));
+    }
+
+    fn do_accept(&mut self, game: &mut Game) -> InputAction {
+        interact(game, &self.candidates[self.selected]);
+        InputAction::Pop
+    }
+
+    fn do_help(&mut self, _game: &mut Game) -> InputAction {
+        let help = catalog_text("help.interact", &[]);
+        validate_help("interact", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_pop(&mut self, _game: &mut Game) -> InputAction {
+        InputAction::Pop
+    }
+
+    fn do_select(&mut self, game: &mut Game, delta: i32) -> InputAction {
+        let n = self.candidates.len() as i32;
+        self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        self.report(game);
+        InputAction::UpdatedGame
+    }
+}
+
+// Interacting is the same as bumping into the candidate.
+fn interact(game: &mut Game, candidate: &Interactable) {
+    let loc = game.player_loc();
+    game.player_acted(Action::Move {
+        dx: candidate.loc.x - loc.x,
+        dy: candidate.loc.y - loc.y,
+    });
+}
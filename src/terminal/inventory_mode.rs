@@ -1,10 +1,12 @@
+use super::camera::ScreenPoint;
 use super::context_menu::{ContextMenu, ContextResult};
 use super::help::{format_help, validate_help};
-use super::inventory_view::InventoryView;
+use super::inventory_view::{self, InventoryView};
 use super::mode::{InputAction, Mode, RenderContext};
+use super::target_mode::TargetMode;
 use super::text_mode::TextMode;
 use fnv::FnvHashMap;
-use one_thousand_deaths::{Action, Game, InvItem, ItemKind, Point, Size, Slot};
+use one_thousand_deaths::{catalog_text, Action, Game, InvItem, ItemKind, Oid, Size, Slot, THROW_RANGE};
 use std::fmt::{self, Formatter};
 use termion::event::Key;
 
@@ -13,19 +15,35 @@ type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum ContextItem {
+    Call,
     Drop,
     Remove,
+    SaveSet(u8),
+    Throw,
+    UseOn,
     Wear,
     WieldBothHands,
     WieldMainHand,
     WieldOffHand,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SortOrder {
+    PickedUp,
+    Name,
+    Equipped,
+}
+
 pub struct InventoryMode {
     commands: CommandTable,
     view: InventoryView,
-    selected: Option<usize>,
+    selected: Option<usize>, // index into items()
     menu: Option<ContextMenu<ContextItem>>,
+    sort: SortOrder,
+    filter: String,
+    filtering: bool,               // true if the player is typing into filter
+    naming: Option<(Oid, String)>, // set if the player is typing a name for an item
+    marked: Vec<Oid>,
 }
 
 impl InventoryMode {
@@ -45,10 +63,15 @@ impl InventoryMode {
         commands.insert(Key::Char('9'), Box::new(|s, game| s.do_select(game, 1, -1)));
         commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
         commands.insert(Key::Char('\n'), Box::new(|s, game| s.do_create_menu(game)));
+        commands.insert(Key::Char(' '), Box::new(|s, game| s.do_mark(game)));
+        commands.insert(Key::Char('d'), Box::new(|s, game| s.do_drop_marked(game)));
+        commands.insert(Key::Char('e'), Box::new(|s, game| s.do_save_set_menu(game)));
+        commands.insert(Key::Char('s'), Box::new(|s, game| s.do_sort(game)));
+        commands.insert(Key::Char('/'), Box::new(|s, game| s.do_filter(game)));
         commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
         commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
 
-        let origin = Point::new(1, 1);
+        let origin = ScreenPoint::new(1, 1);
         let view = InventoryView { origin, size };
         let selected = None;
         let mut mode = InventoryMode {
@@ -56,6 +79,11 @@ impl InventoryMode {
             view,
             selected,
             menu: None,
+            sort: SortOrder::PickedUp,
+            filter: String::new(),
+            filtering: false,
+            naming: None,
+            marked: Vec::new(),
         };
         mode.do_select(game, 0, 1);
         Box::new(mode)
@@ -65,7 +93,9 @@ impl InventoryMode {
 impl Mode for InventoryMode {
     fn render(&self, context: &mut RenderContext) -> bool {
         let desc = self.describe_item(context.game);
-        self.view.render(self.selected, context.stdout, context.game, desc);
+        let inv = inventory_view::mark_items(self.items(context.game), &self.marked);
+        let status = self.status();
+        self.view.render(&inv, self.selected, &status, context.stdout, desc);
         if let Some(menu) = self.menu.as_ref() {
             menu.render(context.stdout);
         }
@@ -83,16 +113,66 @@ impl Mode for InventoryMode {
     // clear how we'd do that: maybe this screen would auto-pop if the player takes
     // damage? Or maybe a warning is displayed?
     fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
-        if let Some(menu) = self.menu.as_mut() {
+        if let Some((oid, name)) = self.naming.as_mut() {
+            match key {
+                Key::Char('\n') => {
+                    let action = Action::Call {
+                        oid: *oid,
+                        name: name.clone(),
+                    };
+                    game.player_acted(action);
+                    self.naming = None;
+                }
+                Key::Esc => self.naming = None,
+                Key::Backspace => {
+                    name.pop();
+                }
+                Key::Char(c) => name.push(c),
+                _ => return InputAction::NotHandled,
+            }
+            InputAction::UpdatedGame
+        } else if self.filtering {
+            match key {
+                Key::Char('\n') => self.filtering = false,
+                Key::Esc => {
+                    self.filter.clear();
+                    self.filtering = false;
+                }
+                Key::Backspace => {
+                    self.filter.pop();
+                }
+                Key::Char(c) => self.filter.push(c),
+                _ => return InputAction::NotHandled,
+            }
+            self.reselect(game);
+            InputAction::UpdatedGame
+        } else if let Some(menu) = self.menu.as_mut() {
             match menu.handle_input(key) {
+                ContextResult::Selected(ContextItem::Call) => {
+                    self.start_naming(game);
+                    self.menu = None;
+                }
                 ContextResult::Selected(ContextItem::Drop) => {
                     self.drop_item(game);
                     self.menu = None;
+                    self.reselect(game);
                 }
                 ContextResult::Selected(ContextItem::Remove) => {
                     self.remove_item(game);
                     self.menu = None;
                 }
+                ContextResult::Selected(ContextItem::SaveSet(index)) => {
+                    game.player_acted(Action::SaveLoadout(index));
+                    self.menu = None;
+                }
+                ContextResult::Selected(ContextItem::Throw) => {
+                    self.menu = None;
+                    return self.throw_item(game);
+                }
+                ContextResult::Selected(ContextItem::UseOn) => {
+                    self.menu = None;
+                    return self.use_item(game);
+                }
                 ContextResult::Selected(ContextItem::Wear) => {
                     self.wear(game);
                     self.menu = None;
@@ -124,41 +204,110 @@ impl Mode for InventoryMode {
 }
 
 impl InventoryMode {
+    /// Returns the inventory items that pass the filter in the current sort order.
+    fn items(&self, game: &Game) -> Vec<InvItem> {
+        let filter = self.filter.to_lowercase();
+        let mut items: Vec<InvItem> = game
+            .inventory()
+            .into_iter()
+            .filter(|item| item.name.to_lowercase().contains(&filter))
+            .collect();
+        match self.sort {
+            SortOrder::PickedUp => (),
+            SortOrder::Name => items.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortOrder::Equipped => items.sort_by_key(|item| item.equipped.is_none()),
+        }
+        items
+    }
+
+    fn status(&self) -> String {
+        let sort = match self.sort {
+            SortOrder::PickedUp => "picked up",
+            SortOrder::Name => "name",
+            SortOrder::Equipped => "equipped",
+        };
+        if let Some((_, name)) = self.naming.as_ref() {
+            return format!("call it: {name}_ (return to finish, escape to cancel)");
+        }
+        let mut status = format!("sorted by {sort}");
+        if self.filtering || !self.filter.is_empty() {
+            status += &format!(", filter: {}", self.filter);
+            if self.filtering {
+                status += "_";
+            }
+        }
+        if !self.marked.is_empty() {
+            status += &format!(", {} marked", self.marked.len());
+        }
+        status
+    }
+
+    // Called when the items list changes.
+    fn reselect(&mut self, game: &Game) {
+        let inv = game.inventory();
+        self.marked.retain(|oid| inv.iter().any(|item| item.oid == *oid));
+        self.selected = None;
+        self.do_select(game, 0, 1);
+    }
+
     fn describe_item(&self, game: &mut Game) -> Vec<String> {
         if let Some(index) = self.selected {
-            let inv = game.inventory();
+            let inv = self.items(game);
             game.describe_item(inv[index].oid)
         } else {
             Vec::new()
         }
     }
 
+    // Names are typed into the status line and committed with return.
+    fn start_naming(&mut self, game: &Game) {
+        let inv = self.items(game);
+        let oid = inv[self.selected.unwrap()].oid;
+        self.naming = Some((oid, String::new()));
+    }
+
     fn drop_item(&self, game: &mut Game) {
-        let inv = game.inventory();
+        let inv = self.items(game);
         let index = self.selected.unwrap();
         game.player_acted(Action::Drop(inv[index].oid));
     }
 
+    // Throwing requires picking a target on the map so this screen is replaced by TargetMode.
+    fn throw_item(&self, game: &mut Game) -> InputAction {
+        let inv = self.items(game);
+        let oid = inv[self.selected.unwrap()].oid;
+        let on_target = Box::new(move |game: &mut Game, target| game.player_acted(Action::Throw { oid, target }));
+        InputAction::Replace(TargetMode::create(game, "verb.throw", THROW_RANGE, on_target))
+    }
+
+    // Like throw_item except that the target has to be adjacent to the player.
+    fn use_item(&self, game: &mut Game) -> InputAction {
+        let inv = self.items(game);
+        let oid = inv[self.selected.unwrap()].oid;
+        let on_target = Box::new(move |game: &mut Game, target| game.player_acted(Action::UseOn { oid, target }));
+        InputAction::Replace(TargetMode::create(game, "use", 1, on_target))
+    }
+
     fn remove_item(&self, game: &mut Game) {
-        let inv = game.inventory();
+        let inv = self.items(game);
         let index = self.selected.unwrap();
         game.player_acted(Action::Remove(inv[index].oid));
     }
 
     fn wear(&self, game: &mut Game) {
-        let inv = game.inventory();
+        let inv = self.items(game);
         let index = self.selected.unwrap();
         game.player_acted(Action::Wear(inv[index].oid));
     }
 
     fn wield_main(&self, game: &mut Game) {
-        let inv = game.inventory();
+        let inv = self.items(game);
         let index = self.selected.unwrap();
         game.player_acted(Action::WieldMainHand(inv[index].oid));
     }
 
     fn wield_off(&self, game: &mut Game) {
-        let inv = game.inventory();
+        let inv = self.items(game);
         let index = self.selected.unwrap();
         game.player_acted(Action::WieldOffHand(inv[index].oid));
     }
@@ -168,11 +317,16 @@ impl InventoryMode {
             return InputAction::NotHandled;
         }
 
-        let inv = game.inventory();
+        let inv = self.items(game);
         let index = self.selected.unwrap();
-        let suffix = inv[index].name;
+        let suffix = &inv[index].name;
 
-        let mut items = vec![ContextItem::Drop];
+        let mut items = vec![
+            ContextItem::Drop,
+            ContextItem::Throw,
+            ContextItem::UseOn,
+            ContextItem::Call,
+        ];
         if inv[index].equipped.is_some() {
             items.push(ContextItem::Remove);
         }
@@ -210,28 +364,78 @@ impl InventoryMode {
     }
 
     fn do_help(&mut self, _game: &mut Game) -> InputAction {
-        let help = r#"Used to manage the items you've picked up.
+        let help = catalog_text("help.inventory", &[]);
+        validate_help("inventory", &help, self.commands.keys());
 
-Selection can be moved using the numeric keypad or arrow keys:
-[[7]] [[8]] [[9]]                  [[up-arrow]]
-[[4]]   [[6]]           [[left-arrow]]   [[right-arrow]]
-[[1]] [[2]] [[3]]                 [[down-arrow]]
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
 
-[[return]] operates on the selection.
-[[?]] shows this help.
-[[escape]] and [[q]] exit the inventory screen."#;
-        validate_help("inventory", help, self.commands.keys());
+    fn do_drop_marked(&mut self, game: &mut Game) -> InputAction {
+        let oids = if self.marked.is_empty() {
+            match self.selected {
+                Some(index) => vec![self.items(game)[index].oid],
+                None => return InputAction::NotHandled,
+            }
+        } else {
+            std::mem::take(&mut self.marked)
+        };
+        game.player_acted(Action::DropMany(oids));
+        self.reselect(game);
+        InputAction::UpdatedGame
+    }
 
-        let lines = format_help(help, self.commands.keys());
-        InputAction::Push(TextMode::at_top().create(lines))
+    fn do_save_set_menu(&mut self, game: &mut Game) -> InputAction {
+        let names = game.loadouts();
+        let items = (0..names.len() as u8).map(ContextItem::SaveSet).collect();
+        let suffix = match names[0] {
+            Some(name) => format!("({name})"),
+            None => String::new(),
+        };
+        self.menu = Some(ContextMenu {
+            parent_origin: self.view.origin,
+            parent_size: self.view.size,
+            items,
+            suffix,
+            selected: 0,
+        });
+        InputAction::UpdatedGame
+    }
+
+    fn do_filter(&mut self, game: &mut Game) -> InputAction {
+        self.filtering = true;
+        self.reselect(game);
+        InputAction::UpdatedGame
+    }
+
+    fn do_mark(&mut self, game: &mut Game) -> InputAction {
+        if let Some(index) = self.selected {
+            let oid = self.items(game)[index].oid;
+            if let Some(i) = self.marked.iter().position(|o| *o == oid) {
+                self.marked.remove(i);
+            } else {
+                self.marked.push(oid);
+            }
+        }
+        InputAction::UpdatedGame
     }
 
     fn do_pop(&mut self, _game: &mut Game) -> InputAction {
         InputAction::Pop
     }
 
+    fn do_sort(&mut self, game: &mut Game) -> InputAction {
+        self.sort = match self.sort {
+            SortOrder::PickedUp => SortOrder::Name,
+            SortOrder::Name => SortOrder::Equipped,
+            SortOrder::Equipped => SortOrder::PickedUp,
+        };
+        self.reselect(game);
+        InputAction::UpdatedGame
+    }
+
     fn do_select(&mut self, game: &Game, dx: i32, dy: i32) -> InputAction {
-        let inv = game.inventory();
+        let inv = self.items(game);
         let weapons = vec![ItemKind::OneHandWeapon, ItemKind::TwoHandWeapon];
         let armor = vec![ItemKind::Armor];
         let other = vec![ItemKind::Other];
@@ -372,8 +576,12 @@ Selection can be moved using the numeric keypad or arrow keys:
 impl fmt::Display for ContextItem {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let s = match self {
+            ContextItem::Call => "Call (give it a name)",
             ContextItem::Drop => "Drop",
             ContextItem::Remove => "Remove",
+            ContextItem::SaveSet(index) => return write!(f, "Save as set {}", index + 1),
+            ContextItem::Throw => "Throw",
+            ContextItem::UseOn => "Use on...",
             ContextItem::Wear => "Wear",
             ContextItem::WieldBothHands => "Wield (both hands)",
             ContextItem::WieldMainHand => "Wield (main hand)",
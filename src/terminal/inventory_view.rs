@@ -1,34 +1,55 @@
+use super::camera::ScreenPoint;
 use super::color;
-use one_thousand_deaths::{Color, Game, InvItem, ItemKind, Point, Size, Slot};
+use one_thousand_deaths::{Color, InvItem, ItemKind, Oid, Size, Slot};
 use std::borrow::Cow;
 use std::io::Write;
 
 const WIDTH: u16 = 30;
 /// Shows info about the player and nearby NPCs.
 pub struct InventoryView {
-    pub origin: Point,
+    pub origin: ScreenPoint,
     pub size: Size,
 }
 
 impl InventoryView {
-    pub fn render(&self, sindex: Option<usize>, stdout: &mut Box<dyn Write>, game: &Game, desc: Vec<String>) {
-        let h = (self.origin.x + 1) as u16; // termion is 1-based
+    pub fn render(
+        &self,
+        inv: &[InvItem],
+        sindex: Option<usize>,
+        status: &str,
+        stdout: &mut Box<dyn Write>,
+        desc: Vec<String>,
+    ) {
+        let h = self.origin.h();
         let mut v = 1;
         self.render_background(stdout);
 
-        let inv = game.inventory();
-        let desc_height = if desc.is_empty() { 0 } else { desc.len() as u16 + 1 };
-        self.render_weapons(&inv, sindex, h, &mut v, stdout, desc_height);
+        // +1 for the status line
+        let desc_height = if desc.is_empty() { 1 } else { desc.len() as u16 + 2 };
+        self.render_weapons(inv, sindex, h, &mut v, stdout, desc_height);
 
         v += 1;
-        self.render_armor(&inv, sindex, h, &mut v, stdout, desc_height);
+        self.render_armor(inv, sindex, h, &mut v, stdout, desc_height);
 
         v = 1;
-        self.render_other(&inv, sindex, h + WIDTH + 1, &mut v, stdout, desc_height);
+        self.render_other(inv, sindex, h + WIDTH + 1, &mut v, stdout, desc_height);
 
+        self.render_status(status, desc.len() as u16, stdout);
         self.render_desc(desc, stdout);
     }
 
+    fn render_status(&self, status: &str, desc_height: u16, stdout: &mut Box<dyn Write>) {
+        let v = self.size.height as u16 - desc_height;
+        let _ = write!(
+            stdout,
+            "{}{}{}{}",
+            termion::cursor::Goto(2, v),
+            termion::color::Bg(color::to_termion(Color::Black)),
+            termion::color::Fg(color::to_termion(Color::LightGrey)),
+            status,
+        );
+    }
+
     fn render_desc(&self, desc: Vec<String>, stdout: &mut Box<dyn Write>) {
         let first = self.size.height as u16 - desc.len() as u16;
         for (i, s) in desc.iter().enumerate() {
@@ -45,7 +66,7 @@ impl InventoryView {
 
     fn render_weapons(
         &self,
-        inv: &Vec<InvItem>,
+        inv: &[InvItem],
         sindex: Option<usize>,
         h: u16,
         v: &mut u16,
@@ -83,7 +104,7 @@ impl InventoryView {
 
     fn render_armor(
         &self,
-        inv: &Vec<InvItem>,
+        inv: &[InvItem],
         sindex: Option<usize>,
         h: u16,
         v: &mut u16,
@@ -114,7 +135,7 @@ impl InventoryView {
 
     fn render_other(
         &self,
-        inv: &Vec<InvItem>,
+        inv: &[InvItem],
         sindex: Option<usize>,
         h: u16,
         v: &mut u16,
@@ -169,6 +190,10 @@ impl InventoryView {
             termion::color::Fg(color::to_termion(fg)),
             text,
         );
+        if selected {
+            let _ = write!(stdout, "{}{}", termion::cursor::Goto(h, v), termion::cursor::Save);
+            // for screen readers
+        }
     }
 
     fn render_background(&self, stdout: &mut Box<dyn Write>) {
@@ -186,6 +211,19 @@ impl InventoryView {
     }
 }
 
+/// Marked items (e.g. the ones that will be dropped) are shown with a leading "+".
+pub fn mark_items(items: Vec<InvItem>, marked: &[Oid]) -> Vec<InvItem> {
+    items
+        .into_iter()
+        .map(|mut item| {
+            if marked.contains(&item.oid) {
+                item.name = format!("+ {}", item.name);
+            }
+            item
+        })
+        .collect()
+}
+
 pub fn truncate_middle(text: &str, max_width: usize) -> Cow<str> {
     if text.len() <= max_width {
         text.into()
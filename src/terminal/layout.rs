@@ -0,0 +1,142 @@
+use super::camera::ScreenPoint;
+use one_thousand_deaths::data_path;
+use one_thousand_deaths::Size;
+use std::fs;
+
+const LAYOUT_PATH: &str = "layout.txt";
+const DETAILS_WIDTH: i32 = 20;
+const MIN_MESSAGES: i32 = 1;
+const MIN_MAP_HEIGHT: i32 = 5;
+
+/// Where the details panel (the player's stats and nearby characters) is drawn.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DetailsSide {
+    Left,
+    Right,
+    Hidden,
+}
+
+/// Where one of the main mode's views goes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Frame {
+    pub origin: ScreenPoint,
+    pub size: Size,
+}
+
+/// How the main screen is split between the map, details, and messages views. This can be
+/// changed in-game and is persisted in layout.txt which has lines like:
+///    messages 5
+///    details right
+pub struct Layout {
+    pub messages: i32, // number of lines in the messages view
+    pub details: DetailsSide,
+}
+
+impl Layout {
+    pub fn load() -> Layout {
+        let mut layout = Layout::default();
+        if let Ok(text) = fs::read_to_string(data_path(LAYOUT_PATH)) {
+            for (i, line) in text.lines().enumerate() {
+                if let Err(err) = layout.parse_line(line) {
+                    warn!("{LAYOUT_PATH}:{} {err}", i + 1);
+                }
+            }
+        }
+        layout
+    }
+
+    pub fn save(&self) {
+        let details = match self.details {
+            DetailsSide::Left => "left",
+            DetailsSide::Right => "right",
+            DetailsSide::Hidden => "hidden",
+        };
+        let text = format!("messages {}\ndetails {details}\n", self.messages);
+        if let Err(err) = fs::write(data_path(LAYOUT_PATH), text) {
+            warn!("couldn't save {LAYOUT_PATH}: {err}");
+        }
+    }
+
+    /// Grows or shrinks the messages view (the map takes up the slack).
+    pub fn resize_messages(&mut self, delta: i32, height: i32) {
+        let max = (height - MIN_MAP_HEIGHT).max(MIN_MESSAGES);
+        self.messages = (self.messages + delta).clamp(MIN_MESSAGES, max);
+    }
+
+    /// Cycles the details panel between the right side, the left side, and hidden.
+    pub fn next_details(&mut self) {
+        self.details = match self.details {
+            DetailsSide::Right => DetailsSide::Left,
+            DetailsSide::Left => DetailsSide::Hidden,
+            DetailsSide::Hidden => DetailsSide::Right,
+        };
+    }
+
+    /// Returns the frames for the map, details, and messages views.
+    pub fn frames(&self, width: i32, height: i32) -> (Frame, Frame, Frame) {
+        let messages = self
+            .messages
+            .clamp(MIN_MESSAGES, (height - MIN_MAP_HEIGHT).max(MIN_MESSAGES));
+        let top = height - messages;
+        let details_width = if self.details == DetailsSide::Hidden {
+            0
+        } else {
+            DETAILS_WIDTH.min(width / 2)
+        };
+        let (map_x, details_x) = match self.details {
+            DetailsSide::Left => (details_width, 0),
+            _ => (0, width - details_width),
+        };
+        (
+            Frame {
+                origin: ScreenPoint::new(map_x, 0),
+                size: Size::new(width - details_width, top),
+            },
+            Frame {
+                origin: ScreenPoint::new(details_x, 0),
+                size: Size::new(details_width, top),
+            },
+            Frame {
+                origin: ScreenPoint::new(0, top),
+                size: Size::new(width, messages),
+            },
+        )
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command {
+            "messages" => {
+                let count = rest.parse::<i32>().map_err(|_| format!("bad count: '{rest}'"))?;
+                if count < MIN_MESSAGES {
+                    return Err(format!("messages should be at least {MIN_MESSAGES}"));
+                }
+                self.messages = count;
+            }
+            "details" => {
+                self.details = match rest {
+                    "left" => DetailsSide::Left,
+                    "right" => DetailsSide::Right,
+                    "hidden" => DetailsSide::Hidden,
+                    _ => return Err(format!("bad details: '{rest}' (should be left, right, or hidden)")),
+                }
+            }
+            _ => return Err(format!("couldn't parse '{line}'")),
+        }
+        Ok(())
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            messages: 5,
+            details: DetailsSide::Right,
+        }
+    }
+}
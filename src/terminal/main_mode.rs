@@ -1,28 +1,69 @@
+use super::context_menu::{ContextMenu, ContextResult};
 use super::details_view::DetailsView;
 use super::help::{format_help, validate_help};
+use super::intent_mode::IntentMode;
+use super::interact_mode::InteractMode;
+use super::layout::Layout;
 use super::map_view::MapView;
-use super::messages_view::{self, MessagesView};
+use super::messages_view::MessagesView;
 use super::mode::{InputAction, Mode, RenderContext};
+use super::pick_up_mode::PickUpMode;
+use super::review_mode::ReviewMode;
+use super::run_mode::RunMode;
+use super::target_mode::TargetMode;
 use super::text_mode::TextMode;
 use super::text_view::{Line, TextRun};
+use super::wizard::{Capability, WizardCommand, WizardCommands};
 use fnv::FnvHashMap;
-use one_thousand_deaths::{Action, Color, Game, Message, Point, Size, Topic};
+use one_thousand_deaths::{
+    catalog_text, Action, Color, Game, Intent, Message, Point, QuestState, Size, State, Topic, POLYMORPH_FORMS,
+};
+use rand::prelude::*;
+use std::fmt;
 use std::fs::File;
 use std::io::{Error, Write};
 use std::path::Path;
 use termion::event::Key;
 
-const NUM_MESSAGES: i32 = 5;
+pub const REVIEW_TURNS: usize = 20;
+
+/// Choices for the wizard undo command.
+const UNDO_TURNS: [usize; 5] = [1, 5, 10, 25, 100];
 
 type KeyHandler = fn(&mut MainMode, &mut Game) -> InputAction;
 type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
 
+/// Extra information drawn over the map.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Overlay {
+    Danger,
+    WizardDanger,
+    Noise,
+}
+
+#[derive(Clone, Copy)]
+struct UndoTurns(usize);
+
+impl fmt::Display for UndoTurns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 1 {
+            write!(f, "undo 1 turn")
+        } else {
+            write!(f, "undo {} turns", self.0)
+        }
+    }
+}
+
 pub struct MainMode {
     map: MapView,
     details: DetailsView,
     messages: MessagesView,
     commands: CommandTable,
+    layout: Layout,
+    wizard: WizardCommands,
     screen_size: Size,
+    overlay: Option<Overlay>,
+    undo_menu: Option<ContextMenu<UndoTurns>>,
 }
 
 impl MainMode {
@@ -36,48 +77,84 @@ impl MainMode {
         commands.insert(Key::Char('2'), Box::new(|s, game| s.do_move(game, 0, 1)));
         commands.insert(Key::Char('3'), Box::new(|s, game| s.do_move(game, 1, 1)));
         commands.insert(Key::Char('4'), Box::new(|s, game| s.do_move(game, -1, 0)));
+        commands.insert(Key::Char('H'), Box::new(|s, game| s.do_run(game, -1, 0)));
+        commands.insert(Key::Char('J'), Box::new(|s, game| s.do_run(game, 0, 1)));
+        commands.insert(Key::Char('K'), Box::new(|s, game| s.do_run(game, 0, -1)));
+        commands.insert(Key::Char('L'), Box::new(|s, game| s.do_run(game, 1, 0)));
+        commands.insert(Key::Char('Y'), Box::new(|s, game| s.do_run(game, -1, -1)));
+        commands.insert(Key::Char('U'), Box::new(|s, game| s.do_run(game, 1, -1)));
+        commands.insert(Key::Char('B'), Box::new(|s, game| s.do_run(game, -1, 1)));
+        commands.insert(Key::Char('N'), Box::new(|s, game| s.do_run(game, 1, 1)));
         commands.insert(Key::Char('5'), Box::new(|s, game| s.do_rest(game)));
         commands.insert(Key::Char('s'), Box::new(|s, game| s.do_rest(game)));
+        commands.insert(Key::Char('S'), Box::new(|s, game| s.do_rest_until_healed(game)));
         commands.insert(Key::Char('6'), Box::new(|s, game| s.do_move(game, 1, 0)));
         commands.insert(Key::Char('7'), Box::new(|s, game| s.do_move(game, -1, -1)));
         commands.insert(Key::Char('8'), Box::new(|s, game| s.do_move(game, 0, -1)));
         commands.insert(Key::Char('9'), Box::new(|s, game| s.do_move(game, 1, -1)));
+        commands.insert(
+            Key::Char('d'),
+            Box::new(|s, game| s.do_toggle_overlay(game, Overlay::Danger)),
+        );
+        commands.insert(Key::Char('a'), Box::new(|s, game| s.do_interact(game)));
+        commands.insert(Key::Char('c'), Box::new(|s, game| s.do_charge(game)));
+        commands.insert(Key::Char('e'), Box::new(|s, game| s.do_swap_set(game)));
+        commands.insert(Key::Char('g'), Box::new(|s, game| s.do_pick_up(game)));
+        commands.insert(Key::Char(':'), Box::new(|s, game| s.do_look_at_floor(game)));
         commands.insert(Key::Char('i'), Box::new(|s, game| s.do_inventory(game)));
+        commands.insert(Key::Char('j'), Box::new(|s, game| s.do_journal(game)));
+        commands.insert(Key::Char('r'), Box::new(|s, game| s.do_review(game)));
+        commands.insert(Key::Char('v'), Box::new(|s, game| s.do_shove(game)));
+        commands.insert(Key::Char('w'), Box::new(|s, game| s.do_withdraw(game)));
         commands.insert(Key::Char('x'), Box::new(|s, game| s.do_examine(game)));
-        if super::wizard_mode() {
-            commands.insert(Key::Ctrl('d'), Box::new(|s, game| s.do_save_state(game)));
-        }
+        commands.insert(Key::Char('\\'), Box::new(|s, game| s.do_discoveries(game)));
+        commands.insert(Key::Char('{'), Box::new(|s, game| s.do_resize_messages(game, -1)));
+        commands.insert(Key::Char('}'), Box::new(|s, game| s.do_resize_messages(game, 1)));
+        commands.insert(Key::Char('|'), Box::new(|s, game| s.do_move_details(game)));
+        let wizard = WizardCommands::register(&mut commands, MainMode::wizard_commands());
 
         // We don't receive ctrl-m so we use ctrl-p because that's what Crawl does.
         commands.insert(Key::Ctrl('p'), Box::new(|s, game| s.do_show_messages(game)));
         commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
         commands.insert(Key::Char('q'), Box::new(|s, game| s.do_quit(game)));
 
-        let details_width = 20;
+        let layout = Layout::load();
+        let (map, details, messages) = layout.frames(width, height);
         Box::new(MainMode {
             map: MapView {
-                origin: Point::new(0, 0),
-                size: Size::new(width - details_width, height - NUM_MESSAGES),
+                origin: map.origin,
+                size: map.size,
             },
             details: DetailsView {
-                origin: Point::new(width - details_width, 0),
-                size: Size::new(details_width, height - NUM_MESSAGES),
-            },
-            messages: MessagesView {
-                origin: Point::new(0, height - NUM_MESSAGES),
-                size: Size::new(width, NUM_MESSAGES),
+                origin: details.origin,
+                size: details.size,
             },
+            messages: MessagesView::new(messages.origin, messages.size),
             commands,
+            layout,
+            wizard,
             screen_size: Size::new(width, height),
+            overlay: None,
+            undo_menu: None,
         })
     }
 }
 
 impl Mode for MainMode {
     fn render(&self, context: &mut RenderContext) -> bool {
-        self.details.render(context.stdout, context.game); // TODO: views should probably take context
-        self.map.render(context.stdout, context.game, context.examined); // TODO: details can write into the next line so this will fix up (which may cause flashing)
+        if self.details.size.width > 0 {
+            self.details.render(context.stdout, context.game); // TODO: views should probably take context
+        }
+        self.add_overlay(context);
+        // Details can write into the next line so the map is drawn afterwards to fix that up.
+        self.map
+            .render(context.stdout, context.game, context.examined, &context.overlay);
+        self.map
+            .render_indicators(context.stdout, context.game, &context.indicators);
         self.messages.render(context.stdout, context.game);
+        if let Some(menu) = self.undo_menu.as_ref() {
+            menu.render(context.stdout);
+        }
         true
     }
 
@@ -86,60 +163,271 @@ impl Mode for MainMode {
     }
 
     fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        // Any key dismisses --more-- (so that players don't act before reading everything).
+        if self.messages.has_more(game) {
+            self.messages.next_page();
+            return InputAction::UpdatedGame;
+        }
+        self.messages.saw_all(game);
+
+        if let Some(menu) = self.undo_menu.as_mut() {
+            match menu.handle_input(key) {
+                ContextResult::Selected(UndoTurns(turns)) => {
+                    self.undo_menu = None;
+                    game.undo(turns);
+                }
+                ContextResult::Pop => self.undo_menu = None,
+                ContextResult::Updated => (),
+                ContextResult::NotHandled => (),
+            }
+            return InputAction::UpdatedGame;
+        }
+
         match self.commands.get(&key).cloned() {
-            Some(handler) => handler(self, game),
+            Some(handler) => {
+                self.wizard.note_key(game, key);
+                handler(self, game)
+            }
             None => InputAction::NotHandled,
         }
     }
 }
 
 impl MainMode {
+    fn wizard_commands() -> Vec<WizardCommand<MainMode>> {
+        use Capability::*;
+        vec![
+            WizardCommand {
+                key: Key::Ctrl('a'),
+                capability: Inspect,
+                help: "help.wizard.main.control_a",
+                handler: MainMode::do_compact,
+            },
+            WizardCommand {
+                key: Key::Ctrl('b'),
+                capability: Cheat,
+                help: "help.wizard.main.control_b",
+                handler: MainMode::do_auto_play,
+            },
+            WizardCommand {
+                key: Key::Ctrl('d'),
+                capability: Inspect,
+                help: "help.wizard.main.control_d",
+                handler: MainMode::do_save_state,
+            },
+            WizardCommand {
+                key: Key::Ctrl('e'),
+                capability: Inspect,
+                help: "help.wizard.main.control_e",
+                handler: MainMode::do_export_map,
+            },
+            WizardCommand {
+                key: Key::Ctrl('k'),
+                capability: Inspect,
+                help: "help.wizard.main.control_k",
+                handler: MainMode::do_toggle_combat_details,
+            },
+            WizardCommand {
+                key: Key::Ctrl('n'),
+                capability: Inspect,
+                help: "help.wizard.main.control_n",
+                handler: MainMode::do_toggle_noise_overlay,
+            },
+            WizardCommand {
+                key: Key::Ctrl('o'),
+                capability: Inspect,
+                help: "help.wizard.main.control_o",
+                handler: MainMode::do_show_scheduler,
+            },
+            WizardCommand {
+                key: Key::Ctrl('t'),
+                capability: Inspect,
+                help: "help.wizard.main.control_t",
+                handler: MainMode::do_toggle_wizard_overlay,
+            },
+            WizardCommand {
+                key: Key::Ctrl('u'),
+                capability: Cheat,
+                help: "help.wizard.main.control_u",
+                handler: MainMode::do_undo,
+            },
+            WizardCommand {
+                key: Key::Ctrl('y'),
+                capability: Cheat,
+                help: "help.wizard.main.control_y",
+                handler: MainMode::do_polymorph,
+            },
+        ]
+    }
+
+    // Modes higher in the stack (e.g. TargetMode) take precedence over our overlay.
+    fn add_overlay(&self, context: &mut RenderContext) {
+        let wizard = match self.overlay {
+            Some(Overlay::Danger) => false,
+            Some(Overlay::WizardDanger) => true,
+            Some(Overlay::Noise) => {
+                for (loc, percent) in context.game.noise_map() {
+                    let color = match percent {
+                        0..=24 => Color::Navy,
+                        25..=49 => Color::DarkSlateBlue,
+                        50..=74 => Color::DarkViolet,
+                        _ => Color::DarkMagenta,
+                    };
+                    context.overlay.entry(loc).or_insert(color);
+                }
+                return;
+            }
+            None => return,
+        };
+        for (loc, threat) in context.game.threats(wizard) {
+            let color = match (threat.reachable_by, threat.seen_by) {
+                (0, 0) => continue,
+                (0, _) => Color::DarkOliveGreen,
+                (1, _) => Color::DarkGoldenrod,
+                (2, _) => Color::DarkOrange,
+                (_, _) => Color::DarkRed,
+            };
+            context.overlay.entry(loc).or_insert(color);
+        }
+    }
+
+    fn do_auto_play(&mut self, game: &mut Game) -> InputAction {
+        InputAction::Push(super::auto_play_mode::AutoPlayMode::create(game))
+    }
+
+    fn do_polymorph(&mut self, game: &mut Game) -> InputAction {
+        let into = *POLYMORPH_FORMS.choose(&mut thread_rng()).unwrap();
+        let loc = game.player_loc();
+        game.player_acted(Action::Polymorph { loc, into });
+        InputAction::UpdatedGame
+    }
+
+    fn do_compact(&mut self, game: &mut Game) -> InputAction {
+        let lines = game
+            .compact()
+            .into_iter()
+            .map(|text| vec![TextRun::Color(Color::White), TextRun::Text(text)])
+            .collect();
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_discoveries(&mut self, game: &mut Game) -> InputAction {
+        InputAction::Push(super::discoveries_mode::DiscoveriesMode::create(game))
+    }
+
+    fn do_resize_messages(&mut self, _game: &mut Game, delta: i32) -> InputAction {
+        self.layout.resize_messages(delta, self.screen_size.height);
+        self.apply_layout();
+        InputAction::UpdatedGame
+    }
+
+    fn do_move_details(&mut self, _game: &mut Game) -> InputAction {
+        self.layout.next_details();
+        self.apply_layout();
+        InputAction::UpdatedGame
+    }
+
+    // Relays the new layout to the views and saves it so that it's used next time.
+    fn apply_layout(&mut self) {
+        let (map, details, messages) = self.layout.frames(self.screen_size.width, self.screen_size.height);
+        self.map.origin = map.origin;
+        self.map.size = map.size;
+        self.details.origin = details.origin;
+        self.details.size = details.size;
+        self.messages.origin = messages.origin;
+        self.messages.size = messages.size;
+        self.layout.save();
+    }
+
     fn do_examine(&mut self, game: &mut Game) -> InputAction {
         let loc = game.player_loc();
         let window = super::examine_mode::ExamineMode::create(loc);
         InputAction::Push(window)
     }
 
+    fn do_export_map(&mut self, game: &mut Game) -> InputAction {
+        let path = self.state_path("map", "html");
+        let result = File::create(&path).and_then(|mut file| super::html_export::export_html(&mut file, game));
+        let mesg = match result {
+            Ok(_) => Message {
+                topic: Topic::Important,
+                text: format!("Exported map to {path}"),
+            },
+            Err(err) => Message {
+                topic: Topic::Error,
+                text: format!("Couldn't export map to {path}: {err}"),
+            },
+        };
+        game.add_mesg(mesg);
+        InputAction::UpdatedGame
+    }
+
     fn do_help(&mut self, _game: &mut Game) -> InputAction {
-        let mut help = r#"Help for the main game. Note that help is context sensitive,
-e.g. examine mode has its own set of commands and its own help screen.
-
-Movement is done using the numeric keypad or arrow keys:
-[[7]] [[8]] [[9]]                  [[up-arrow]]
-[[4]]   [[6]]           [[left-arrow]]   [[right-arrow]]
-[[1]] [[2]] [[3]]                 [[down-arrow]]
-
-[[5]] or [[s]] rest for one turn.
-[[i]] manage inventory items.
-[[x]] examine visible cells.
-[[control-p]] show recent messages.
-[[?]] show this help.
-[[q]] save and quit
-"#
-        .to_string();
-        if super::wizard_mode() {
-            help += r#"
-
-Wizard mode commands:
-[[control-d]] dump game state to state-xxx.txt.
-"#;
-        }
+        let mut help = catalog_text("help.main", &[]);
+        help += &self.wizard.help();
         validate_help("main", &help, self.commands.keys());
 
         let lines = format_help(&help, self.commands.keys());
         InputAction::Push(TextMode::at_top().create(lines))
     }
 
+    fn do_interact(&mut self, game: &mut Game) -> InputAction {
+        match InteractMode::create(game) {
+            Some(mode) => InputAction::Push(mode),
+            None => InputAction::UpdatedGame,
+        }
+    }
+
     fn do_inventory(&mut self, game: &mut Game) -> InputAction {
         let window = super::inventory_mode::InventoryMode::create(game, self.screen_size);
         InputAction::Push(window)
     }
 
+    fn do_journal(&mut self, game: &mut Game) -> InputAction {
+        let mut lines = vec![vec![
+            TextRun::Color(Color::Yellow),
+            TextRun::Text("Journal".to_string()),
+        ]];
+        for quest in game.quests() {
+            let fg = match quest.state {
+                QuestState::Active => Color::White,
+                QuestState::Complete => Color::LimeGreen,
+                QuestState::Failed => Color::Red,
+            };
+            lines.push(vec![]);
+            lines.push(vec![
+                TextRun::Color(fg),
+                TextRun::Text(format!("{} ({})", quest.objective, quest.state)),
+            ]);
+            lines.push(vec![
+                TextRun::Color(Color::LightGrey),
+                TextRun::Text(format!("    {}", quest.hint)),
+            ]);
+        }
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
     fn do_move(&mut self, game: &mut Game, dx: i32, dy: i32) -> InputAction {
         game.player_acted(Action::Move { dx, dy });
         InputAction::UpdatedGame
     }
 
+    fn do_look_at_floor(&mut self, game: &mut Game) -> InputAction {
+        let lines = game
+            .describe_floor()
+            .into_iter()
+            .map(|text| vec![TextRun::Color(Color::White), TextRun::Text(text)])
+            .collect();
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_pick_up(&mut self, game: &mut Game) -> InputAction {
+        match PickUpMode::create(game, self.screen_size) {
+            Some(mode) => InputAction::Push(mode),
+            None => InputAction::UpdatedGame,
+        }
+    }
+
     fn do_quit(&mut self, _game: &mut Game) -> InputAction {
         InputAction::Quit
     }
@@ -149,9 +437,22 @@ Wizard mode commands:
         InputAction::UpdatedGame
     }
 
-    fn state_path(&self, base: &str) -> String {
+    fn do_rest_until_healed(&mut self, game: &mut Game) -> InputAction {
+        if game.player_regenerating() {
+            InputAction::Push(IntentMode::create(game, Intent::RestUntilHealed))
+        } else {
+            game.add_mesg(Message::new(Topic::Failed, &catalog_text("rest.not_needed", &[])));
+            InputAction::UpdatedGame
+        }
+    }
+
+    fn do_run(&mut self, game: &mut Game, dx: i32, dy: i32) -> InputAction {
+        InputAction::Push(RunMode::create(game, dx, dy))
+    }
+
+    fn state_path(&self, base: &str, extension: &str) -> String {
         for i in 1..1000 {
-            let candidate = format!("{base}-{:0>3}.txt", i);
+            let candidate = one_thousand_deaths::data_path(&format!("{base}-{:0>3}.{extension}", i));
             if !Path::new(&candidate).is_file() {
                 return candidate;
             }
@@ -168,8 +469,34 @@ Wizard mode commands:
         Ok(())
     }
 
+    fn do_review(&mut self, game: &mut Game) -> InputAction {
+        if game.state() != State::LostGame {
+            game.add_mesg(Message::new(Topic::Failed, &catalog_text("review.not_dead", &[])));
+            return InputAction::UpdatedGame;
+        }
+        match game.review(REVIEW_TURNS) {
+            Some((review, replay)) => {
+                let (width, height) = (self.screen_size.width, self.screen_size.height);
+                InputAction::Push(ReviewMode::create(width, height, review, replay))
+            }
+            None => InputAction::UpdatedGame,
+        }
+    }
+
+    fn do_charge(&mut self, game: &mut Game) -> InputAction {
+        let on_target = Box::new(|game: &mut Game, target: Point| {
+            let player_loc = game.player_loc();
+            let dx = (target.x - player_loc.x).signum();
+            let dy = (target.y - player_loc.y).signum();
+            if dx != 0 || dy != 0 {
+                game.player_acted(Action::Charge { dx, dy });
+            }
+        });
+        InputAction::Push(TargetMode::create(game, "verb.charge", 1, on_target))
+    }
+
     fn do_save_state(&mut self, game: &mut Game) -> InputAction {
-        let path = self.state_path("state");
+        let path = self.state_path("state", "txt");
         if let Err(err) = File::create(&path).and_then(|mut file| self.save_state(&path, &mut file, game)) {
             game.add_mesg(Message {
                 topic: Topic::Error,
@@ -179,18 +506,93 @@ Wizard mode commands:
         InputAction::UpdatedGame
     }
 
-    fn do_show_messages(&mut self, game: &mut Game) -> InputAction {
-        fn get_lines(game: &mut Game) -> Vec<Line> {
-            let mut lines = Vec::new();
-            for message in game.recent_messages(usize::MAX) {
-                let fg = messages_view::to_fore_color(message.topic);
-                let line = vec![TextRun::Color(fg), TextRun::Text(message.text.clone())];
-                lines.push(line);
+    fn do_shove(&mut self, game: &mut Game) -> InputAction {
+        let on_target = Box::new(|game: &mut Game, target: Point| {
+            let player_loc = game.player_loc();
+            let dx = (target.x - player_loc.x).signum();
+            let dy = (target.y - player_loc.y).signum();
+            if dx != 0 || dy != 0 {
+                game.player_acted(Action::Shove { dx, dy });
+            }
+        });
+        InputAction::Push(TargetMode::create(game, "verb.shove", 1, on_target))
+    }
+
+    fn do_undo(&mut self, _game: &mut Game) -> InputAction {
+        self.undo_menu = Some(ContextMenu {
+            parent_origin: self.map.origin,
+            parent_size: self.map.size,
+            items: UNDO_TURNS.iter().map(|&turns| UndoTurns(turns)).collect(),
+            suffix: "(rewrites the saved game)".to_string(),
+            selected: 0,
+        });
+        InputAction::UpdatedGame
+    }
+
+    fn do_withdraw(&mut self, game: &mut Game) -> InputAction {
+        let on_target = Box::new(|game: &mut Game, target: Point| {
+            let player_loc = game.player_loc();
+            let dx = (target.x - player_loc.x).signum();
+            let dy = (target.y - player_loc.y).signum();
+            if dx != 0 || dy != 0 {
+                game.player_acted(Action::Withdraw { dx, dy });
+            }
+        });
+        InputAction::Push(TargetMode::create(game, "verb.withdraw", 1, on_target))
+    }
+
+    fn do_toggle_wizard_overlay(&mut self, game: &mut Game) -> InputAction {
+        self.do_toggle_overlay(game, Overlay::WizardDanger)
+    }
+
+    fn do_toggle_combat_details(&mut self, game: &mut Game) -> InputAction {
+        let enabled = !game.combat_details();
+        game.set_combat_details(enabled);
+        let state = if enabled { "on" } else { "off" };
+        game.add_mesg(Message {
+            topic: Topic::Normal,
+            text: format!("Combat details are {state}."),
+        });
+        InputAction::UpdatedGame
+    }
+
+    fn do_toggle_noise_overlay(&mut self, game: &mut Game) -> InputAction {
+        self.do_toggle_overlay(game, Overlay::Noise)
+    }
+
+    fn do_toggle_overlay(&mut self, _game: &mut Game, overlay: Overlay) -> InputAction {
+        if self.overlay == Some(overlay) {
+            self.overlay = None;
+        } else {
+            self.overlay = Some(overlay);
+        }
+        InputAction::UpdatedGame
+    }
+
+    fn do_swap_set(&mut self, game: &mut Game) -> InputAction {
+        match game.next_loadout() {
+            Some(index) => InputAction::Push(IntentMode::create(game, Intent::EquipSet(index))),
+            None => {
+                game.add_mesg(Message::new(Topic::Failed, &catalog_text("loadout.none", &[])));
+                InputAction::UpdatedGame
             }
-            lines
         }
+    }
 
-        let lines = get_lines(game);
+    fn do_show_scheduler(&mut self, game: &mut Game) -> InputAction {
+        InputAction::Push(super::scheduler_mode::SchedulerMode::create(game))
+    }
+
+    fn do_show_messages(&mut self, game: &mut Game) -> InputAction {
+        let options = &self.messages.options;
+        let lines: Vec<Line> = options
+            .history(game)
+            .iter()
+            .map(|line| {
+                let fg = options.fore_color(line.message.topic);
+                vec![TextRun::Color(fg), TextRun::Text(line.text())]
+            })
+            .collect();
         InputAction::Push(TextMode::at_bottom().with_bg(Color::White).create(lines))
     }
 }
@@ -1,10 +1,13 @@
+use super::camera::{Camera, ScreenPoint};
+use super::indicators::Indicator;
+use fnv::FnvHashMap;
 use one_thousand_deaths::{Color, Game, Point, Size, Symbol, Tile};
 use std::io::Write;
-use termion::{color, cursor, style};
+use termion::{color, style};
 
 /// Responsible for drawing the level, i.e. the terrain, characters, items, etc.
 pub struct MapView {
-    pub origin: Point,
+    pub origin: ScreenPoint,
     pub size: Size,
 }
 
@@ -14,28 +17,34 @@ pub struct MapView {
 pub struct Run {
     tile: Tile,
     focused: bool,
+    overlay: Option<Color>,
 }
 
 impl MapView {
-    pub fn render(&self, stdout: &mut Box<dyn Write>, game: &mut Game, examined: Option<Point>) {
-        let start_loc = Point::new(
-            game.player_loc().x - self.size.width / 2,
-            game.player_loc().y - self.size.height / 2,
-        );
+    pub fn render(
+        &self,
+        stdout: &mut Box<dyn Write>,
+        game: &mut Game,
+        examined: Option<Point>,
+        overlay: &FnvHashMap<Point, Color>,
+    ) {
+        let camera = Camera::centered(self.origin, self.size, game.player_loc());
         for y in 0..self.size.height {
-            let v = (self.origin.y + y + 1) as u16;
-            let _ = write!(stdout, "{}", cursor::Goto(1, v),);
+            let row = ScreenPoint::new(self.origin.x, self.origin.y + y);
+            let _ = write!(stdout, "{}", row.goto());
 
             let mut run = Run {
                 tile: Tile::NotVisible,
                 focused: false,
+                overlay: None,
             };
             let mut count = 0;
             for x in 0..self.size.width {
-                let pt = Point::new(start_loc.x + x, start_loc.y + y);
+                let pt = camera.to_world(ScreenPoint::new(row.x + x, row.y));
                 let candidate = Run {
                     tile: game.tile(&pt),
                     focused: examined.map_or(false, |loc| loc == pt),
+                    overlay: overlay.get(&pt).copied(),
                 };
                 if candidate == run {
                     count += 1;
@@ -51,16 +60,31 @@ impl MapView {
         }
     }
 
+    /// Indicators are drawn starting at their cell and are clipped to the view.
+    pub fn render_indicators(&self, stdout: &mut Box<dyn Write>, game: &Game, indicators: &[Indicator]) {
+        let camera = Camera::centered(self.origin, self.size, game.player_loc());
+        for indicator in indicators {
+            let pt = camera.to_screen(indicator.loc);
+            let right = self.origin.x + self.size.width;
+            if pt.x < self.origin.x || pt.x >= right || pt.y < self.origin.y || pt.y >= self.origin.y + self.size.height
+            {
+                continue;
+            }
+            let text: String = indicator.text.chars().take((right - pt.x) as usize).collect();
+            let _ = write!(
+                stdout,
+                "{}{}{}{}",
+                pt.goto(),
+                color::Bg(super::color::to_termion(Color::Black)),
+                color::Fg(super::color::to_termion(indicator.color)),
+                text
+            );
+        }
+    }
+
     fn render_run(&self, stdout: &mut Box<dyn Write>, run: &Run, count: usize) {
-        let (bg, fg, symbol) = match run.tile {
-            Tile::Visible {
-                bg: b,
-                fg: f,
-                symbol: s,
-            } => (b, f, s), // TODO: use black if there is a character or item?
-            Tile::Stale(s) => (Color::LightGrey, Color::DarkGray, s),
-            Tile::NotVisible => (Color::Black, Color::Black, Symbol::Unseen),
-        };
+        let (bg, fg, symbol) = tile_colors(&run.tile);
+        let bg = run.overlay.unwrap_or(bg);
         let text = self.symbols(symbol, count);
         if run.focused {
             let _ = write!(
@@ -84,24 +108,60 @@ impl MapView {
     }
 
     fn symbols(&self, symbol: Symbol, count: usize) -> String {
-        use Symbol::*;
-        match symbol {
-            ClosedDoor => "+".repeat(count),
-            DeepLiquid => "\u{224B}".repeat(count), // TRIPLE TILDE
-            Dirt => ".".repeat(count),
-            Npc(ch) => format!("{}", ch).repeat(count),
-            OpenDoor => ":".repeat(count),
-            PickAxe => "\u{26CF}".repeat(count), // pick
-            Player => "\u{265D}".repeat(count),  // BLACK CHESS BISHOP
-            Rubble => "\u{2237}".repeat(count),  // PROPORTION
-            ShallowLiquid => "~".repeat(count),
-            Armor => "\u{2720}".repeat(count),               // MALTESE CROSS
-            Sign => "\u{261E}".repeat(count),                // WHITE RIGHT POINTING INDEX
-            StrongSword => "\u{2694}\u{FE0F}".repeat(count), // crossed swords
-            Tree => "\u{2B06}\u{FE0E}".repeat(count),        // UPWARDS BLACK ARROW
-            Unseen => " ".repeat(count),
-            Wall => "\u{25FC}\u{FE0E}".repeat(count), // BLACK MEDIUM SQUARE
-            WeakSword => "\u{1F5E1}".repeat(count),   // dagger
-        }
+        glyph(symbol).repeat(count)
+    }
+}
+
+/// Returns the background and foreground colors used to draw a tile along with its symbol.
+/// With shading on remembered cells are drawn dimmed (and faded cells even more so) and
+/// cells that have never been seen are drawn solid so that neither can be confused with
+/// visible cells.
+pub fn tile_colors(tile: &Tile) -> (Color, Color, Symbol) {
+    let shading = super::symbol_options::shading();
+    match *tile {
+        Tile::Visible {
+            bg: b,
+            fg: f,
+            symbol: s,
+        } => (b, f, s), // TODO: use black if there is a character or item?
+        Tile::Stale(s) if shading => (Color::Black, Color::DimGray, s),
+        Tile::Stale(s) => (Color::LightGrey, Color::DarkGray, s),
+        Tile::Faded(s) if shading => (Color::Black, Color::DarkSlateGray, s),
+        Tile::Faded(s) => (Color::LightGrey, Color::Silver, s),
+        Tile::NotVisible if shading => (Color::DarkSlateGray, Color::DarkSlateGray, Symbol::Unseen),
+        Tile::NotVisible => (Color::Black, Color::Black, Symbol::Unseen),
+    }
+}
+
+pub fn glyph(symbol: Symbol) -> String {
+    super::symbol_options::custom_glyph(symbol).unwrap_or_else(|| default_glyph(symbol))
+}
+
+pub fn default_glyph(symbol: Symbol) -> String {
+    use Symbol::*;
+    match symbol {
+        ClosedDoor => "+".to_string(),
+        DeepLiquid => "\u{224B}".to_string(), // TRIPLE TILDE
+        Dirt => ".".to_string(),
+        Key => "\u{1F5DD}".to_string(),   // OLD KEY
+        Lever => "\u{2A5B}".to_string(),  // LOGICAL OR WITH MIDDLE STEM
+        Light => "\u{1F56F}".to_string(), // CANDLE
+        Npc(ch) => ch.to_string(),
+        OpenDoor => ":".to_string(),
+        PickAxe => "\u{26CF}".to_string(),       // pick
+        Player => "\u{265D}".to_string(),        // BLACK CHESS BISHOP
+        PressurePlate => "\u{25AD}".to_string(), // WHITE RECTANGLE
+        Rubble => "\u{2237}".to_string(),        // PROPORTION
+        ShallowLiquid => "~".to_string(),
+        Armor => "\u{2720}".to_string(),               // MALTESE CROSS
+        Bell => "\u{1F514}".to_string(),               // BELL
+        Rock => "\u{2022}".to_string(),                // BULLET
+        Sign => "\u{261E}".to_string(),                // WHITE RIGHT POINTING INDEX
+        Spear => "\u{2191}".to_string(),               // UPWARDS ARROW
+        StrongSword => "\u{2694}\u{FE0F}".to_string(), // crossed swords
+        Tree => "\u{2B06}\u{FE0E}".to_string(),        // UPWARDS BLACK ARROW
+        Unseen => " ".to_string(),
+        Wall => "\u{25FC}\u{FE0E}".to_string(), // BLACK MEDIUM SQUARE
+        WeakSword => "\u{1F5E1}".to_string(),   // dagger
     }
 }
@@ -0,0 +1,185 @@
+use super::color;
+use fnv::FnvHashMap;
+use one_thousand_deaths::data_path;
+use one_thousand_deaths::{Color, Game, Message, Topic};
+use std::fs;
+
+const OPTIONS_PATH: &str = "message-options.txt";
+
+/// Controls how chatty the messages view is. Each topic has a level and messages are
+/// only shown if their level is at or below the player's chosen level.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// Which messages are shown in the messages view and how they are colored. The defaults
+/// can be changed using message-options.txt which has lines like:
+///    # Comment
+///    topic PlayerIsDamaged Crimson quiet
+///    verbosity normal
+///    squelch You splash through the water
+///    more off
+/// Squelched messages are never shown in the messages view (but do appear in the
+/// message history).
+pub struct MessageOptions {
+    topics: FnvHashMap<Topic, (Color, Verbosity)>,
+    verbosity: Verbosity,
+    squelch: Vec<String>, // messages containing one of these are suppressed
+    pub more: bool,       // pause with --more-- when there are too many new messages to show
+}
+
+/// Consecutive identical messages are collapsed into a single line.
+pub struct Collapsed<'a> {
+    pub message: &'a Message,
+    pub count: usize,
+}
+
+impl<'a> Collapsed<'a> {
+    pub fn text(&self) -> String {
+        if self.count > 1 {
+            format!("{} x{}", self.message.text, self.count)
+        } else {
+            self.message.text.clone()
+        }
+    }
+}
+
+impl MessageOptions {
+    pub fn load() -> MessageOptions {
+        let mut options = MessageOptions::default();
+        if let Ok(text) = fs::read_to_string(data_path(OPTIONS_PATH)) {
+            for (i, line) in text.lines().enumerate() {
+                if let Err(err) = options.parse_line(line) {
+                    warn!("{OPTIONS_PATH}:{} {err}", i + 1);
+                }
+            }
+        }
+        options
+    }
+
+    pub fn fore_color(&self, topic: Topic) -> Color {
+        self.topics.get(&topic).map_or(Color::Black, |(color, _)| *color)
+    }
+
+    /// Returns up to limit of the most recent messages that pass the verbosity and
+    /// squelch filters, oldest first.
+    pub fn recent<'a>(&self, game: &'a Game, limit: usize) -> Vec<Collapsed<'a>> {
+        let mut lines: Vec<Collapsed> = Vec::new();
+        for message in game.recent_messages(usize::MAX).rev().filter(|m| self.shown(m)) {
+            match lines.last_mut() {
+                Some(Collapsed { message: last, count }) if *last == message => *count += 1,
+                _ => {
+                    if lines.len() == limit {
+                        break;
+                    }
+                    lines.push(Collapsed { message, count: 1 });
+                }
+            }
+        }
+        lines.reverse();
+        lines
+    }
+
+    /// Returns the messages added after the first seen messages that pass the filters,
+    /// oldest first. Seen is a count from Game::num_messages.
+    pub fn unseen<'a>(&self, game: &'a Game, seen: usize) -> Vec<Collapsed<'a>> {
+        let count = game.num_messages().saturating_sub(seen);
+        collapse(game.recent_messages(count).filter(|m| self.shown(m)))
+    }
+
+    /// Returns all the messages with duplicates collapsed, oldest first.
+    pub fn history<'a>(&self, game: &'a Game) -> Vec<Collapsed<'a>> {
+        collapse(game.recent_messages(usize::MAX))
+    }
+
+    fn shown(&self, message: &Message) -> bool {
+        let level = self.topics.get(&message.topic).map_or(Verbosity::Normal, |(_, v)| *v);
+        level <= self.verbosity && !self.squelch.iter().any(|s| message.text.contains(s.as_str()))
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command {
+            "topic" => {
+                let parts: Vec<&str> = rest.split_whitespace().collect();
+                if parts.len() != 3 {
+                    return Err("topic should be followed by a topic, a color, and a verbosity".to_string());
+                }
+                let topic = parse_topic(parts[0]).ok_or(format!("bad topic: '{}'", parts[0]))?;
+                let color = color::from_name(parts[1]).ok_or(format!("bad color: '{}'", parts[1]))?;
+                let verbosity = parse_verbosity(parts[2])?;
+                self.topics.insert(topic, (color, verbosity));
+            }
+            "verbosity" => self.verbosity = parse_verbosity(rest)?,
+            "squelch" if !rest.is_empty() => self.squelch.push(rest.to_string()),
+            "more" if rest == "on" || rest == "off" => self.more = rest == "on",
+            _ => return Err(format!("couldn't parse '{line}'")),
+        }
+        Ok(())
+    }
+}
+
+impl Default for MessageOptions {
+    fn default() -> Self {
+        use Topic::*;
+        use Verbosity::{Normal as Shown, Quiet, Verbose};
+        let topics = [
+            (Error, Color::Red, Quiet),
+            (Normal, Color::Black, Shown),
+            (Failed, Color::Red, Quiet),
+            (Hint, Color::DarkGreen, Quiet),
+            (Important, Color::Blue, Quiet),
+            (NpcIsDamaged, Color::LightSkyBlue, Shown),
+            (NpcIsNotDamaged, Color::Black, Verbose),
+            (NPCSpeaks, Color::Coral, Shown),
+            (PlayerDidDamage, Color::Goldenrod, Quiet),
+            (PlayerDidNoDamage, Color::Khaki, Verbose),
+            (PlayerIsDamaged, Color::Crimson, Quiet),
+            (PlayerIsNotDamaged, Color::Pink, Verbose),
+            (Warning, Color::Orange, Quiet),
+            (Fatal, Color::Red, Quiet),
+            (Environmental, Color::SlateGray, Shown),
+        ]
+        .iter()
+        .map(|(topic, color, verbosity)| (*topic, (*color, *verbosity)))
+        .collect();
+        MessageOptions {
+            topics,
+            verbosity: Verbosity::Normal,
+            squelch: Vec::new(),
+            more: true,
+        }
+    }
+}
+
+fn collapse<'a>(messages: impl Iterator<Item = &'a Message>) -> Vec<Collapsed<'a>> {
+    let mut lines: Vec<Collapsed> = Vec::new();
+    for message in messages {
+        match lines.last_mut() {
+            Some(Collapsed { message: last, count }) if *last == message => *count += 1,
+            _ => lines.push(Collapsed { message, count: 1 }),
+        }
+    }
+    lines
+}
+
+pub fn parse_topic(name: &str) -> Option<Topic> {
+    Topic::ALL.into_iter().find(|topic| topic.to_string() == name)
+}
+
+fn parse_verbosity(name: &str) -> Result<Verbosity, String> {
+    match name {
+        "quiet" => Ok(Verbosity::Quiet),
+        "normal" => Ok(Verbosity::Normal),
+        "verbose" => Ok(Verbosity::Verbose),
+        _ => Err(format!("bad verbosity: '{name}' (should be quiet, normal, or verbose)")),
+    }
+}
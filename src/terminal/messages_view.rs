@@ -1,24 +1,71 @@
+use super::camera::ScreenPoint;
 use super::color;
-use one_thousand_deaths::{Color, Game, Point, Size, Topic};
+use super::message_options::{Collapsed, MessageOptions};
+use one_thousand_deaths::{Color, Game, Size};
 use std::io::Write;
 
+// If a replay or auto-play leaves more unseen lines than this then we don't bother
+// paging through them.
+const MAX_MORE_LINES: usize = 100;
+
 /// Responsible for drawing the last few messages.
 pub struct MessagesView {
-    pub origin: Point,
+    pub origin: ScreenPoint,
     pub size: Size,
+    pub options: MessageOptions,
+    seen: usize, // from Game::num_messages
+    page: usize, // number of --more-- pages the player has gone through
 }
 
 impl MessagesView {
+    pub fn new(origin: ScreenPoint, size: Size) -> MessagesView {
+        MessagesView {
+            origin,
+            size,
+            options: MessageOptions::load(),
+            seen: 0,
+            page: 0,
+        }
+    }
+
+    /// Returns true if there are new messages that the player hasn't seen that won't fit
+    /// into the view. If so the view will render those with a --more-- prompt.
+    pub fn has_more(&self, game: &Game) -> bool {
+        self.pending(game).is_some()
+    }
+
+    /// Called when the player presses a key while --more-- is displayed.
+    pub fn next_page(&mut self) {
+        self.page += 1;
+    }
+
+    /// Called when the player presses a key when all the new messages are visible.
+    pub fn saw_all(&mut self, game: &Game) {
+        self.seen = game.num_messages();
+        self.page = 0;
+    }
+
     pub fn render(&self, stdout: &mut Box<dyn Write>, game: &Game) {
-        let h = (self.origin.x + 1) as u16; // termion is 1-based
-        let mut v = (self.origin.y + 1) as u16;
+        let h = self.origin.h();
+        let mut v = self.origin.v();
         let bg = Color::White;
-        for message in game.recent_messages(self.size.height as usize) {
-            let fg = to_fore_color(message.topic);
 
+        let mut lines: Vec<(Color, String)> = Vec::new();
+        if let Some(pending) = self.pending(game) {
+            let page_size = self.page_size();
+            for line in pending.iter().take(page_size) {
+                lines.push((self.options.fore_color(line.message.topic), line.text()));
+            }
+            lines.push((Color::Blue, "--more--".to_string()));
+        } else {
+            for line in self.options.recent(game, self.size.height as usize) {
+                lines.push((self.options.fore_color(line.message.topic), line.text()));
+            }
+        }
+
+        for (fg, mut text) in lines {
             // Pad the string out to the full terminal width so that the back
             // color of the line is correct.
-            let mut text = message.text.clone();
             if self.size.width as usize > text.len() {
                 text.push_str(&String::from(' ').repeat(self.size.width as usize - text.len()));
             }
@@ -34,7 +81,7 @@ impl MessagesView {
         }
 
         let text = " ".repeat(self.size.width as usize);
-        while (v as i32) - self.origin.y <= self.size.height {
+        while v - self.origin.v() < self.size.height as u16 {
             let _ = write!(
                 stdout,
                 "{}{}{}{}",
@@ -46,22 +93,26 @@ impl MessagesView {
             v += 1;
         }
     }
-}
 
-pub fn to_fore_color(topic: Topic) -> Color {
-    use Topic::*;
-    match topic {
-        Error => Color::Red,
-        Normal => Color::Black,
-        Failed => Color::Red,
-        Important => Color::Blue,
-        NpcIsDamaged => Color::LightSkyBlue,
-        NpcIsNotDamaged => Color::Black,
-        NPCSpeaks => Color::Coral,
-        PlayerDidDamage => Color::Goldenrod,
-        PlayerDidNoDamage => Color::Khaki,
-        PlayerIsDamaged => Color::Crimson,
-        PlayerIsNotDamaged => Color::Pink,
-        Warning => Color::Orange,
+    // The last line is used for the --more-- prompt.
+    fn page_size(&self) -> usize {
+        (self.size.height as usize).saturating_sub(1).max(1)
+    }
+
+    // Returns the unseen lines starting at the current page if they won't all fit.
+    fn pending<'a>(&self, game: &'a Game) -> Option<Vec<Collapsed<'a>>> {
+        if !self.options.more {
+            return None;
+        }
+        let unseen = self.options.unseen(game, self.seen);
+        if unseen.len() > MAX_MORE_LINES {
+            return None;
+        }
+        let start = self.page * self.page_size();
+        if unseen.len().saturating_sub(start) > self.size.height as usize {
+            Some(unseen.into_iter().skip(start).collect())
+        } else {
+            None
+        }
     }
 }
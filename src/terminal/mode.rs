@@ -1,11 +1,28 @@
-use one_thousand_deaths::{Game, Point};
+use super::indicators::Indicator;
+use fnv::FnvHashMap;
+use one_thousand_deaths::{Color, Conduct, Game, Point};
 use std::io::Write;
 use termion::event::Key;
 
 pub struct RenderContext<'a> {
     pub stdout: &'a mut Box<dyn Write>,
     pub game: &'a mut Game,
-    pub examined: Option<Point>, // ExamineWindow will set this
+    pub examined: Option<Point>,           // ExamineWindow will set this
+    pub overlay: FnvHashMap<Point, Color>, // background colors to use instead of the normal colors
+    pub indicators: Vec<Indicator>,        // transient text drawn over the map, e.g. damage
+}
+
+/// Used by the title screen to start the real game.
+pub enum Launch {
+    NewGame {
+        seed: Option<u64>, // None means use a random seed
+        challenges: Vec<Conduct>,
+        hints: bool,
+    },
+    Load {
+        path: String,
+        hints: bool,
+    },
 }
 
 pub enum InputAction {
@@ -13,6 +30,8 @@ pub enum InputAction {
     Quit,
     Push(Box<dyn Mode>),
     Pop,
+    Replace(Box<dyn Mode>), // pop the current mode and push a new mode
+    Launch(Launch),         // replace the current game and all the modes
     NotHandled,
 }
 
@@ -34,4 +53,10 @@ pub trait Mode {
     fn replaying(&self) -> bool {
         false
     }
+
+    /// Used for logging and by tests to check which modes are on the stack.
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
 }
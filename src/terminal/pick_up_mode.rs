@@ -0,0 +1,141 @@
+use super::camera::ScreenPoint;
+use super::help::{format_help, validate_help};
+use super::inventory_view::{self, InventoryView};
+use super::mode::{InputAction, Mode, RenderContext};
+use super::text_mode::TextMode;
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Action, Game, InvItem, ItemKind, Message, Oid, Size, Topic};
+use termion::event::Key;
+
+type KeyHandler = fn(&mut PickUpMode, &mut Game) -> InputAction;
+type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
+
+/// Used to pick up some or all of the items in the player's cell. This uses the same view
+/// (and marking keys) as the inventory screen.
+pub struct PickUpMode {
+    commands: CommandTable,
+    view: InventoryView,
+    selected: usize, // index into items()
+    marked: Vec<Oid>,
+}
+
+impl PickUpMode {
+    pub fn create(game: &mut Game, size: Size) -> Option<Box<dyn Mode>> {
+        if game.items_here().is_empty() {
+            game.add_mesg(Message::new(Topic::Failed, &catalog_text("pick_up.nothing", &[])));
+            return None;
+        }
+
+        let mut commands: CommandTable = FnvHashMap::default();
+        commands.insert(Key::Up, Box::new(|s, game| s.do_select(game, -1)));
+        commands.insert(Key::Down, Box::new(|s, game| s.do_select(game, 1)));
+        commands.insert(Key::Char('8'), Box::new(|s, game| s.do_select(game, -1)));
+        commands.insert(Key::Char('2'), Box::new(|s, game| s.do_select(game, 1)));
+        commands.insert(Key::Char(' '), Box::new(|s, game| s.do_mark(game)));
+        commands.insert(Key::Char('a'), Box::new(|s, game| s.do_mark_all(game)));
+        commands.insert(Key::Char('\n'), Box::new(|s, game| s.do_pick_up(game)));
+        commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
+        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
+        commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+
+        let origin = ScreenPoint::new(1, 1);
+        Some(Box::new(PickUpMode {
+            commands,
+            view: InventoryView { origin, size },
+            selected: 0,
+            marked: Vec::new(),
+        }))
+    }
+}
+
+impl Mode for PickUpMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        let items = inventory_view::mark_items(items(context.game), &self.marked);
+        let desc = context.game.describe_item(items[self.selected].oid);
+        let status = self.status(context.game);
+        self.view
+            .render(&items, Some(self.selected), &status, context.stdout, desc);
+        true
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        None
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        match self.commands.get(&key).cloned() {
+            Some(handler) => handler(self, game),
+            None => InputAction::NotHandled,
+        }
+    }
+}
+
+impl PickUpMode {
+    fn status(&self, game: &Game) -> String {
+        let (used, max) = game.inventory_space();
+        let free = max - used;
+        let mut status = format!("{used} of {max} inventory slots used");
+        if !self.marked.is_empty() {
+            status += &format!(", {} marked", self.marked.len());
+            if self.marked.len() > free {
+                status += &format!(" (only {free} will fit)");
+            }
+        }
+        status
+    }
+
+    fn do_help(&mut self, _game: &mut Game) -> InputAction {
+        let help = catalog_text("help.pick_up", &[]);
+        validate_help("pick up", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_mark(&mut self, game: &mut Game) -> InputAction {
+        let oid = items(game)[self.selected].oid;
+        if let Some(i) = self.marked.iter().position(|o| *o == oid) {
+            self.marked.remove(i);
+        } else {
+            self.marked.push(oid);
+        }
+        InputAction::UpdatedGame
+    }
+
+    fn do_mark_all(&mut self, game: &mut Game) -> InputAction {
+        self.marked = items(game).iter().map(|item| item.oid).collect();
+        InputAction::UpdatedGame
+    }
+
+    // Items that don't fit are left on the ground (and the backend says why).
+    fn do_pick_up(&mut self, game: &mut Game) -> InputAction {
+        let oids = if self.marked.is_empty() {
+            vec![items(game)[self.selected].oid]
+        } else {
+            std::mem::take(&mut self.marked)
+        };
+        game.player_acted(Action::PickUpMany(oids));
+        InputAction::Pop
+    }
+
+    fn do_pop(&mut self, _game: &mut Game) -> InputAction {
+        InputAction::Pop
+    }
+
+    fn do_select(&mut self, game: &mut Game, delta: i32) -> InputAction {
+        let n = items(game).len() as i32;
+        self.selected = (self.selected as i32 + delta).rem_euclid(n) as usize;
+        InputAction::UpdatedGame
+    }
+}
+
+// Sorted so that the selection moves in the same order as the view draws the items.
+fn items(game: &Game) -> Vec<InvItem> {
+    let mut items = game.items_here();
+    items.sort_by_key(|item| match item.kind {
+        ItemKind::TwoHandWeapon | ItemKind::OneHandWeapon => 0,
+        ItemKind::Armor => 1,
+        ItemKind::Other => 2,
+    });
+    items
+}
@@ -0,0 +1,182 @@
+use fnv::FnvHashMap;
+use one_thousand_deaths::data_path;
+use one_thousand_deaths::{Conduct, Game, MonsterNotes, State};
+use std::fs;
+
+const PROFILE_PATH: &str = "profile.txt";
+
+/// Statistics and titles that carry over from one game to the next. These are kept
+/// separately from saved games (and games where wizard mode was used don't count). The
+/// file has lines like:
+///    games 12
+///    wins 1
+///    deaths 11
+///    title the Persistent
+///    monster 7 20 Guard
+/// where the monster lines are the max damage and blocked percent the player has noticed
+/// for that kind of character.
+pub struct Profile {
+    pub games: u32,
+    pub wins: u32,
+    pub deaths: u32,
+    pub titles: Vec<String>,
+    pub monsters: FnvHashMap<String, MonsterNotes>,
+
+    /// False if --no-monster-memory was used (the notes are still saved but aren't shown).
+    pub remember_monsters: bool,
+}
+
+impl Profile {
+    pub fn load() -> Profile {
+        let mut profile = Profile {
+            games: 0,
+            wins: 0,
+            deaths: 0,
+            titles: Vec::new(),
+            monsters: FnvHashMap::default(),
+            remember_monsters: true,
+        };
+        if let Ok(text) = fs::read_to_string(data_path(PROFILE_PATH)) {
+            for (i, line) in text.lines().enumerate() {
+                if let Err(err) = profile.parse_line(line) {
+                    warn!("{PROFILE_PATH}:{} {err}", i + 1);
+                }
+            }
+        }
+        profile
+    }
+
+    pub fn started_game(&mut self) {
+        self.games += 1;
+        self.save();
+    }
+
+    /// Lets the game's discoveries include what was noticed in earlier games.
+    pub fn share_monsters(&self, game: &mut Game) {
+        if self.remember_monsters {
+            let notes = self
+                .monsters
+                .iter()
+                .map(|(name, notes)| (name.clone(), *notes))
+                .collect();
+            game.remember_monsters(notes);
+        }
+    }
+
+    /// Called when a game is won or lost. Returns any newly earned titles.
+    pub fn finished_game(&mut self, game: &Game) -> Vec<String> {
+        if game.used_wizard_mode() {
+            return Vec::new();
+        }
+
+        let mut earned = Vec::new();
+        match game.state() {
+            State::WonGame => {
+                self.wins += 1;
+                earned.push("Sword Breaker");
+                for (conduct, kept) in game.conducts() {
+                    if kept {
+                        earned.push(match conduct {
+                            Conduct::NoArmor => "the Unarmored",
+                            Conduct::NoNeutralKills => "the Merciful",
+                            Conduct::Pacifist => "the Gentle",
+                            Conduct::FogOfMemory => continue, // not listed as a conduct
+                        });
+                    }
+                }
+                if game.challenges().contains(&Conduct::FogOfMemory) {
+                    earned.push("the Unforgetting");
+                }
+            }
+            State::LostGame => {
+                self.deaths += 1;
+                for (count, title) in [
+                    (10, "the Persistent"),
+                    (100, "the Undying"),
+                    (1000, "of a Thousand Deaths"),
+                ] {
+                    if self.deaths >= count {
+                        earned.push(title);
+                    }
+                }
+            }
+            _ => (),
+        }
+        for (name, notes) in game.monster_notes() {
+            self.monsters.entry(name).or_default().merge(&notes);
+        }
+
+        let earned: Vec<String> = earned
+            .into_iter()
+            .filter(|title| !self.titles.iter().any(|t| t == title))
+            .map(|title| title.to_string())
+            .collect();
+        self.titles.extend(earned.iter().cloned());
+        self.save();
+        earned
+    }
+
+    /// Returns a short summary suitable for the title screen.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{} games, {} wins, {} deaths",
+            self.games, self.wins, self.deaths
+        )];
+        if !self.titles.is_empty() {
+            lines.push(format!("titles: {}", self.titles.join(", ")));
+        }
+        lines
+    }
+
+    fn save(&self) {
+        let mut text = format!("games {}\nwins {}\ndeaths {}\n", self.games, self.wins, self.deaths);
+        for title in self.titles.iter() {
+            text += &format!("title {title}\n");
+        }
+        let mut monsters: Vec<_> = self.monsters.iter().collect();
+        monsters.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, notes) in monsters {
+            text += &format!("monster {} {} {name}\n", notes.max_damage, notes.blocked);
+        }
+        if let Err(err) = fs::write(data_path(PROFILE_PATH), text) {
+            warn!("couldn't save {PROFILE_PATH}: {err}");
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let count = || rest.parse::<u32>().map_err(|_| format!("bad count: '{rest}'"));
+        match command {
+            "games" => self.games = count()?,
+            "wins" => self.wins = count()?,
+            "deaths" => self.deaths = count()?,
+            "title" if !rest.is_empty() => self.titles.push(rest.to_string()),
+            "monster" => {
+                let parts: Vec<&str> = rest.splitn(3, ' ').collect();
+                let stat = |i: usize| {
+                    parts
+                        .get(i)
+                        .and_then(|s| s.parse::<i32>().ok())
+                        .ok_or_else(|| format!("bad monster: '{rest}'"))
+                };
+                let notes = MonsterNotes {
+                    max_damage: stat(0)?,
+                    blocked: stat(1)?,
+                };
+                match parts.get(2) {
+                    Some(name) if !name.trim().is_empty() => {
+                        self.monsters.insert(name.trim().to_string(), notes);
+                    }
+                    _ => return Err(format!("bad monster: '{rest}'")),
+                }
+            }
+            _ => return Err(format!("couldn't parse '{line}'")),
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
 use super::help::{format_help, validate_help};
 use super::mode::{InputAction, Mode, RenderContext};
 use super::text_mode::TextMode;
+use super::text_view::TextRun;
 use fnv::FnvHashMap;
-use one_thousand_deaths::{Action, Game};
+use one_thousand_deaths::{catalog_text, Action, Color, Game};
 use std::time::Instant;
 use termion::event::Key;
 
@@ -21,6 +22,7 @@ pub struct ReplayMode {
     timeout: i32, // ms
     commands: CommandTable,
     start_time: Instant,
+    reported: bool, // true if the user has been told that the replay diverged
 }
 
 const REPLAY_DELTA: i32 = 20;
@@ -42,6 +44,7 @@ impl ReplayMode {
             timeout: 10,
             commands,
             start_time: Instant::now(),
+            reported: false,
         })
     }
 }
@@ -71,7 +74,7 @@ impl Mode for ReplayMode {
         } else if key == Key::Null {
             let action = self.replay.remove(0);
             game.replay_action(action);
-            InputAction::UpdatedGame
+            self.check_divergence(game)
         } else {
             match self.commands.get(&key).cloned() {
                 Some(handler) => handler(self, game),
@@ -82,19 +85,33 @@ impl Mode for ReplayMode {
 }
 
 impl ReplayMode {
+    // If the replay no longer matches the saved game then we stop replaying so that the
+    // user can see what went wrong.
+    fn check_divergence(&mut self, game: &mut Game) -> InputAction {
+        if !self.reported {
+            if let Some(divergence) = game.divergence() {
+                self.reported = true;
+                self.replaying = Replaying::Blocking;
+
+                let mut lines = Vec::new();
+                for text in divergence.report.iter() {
+                    lines.push(vec![TextRun::Color(Color::Red), TextRun::Text(text.clone())]);
+                }
+                lines.push(vec![TextRun::Text("".to_string())]);
+                lines.push(vec![TextRun::Text(
+                    "Replay has been paused (see 1k-deaths.log for details).".to_string(),
+                )]);
+                return InputAction::Push(TextMode::at_top().create(lines));
+            }
+        }
+        InputAction::UpdatedGame
+    }
+
     fn do_help(&mut self, _game: &mut Game) -> InputAction {
-        let help = r#"Replaying a saved game.
-
-[[space]] toggles replay on and off.
-[[s]] single step replay.
-[[+]] speed up replay.
-[[-]] slow down replay.
-[[?]] show this help.
-[[q]] save and quit.
-[[escape]] exits replay mode."#;
-        validate_help("replay", help, self.commands.keys());
-
-        let lines = format_help(help, self.commands.keys());
+        let help = catalog_text("help.replay", &[]);
+        validate_help("replay", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
         InputAction::Push(TextMode::at_top().create(lines))
     }
 
@@ -109,9 +126,12 @@ impl ReplayMode {
         // this is tricky to do because we'd need to somehow truncate the
         // saved file. The way to do this is probably to write the replayed
         // events to a temp file and swap the two files if the user aborts.
-        let actions = std::mem::take(&mut self.replay);
-        for action in actions.into_iter() {
+        while !self.replay.is_empty() {
+            let action = self.replay.remove(0);
             game.replay_action(action);
+            if !self.reported && game.divergence().is_some() {
+                return self.check_divergence(game);
+            }
         }
         let elapsed = self.start_time.elapsed();
         info!("done replaying after {elapsed:.1?} secs");
@@ -137,9 +157,11 @@ impl ReplayMode {
 
     fn do_step(&mut self, game: &mut Game) -> InputAction {
         self.replaying = Replaying::SingleStep;
-        let action = self.replay.remove(0);
-        game.replay_action(action);
-        InputAction::UpdatedGame
+        if !self.replay.is_empty() {
+            let action = self.replay.remove(0);
+            game.replay_action(action);
+        }
+        self.check_divergence(game)
     }
 
     fn do_toggle(&mut self, _game: &mut Game) -> InputAction {
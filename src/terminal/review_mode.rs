@@ -0,0 +1,79 @@
+use super::main_mode::MainMode;
+use super::mode::{InputAction, Mode, RenderContext};
+use super::replay_mode::ReplayMode;
+use fnv::FnvHashMap;
+use one_thousand_deaths::{Action, Game};
+use std::cell::RefCell;
+use termion::event::Key;
+
+/// Used after the player dies to replay their last few turns so that they can see what
+/// killed them. This uses its own copy of the game so the real game is unaffected.
+pub struct ReviewMode {
+    game: RefCell<Game>,   // RefCell because render needs a mutable game
+    view: Box<dyn Mode>,   // MainMode, only used for rendering
+    replay: Box<dyn Mode>, // ReplayMode
+    finished: bool,        // true once all the actions have been replayed
+}
+
+impl ReviewMode {
+    pub fn create(width: i32, height: i32, game: Game, replay: Vec<Action>) -> Box<dyn Mode> {
+        Box::new(ReviewMode {
+            game: RefCell::new(game),
+            view: MainMode::create(width, height),
+            replay: ReplayMode::create(replay),
+            finished: false,
+        })
+    }
+
+    // Objects act after the last player action so we need to give them a chance to
+    // deliver the fatal blow.
+    fn finish(&mut self) {
+        self.finished = true;
+        let game = self.game.get_mut();
+        while !game.players_turn() {
+            game.advance_time(true);
+        }
+    }
+}
+
+impl Mode for ReviewMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        let mut game = self.game.borrow_mut();
+        let mut inner = RenderContext {
+            stdout: context.stdout,
+            game: &mut game,
+            examined: None,
+            overlay: FnvHashMap::default(),
+            indicators: Vec::new(),
+        };
+        self.view.render(&mut inner)
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        if self.finished {
+            None
+        } else {
+            self.replay.input_timeout_ms()
+        }
+    }
+
+    fn handle_input(&mut self, _game: &mut Game, key: Key) -> InputAction {
+        if self.finished {
+            return match key {
+                Key::Esc | Key::Char('q') | Key::Char('\n') => InputAction::Pop,
+                _ => InputAction::NotHandled,
+            };
+        }
+        if key == Key::Char('q') {
+            return InputAction::Pop;
+        }
+        match self.replay.handle_input(self.game.get_mut(), key) {
+            InputAction::Pop => {
+                self.finish();
+                InputAction::UpdatedGame
+            }
+            InputAction::Quit => InputAction::Pop,
+            action => action,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use super::mode::{InputAction, Mode, RenderContext};
+use one_thousand_deaths::{Game, Run};
+use termion::event::Key;
+
+const RUN_DELAY: i32 = 30; // ms per step so that the player can see what's happening
+
+/// Moves the player in one direction until the backend says to stop (or the player
+/// presses a key).
+pub struct RunMode {
+    run: Run,
+}
+
+impl RunMode {
+    pub fn create(game: &Game, dx: i32, dy: i32) -> Box<dyn Mode> {
+        Box::new(RunMode {
+            run: game.start_run(dx, dy),
+        })
+    }
+}
+
+impl Mode for RunMode {
+    fn render(&self, _context: &mut RenderContext) -> bool {
+        false
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        Some(RUN_DELAY)
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        if key != Key::Null {
+            return InputAction::Pop;
+        }
+        match game.run_step(&mut self.run) {
+            Some(action) => {
+                game.player_acted(action);
+                InputAction::UpdatedGame
+            }
+            None => InputAction::Pop,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use super::help::{format_help, validate_help};
+use super::mode::{InputAction, Mode, RenderContext};
+use super::text_mode::TextMode;
+use super::text_view::{Line, TextRun, TextView};
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Color, Game, ScheduledObj};
+use termion::event::Key;
+
+type KeyHandler = fn(&mut SchedulerMode, &mut Game) -> InputAction;
+type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SortOrder {
+    NextAct,
+    Name,
+    Oid,
+}
+
+/// Wizard mode screen that lists the objects that are scheduled along with when they will
+/// next act and what happened the last few times they were given a chance to act.
+pub struct SchedulerMode {
+    text: TextView,
+    sort: SortOrder,
+    commands: CommandTable,
+}
+
+impl SchedulerMode {
+    pub fn create(game: &Game) -> Box<dyn Mode> {
+        let mut commands: CommandTable = FnvHashMap::default();
+        commands.insert(Key::Char(' '), Box::new(|s, game| s.do_page(game, 1)));
+        commands.insert(Key::Char('b'), Box::new(|s, game| s.do_page(game, -1)));
+        commands.insert(Key::Down, Box::new(|s, game| s.do_scroll(game, 1)));
+        commands.insert(Key::Up, Box::new(|s, game| s.do_scroll(game, -1)));
+        commands.insert(Key::Char('s'), Box::new(|s, game| s.do_sort(game)));
+        commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
+        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
+        commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+
+        let sort = SortOrder::NextAct;
+        Box::new(SchedulerMode {
+            text: SchedulerMode::view(game, sort),
+            sort,
+            commands,
+        })
+    }
+
+    fn view(game: &Game, sort: SortOrder) -> TextView {
+        let mut objs = game.scheduled();
+        match sort {
+            SortOrder::NextAct => (), // scheduled returns them in this order
+            SortOrder::Name => objs.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortOrder::Oid => objs.sort_by_key(|obj| obj.oid),
+        }
+
+        let mut lines = vec![vec![
+            TextRun::Color(Color::Yellow),
+            TextRun::Text(format!(
+                "{} scheduled objects at {} sorted by {sort:?}",
+                objs.len(),
+                game.now()
+            )),
+        ]];
+        for obj in objs.iter() {
+            lines.push(SchedulerMode::line(obj));
+        }
+
+        let num_lines = lines.len() as i32;
+        let mut view = TextView::new(lines, Color::Black);
+        view.scroll(-num_lines);
+        view
+    }
+
+    fn line(obj: &ScheduledObj) -> Line {
+        let recent: Vec<String> = obj.recent.iter().map(|(_, outcome)| outcome.to_string()).collect();
+        vec![
+            TextRun::Color(Color::White),
+            TextRun::Text(format!("{} {} ", obj.oid, obj.name)),
+            TextRun::Color(Color::LightSkyBlue),
+            TextRun::Text(format!("next at {} ({} units) ", obj.next_act, obj.units)),
+            TextRun::Color(Color::DarkGray),
+            TextRun::Text(recent.join(", ")),
+        ]
+    }
+}
+
+impl Mode for SchedulerMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        self.text.render(context.stdout);
+        true
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        None
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        match self.commands.get(&key).cloned() {
+            Some(handler) => handler(self, game),
+            None => InputAction::NotHandled,
+        }
+    }
+}
+
+impl SchedulerMode {
+    fn do_help(&mut self, _game: &mut Game) -> InputAction {
+        let help = catalog_text("help.scheduler", &[]);
+        validate_help("scheduler", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_page(&mut self, _game: &mut Game, sign: i32) -> InputAction {
+        self.text.scroll(sign * self.text.size().height);
+        InputAction::UpdatedGame
+    }
+
+    fn do_pop(&mut self, _game: &mut Game) -> InputAction {
+        InputAction::Pop
+    }
+
+    fn do_scroll(&mut self, _game: &mut Game, delta: i32) -> InputAction {
+        self.text.scroll(delta);
+        InputAction::UpdatedGame
+    }
+
+    fn do_sort(&mut self, game: &mut Game) -> InputAction {
+        self.sort = match self.sort {
+            SortOrder::NextAct => SortOrder::Name,
+            SortOrder::Name => SortOrder::Oid,
+            SortOrder::Oid => SortOrder::NextAct,
+        };
+        self.text = SchedulerMode::view(game, self.sort);
+        InputAction::UpdatedGame
+    }
+}
@@ -0,0 +1,356 @@
+//! Modes and views draw into a virtual screen instead of directly to stdout. Once a frame
+//! is finished the screen is compared with what was last presented and only the cells that
+//! changed are written out. This avoids flicker (even for modes that clear the screen each
+//! frame) and allows tests to check exactly what was rendered.
+//!
+//! The screen understands the subset of termion escape sequences that we use: cursor::Goto,
+//! color::Fg and color::Bg with AnsiValue colors, style::Invert, style::Reset, clear::All,
+//! and clear::CurrentLine. Anything else is ignored. cursor::Save is used by views to mark
+//! the selected item (screen readers follow the cursor).
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use termion::color::{self, AnsiValue};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct Style {
+    fg: Option<u8>, // None means the terminal's default color
+    bg: Option<u8>,
+    invert: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Cell {
+    text: String, // usually one char but may include trailing variation selectors
+    style: Style,
+}
+
+impl Cell {
+    fn blank(style: Style) -> Cell {
+        Cell {
+            text: " ".to_string(),
+            style,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Buffer {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Buffer {
+    fn new(width: usize, height: usize) -> Buffer {
+        Buffer {
+            width,
+            height,
+            cells: vec![Cell::blank(Style::default()); width * height],
+        }
+    }
+}
+
+/// The back buffer that modes render into. Rc'ed so that it can be used as a
+/// Box<dyn Write> (which is what the views expect).
+#[derive(Clone)]
+pub struct Screen {
+    back: Rc<RefCell<Back>>,
+}
+
+struct Back {
+    buffer: Buffer,
+    x: usize,
+    y: usize,
+    style: Style,
+    focus: Option<(usize, usize)>, // set with cursor::Save
+    pending: Vec<u8>,              // partial escape sequence or UTF-8 char
+}
+
+impl Screen {
+    pub fn new(width: i32, height: i32) -> Screen {
+        let back = Back {
+            buffer: Buffer::new(width.max(1) as usize, height.max(1) as usize),
+            x: 0,
+            y: 0,
+            style: Style::default(),
+            focus: None,
+            pending: Vec::new(),
+        };
+        Screen {
+            back: Rc::new(RefCell::new(back)),
+        }
+    }
+
+    /// Returns a Write that renders into the screen.
+    pub fn writer(&self) -> Box<dyn Write> {
+        Box::new(self.clone())
+    }
+
+    /// Called before modes start rendering a new frame.
+    pub fn begin_frame(&self) {
+        self.back.borrow_mut().focus = None;
+    }
+
+    pub fn resize(&self, width: i32, height: i32) {
+        let mut back = self.back.borrow_mut();
+        back.buffer = Buffer::new(width.max(1) as usize, height.max(1) as usize);
+        back.x = 0;
+        back.y = 0;
+    }
+
+    /// Returns the text for row y (0-based) with trailing spaces removed.
+    #[cfg(test)]
+    pub fn row_text(&self, y: usize) -> String {
+        let back = self.back.borrow();
+        let start = y * back.buffer.width;
+        let row = &back.buffer.cells[start..start + back.buffer.width];
+        let text: String = row.iter().map(|cell| cell.text.as_str()).collect();
+        text.trim_end().to_string()
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut back = self.back.borrow_mut();
+        back.pending.extend_from_slice(buf);
+        back.process();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Back {
+    fn process(&mut self) {
+        let bytes = std::mem::take(&mut self.pending);
+        let mut i = 0;
+        while i < bytes.len() {
+            let consumed = if bytes[i] == 0x1b {
+                self.escape(&bytes[i..])
+            } else {
+                self.text(&bytes[i..])
+            };
+            match consumed {
+                Some(n) => i += n,
+                None => {
+                    // Need more bytes to finish this escape sequence or char.
+                    self.pending = bytes[i..].to_vec();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn escape(&mut self, bytes: &[u8]) -> Option<usize> {
+        if bytes.len() < 2 {
+            return None;
+        }
+        if bytes[1] != b'[' {
+            return Some(2); // not a CSI sequence so ignore it
+        }
+        let end = bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b))? + 2;
+        let params: Vec<Option<usize>> = std::str::from_utf8(&bytes[2..end])
+            .unwrap_or("")
+            .split(';')
+            .map(|p| p.parse().ok())
+            .collect();
+        let param = |i: usize, default: usize| params.get(i).copied().flatten().unwrap_or(default);
+        match bytes[end] {
+            b'H' => {
+                self.y = param(0, 1).saturating_sub(1);
+                self.x = param(1, 1).saturating_sub(1);
+            }
+            b'J' if param(0, 0) == 2 => {
+                let blank = Cell::blank(self.style);
+                self.buffer.cells.iter_mut().for_each(|cell| *cell = blank.clone());
+            }
+            b'K' if param(0, 0) == 2 && self.y < self.buffer.height => {
+                let blank = Cell::blank(self.style);
+                let start = self.y * self.buffer.width;
+                let row = &mut self.buffer.cells[start..start + self.buffer.width];
+                row.iter_mut().for_each(|cell| *cell = blank.clone());
+            }
+            b'm' => self.sgr(&params),
+            b's' => self.focus = Some((self.x, self.y)),
+            _ => (),
+        }
+        Some(end + 1)
+    }
+
+    fn sgr(&mut self, params: &[Option<usize>]) {
+        let mut i = 0;
+        while i < params.len() {
+            match params[i].unwrap_or(0) {
+                0 => self.style = Style::default(),
+                7 => self.style.invert = true,
+                38 if params.get(i + 1) == Some(&Some(5)) => {
+                    self.style.fg = params.get(i + 2).copied().flatten().map(|c| c as u8);
+                    i += 2;
+                }
+                48 if params.get(i + 1) == Some(&Some(5)) => {
+                    self.style.bg = params.get(i + 2).copied().flatten().map(|c| c as u8);
+                    i += 2;
+                }
+                _ => (),
+            }
+            i += 1;
+        }
+    }
+
+    fn text(&mut self, bytes: &[u8]) -> Option<usize> {
+        let len = match bytes[0] {
+            b if b < 0x80 => 1,
+            b if b >= 0xf0 => 4,
+            b if b >= 0xe0 => 3,
+            _ => 2,
+        };
+        if bytes.len() < len {
+            return None;
+        }
+        let s = std::str::from_utf8(&bytes[..len]).unwrap_or("?");
+        match s {
+            "\r" => self.x = 0,
+            "\n" => self.y += 1,
+            "\u{FE0E}" | "\u{FE0F}" | "\u{200D}" => {
+                // Zero width so these modify the previous char.
+                if self.x > 0 {
+                    if let Some(index) = self.index(self.x - 1, self.y) {
+                        self.buffer.cells[index].text.push_str(s);
+                    }
+                }
+            }
+            s if s.chars().all(|c| c.is_control()) => (),
+            s => {
+                if self.x >= self.buffer.width {
+                    // Like a terminal we wrap onto the next line.
+                    self.x = 0;
+                    self.y += 1;
+                }
+                if let Some(index) = self.index(self.x, self.y) {
+                    self.buffer.cells[index] = Cell {
+                        text: s.to_string(),
+                        style: self.style,
+                    };
+                }
+                self.x += 1;
+            }
+        }
+        Some(len)
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.buffer.width && y < self.buffer.height {
+            Some(y * self.buffer.width + x)
+        } else {
+            None
+        }
+    }
+}
+
+impl Back {
+    /// Where the terminal cursor should go when using a screen reader: the selected item
+    /// if a view marked one, else the first inverted cell (e.g. the examined cell), and
+    /// otherwise the start of the last non-blank row (usually the newest message).
+    fn focus(&self) -> (usize, usize) {
+        if let Some(focus) = self.focus {
+            return focus;
+        }
+        let buffer = &self.buffer;
+        if let Some(index) = buffer.cells.iter().position(|cell| cell.style.invert) {
+            return (index % buffer.width, index / buffer.width);
+        }
+        let y = (0..buffer.height)
+            .rev()
+            .find(|y| {
+                buffer.cells[y * buffer.width..(y + 1) * buffer.width]
+                    .iter()
+                    .any(|c| c.text != " ")
+            })
+            .unwrap_or(0);
+        (0, y)
+    }
+}
+
+/// Writes whatever changed in a Screen since the last present to the real stdout.
+pub struct Presenter {
+    front: Option<Buffer>,         // None if we don't know what's on the terminal
+    focus: Option<(usize, usize)>, // set when the cursor is being moved for screen readers
+}
+
+impl Presenter {
+    pub fn new() -> Presenter {
+        Presenter {
+            front: None,
+            focus: None,
+        }
+    }
+
+    pub fn present(&mut self, screen: &Screen, stdout: &mut Box<dyn Write>) {
+        let screen = screen.back.borrow();
+        let back = &screen.buffer;
+        let front = match self.front.take() {
+            Some(front) if front.width == back.width && front.height == back.height => Some(front),
+            _ => None,
+        };
+
+        let mut out = String::new();
+        let mut style: Option<Style> = None; // None if we don't know the terminal's style
+        let mut cursor: Option<(usize, usize)> = None;
+        for y in 0..back.height {
+            for x in 0..back.width {
+                let cell = &back.cells[y * back.width + x];
+                if front.as_ref().is_some_and(|f| f.cells[y * back.width + x] == *cell) {
+                    continue;
+                }
+                if cursor != Some((x, y)) {
+                    out += &termion::cursor::Goto(x as u16 + 1, y as u16 + 1).to_string();
+                }
+                if style != Some(cell.style) {
+                    out += &style_codes(cell.style, style);
+                    style = Some(cell.style);
+                }
+                out += &cell.text;
+                cursor = Some((x + 1, y));
+            }
+        }
+        if super::screen_reader::enabled() {
+            // Screen readers follow the cursor so leave it somewhere predictable.
+            let focus = screen.focus();
+            if !out.is_empty() || self.focus != Some(focus) {
+                out += &termion::cursor::Goto(focus.0 as u16 + 1, focus.1 as u16 + 1).to_string();
+                self.focus = Some(focus);
+            }
+        }
+        if !out.is_empty() {
+            let _ = write!(stdout, "{}", out);
+        }
+        self.front = Some(back.clone());
+    }
+}
+
+/// Returns the escape codes needed to switch from old to new.
+fn style_codes(new: Style, old: Option<Style>) -> String {
+    let mut codes = String::new();
+    let old = match old {
+        Some(old) if (old.invert && !new.invert) || (old.fg.is_some() && new.fg.is_none()) => None,
+        Some(old) if old.bg.is_some() && new.bg.is_none() => None,
+        old => old,
+    };
+    let old = old.unwrap_or_else(|| {
+        codes += termion::style::Reset.as_ref();
+        Style::default()
+    });
+    if new.invert && !old.invert {
+        codes += termion::style::Invert.as_ref();
+    }
+    if let Some(fg) = new.fg.filter(|fg| old.fg != Some(*fg)) {
+        codes += &color::Fg(AnsiValue(fg)).to_string();
+    }
+    if let Some(bg) = new.bg.filter(|bg| old.bg != Some(*bg)) {
+        codes += &color::Bg(AnsiValue(bg)).to_string();
+    }
+    codes
+}
@@ -0,0 +1,112 @@
+use one_thousand_deaths::{Game, Message, Point, Symbol, Tile, Time, Topic};
+use std::cell::RefCell;
+
+thread_local!(pub static SCREEN_READER: RefCell<bool> = const { RefCell::new(false) });
+
+/// Returns true if the --screen-reader option was used.
+pub fn enabled() -> bool {
+    SCREEN_READER.with(|s| *s.borrow())
+}
+
+/// Supplements the map with a short textual summary of what the player can see each turn,
+/// e.g. "Guard two cells north; water to the east". This is added as a message so that it
+/// shows up in the messages view (and the message history).
+pub struct ScreenReader {
+    last_turn: Option<Time>,
+}
+
+impl ScreenReader {
+    pub fn new() -> ScreenReader {
+        ScreenReader { last_turn: None }
+    }
+
+    pub fn update(&mut self, game: &mut Game, replaying: bool) {
+        if !enabled() || replaying || !game.players_turn() || self.last_turn == Some(game.now()) {
+            return;
+        }
+        self.last_turn = Some(game.now());
+        let text = summarize(game);
+        game.add_mesg(Message::new(Topic::Normal, &text));
+    }
+}
+
+/// Describes the nearest visible NPCs and notable terrain next to the player.
+pub fn summarize(game: &Game) -> String {
+    let player_loc = game.player_loc();
+    let mut parts: Vec<String> = game
+        .npcs(false)
+        .iter()
+        .take(5)
+        .map(|npc| {
+            let (dx, dy) = (npc.loc.x - player_loc.x, npc.loc.y - player_loc.y);
+            let cells = dx.abs().max(dy.abs());
+            let unit = if cells == 1 { "cell" } else { "cells" };
+            format!("{} {} {unit} {}", npc.name, number(cells), direction(dx, dy))
+        })
+        .collect();
+
+    let mut features: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (dx, dy) in [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)] {
+        let loc = Point::new(player_loc.x + dx, player_loc.y + dy);
+        if let Tile::Visible { symbol, .. } = game.tile(&loc) {
+            if let Some(feature) = feature(symbol) {
+                match features.iter_mut().find(|(f, _)| *f == feature) {
+                    Some((_, dirs)) => dirs.push(direction(dx, dy)),
+                    None => features.push((feature, vec![direction(dx, dy)])),
+                }
+            }
+        }
+    }
+    for (feature, dirs) in features {
+        parts.push(format!("{feature} to the {}", dirs.join(" and ")));
+    }
+
+    if parts.is_empty() {
+        "Nothing nearby.".to_string()
+    } else {
+        let mut text = parts.join("; ");
+        text[..1].make_ascii_uppercase();
+        text + "."
+    }
+}
+
+fn feature(symbol: Symbol) -> Option<&'static str> {
+    match symbol {
+        Symbol::ClosedDoor => Some("door"),
+        Symbol::OpenDoor => Some("open door"),
+        Symbol::DeepLiquid => Some("deep water"),
+        Symbol::ShallowLiquid => Some("water"),
+        Symbol::Rubble => Some("rubble"),
+        Symbol::Sign => Some("sign"),
+        Symbol::Lever => Some("lever"),
+        Symbol::PressurePlate => Some("pressure plate"),
+        _ => None,
+    }
+}
+
+/// Returns one of the eight compass directions (y increases to the south).
+pub fn direction(dx: i32, dy: i32) -> &'static str {
+    let ns = if dy < 0 { "north" } else { "south" };
+    let ew = if dx < 0 { "west" } else { "east" };
+    if dx.abs() > 2 * dy.abs() {
+        ew
+    } else if dy.abs() > 2 * dx.abs() {
+        ns
+    } else {
+        match (dy < 0, dx < 0) {
+            (true, true) => "north-west",
+            (true, false) => "north-east",
+            (false, true) => "south-west",
+            (false, false) => "south-east",
+        }
+    }
+}
+
+fn number(n: i32) -> String {
+    const NAMES: [&str; 11] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    ];
+    NAMES
+        .get(n as usize)
+        .map_or_else(|| n.to_string(), |name| name.to_string())
+}
@@ -0,0 +1,68 @@
+use super::html_export::export_snapshot;
+use one_thousand_deaths::{Game, State, Topic};
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::path::Path;
+
+/// Number of recent messages included in snapshots.
+const NUM_MESSAGES: usize = 20;
+
+thread_local!(pub static SNAPSHOT_DIR: RefCell<Option<String>> = const { RefCell::new(None) });
+
+/// Optionally saves an HTML page with the map and recent messages whenever something
+/// important happens, e.g. Rhulad is killed or the player dies. This is enabled with the
+/// --snapshots command line option. Snapshots are not saved while replaying.
+pub struct Snapshots {
+    dir: Option<String>,
+    seen: usize, // from Game::num_messages
+    state: State,
+}
+
+impl Snapshots {
+    pub fn load(game: &Game) -> Snapshots {
+        Snapshots {
+            dir: SNAPSHOT_DIR.with(|d| d.borrow().clone()),
+            seen: game.num_messages(),
+            state: game.state(),
+        }
+    }
+
+    /// Saves a snapshot if an important message was added since the last update or if
+    /// the game state changed.
+    pub fn update(&mut self, game: &Game, replaying: bool) {
+        let count = game.num_messages().saturating_sub(self.seen);
+        self.seen = game.num_messages();
+        let old_state = std::mem::replace(&mut self.state, game.state());
+        let dir = match self.dir {
+            Some(ref dir) if !replaying => dir,
+            _ => return,
+        };
+
+        let caption = game
+            .recent_messages(count)
+            .find(|m| m.topic == Topic::Important)
+            .map(|m| m.text.clone())
+            .or_else(|| (game.state() != old_state).then(|| format!("{}", game.state())));
+        if let Some(caption) = caption {
+            let messages: Vec<String> = game.recent_messages(NUM_MESSAGES).map(|m| m.text.clone()).collect();
+            let path = snapshot_path(dir);
+            let result = fs::create_dir_all(dir)
+                .and_then(|_| File::create(&path))
+                .and_then(|mut file| export_snapshot(&mut file, game, &caption, &messages));
+            match result {
+                Ok(_) => info!("saved snapshot to {path}"),
+                Err(err) => warn!("couldn't save snapshot to {path}: {err}"),
+            }
+        }
+    }
+}
+
+fn snapshot_path(dir: &str) -> String {
+    for i in 1..10000 {
+        let candidate = format!("{dir}/snapshot-{:0>4}.html", i);
+        if !Path::new(&candidate).is_file() {
+            return candidate;
+        }
+    }
+    format!("{dir}/snapshot-overflow.html")
+}
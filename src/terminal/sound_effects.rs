@@ -0,0 +1,116 @@
+use super::message_options::parse_topic;
+use fnv::FnvHashMap;
+use one_thousand_deaths::data_path;
+use one_thousand_deaths::{Game, Topic};
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const OPTIONS_PATH: &str = "sound-options.txt";
+
+/// What happens when a message with a topic is added.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Effect {
+    Bell,
+    Run(String), // shell command, e.g. "aplay -q ~/sounds/hit.wav"
+}
+
+/// Optional audio feedback for new messages. By default there are no sounds but they
+/// can be enabled using sound-options.txt which has lines like:
+///    # Comment
+///    sound PlayerIsDamaged bell
+///    sound Important run aplay -q ~/sounds/gong.wav
+///    sounds off
+/// Each topic plays at most once per turn and nothing is played while replaying.
+pub struct SoundEffects {
+    effects: FnvHashMap<Topic, Effect>,
+    enabled: bool,
+    heard: usize, // from Game::num_messages
+}
+
+impl SoundEffects {
+    pub fn load() -> SoundEffects {
+        let mut sounds = SoundEffects {
+            effects: FnvHashMap::default(),
+            enabled: true,
+            heard: 0,
+        };
+        if let Ok(text) = fs::read_to_string(data_path(OPTIONS_PATH)) {
+            for (i, line) in text.lines().enumerate() {
+                if let Err(err) = sounds.parse_line(line) {
+                    warn!("{OPTIONS_PATH}:{} {err}", i + 1);
+                }
+            }
+        }
+        sounds
+    }
+
+    /// Plays the effects for messages added since the last update.
+    pub fn update(&mut self, game: &Game, stdout: &mut Box<dyn Write>, replaying: bool) {
+        let count = game.num_messages().saturating_sub(self.heard);
+        self.heard = game.num_messages();
+        if !self.enabled || replaying || count == 0 {
+            return;
+        }
+
+        let mut played = Vec::new();
+        for message in game.recent_messages(count) {
+            if let Some(effect) = self.effects.get(&message.topic) {
+                if !played.contains(&message.topic) {
+                    played.push(message.topic);
+                    play(effect, stdout);
+                }
+            }
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command {
+            "sound" => {
+                let (name, effect) = rest.split_once(' ').unwrap_or((rest, ""));
+                let topic = parse_topic(name).ok_or(format!("bad topic: '{name}'"))?;
+                let effect = parse_effect(effect.trim())?;
+                self.effects.insert(topic, effect);
+            }
+            "sounds" if rest == "on" || rest == "off" => self.enabled = rest == "on",
+            _ => return Err(format!("couldn't parse '{line}'")),
+        }
+        Ok(())
+    }
+}
+
+fn parse_effect(text: &str) -> Result<Effect, String> {
+    let (kind, rest) = text.split_once(' ').unwrap_or((text, ""));
+    match kind {
+        "bell" if rest.is_empty() => Ok(Effect::Bell),
+        "run" if !rest.trim().is_empty() => Ok(Effect::Run(rest.trim().to_string())),
+        _ => Err(format!("bad effect: '{text}' (should be bell or run <command>)")),
+    }
+}
+
+// Commands are run in the background so that slow players don't stall the game.
+fn play(effect: &Effect, stdout: &mut Box<dyn Write>) {
+    match effect {
+        Effect::Bell => {
+            let _ = write!(stdout, "\x07");
+        }
+        Effect::Run(command) => {
+            let result = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            if let Err(err) = result {
+                warn!("couldn't run sound command '{command}': {err}");
+            }
+        }
+    }
+}
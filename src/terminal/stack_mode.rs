@@ -0,0 +1,121 @@
+use super::discoveries_mode::DiscoveriesMode;
+use super::help::{format_help, validate_help};
+use super::mode::{InputAction, Mode, RenderContext};
+use super::text_mode::TextMode;
+use super::text_view::{TextRun, TextView};
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Color, Discovery, Game, Point};
+use termion::event::Key;
+
+type KeyHandler = fn(&mut StackMode, &mut Game) -> InputAction;
+type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
+
+/// Lists each of the objects in a cell, e.g. the pile of stuff left after a battle. The
+/// selected object can be looked up in discoveries.
+pub struct StackMode {
+    entries: Vec<Discovery>,
+    selected: usize,
+    text: TextView,
+    commands: CommandTable,
+}
+
+impl StackMode {
+    pub fn create(game: &Game, loc: Point) -> Box<dyn Mode> {
+        let mut commands: CommandTable = FnvHashMap::default();
+        commands.insert(Key::Char(' '), Box::new(|s, game| s.do_page(game, 1)));
+        commands.insert(Key::Char('b'), Box::new(|s, game| s.do_page(game, -1)));
+        commands.insert(Key::Down, Box::new(|s, game| s.do_select(game, 1)));
+        commands.insert(Key::Up, Box::new(|s, game| s.do_select(game, -1)));
+        commands.insert(Key::Char('\n'), Box::new(|s, game| s.do_discoveries(game)));
+        commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
+        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
+        commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+
+        let entries = game.examine_stack(&loc);
+        Box::new(StackMode {
+            text: StackMode::view(&entries, 0),
+            entries,
+            selected: 0,
+            commands,
+        })
+    }
+
+    fn view(entries: &[Discovery], selected: usize) -> TextView {
+        let header = format!("{} objects", entries.len());
+        let mut lines = vec![vec![TextRun::Color(Color::Yellow), TextRun::Text(header)]];
+        let mut selected_line = 0;
+        for (i, entry) in entries.iter().enumerate() {
+            let marker = if i == selected {
+                selected_line = lines.len();
+                "> "
+            } else {
+                "  "
+            };
+            for (j, mut line) in DiscoveriesMode::lines(entry).into_iter().enumerate() {
+                let prefix = if j == 0 { marker } else { "  " };
+                line.insert(0, TextRun::Color(Color::Yellow));
+                line.insert(1, TextRun::Text(prefix.to_string()));
+                lines.push(line);
+            }
+        }
+
+        // Keep the selection roughly centered.
+        let num_lines = lines.len() as i32;
+        let mut view = TextView::new(lines, Color::Black);
+        view.scroll(-num_lines);
+        view.scroll(selected_line as i32 - view.size().height / 2);
+        view
+    }
+}
+
+impl Mode for StackMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        self.text.render(context.stdout);
+        true
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        None
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        match self.commands.get(&key).cloned() {
+            Some(handler) => handler(self, game),
+            None => InputAction::NotHandled,
+        }
+    }
+}
+
+impl StackMode {
+    fn do_discoveries(&mut self, game: &mut Game) -> InputAction {
+        match self.entries.get(self.selected) {
+            Some(entry) => InputAction::Push(DiscoveriesMode::create_filtered(game, &entry.name)),
+            None => InputAction::UpdatedGame,
+        }
+    }
+
+    fn do_help(&mut self, _game: &mut Game) -> InputAction {
+        let help = catalog_text("help.stack", &[]);
+        validate_help("stack", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_page(&mut self, game: &mut Game, sign: i32) -> InputAction {
+        self.do_select(game, sign * self.text.size().height / 2)
+    }
+
+    fn do_pop(&mut self, _game: &mut Game) -> InputAction {
+        InputAction::Pop
+    }
+
+    fn do_select(&mut self, _game: &mut Game, delta: i32) -> InputAction {
+        if !self.entries.is_empty() {
+            let max = self.entries.len() as i32 - 1;
+            self.selected = (self.selected as i32 + delta).clamp(0, max) as usize;
+            self.text = StackMode::view(&self.entries, self.selected);
+        }
+        InputAction::UpdatedGame
+    }
+}
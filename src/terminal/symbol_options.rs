@@ -0,0 +1,167 @@
+use super::map_view::default_glyph;
+use fnv::FnvHashMap;
+use one_thousand_deaths::data_path;
+use one_thousand_deaths::Symbol;
+use std::cell::RefCell;
+use std::fs;
+
+const OPTIONS_PATH: &str = "symbol-options.txt";
+
+// Symbols that can be overridden (NPC letters are picked by the backend so they can't
+// be changed).
+const SYMBOLS: [(&str, Symbol); 22] = [
+    ("Armor", Symbol::Armor),
+    ("Bell", Symbol::Bell),
+    ("ClosedDoor", Symbol::ClosedDoor),
+    ("DeepLiquid", Symbol::DeepLiquid),
+    ("Dirt", Symbol::Dirt),
+    ("Key", Symbol::Key),
+    ("Lever", Symbol::Lever),
+    ("Light", Symbol::Light),
+    ("OpenDoor", Symbol::OpenDoor),
+    ("PickAxe", Symbol::PickAxe),
+    ("Player", Symbol::Player),
+    ("PressurePlate", Symbol::PressurePlate),
+    ("Rock", Symbol::Rock),
+    ("Rubble", Symbol::Rubble),
+    ("ShallowLiquid", Symbol::ShallowLiquid),
+    ("Sign", Symbol::Sign),
+    ("Spear", Symbol::Spear),
+    ("StrongSword", Symbol::StrongSword),
+    ("Tree", Symbol::Tree),
+    ("Unseen", Symbol::Unseen),
+    ("Wall", Symbol::Wall),
+    ("WeakSword", Symbol::WeakSword),
+];
+
+thread_local!(static GLYPHS: RefCell<FnvHashMap<Symbol, String>> = RefCell::new(FnvHashMap::default()));
+thread_local!(static SHADING: RefCell<bool> = const { RefCell::new(true) });
+thread_local!(static INDICATORS: RefCell<bool> = const { RefCell::new(true) });
+thread_local!(static HEALTH: RefCell<bool> = const { RefCell::new(true) });
+
+enum Setting {
+    Glyph(Symbol, String),
+    Shading(bool),
+    Indicators(bool),
+    Health(bool),
+}
+
+/// Returns the glyph the player wants to use for symbol, if any.
+pub fn custom_glyph(symbol: Symbol) -> Option<String> {
+    GLYPHS.with(|g| g.borrow().get(&symbol).cloned())
+}
+
+/// Returns true if cells that have never been seen and cells that were seen earlier
+/// should be drawn differently from visible cells (otherwise only remembered cells are
+/// distinguished).
+pub fn shading() -> bool {
+    SHADING.with(|s| *s.borrow())
+}
+
+/// Returns true if damage and new statuses should be briefly shown over characters.
+pub fn indicators() -> bool {
+    INDICATORS.with(|i| *i.borrow())
+}
+
+/// Returns true if the details view should show how hurt visible NPCs are.
+pub fn health() -> bool {
+    HEALTH.with(|h| *h.borrow())
+}
+
+/// Glyphs used to draw the map can be changed using symbol-options.txt which has lines
+/// like:
+///    # Comment
+///    symbol Wall #
+///    symbol Unseen U+00B7
+///    shading off
+///    indicators off
+///    health off
+/// U+ can be used for glyphs that are awkward to type (including spaces). Returns
+/// warnings for lines that couldn't be parsed and for glyphs that aren't usable.
+pub fn load() -> Vec<String> {
+    let mut glyphs = FnvHashMap::default();
+    let mut shading = true;
+    let mut indicators = true;
+    let mut health = true;
+    let mut warnings = Vec::new();
+    if let Ok(text) = fs::read_to_string(data_path(OPTIONS_PATH)) {
+        for (i, line) in text.lines().enumerate() {
+            match parse_line(line) {
+                Ok(Some(Setting::Glyph(symbol, glyph))) => {
+                    glyphs.insert(symbol, glyph);
+                }
+                Ok(Some(Setting::Shading(enabled))) => shading = enabled,
+                Ok(Some(Setting::Indicators(enabled))) => indicators = enabled,
+                Ok(Some(Setting::Health(enabled))) => health = enabled,
+                Ok(None) => (),
+                Err(err) => warnings.push(format!("{OPTIONS_PATH}:{} {err}", i + 1)),
+            }
+        }
+    }
+    warnings.extend(validate(&glyphs));
+    for warning in warnings.iter() {
+        warn!("{warning}");
+    }
+
+    GLYPHS.with(|g| *g.borrow_mut() = glyphs);
+    SHADING.with(|s| *s.borrow_mut() = shading);
+    INDICATORS.with(|i| *i.borrow_mut() = indicators);
+    HEALTH.with(|h| *h.borrow_mut() = health);
+    warnings
+}
+
+// Duplicates are allowed (so the player can do it if they really want to) but they make
+// the map ambiguous so we warn about them.
+fn validate(glyphs: &FnvHashMap<Symbol, String>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut used: Vec<(&str, String)> = Vec::new();
+    for (name, symbol) in SYMBOLS {
+        let glyph = glyphs.get(&symbol).cloned().unwrap_or_else(|| default_glyph(symbol));
+        if glyphs.contains_key(&symbol) && glyph.chars().any(|c| c.is_control()) {
+            warnings.push(format!("{OPTIONS_PATH} glyph for {name} is unprintable"));
+        }
+        if let Some((other, _)) = used.iter().find(|(_, g)| *g == glyph) {
+            warnings.push(format!("{OPTIONS_PATH} {name} and {other} both use '{glyph}'"));
+        }
+        used.push((name, glyph));
+    }
+    warnings
+}
+
+fn parse_line(line: &str) -> Result<Option<Setting>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match command {
+        "symbol" => {
+            let (name, glyph) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+            let symbol = parse_symbol(name).ok_or(format!("bad symbol: '{name}'"))?;
+            let glyph = parse_glyph(glyph.trim())?;
+            Ok(Some(Setting::Glyph(symbol, glyph)))
+        }
+        "shading" if rest == "on" || rest == "off" => Ok(Some(Setting::Shading(rest == "on"))),
+        "indicators" if rest == "on" || rest == "off" => Ok(Some(Setting::Indicators(rest == "on"))),
+        "health" if rest == "on" || rest == "off" => Ok(Some(Setting::Health(rest == "on"))),
+        _ => Err(format!("couldn't parse '{line}'")),
+    }
+}
+
+fn parse_symbol(name: &str) -> Option<Symbol> {
+    SYMBOLS.iter().find(|(n, _)| *n == name).map(|(_, s)| *s)
+}
+
+fn parse_glyph(text: &str) -> Result<String, String> {
+    if let Some(hex) = text.strip_prefix("U+") {
+        u32::from_str_radix(hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .map(|c| c.to_string())
+            .ok_or(format!("bad code point: '{text}'"))
+    } else if text.chars().count() == 1 {
+        Ok(text.to_string())
+    } else {
+        Err(format!("glyph should be one character or U+XXXX: '{text}'"))
+    }
+}
@@ -0,0 +1,135 @@
+use super::help::{format_help, validate_help};
+use super::mode::{InputAction, Mode, RenderContext};
+use super::text_mode::TextMode;
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Action, Color, Game, Point};
+use termion::event::Key;
+
+type KeyHandler = fn(&mut TargetMode, &mut Game) -> InputAction;
+type CommandTable = FnvHashMap<Key, Box<KeyHandler>>;
+
+/// Called with the selected cell when the player accepts a target.
+pub type TargetHandler = Box<dyn Fn(&mut Game, Point)>;
+
+/// Used to pick a distant cell for actions like throwing an item (and eventually for
+/// ranged weapons and spells). The path the projectile would take is drawn over the map
+/// along with whatever would block it.
+pub struct TargetMode {
+    verb: &'static str, // catalog key, e.g. "verb.throw"
+    range: i32,
+    target: Point,
+    on_target: TargetHandler,
+    commands: CommandTable,
+}
+
+impl TargetMode {
+    pub fn create(game: &mut Game, verb: &'static str, range: i32, on_target: TargetHandler) -> Box<dyn Mode> {
+        let mut commands: CommandTable = FnvHashMap::default();
+        commands.insert(Key::Left, Box::new(|s, game| s.do_move(game, -1, 0)));
+        commands.insert(Key::Right, Box::new(|s, game| s.do_move(game, 1, 0)));
+        commands.insert(Key::Up, Box::new(|s, game| s.do_move(game, 0, -1)));
+        commands.insert(Key::Down, Box::new(|s, game| s.do_move(game, 0, 1)));
+        commands.insert(Key::Char('1'), Box::new(|s, game| s.do_move(game, -1, 1)));
+        commands.insert(Key::Char('2'), Box::new(|s, game| s.do_move(game, 0, 1)));
+        commands.insert(Key::Char('3'), Box::new(|s, game| s.do_move(game, 1, 1)));
+        commands.insert(Key::Char('4'), Box::new(|s, game| s.do_move(game, -1, 0)));
+        commands.insert(Key::Char('6'), Box::new(|s, game| s.do_move(game, 1, 0)));
+        commands.insert(Key::Char('7'), Box::new(|s, game| s.do_move(game, -1, -1)));
+        commands.insert(Key::Char('8'), Box::new(|s, game| s.do_move(game, 0, -1)));
+        commands.insert(Key::Char('9'), Box::new(|s, game| s.do_move(game, 1, -1)));
+        commands.insert(Key::Char('\t'), Box::new(|s, game| s.do_tab_target(game, 1)));
+        commands.insert(Key::BackTab, Box::new(|s, game| s.do_tab_target(game, -1)));
+        commands.insert(Key::Char('\n'), Box::new(|s, game| s.do_accept(game)));
+        commands.insert(Key::Char('?'), Box::new(|s, game| s.do_help(game)));
+        commands.insert(Key::Char('q'), Box::new(|s, game| s.do_pop(game)));
+        commands.insert(Key::Esc, Box::new(|s, game| s.do_pop(game)));
+
+        let target = game.targets().first().copied().unwrap_or_else(|| game.player_loc());
+        let mode = TargetMode {
+            verb,
+            range,
+            target,
+            on_target,
+            commands,
+        };
+        mode.examine(game);
+        Box::new(mode)
+    }
+}
+
+impl Mode for TargetMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        if self.target != context.game.player_loc() {
+            let projection = context.game.projected_path(&self.target, self.range);
+            for loc in projection.path {
+                context.overlay.insert(loc, Color::DarkSlateGray);
+            }
+            if let Some(loc) = projection.blocker {
+                context.overlay.insert(loc, Color::DarkRed);
+            }
+        }
+        context.examined = Some(self.target);
+        false
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        None
+    }
+
+    fn handle_input(&mut self, game: &mut Game, key: Key) -> InputAction {
+        match self.commands.get(&key).cloned() {
+            Some(handler) => handler(self, game),
+            None => InputAction::NotHandled,
+        }
+    }
+}
+
+impl TargetMode {
+    fn examine(&self, game: &mut Game) {
+        game.free_action(Action::Examine {
+            loc: self.target,
+            wizard: super::wizard_mode(),
+        });
+    }
+
+    fn do_accept(&mut self, game: &mut Game) -> InputAction {
+        if self.target == game.player_loc() {
+            return InputAction::NotHandled;
+        }
+        (self.on_target)(game, self.target);
+        InputAction::Pop
+    }
+
+    fn do_help(&mut self, _game: &mut Game) -> InputAction {
+        let verb = catalog_text(self.verb, &[]);
+        let help = catalog_text("help.target", &[("verb", &verb)]);
+        validate_help("target", &help, self.commands.keys());
+
+        let lines = format_help(&help, self.commands.keys());
+        InputAction::Push(TextMode::at_top().create(lines))
+    }
+
+    fn do_move(&mut self, game: &mut Game, dx: i32, dy: i32) -> InputAction {
+        self.target = Point::new(self.target.x + dx, self.target.y + dy);
+        self.examine(game);
+        InputAction::UpdatedGame
+    }
+
+    fn do_pop(&mut self, _game: &mut Game) -> InputAction {
+        InputAction::Pop
+    }
+
+    fn do_tab_target(&mut self, game: &mut Game, delta: i32) -> InputAction {
+        let targets = game.targets();
+        if !targets.is_empty() {
+            let n = targets.len() as i32;
+            let index = match targets.iter().position(|loc| *loc == self.target) {
+                Some(i) => (i as i32 + delta).rem_euclid(n),
+                None => 0,
+            };
+            self.target = targets[index as usize];
+            self.examine(game);
+        }
+        InputAction::UpdatedGame
+    }
+}
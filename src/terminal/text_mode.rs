@@ -2,7 +2,7 @@ use super::help::{format_help, validate_help};
 use super::mode::{InputAction, Mode, RenderContext};
 use super::text_view::{Line, TextView};
 use fnv::FnvHashMap;
-use one_thousand_deaths::{Color, Game};
+use one_thousand_deaths::{catalog_text, Color, Game};
 use termion::event::Key;
 
 type KeyHandler = fn(&mut TextMode, &mut Game) -> InputAction;
@@ -131,26 +131,10 @@ impl Mode for TextMode {
 
 impl TextMode {
     fn do_help(&mut self, _game: &mut Game) -> InputAction {
-        let help = r#"There are a number of keys that allow the screen to be scrolled.
+        let help = catalog_text("help.text", &[]);
+        validate_help("text", &help, self.commands.keys());
 
-Scroll down by one full screen:
-[[space]] or [[f]] or [[control-f]] or [[control-v]]
-
-[[b]] or [[control-b]] scroll up by one full screen.
-
-Scroll down by one line:
-[[down-arrow]] or [[return]] or [[d]] or [[e]] or [[j]]
-[[control-d]] or [[control-e]] or [[control-j]] or [[control-n]]
-
-Scroll up by one line:
-[[up-arrow]] or [[u]] or [[k]] or [[p]] or [[y]]
-[[control-u]] or [[control-k]] or [[control-p]] or [[control-y]]
-
-[[?]] show this help.
-[[escape]] and [[q]] exit this mode."#;
-        validate_help("text", help, self.commands.keys());
-
-        let lines = format_help(help, self.commands.keys());
+        let lines = format_help(&help, self.commands.keys());
         InputAction::Push(TextMode::at_top().create(lines))
     }
 
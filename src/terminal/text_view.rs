@@ -1,5 +1,6 @@
+use super::camera::ScreenPoint;
 use super::color;
-use one_thousand_deaths::{Color, Point, Size};
+use one_thousand_deaths::{Color, Size};
 use std::io::Write;
 
 #[derive(Debug)]
@@ -12,7 +13,7 @@ pub type Line = Vec<TextRun>;
 
 /// Takes over the window and renders a scrollable number of lines.
 pub struct TextView {
-    origin: Point,
+    origin: ScreenPoint,
     size: Size,
     lines: Vec<Line>,
     start: usize,
@@ -23,14 +24,14 @@ pub struct TextView {
 impl TextView {
     pub fn new(lines: Vec<Line>, bg: Color) -> TextView {
         let num_lines = lines.len();
-        let (width, height) = termion::terminal_size().expect("couldn't get terminal size");
+        let (width, height) = termion::terminal_size().unwrap_or((80, 24)); // no tty when running tests
         let start = if num_lines >= (height as usize) {
             num_lines - (height as usize)
         } else {
             0
         };
         TextView {
-            origin: Point::origin(),
+            origin: ScreenPoint::origin(),
             size: Size::new(width as i32, height as i32),
             lines,
             start,
@@ -43,7 +44,7 @@ impl TextView {
     }
 
     pub fn render(&self, stdout: &mut Box<dyn Write>) {
-        let mut v = (self.origin.y + 1) as u16;
+        let mut v = self.origin.v();
         let width = self.size.width as usize;
         let height = self.size.height as usize;
         let height = if self.start + height <= self.lines.len() {
@@ -53,7 +54,7 @@ impl TextView {
         };
         let _ = write!(stdout, "{}", termion::color::Bg(color::to_termion(self.bg)));
         for index in self.start..self.start + height {
-            let mut h = (self.origin.x + 1) as u16; // termion is 1-based
+            let mut h = self.origin.h();
             for run in self.lines[index].iter() {
                 // Note that we continue rendering even when truncating to ensure that
                 // styling is set correctly.
@@ -0,0 +1,259 @@
+use super::color;
+use super::mode::{InputAction, Launch, Mode, RenderContext};
+use super::tutorial;
+use one_thousand_deaths::{Color, Conduct, Game, SaveInfo};
+use std::io::Write;
+use std::path::Path;
+use termion::event::Key;
+
+const CHALLENGES: [Conduct; 4] = [
+    Conduct::NoArmor,
+    Conduct::NoNeutralKills,
+    Conduct::Pacifist,
+    Conduct::FogOfMemory,
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Menu {
+    Main,
+    NewGame,
+    Load,
+    Options,
+    HighScores,
+}
+
+/// Shown at startup (before the real game has been created) so that the player can start a
+/// new game, load a saved game, change options, or see how they've done in the past.
+pub struct TitleMode {
+    menu: Menu,
+    selected: usize,
+    saves: Vec<SaveInfo>,
+    profile: Vec<String>, // summary of the player's profile
+    seed: String,         // empty for a random seed
+    entering_seed: bool,  // true if the player is typing into seed
+    challenges: Vec<Conduct>,
+    hints: bool,
+}
+
+impl TitleMode {
+    pub fn create(profile: Vec<String>, seed: Option<u64>, challenges: Vec<Conduct>) -> Box<dyn Mode> {
+        Box::new(TitleMode {
+            menu: Menu::Main,
+            selected: 0,
+            saves: Game::saved_games(&one_thousand_deaths::data_dir().to_string_lossy()),
+            profile,
+            seed: seed.map_or(String::new(), |s| s.to_string()),
+            entering_seed: false,
+            challenges,
+            hints: tutorial::hints_enabled(),
+        })
+    }
+
+    fn items(&self) -> Vec<String> {
+        match self.menu {
+            Menu::Main => vec![
+                "New Game".to_string(),
+                format!("Load ({} saved)", self.saves.len()),
+                "Options".to_string(),
+                "High Scores".to_string(),
+                "Quit".to_string(),
+            ],
+            Menu::NewGame => {
+                let mut items = vec!["Start".to_string()];
+                let seed = if self.seed.is_empty() && !self.entering_seed {
+                    "random".to_string()
+                } else if self.entering_seed {
+                    format!("{}_", self.seed)
+                } else {
+                    self.seed.clone()
+                };
+                items.push(format!("Seed: {seed}"));
+                for conduct in CHALLENGES {
+                    let mark = if self.challenges.contains(&conduct) { "x" } else { " " };
+                    items.push(format!("[{mark}] {conduct} challenge"));
+                }
+                items
+            }
+            Menu::Load => self
+                .saves
+                .iter()
+                .map(|save| {
+                    let name = Path::new(&save.path)
+                        .file_name()
+                        .map_or(save.path.clone(), |n| n.to_string_lossy().to_string());
+                    format!(
+                        "{name}  started {}, {} turns, {}",
+                        save.date, save.turns, save.play_time
+                    )
+                })
+                .collect(),
+            Menu::Options => {
+                let hints = if self.hints { "on" } else { "off" };
+                vec![format!("Tutorial hints: {hints}")]
+            }
+            Menu::HighScores => Vec::new(),
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self.menu {
+            Menu::Main => "up/down arrows select, return chooses, q quits",
+            Menu::NewGame if self.entering_seed => "type digits for the seed, return finishes",
+            Menu::NewGame => "up/down arrows select, return chooses or toggles, escape goes back",
+            Menu::Load => "up/down arrows select, return loads, escape goes back",
+            Menu::Options => "return toggles, escape goes back",
+            Menu::HighScores => "escape goes back",
+        }
+    }
+
+    fn choose(&mut self) -> InputAction {
+        match (self.menu, self.selected) {
+            (Menu::Main, 0) => self.open(Menu::NewGame),
+            (Menu::Main, 1) => self.open(Menu::Load),
+            (Menu::Main, 2) => self.open(Menu::Options),
+            (Menu::Main, 3) => self.open(Menu::HighScores),
+            (Menu::Main, _) => return InputAction::Quit,
+            (Menu::NewGame, 0) => {
+                return InputAction::Launch(Launch::NewGame {
+                    seed: self.seed.parse().ok(),
+                    challenges: self.challenges.clone(),
+                    hints: self.hints,
+                })
+            }
+            (Menu::NewGame, 1) => self.entering_seed = true,
+            (Menu::NewGame, i) => {
+                let conduct = CHALLENGES[i - 2];
+                if let Some(index) = self.challenges.iter().position(|c| *c == conduct) {
+                    self.challenges.remove(index);
+                } else {
+                    self.challenges.push(conduct);
+                }
+            }
+            (Menu::Load, i) => {
+                return InputAction::Launch(Launch::Load {
+                    path: self.saves[i].path.clone(),
+                    hints: self.hints,
+                })
+            }
+            (Menu::Options, _) => {
+                self.hints = !self.hints;
+                tutorial::save_hints_enabled(self.hints);
+            }
+            (Menu::HighScores, _) => (),
+        }
+        InputAction::UpdatedGame
+    }
+
+    fn open(&mut self, menu: Menu) {
+        self.menu = menu;
+        self.selected = 0;
+    }
+
+    fn render_line(&self, stdout: &mut Box<dyn Write>, v: &mut u16, fg: Color, text: &str) {
+        let _ = write!(
+            stdout,
+            "{}{}{}{}{}",
+            termion::cursor::Goto(1, *v),
+            termion::color::Bg(color::to_termion(Color::Black)),
+            termion::color::Fg(color::to_termion(fg)),
+            termion::clear::CurrentLine,
+            text
+        );
+        *v += 1;
+    }
+}
+
+impl Mode for TitleMode {
+    fn render(&self, context: &mut RenderContext) -> bool {
+        let stdout = &mut context.stdout;
+        let _ = write!(stdout, "{}", termion::clear::All);
+
+        let mut v = 2;
+        self.render_line(stdout, &mut v, Color::Yellow, "1k Deaths");
+        v += 1;
+
+        if self.menu == Menu::Main {
+            for line in self.profile.iter() {
+                self.render_line(stdout, &mut v, Color::LightGrey, line);
+            }
+            v += 1;
+        }
+
+        let heading = match self.menu {
+            Menu::Main => None,
+            Menu::NewGame => Some("New Game"),
+            Menu::Load => Some("Load a saved game"),
+            Menu::Options => Some("Options"),
+            Menu::HighScores => Some("High Scores"),
+        };
+        if let Some(heading) = heading {
+            self.render_line(stdout, &mut v, Color::Yellow, heading);
+            v += 1;
+        }
+
+        if self.menu == Menu::HighScores {
+            for line in self.profile.iter() {
+                self.render_line(stdout, &mut v, Color::White, line);
+            }
+        } else if self.menu == Menu::Load && self.saves.is_empty() {
+            self.render_line(stdout, &mut v, Color::White, "There are no saved games.");
+        }
+        for (i, item) in self.items().iter().enumerate() {
+            let fg = if i == self.selected {
+                let _ = write!(stdout, "{}{}", termion::cursor::Goto(1, v), termion::cursor::Save); // for screen readers
+                Color::SkyBlue
+            } else {
+                Color::White
+            };
+            self.render_line(stdout, &mut v, fg, item);
+        }
+        v += 1;
+
+        self.render_line(stdout, &mut v, Color::LightGrey, self.help());
+        true
+    }
+
+    fn input_timeout_ms(&self) -> Option<i32> {
+        None
+    }
+
+    fn handle_input(&mut self, _game: &mut Game, key: Key) -> InputAction {
+        if self.entering_seed {
+            match key {
+                Key::Char('\n') | Key::Esc => self.entering_seed = false,
+                Key::Backspace => {
+                    self.seed.pop();
+                }
+                Key::Char(c) if c.is_ascii_digit() && self.seed.len() < 19 => self.seed.push(c),
+                _ => return InputAction::NotHandled,
+            }
+            return InputAction::UpdatedGame;
+        }
+
+        let num_items = self.items().len();
+        match key {
+            Key::Down | Key::Char('2') if num_items > 0 => {
+                self.selected = (self.selected + 1) % num_items;
+                InputAction::UpdatedGame
+            }
+            Key::Up | Key::Char('8') if num_items > 0 => {
+                self.selected = (self.selected + num_items - 1) % num_items;
+                InputAction::UpdatedGame
+            }
+            Key::Char('\n') if num_items > 0 => self.choose(),
+            Key::Esc | Key::Char('q') if self.menu != Menu::Main => {
+                let index = match self.menu {
+                    Menu::NewGame => 0,
+                    Menu::Load => 1,
+                    Menu::Options => 2,
+                    _ => 3,
+                };
+                self.open(Menu::Main);
+                self.selected = index;
+                InputAction::UpdatedGame
+            }
+            Key::Char('q') => InputAction::Quit,
+            _ => InputAction::NotHandled,
+        }
+    }
+}
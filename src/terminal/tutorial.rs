@@ -0,0 +1,62 @@
+use fnv::FnvHashSet;
+use one_thousand_deaths::{catalog_text, data_path};
+use one_thousand_deaths::{Game, Message, Topic};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+const SEEN_PATH: &str = "seen-hints.txt";
+const OPTIONS_PATH: &str = "tutorial-options.txt";
+
+/// Hints are on unless the player turns them off using the title screen's options. That
+/// choice is saved in tutorial-options.txt which has a line like `hints off`.
+pub fn hints_enabled() -> bool {
+    match fs::read_to_string(data_path(OPTIONS_PATH)) {
+        Ok(text) => !text.lines().any(|line| line.trim() == "hints off"),
+        Err(_) => true, // normal if the player has never changed the option
+    }
+}
+
+pub fn save_hints_enabled(enabled: bool) {
+    let value = if enabled { "on" } else { "off" };
+    if let Err(err) = fs::write(data_path(OPTIONS_PATH), format!("hints {value}\n")) {
+        warn!("couldn't save {OPTIONS_PATH}: {err}");
+    }
+}
+
+/// Shows tutorial hints the first time the player runs into a mechanic. The hints the
+/// player has seen are shared across all of their games.
+pub struct Tutorial {
+    enabled: bool,
+    seen: FnvHashSet<String>, // hint names
+}
+
+impl Tutorial {
+    pub fn new(enabled: bool) -> Tutorial {
+        let seen = match fs::read_to_string(data_path(SEEN_PATH)) {
+            Ok(text) => text.lines().map(|line| line.to_string()).collect(),
+            Err(_) => FnvHashSet::default(), // normal for the first game
+        };
+        Tutorial { enabled, seen }
+    }
+
+    /// Adds messages for hints that haven't been seen before.
+    pub fn update(&mut self, game: &mut Game, replaying: bool) {
+        for hint in game.take_hints() {
+            if self.enabled && !replaying && self.seen.insert(hint.to_string()) {
+                let text = catalog_text("hint", &[("text", &hint.text())]);
+                game.add_mesg(Message::new(Topic::Hint, &text));
+                if let Err(err) = self.save(&hint.to_string()) {
+                    warn!("couldn't save {SEEN_PATH}: {err}");
+                }
+            }
+        }
+    }
+
+    fn save(&self, name: &str) -> Result<(), std::io::Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(data_path(SEEN_PATH))?;
+        writeln!(file, "{name}")
+    }
+}
@@ -1,7 +1,11 @@
+use super::cutscene_mode::{self, CutsceneMode};
+use super::indicators::Indicators;
 use super::main_mode::MainMode;
-use super::mode::{InputAction, Mode, RenderContext};
+use super::mode::{InputAction, Launch, Mode, RenderContext};
 use super::replay_mode::ReplayMode;
+use super::screen::{Presenter, Screen};
 use super::GameState;
+use fnv::FnvHashMap;
 use one_thousand_deaths::{Action, Game};
 use std::io::{self, Write};
 use std::sync::mpsc::{self, Receiver};
@@ -12,10 +16,24 @@ use termion::input::TermRead; // for keys trait
 pub struct UI {
     modes: Vec<Box<dyn Mode>>,
     recv: Receiver<Key>,
+    launch: Option<Launch>, // set when the title screen has picked a game to play
+    screen: Screen,         // modes render into this
+    presenter: Presenter,   // and this writes out the changes to stdout
+    indicators: Indicators, // damage numbers, etc drawn over the map
 }
 
 impl UI {
     pub fn new(width: i32, height: i32, replay: Vec<Action>) -> UI {
+        UI::with_modes(UI::game_modes(width, height, replay), width, height)
+    }
+
+    /// Normally the title screen sits on top of an unsaved game until the player chooses
+    /// which game to play.
+    pub fn title(width: i32, height: i32, title: Box<dyn Mode>) -> UI {
+        UI::with_modes(vec![MainMode::create(width, height), title], width, height)
+    }
+
+    fn with_modes(modes: Vec<Box<dyn Mode>>, width: i32, height: i32) -> UI {
         let (send, recv) = mpsc::channel();
         let _ = thread::spawn(move || {
             let stdin = io::stdin();
@@ -32,12 +50,44 @@ impl UI {
                 }
             }
         });
+        UI::with_keys(modes, recv, width, height)
+    }
 
-        let mut modes = vec![MainMode::create(width, height)];
-        if !replay.is_empty() {
-            modes.push(ReplayMode::create(replay));
+    /// Keys normally come from a thread reading stdin but tests can use their own channel.
+    fn with_keys(modes: Vec<Box<dyn Mode>>, recv: Receiver<Key>, width: i32, height: i32) -> UI {
+        UI {
+            modes,
+            recv,
+            launch: None,
+            screen: Screen::new(width, height),
+            presenter: Presenter::new(),
+            indicators: Indicators::new(),
         }
-        UI { modes, recv }
+    }
+
+    pub fn take_launch(&mut self) -> Option<Launch> {
+        self.launch.take()
+    }
+
+    /// Called after the title screen has launched a new game.
+    pub fn restart(&mut self, width: i32, height: i32, replay: Vec<Action>) {
+        self.modes = UI::game_modes(width, height, replay);
+        self.screen.resize(width, height);
+        self.clear();
+    }
+
+    fn game_modes(width: i32, height: i32, replay: Vec<Action>) -> Vec<Box<dyn Mode>> {
+        let first = if replay.is_empty() {
+            CutsceneMode::create(cutscene_mode::INTRO)
+        } else {
+            ReplayMode::create(replay)
+        };
+        vec![MainMode::create(width, height), first]
+    }
+
+    /// Returns true if the top mode updates without waiting for the player, e.g. RunMode.
+    pub fn animating(&self) -> bool {
+        self.modes.last().unwrap().input_timeout_ms().is_some()
     }
 
     pub fn replaying(&self) -> bool {
@@ -49,14 +99,21 @@ impl UI {
         false
     }
 
-    pub fn render(&self, stdout: &mut Box<dyn Write>, game: &mut Game) {
+    /// Renders the modes into the screen and then writes whatever changed to stdout.
+    pub fn render(&mut self, stdout: &mut Box<dyn Write>, game: &mut Game) {
+        let mut writer = self.screen.writer();
+        self.screen.begin_frame();
+        let indicators = self.indicators.frame(game);
         let mut context = RenderContext {
-            stdout,
+            stdout: &mut writer,
             game,
             examined: None,
+            overlay: FnvHashMap::default(),
+            indicators,
         };
         for mode in self.modes.iter().rev() {
             if mode.render(&mut context) {
+                self.presenter.present(&self.screen, stdout);
                 return;
             }
         }
@@ -75,25 +132,49 @@ impl UI {
         }
     }
 
-    fn clear(&self, stdout: &mut Box<dyn Write>) {
-        write!(stdout, "{}", termion::clear::All).unwrap();
+    pub fn push(&mut self, mode: Box<dyn Mode>) {
+        debug!("pushed {}", mode.name());
+        self.modes.push(mode);
+        self.clear();
     }
 
-    pub(super) fn handle_input(&mut self, stdout: &mut Box<dyn Write>, game: &mut Game) -> GameState {
-        use InputAction::*;
+    // Modes don't necessarily draw every cell so we start over when the top mode changes.
+    // Only the screen is cleared so this won't cause flicker.
+    fn clear(&self) {
+        write!(self.screen.writer(), "{}{}", termion::style::Reset, termion::clear::All).unwrap();
+    }
+
+    pub(super) fn handle_input(&mut self, game: &mut Game) -> GameState {
         let key = self.get_key();
+        self.dispatch(game, key)
+    }
+
+    fn dispatch(&mut self, game: &mut Game, key: Key) -> GameState {
+        use InputAction::*;
         let mode = self.modes.last_mut().unwrap();
         match mode.handle_input(game, key) {
             UpdatedGame => (),
             Quit => return GameState::Exiting,
+            Launch(launch) => {
+                self.launch = Some(launch);
+                return GameState::Launching;
+            }
             Push(mode) => {
+                debug!("pushed {}", mode.name());
                 self.modes.push(mode);
-                self.clear(stdout);
+                self.clear();
             }
             Pop => {
-                let _ = self.modes.pop();
+                let old = self.modes.pop().unwrap();
+                debug!("popped {}", old.name());
                 assert!(!self.modes.is_empty());
-                self.clear(stdout);
+                self.clear();
+            }
+            Replace(mode) => {
+                let old = self.modes.pop().unwrap();
+                debug!("replaced {} with {}", old.name(), mode.name());
+                self.modes.push(mode);
+                self.clear();
             }
             NotHandled => {
                 debug!("player pressed {key:?}"); // TODO: beep?
@@ -102,3 +183,347 @@ impl UI {
         GameState::Running
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    const KEYS: [Key; 36] = [
+        Key::Left,
+        Key::Right,
+        Key::Up,
+        Key::Down,
+        Key::Esc,
+        Key::BackTab,
+        Key::Backspace,
+        Key::Char('\n'),
+        Key::Char('\t'),
+        Key::Char(' '),
+        Key::Char('?'),
+        Key::Char('/'),
+        Key::Char('\\'),
+        Key::Char('1'),
+        Key::Char('2'),
+        Key::Char('3'),
+        Key::Char('4'),
+        Key::Char('5'),
+        Key::Char('6'),
+        Key::Char('7'),
+        Key::Char('8'),
+        Key::Char('9'),
+        Key::Char('a'),
+        Key::Char('b'),
+        Key::Char('d'),
+        Key::Char('e'),
+        Key::Char('g'),
+        Key::Char('i'),
+        Key::Char('j'),
+        Key::Char('q'),
+        Key::Char('s'),
+        Key::Char('u'),
+        Key::Char('v'),
+        Key::Char('x'),
+        Key::Ctrl('f'),
+        Key::Ctrl('p'),
+    ];
+
+    fn new_ui() -> UI {
+        let (_, recv) = mpsc::channel();
+        let modes = vec![MainMode::create(80, 24), CutsceneMode::create(cutscene_mode::INTRO)];
+        UI::with_keys(modes, recv, 80, 24)
+    }
+
+    // Drives the UI with synthetic keys (the same way Terminal::run does).
+    struct Harness {
+        ui: UI,
+        game: Game,
+        send: mpsc::Sender<Key>,
+        stdout: Box<dyn Write>,
+    }
+
+    impl Harness {
+        fn new(seed: u64) -> Harness {
+            let path = format!("/tmp/harness-{seed}.game");
+            let _ = std::fs::remove_file(&path);
+            let (send, recv) = mpsc::channel();
+            let modes = vec![MainMode::create(80, 24)];
+            let mut harness = Harness {
+                ui: UI::with_keys(modes, recv, 80, 24),
+                game: Game::new_game(&path, seed),
+                send,
+                stdout: Box::new(io::sink()),
+            };
+            harness.wait_for_player();
+            harness
+        }
+
+        fn press(&mut self, key: Key) -> GameState {
+            self.send.send(key).unwrap();
+            let state = self.ui.handle_input(&mut self.game);
+            self.wait_for_player();
+            state
+        }
+
+        fn wait_for_player(&mut self) {
+            self.ui.render(&mut self.stdout, &mut self.game);
+            while !self.game.players_turn() {
+                self.game.advance_time(false);
+            }
+        }
+
+        fn modes(&self) -> Vec<&'static str> {
+            self.ui.modes.iter().map(|m| m.name()).collect()
+        }
+    }
+
+    // Feeds random keys into the mode stack and renders after each key.
+    fn fuzz(seed: u64, count: usize) {
+        let path = format!("/tmp/fuzz-{seed}.game");
+        let mut game = Game::new_game(&path, seed);
+        let mut ui = new_ui();
+        let mut stdout: Box<dyn Write> = Box::new(io::sink());
+        let mut rng = SmallRng::seed_from_u64(seed);
+
+        let mut i = 0;
+        while i < count {
+            ui.render(&mut stdout, &mut game);
+            if game.players_turn() {
+                let key = KEYS[rng.gen_range(0..KEYS.len())];
+                if ui.dispatch(&mut game, key) == GameState::Exiting {
+                    ui = new_ui();
+                }
+                i += 1;
+            } else {
+                game.advance_time(false);
+            }
+        }
+
+        // All the modes should be able to get back to main mode.
+        for _ in 0..20 {
+            if ui.modes.len() == 1 {
+                break;
+            }
+            ui.dispatch(&mut game, Key::Esc);
+        }
+        assert_eq!(ui.modes.len(), 1, "seed {seed} left modes on the stack");
+    }
+
+    #[test]
+    fn test_mode_stack() {
+        let mut harness = Harness::new(1);
+        assert_eq!(harness.modes(), vec!["MainMode"]);
+
+        harness.press(Key::Char('i'));
+        assert_eq!(harness.modes(), vec!["MainMode", "InventoryMode"]);
+
+        harness.press(Key::Char('?'));
+        assert_eq!(harness.modes(), vec!["MainMode", "InventoryMode", "TextMode"]);
+
+        harness.press(Key::Esc);
+        assert_eq!(harness.modes(), vec!["MainMode", "InventoryMode"]);
+
+        harness.press(Key::Esc);
+        harness.press(Key::Char('x'));
+        assert_eq!(harness.modes(), vec!["MainMode", "ExamineMode"]);
+
+        harness.press(Key::Esc);
+        assert_eq!(harness.modes(), vec!["MainMode"]);
+        assert!(harness.press(Key::Char('q')) == GameState::Exiting);
+    }
+
+    #[test]
+    fn test_emitted_actions() {
+        let mut harness = Harness::new(1);
+        harness.press(Key::Char('s'));
+        assert_eq!(harness.game.unsaved_actions().last(), Some(&&Action::Rest));
+
+        // Help doesn't take any time.
+        let count = harness.game.unsaved_actions().len();
+        harness.press(Key::Char('?'));
+        harness.press(Key::Esc);
+        assert_eq!(harness.game.unsaved_actions().len(), count);
+        assert_eq!(harness.modes(), vec!["MainMode"]);
+    }
+
+    #[test]
+    fn test_camera() {
+        use super::super::camera::{Camera, ScreenPoint};
+        use one_thousand_deaths::{Point, Size};
+
+        // The player should be drawn in the middle of the map view (which is offset to
+        // check that the view's origin is taken into account).
+        let origin = ScreenPoint::new(2, 3);
+        let camera = Camera::centered(origin, Size::new(11, 7), Point::new(50, 60));
+        assert_eq!(camera.to_world(ScreenPoint::new(2 + 5, 3 + 3)), Point::new(50, 60));
+        assert_eq!(camera.to_world(origin), Point::new(45, 57));
+
+        // termion is 1-based.
+        assert_eq!((origin.h(), origin.v()), (3, 4));
+    }
+
+    #[test]
+    fn test_snapshots() {
+        use super::super::snapshots::{Snapshots, SNAPSHOT_DIR};
+        use one_thousand_deaths::{Message, Topic};
+
+        let dir = format!("/tmp/snapshots-{}", line!());
+        let _ = std::fs::remove_dir_all(&dir);
+        SNAPSHOT_DIR.with(|d| *d.borrow_mut() = Some(dir.clone()));
+
+        let mut harness = Harness::new(1);
+        let mut snapshots = Snapshots::load(&harness.game);
+        harness.game.add_mesg(Message::new(Topic::Normal, "nothing to see"));
+        snapshots.update(&harness.game, false);
+        assert!(std::fs::read_dir(&dir).is_err());
+
+        harness.game.add_mesg(Message::new(Topic::Important, "Rhulad <falls>"));
+        snapshots.update(&harness.game, true); // replaying
+        assert!(std::fs::read_dir(&dir).is_err());
+
+        harness.game.add_mesg(Message::new(Topic::Important, "Rhulad <falls>"));
+        snapshots.update(&harness.game, false);
+        let text = std::fs::read_to_string(format!("{dir}/snapshot-0001.html")).unwrap();
+        assert!(text.contains("<title>Rhulad &lt;falls&gt;</title>"));
+        assert!(text.contains("nothing to see"));
+    }
+
+    // Saves whatever is written to it.
+    #[derive(Clone, Default)]
+    struct Capture(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl Capture {
+        fn take(&self) -> String {
+            String::from_utf8(self.0.take()).unwrap()
+        }
+    }
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_unchanged_frames() {
+        use one_thousand_deaths::{Message, Topic};
+
+        let capture = Capture::default();
+        let mut harness = Harness::new(1);
+        harness.stdout = Box::new(capture.clone());
+
+        // Nothing changed so the second render shouldn't write anything.
+        harness.ui.render(&mut harness.stdout, &mut harness.game);
+        assert_eq!(capture.take(), "");
+
+        // Help replaces most of the screen.
+        harness.press(Key::Char('?'));
+        let help = capture.take().len();
+        assert!(help > 0);
+
+        // A new message only changes a few cells.
+        harness.press(Key::Esc);
+        capture.take();
+        harness.game.add_mesg(Message::new(Topic::Normal, "a new message"));
+        harness.ui.render(&mut harness.stdout, &mut harness.game);
+        let text = capture.take();
+        assert!(!text.is_empty());
+        assert!(2 * text.len() < help, "help: {help} message: {}", text.len());
+    }
+
+    #[test]
+    fn test_screen() {
+        use super::super::screen::{Presenter, Screen};
+        use termion::color::{Bg, Fg};
+        use termion::{clear, cursor, style};
+
+        let screen = Screen::new(10, 3);
+        let mut writer = screen.writer();
+        let red = termion::color::AnsiValue(9);
+        write!(writer, "{}{}hello", cursor::Goto(3, 1), Fg(red)).unwrap();
+        write!(writer, "{}\u{2694}\u{FE0F}ab", cursor::Goto(9, 2)).unwrap(); // wraps onto the next line
+        assert_eq!(screen.row_text(0), "  hello");
+        assert_eq!(screen.row_text(1), "        \u{2694}\u{FE0F}a");
+        assert_eq!(screen.row_text(2), "b");
+
+        // The first present has to write every cell.
+        let capture = Capture::default();
+        let mut stdout: Box<dyn Write> = Box::new(capture.clone());
+        let mut presenter = Presenter::new();
+        presenter.present(&screen, &mut stdout);
+        assert!(capture.take().contains("hello"));
+
+        // After that only changes are written (the style is reset because other code may
+        // have written to stdout between presents).
+        write!(writer, "{}{}J", cursor::Goto(4, 1), Bg(red)).unwrap();
+        presenter.present(&screen, &mut stdout);
+        assert_eq!(
+            capture.take(),
+            format!("{}{}{}{}J", cursor::Goto(4, 1), style::Reset, Fg(red), Bg(red))
+        );
+
+        write!(writer, "{}{}", style::Reset, clear::All).unwrap();
+        assert_eq!(screen.row_text(0), "");
+        presenter.present(&screen, &mut stdout);
+        let text = capture.take();
+        assert!(
+            text.starts_with(&format!("{}{}", cursor::Goto(3, 1), style::Reset)),
+            "{text:?}"
+        );
+    }
+
+    #[test]
+    fn test_screen_reader() {
+        use super::super::screen::{Presenter, Screen};
+        use super::super::screen_reader::{direction, summarize, SCREEN_READER};
+        use termion::cursor;
+
+        assert_eq!(direction(0, -1), "north");
+        assert_eq!(direction(3, 1), "east");
+        assert_eq!(direction(-2, 2), "south-west");
+        assert_eq!(direction(1, -3), "north");
+
+        let harness = Harness::new(1);
+        let summary = summarize(&harness.game);
+        assert!(summary.ends_with('.'), "{summary}");
+        assert!(summary.chars().next().unwrap().is_uppercase(), "{summary}");
+
+        // The cursor is left on the selected item.
+        SCREEN_READER.with(|s| *s.borrow_mut() = true);
+        let screen = Screen::new(10, 3);
+        let mut writer = screen.writer();
+        write!(
+            writer,
+            "{}one{}{}two",
+            cursor::Goto(1, 1),
+            cursor::Goto(1, 2),
+            cursor::Save
+        )
+        .unwrap();
+        let capture = Capture::default();
+        let mut stdout: Box<dyn Write> = Box::new(capture.clone());
+        let mut presenter = Presenter::new();
+        presenter.present(&screen, &mut stdout);
+        assert!(capture.take().ends_with(&cursor::Goto(1, 2).to_string()));
+
+        // Without a selection it goes to the last line.
+        screen.begin_frame();
+        write!(writer, "{}three", cursor::Goto(1, 3)).unwrap();
+        presenter.present(&screen, &mut stdout);
+        assert!(capture.take().ends_with(&cursor::Goto(1, 3).to_string()));
+        SCREEN_READER.with(|s| *s.borrow_mut() = false);
+    }
+
+    #[test]
+    fn test_fuzz_keys() {
+        for seed in 1..4 {
+            fuzz(seed, 2000);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::mode::InputAction;
+use fnv::FnvHashMap;
+use one_thousand_deaths::{catalog_text, Action, Game};
+use std::cell::RefCell;
+use termion::event::Key;
+
+type KeyHandler<M> = fn(&mut M, &mut Game) -> InputAction;
+type CommandTable<M> = FnvHashMap<Key, Box<KeyHandler<M>>>;
+
+/// What a wizard command is allowed to do. Commands are registered with the capability
+/// they require and are only added if that capability was granted (--wizard grants all
+/// of them).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Capability {
+    /// Show information the player normally can't see, e.g. exact HPs or AI routes.
+    Inspect,
+
+    /// Change the game in ways the player normally can't, e.g. teleporting or undo.
+    Cheat,
+}
+
+thread_local!(static CAPABILITIES: RefCell<Vec<Capability>> = const { RefCell::new(Vec::new()) });
+
+/// Called when --wizard is used.
+pub fn grant_all() {
+    CAPABILITIES.with(|c| {
+        *c.borrow_mut() = vec![Capability::Inspect, Capability::Cheat];
+    })
+}
+
+pub fn granted(capability: Capability) -> bool {
+    CAPABILITIES.with(|c| c.borrow().contains(&capability))
+}
+
+/// Records that wizard mode was used with this game so that it no longer counts towards
+/// the player's profile. This is saved along with the rest of the player's actions so
+/// replays (and reloaded games) are invalidated the same way.
+pub fn record_use(game: &mut Game) {
+    if !game.used_wizard_mode() {
+        game.player_acted(Action::WizardMode);
+    }
+}
+
+/// A wizard command that a mode would like to add.
+pub struct WizardCommand<M> {
+    pub key: Key,
+    pub capability: Capability,
+    pub help: &'static str, // catalog key for text like "[[control-u]] undo the last turn."
+    pub handler: KeyHandler<M>,
+}
+
+/// The wizard commands a mode was allowed to add.
+pub struct WizardCommands {
+    keys: Vec<Key>,
+    help: Vec<&'static str>,
+}
+
+impl WizardCommands {
+    /// Adds the commands whose capability was granted to the mode's command table.
+    pub fn register<M>(commands: &mut CommandTable<M>, candidates: Vec<WizardCommand<M>>) -> WizardCommands {
+        let mut wizard = WizardCommands {
+            keys: Vec::new(),
+            help: Vec::new(),
+        };
+        for command in candidates.into_iter().filter(|c| granted(c.capability)) {
+            let old = commands.insert(command.key, Box::new(command.handler));
+            assert!(old.is_none(), "wizard command {:?} is already bound", command.key);
+            wizard.keys.push(command.key);
+            wizard.help.push(command.help);
+        }
+        wizard
+    }
+
+    /// Should be called before a mode runs a command handler.
+    pub fn note_key(&self, game: &mut Game, key: Key) {
+        if self.keys.contains(&key) {
+            record_use(game);
+        }
+    }
+
+    /// Returns text to append to the mode's help (empty if no commands were added).
+    pub fn help(&self) -> String {
+        if self.help.is_empty() {
+            String::new()
+        } else {
+            let help: Vec<String> = self.help.iter().map(|key| catalog_text(key, &[])).collect();
+            format!("\n\n{}\n{}", catalog_text("help.wizard", &[]), help.join("\n"))
+        }
+    }
+}